@@ -0,0 +1,285 @@
+use clap::{Args, Subcommand};
+
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::channel;
+
+use crate::dashboard::summarize;
+use crate::exit_with_error;
+use crate::metadata::Metadata;
+use crate::registry;
+use crate::tools::versions;
+
+#[derive(Args)]
+pub struct AllArgs {
+    /// Number of projects to work on at once (default: the number of CPUs)
+    #[clap(short, long)]
+    jobs: Option<usize>,
+
+    #[clap(subcommand)]
+    command: AllCommand,
+}
+
+#[derive(Subcommand)]
+pub enum AllCommand {
+    /// Print what needs attention in each project
+    Status,
+
+    /// Remove expired scratch directories and stale cached files from each project
+    Clean(CleanArgs),
+
+    /// Check the consistency of each project, with `investigate fsck`
+    Verify,
+
+    /// Run an investigate command in each project, given after `--`
+    Run(RunArgs),
+}
+
+#[derive(Args)]
+pub struct CleanArgs {
+    /// Move expired scratch directories in tmp/ to the trash, like `investigate tmp gc`
+    #[clap(long)]
+    tmp: bool,
+
+    /// Move stale files in cache/ to the trash, like `investigate cache gc --stale`
+    #[clap(long)]
+    cache: bool,
+
+    /// Only list what would be removed
+    #[clap(long)]
+    dry_run: bool,
+}
+
+#[derive(Args)]
+pub struct RunArgs {
+    /// Arguments to investigate, e.g. `-- fsck --checksums`
+    #[clap(last = true, required = true)]
+    args: Vec<OsString>,
+}
+
+/// A registered project which still exists.
+struct Project {
+    name: String,
+    root: PathBuf,
+}
+
+/// What running the commands in a project gave.
+struct Outcome {
+    ok: bool,
+    output: String,
+}
+
+/// Get the registered projects which still exist, and the number of those which don't.
+fn projects() -> (Vec<Project>, usize) {
+    let roots = registry::load();
+    let n_roots = roots.len();
+    let projects: Vec<Project> = roots
+        .into_iter()
+        .filter_map(|root| {
+            let metadata = Metadata::try_load(&root)?;
+            Some(Project {
+                name: metadata.name,
+                root,
+            })
+        })
+        .collect();
+    if n_roots == 0 {
+        exit_with_error("No projects are registered")
+    }
+    let n_missing = n_roots - projects.len();
+    (projects, n_missing)
+}
+
+/// Call `f` on each of `projects`, with up to `jobs` projects at once, and `report` each
+/// outcome as it comes in.
+fn for_each_project<F>(
+    projects: &[Project],
+    jobs: usize,
+    f: F,
+    mut report: impl FnMut(&Project, Outcome),
+) where
+    F: Fn(&Project) -> Outcome + Sync,
+{
+    let next = AtomicUsize::new(0);
+    let (sender, outcomes) = channel();
+    std::thread::scope(|scope| {
+        for _ in 0..jobs.clamp(1, projects.len().max(1)) {
+            let sender = sender.clone();
+            let (next, f) = (&next, &f);
+            scope.spawn(move || {
+                while let Some(project) = projects.get(next.fetch_add(1, Ordering::SeqCst)) {
+                    if sender.send((project, f(project))).is_err() {
+                        return;
+                    }
+                }
+            });
+        }
+        drop(sender);
+        for (project, outcome) in outcomes {
+            report(project, outcome);
+        }
+    });
+}
+
+/// Run investigate with each of `commands` in the project at `root`, stopping at the first
+/// that fails. Each runs in its own process, so one project's errors don't end the others.
+fn run_commands(root: &Path, commands: &[Vec<OsString>]) -> Outcome {
+    let executable = std::env::current_exe()
+        .unwrap_or_else(|_| exit_with_error("Could not find the investigate executable"));
+    let mut text = String::new();
+    for command in commands {
+        match Command::new(&executable)
+            .args(command)
+            .current_dir(root)
+            .output()
+        {
+            Err(e) => {
+                return Outcome {
+                    ok: false,
+                    output: format!("Could not run investigate: {}", e),
+                }
+            }
+            Ok(output) => {
+                text.push_str(&String::from_utf8_lossy(&output.stdout));
+                text.push_str(&String::from_utf8_lossy(&output.stderr));
+                if !output.status.success() {
+                    return Outcome {
+                        ok: false,
+                        output: text,
+                    };
+                }
+            }
+        }
+    }
+    Outcome {
+        ok: true,
+        output: text,
+    }
+}
+
+fn status(projects: &[Project], jobs: usize) -> Vec<String> {
+    let installed = versions();
+    let mut attention = Vec::new();
+    for_each_project(
+        projects,
+        jobs,
+        |project| {
+            let metadata = Metadata::try_load(&project.root);
+            let warnings = match metadata {
+                None => vec!["Could not read its metadata".to_owned()],
+                Some(metadata) => summarize(&project.root, &metadata, &installed)
+                    .warnings()
+                    .into_iter()
+                    .map(str::to_owned)
+                    .collect(),
+            };
+            Outcome {
+                ok: warnings.is_empty(),
+                output: warnings.join("\n"),
+            }
+        },
+        |project, outcome| {
+            println!(
+                "== {} ({}): {}",
+                project.name,
+                project.root.display(),
+                if outcome.ok { "ok" } else { "needs attention" }
+            );
+            for line in outcome.output.lines() {
+                println!("    {}", line);
+            }
+            if !outcome.ok {
+                attention.push(project.name.clone());
+            }
+        },
+    );
+    attention
+}
+
+fn commands(command: AllCommand) -> Vec<Vec<OsString>> {
+    let args = |args: &[&str]| args.iter().map(OsString::from).collect::<Vec<_>>();
+    match command {
+        AllCommand::Status => unreachable!("Status is not run as commands"),
+        AllCommand::Clean(clean) => {
+            if !clean.tmp && !clean.cache {
+                exit_with_error("Give what to clean: --tmp, --cache or both")
+            }
+            let dry_run: &[&str] = if clean.dry_run { &["--dry-run"] } else { &[] };
+            let mut commands = Vec::new();
+            if clean.tmp {
+                commands.push(args(&[&["tmp", "gc"], dry_run].concat()));
+            }
+            if clean.cache {
+                commands.push(args(&[&["cache", "gc", "--stale"], dry_run].concat()));
+            }
+            commands
+        }
+        AllCommand::Verify => vec![args(&["fsck"])],
+        AllCommand::Run(run) => vec![run.args],
+    }
+}
+
+pub fn run(args: AllArgs) {
+    let (projects, n_missing) = projects();
+    let jobs = args.jobs.unwrap_or_else(|| {
+        std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1)
+    });
+    let is_status = matches!(args.command, AllCommand::Status);
+    let failed = match args.command {
+        AllCommand::Status => status(&projects, jobs),
+        command => {
+            let commands = commands(command);
+            let mut failed = Vec::new();
+            for_each_project(
+                &projects,
+                jobs,
+                |project| run_commands(&project.root, &commands),
+                |project, outcome| {
+                    println!(
+                        "== {} ({}): {}",
+                        project.name,
+                        project.root.display(),
+                        if outcome.ok { "ok" } else { "failed" }
+                    );
+                    for line in outcome.output.lines() {
+                        println!("    {}", line);
+                    }
+                    if !outcome.ok {
+                        failed.push(project.name.clone());
+                    }
+                },
+            );
+            failed
+        }
+    };
+    println!();
+    println!(
+        "{} projects, {} ok, {} {}{}",
+        projects.len(),
+        projects.len() - failed.len(),
+        failed.len(),
+        if is_status {
+            "need attention"
+        } else {
+            "failed"
+        },
+        if failed.is_empty() {
+            String::new()
+        } else {
+            format!(": {}", failed.join(", "))
+        }
+    );
+    if n_missing > 0 {
+        println!(
+            "Skipped {} registered projects which no longer exist",
+            n_missing
+        );
+    }
+    if !failed.is_empty() {
+        std::process::exit(1)
+    }
+}
@@ -0,0 +1,95 @@
+use std::io::{IsTerminal, Write};
+
+use crate::config::Config;
+use crate::warning::{warn, Code};
+
+/// Get a value from the user's global git config.
+fn git_config_value(key: &str) -> Option<String> {
+    let config = git2::Config::open_default().ok()?;
+    let value = config.get_string(key).ok()?;
+    (!value.trim().is_empty()).then_some(value)
+}
+
+fn env_value(key: &str) -> Option<String> {
+    std::env::var(key).ok().filter(|v| !v.trim().is_empty())
+}
+
+/// Ask the user for a value, if stdin is a terminal.
+fn prompt(question: &str) -> Option<String> {
+    if !std::io::stdin().is_terminal() {
+        return None;
+    }
+    eprint!("{}: ", question);
+    std::io::stderr().flush().ok()?;
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer).ok()?;
+    let answer = answer.trim();
+    (!answer.is_empty()).then(|| answer.to_owned())
+}
+
+/// Placeholder for the author's name in generated files, when it can't be determined. It's
+/// marked so it's easy to find and replace, and `investigate fsck` reports files containing it.
+pub const PLACEHOLDER_NAME: &str = "UNKNOWN_AUTHOR";
+
+/// Placeholder for the author's email, in a domain which can't exist
+pub const PLACEHOLDER_EMAIL: &str = "unknown_author@example.invalid";
+
+/// Warn that the author's name and email are unknown, so placeholders are used.
+pub fn warn_unknown_author() {
+    warn(
+        Code::UnknownAuthor,
+        &format!(
+            "Could not determine author name and email from git config,\n\
+            environment variables or the config file, so {} is used instead.\n\
+            Set name and email with:\n\
+            git config --global user.name \"FIRST_NAME LAST_NAME\"\n\
+            git config --global user.email \"EXAMPLE@EMAIL.COM\"\n",
+            PLACEHOLDER_NAME
+        ),
+    )
+}
+
+/// Split an author given as "NAME <EMAIL>" into the name and email.
+pub fn parse_author(author: &str) -> Option<(String, String)> {
+    let (name, email) = author.trim_end().strip_suffix('>')?.split_once('<')?;
+    Some((name.trim().to_owned(), email.trim().to_owned()))
+}
+
+/// Get the author's name and email from `$INVESTIGATE_AUTHOR`, given as "NAME <EMAIL>".
+fn investigate_author() -> Option<(String, String)> {
+    parse_author(&env_value("INVESTIGATE_AUTHOR")?)
+}
+
+/// Get the author's name and email. `$INVESTIGATE_AUTHOR` takes precedence,
+/// else each is taken from the first source that has it: git config, environment variables,
+/// the config file's `[author]` section, and finally an interactive prompt.
+pub fn get_author_email(config: &Config) -> Option<(String, String)> {
+    if let Some(author) = investigate_author() {
+        return Some(author);
+    }
+    if env_value("INVESTIGATE_AUTHOR").is_some() {
+        warn(
+            Code::InvalidAuthor,
+            "Ignoring $INVESTIGATE_AUTHOR, which is not like \"NAME <EMAIL>\"",
+        );
+    }
+    let name = git_config_value("user.name")
+        .or_else(|| env_value("GIT_AUTHOR_NAME"))
+        .or_else(|| config.author.name.clone())
+        .or_else(|| prompt("Author name"));
+    let email = git_config_value("user.email")
+        .or_else(|| env_value("EMAIL"))
+        .or_else(|| env_value("GIT_AUTHOR_EMAIL"))
+        .or_else(|| config.author.email.clone())
+        .or_else(|| prompt("Author email"));
+    Some((name?, email?))
+}
+
+/// Get the author as "NAME <EMAIL>", or the placeholders if they can't be determined.
+pub fn author_string(config: &Config) -> String {
+    let (name, email) = get_author_email(config).unwrap_or_else(|| {
+        warn_unknown_author();
+        (PLACEHOLDER_NAME.to_owned(), PLACEHOLDER_EMAIL.to_owned())
+    });
+    format!("{} <{}>", name, email)
+}
@@ -0,0 +1,351 @@
+use clap::{Args, Subcommand};
+use serde_json::Value;
+
+use std::collections::BTreeSet;
+use std::fs::read_to_string;
+use std::path::PathBuf;
+use std::process::Command;
+
+use crate::report::walk_files;
+use crate::warning::{warn, Code};
+use crate::{exit_with_error, filesystem, find_project_root, require_network};
+
+/// Extensions of the files in paper/ searched for citations
+const MANUSCRIPT_EXTENSIONS: [&str; 4] = ["tex", "md", "qmd", "Rmd"];
+
+/// Prefixes of Quarto and pandoc-crossref references, which look like citations
+const CROSSREF_PREFIXES: [&str; 10] = [
+    "fig-", "tbl-", "sec-", "eq-", "lst-", "fig:", "tbl:", "sec:", "eq:", "lst:",
+];
+
+#[derive(Subcommand)]
+pub enum BibCommand {
+    /// Add BibTeX entries for DOIs, looked up with Crossref
+    Add(AddArgs),
+
+    /// Find cite keys used in paper/ which are missing from the bibliography
+    Check(CheckArgs),
+}
+
+#[derive(Args)]
+pub struct AddArgs {
+    /// DOIs to add, e.g. 10.1038/s41586-020-2649-2
+    #[clap(required = true)]
+    dois: Vec<String>,
+
+    /// Bibliography file, relative to the project root
+    #[clap(long, default_value = "paper/references.bib")]
+    file: PathBuf,
+}
+
+#[derive(Args)]
+pub struct CheckArgs {
+    /// Bibliography file, relative to the project root
+    #[clap(long, default_value = "paper/references.bib")]
+    file: PathBuf,
+}
+
+/// Get the keys and DOIs of the entries in the BibTeX file `bib`.
+fn parse_bib(bib: &str) -> (BTreeSet<String>, BTreeSet<String>) {
+    let mut keys = BTreeSet::new();
+    for entry in bib.split('@').skip(1) {
+        let Some((kind, rest)) = entry.split_once('{') else {
+            continue;
+        };
+        if ["comment", "string", "preamble"].contains(&kind.trim().to_lowercase().as_str()) {
+            continue;
+        }
+        if let Some((key, _)) = rest.split_once(',') {
+            keys.insert(key.trim().to_owned());
+        }
+    }
+    let dois = bib
+        .lines()
+        .filter_map(|line| {
+            let (field, value) = line.split_once('=')?;
+            (field.trim().eq_ignore_ascii_case("doi"))
+                .then(|| {
+                    value
+                        .trim()
+                        .trim_end_matches(',')
+                        .trim_matches(['{', '}', '"'])
+                })
+                .map(|doi| doi.to_lowercase())
+        })
+        .collect();
+    (keys, dois)
+}
+
+/// Look up the metadata of `doi` with the Crossref API.
+fn crossref(doi: &str) -> Value {
+    require_network("Looking up DOIs");
+    let output = Command::new("curl")
+        .args(["-sSfL", "-H", "Accept: application/json"])
+        .arg(format!("https://api.crossref.org/works/{}", doi))
+        .output()
+        .unwrap_or_else(|_| exit_with_error("Could not run curl"));
+    if !output.status.success() {
+        exit_with_error(&format!(
+            "Could not look up DOI {} with Crossref: {}",
+            doi,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))
+    }
+    let response: Value = serde_json::from_slice(&output.stdout)
+        .unwrap_or_else(|_| exit_with_error("Could not parse response from Crossref"));
+    response
+        .get("message")
+        .cloned()
+        .unwrap_or_else(|| exit_with_error("Unexpected response from Crossref"))
+}
+
+/// Get the first string of `field` in the Crossref metadata `work`, which may be a list.
+fn first_string(work: &Value, field: &str) -> Option<String> {
+    let value = work.get(field)?;
+    let string = value.as_str().or_else(|| value.get(0)?.as_str())?;
+    (!string.is_empty()).then(|| string.to_owned())
+}
+
+/// Format the Crossref metadata `work` as a BibTeX entry with a key not in `keys`.
+fn to_bibtex(work: &Value, doi: &str, keys: &BTreeSet<String>) -> (String, String) {
+    let kind = work.get("type").and_then(Value::as_str).unwrap_or_default();
+    let (entry_type, container) = match kind {
+        "journal-article" => ("article", "journal"),
+        "proceedings-article" => ("inproceedings", "booktitle"),
+        "book-chapter" => ("incollection", "booktitle"),
+        "book" | "monograph" => ("book", "series"),
+        _ => ("misc", "howpublished"),
+    };
+    let authors: Vec<&Value> = work
+        .get("author")
+        .and_then(Value::as_array)
+        .map(|authors| authors.iter().collect())
+        .unwrap_or_default();
+    let year = work
+        .get("issued")
+        .and_then(|issued| issued.get("date-parts")?.get(0)?.get(0)?.as_i64())
+        .map(|year| year.to_string());
+
+    // Keys are like "harris2020", with a letter appended if that's taken
+    let family = authors
+        .first()
+        .and_then(|author| {
+            author
+                .get("family")
+                .or_else(|| author.get("name"))?
+                .as_str()
+        })
+        .unwrap_or("anonymous");
+    let base: String = family
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .collect::<String>()
+        .to_lowercase()
+        + year.as_deref().unwrap_or("");
+    let key = std::iter::once(base.clone())
+        .chain(('a'..='z').map(|letter| format!("{}{}", base, letter)))
+        .find(|key| !keys.contains(key))
+        .unwrap_or_else(|| exit_with_error(&format!("Too many entries with key {}", base)));
+
+    let author_list: Vec<String> = authors
+        .iter()
+        .filter_map(|author| {
+            match (
+                author.get("family").and_then(Value::as_str),
+                author.get("given").and_then(Value::as_str),
+            ) {
+                (Some(family), Some(given)) => Some(format!("{}, {}", family, given)),
+                (Some(family), None) => Some(family.to_owned()),
+                _ => Some(format!("{{{}}}", author.get("name")?.as_str()?)),
+            }
+        })
+        .collect();
+    let mut fields: Vec<(&str, String)> = Vec::new();
+    if !author_list.is_empty() {
+        fields.push(("author", author_list.join(" and ")));
+    }
+    if let Some(title) = first_string(work, "title") {
+        // Double braces keep the capitalization of the title
+        fields.push(("title", format!("{{{}}}", title)));
+    }
+    if let Some(container_title) = first_string(work, "container-title") {
+        fields.push((container, container_title));
+    }
+    for (bib_field, crossref_field) in [
+        ("volume", "volume"),
+        ("number", "issue"),
+        ("pages", "page"),
+        ("publisher", "publisher"),
+    ] {
+        if let Some(value) = first_string(work, crossref_field) {
+            // BibTeX separates page ranges by an en dash
+            let value = match bib_field {
+                "pages" => value.replace('-', "--"),
+                _ => value,
+            };
+            fields.push((bib_field, value));
+        }
+    }
+    if let Some(year) = year {
+        fields.push(("year", year));
+    }
+    fields.push(("doi", doi.to_owned()));
+    let body: Vec<String> = fields
+        .iter()
+        .map(|(field, value)| format!("  {} = {{{}}}", field, value))
+        .collect();
+    let entry = format!("@{}{{{},\n{}\n}}\n", entry_type, key, body.join(",\n"));
+    (key, entry)
+}
+
+/// Remove the resolver prefix from `doi`, e.g. "https://doi.org/".
+fn normalize_doi(doi: &str) -> String {
+    let doi = doi.trim();
+    [
+        "https://doi.org/",
+        "http://doi.org/",
+        "https://dx.doi.org/",
+        "doi:",
+    ]
+    .iter()
+    .find_map(|prefix| doi.strip_prefix(prefix))
+    .unwrap_or(doi)
+    .to_owned()
+}
+
+fn add(args: AddArgs) {
+    let root = find_project_root();
+    let path = root.join(&args.file);
+    let mut bib = read_to_string(&path).unwrap_or_default();
+    let (mut keys, dois) = parse_bib(&bib);
+    for doi in &args.dois {
+        let doi = normalize_doi(doi);
+        if dois.contains(&doi.to_lowercase()) {
+            warn(
+                Code::Skipped,
+                &format!("DOI {} is already in {:?}, skipping", doi, args.file),
+            );
+            continue;
+        }
+        let work = crossref(&doi);
+        let (key, entry) = to_bibtex(&work, &doi, &keys);
+        if !bib.is_empty() && !bib.ends_with("\n\n") {
+            bib.push_str(if bib.ends_with('\n') { "\n" } else { "\n\n" });
+        }
+        bib.push_str(&entry);
+        println!("Added {} as {}", doi, key);
+        keys.insert(key);
+    }
+    if let Some(parent) = path.parent() {
+        filesystem::create_dir_all(parent)
+            .unwrap_or_else(|_| panic!("Error when creating directory {:?}", parent));
+    }
+    filesystem::write(&path, bib).unwrap_or_else(|_| panic!("Error when writing file {:?}", path));
+}
+
+/// Get the keys of LaTeX citations like `\citep[p. 4]{key1,key2}` in `line`.
+fn latex_citations(line: &str) -> Vec<String> {
+    let mut keys = Vec::new();
+    let mut rest = line;
+    while let Some(start) = rest.find('\\') {
+        rest = &rest[start + 1..];
+        let command: String = rest.chars().take_while(char::is_ascii_alphabetic).collect();
+        if !command.to_lowercase().contains("cite") {
+            continue;
+        }
+        let mut after = rest[command.len()..].trim_start_matches('*');
+        // Skip optional arguments like [p. 4]
+        while let Some(optional) = after.strip_prefix('[') {
+            after = optional.split_once(']').map(|(_, a)| a).unwrap_or("");
+        }
+        if let Some((list, _)) = after.strip_prefix('{').and_then(|a| a.split_once('}')) {
+            keys.extend(
+                list.split(',')
+                    .map(str::trim)
+                    .filter(|key| !key.is_empty() && *key != "*")
+                    .map(str::to_owned),
+            );
+        }
+    }
+    keys
+}
+
+/// Get the keys of pandoc citations like `[@key1; @key2]` in `line`.
+fn pandoc_citations(line: &str) -> Vec<String> {
+    let mut keys = Vec::new();
+    for (i, _) in line.match_indices('@') {
+        // An email address or similar, not a citation
+        if line[..i]
+            .chars()
+            .next_back()
+            .is_some_and(|c| c.is_alphanumeric())
+        {
+            continue;
+        }
+        let key: String = line[i + 1..]
+            .chars()
+            .take_while(|c| c.is_alphanumeric() || "_:.#$%&-+?<>~/".contains(*c))
+            .collect();
+        let key = key.trim_end_matches(['.', ':', '?', '/']);
+        if !key.is_empty()
+            && key.starts_with(|c: char| c.is_alphanumeric() || c == '_')
+            && !CROSSREF_PREFIXES
+                .iter()
+                .any(|prefix| key.starts_with(prefix))
+        {
+            keys.push(key.to_owned());
+        }
+    }
+    keys
+}
+
+fn check(args: CheckArgs) {
+    let root = find_project_root();
+    let path = root.join(&args.file);
+    let bib = read_to_string(&path)
+        .unwrap_or_else(|_| exit_with_error(&format!("Could not read {:?}", path)));
+    let (keys, _) = parse_bib(&bib);
+    let mut n_missing = 0;
+    for file in walk_files(&root.join("paper")) {
+        let extension = file
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or_default();
+        if !MANUSCRIPT_EXTENSIONS.contains(&extension) {
+            continue;
+        }
+        let Ok(content) = read_to_string(&file) else {
+            continue;
+        };
+        let relative = file.strip_prefix(&root).unwrap_or(&file);
+        for (i, line) in content.lines().enumerate() {
+            // Skip LaTeX comments
+            let line = match extension {
+                "tex" => line.split('%').next().unwrap_or_default(),
+                _ => line,
+            };
+            let cited = match extension {
+                "tex" => latex_citations(line),
+                _ => pandoc_citations(line),
+            };
+            for key in cited.into_iter().filter(|key| !keys.contains(key)) {
+                println!("{}:{}: {}", relative.display(), i + 1, key);
+                n_missing += 1;
+            }
+        }
+    }
+    if n_missing > 0 {
+        exit_with_error(&format!(
+            "{} citations are missing from {:?}",
+            n_missing, args.file
+        ))
+    }
+    println!("All citations are in {:?}", args.file);
+}
+
+pub fn run(command: BibCommand) {
+    match command {
+        BibCommand::Add(args) => add(args),
+        BibCommand::Check(args) => check(args),
+    }
+}
@@ -0,0 +1,116 @@
+use chrono::Local;
+use clap::{ArgEnum, Args};
+
+use std::fs::read_to_string;
+use std::path::Path;
+
+use crate::git::commit_and_tag;
+use crate::metadata::{Metadata, METADATA_FILE};
+use crate::{exit_with_error, filesystem, find_project_root};
+
+const CHANGELOG_FILE: &str = "CHANGELOG.md";
+
+#[derive(Copy, Clone, PartialEq, Eq, ArgEnum)]
+enum Part {
+    Major,
+    Minor,
+    Patch,
+}
+
+#[derive(Args)]
+pub struct BumpArgs {
+    /// Which part of the version to increment
+    #[clap(arg_enum, value_parser)]
+    part: Part,
+
+    /// Description of the milestone, added to the changelog
+    #[clap(short, long)]
+    message: String,
+
+    /// Commit the changed files and tag the commit with the new version
+    #[clap(long)]
+    tag: bool,
+}
+
+fn bumped_version(version: &str, part: Part) -> String {
+    let numbers: Vec<u64> = version
+        .split('.')
+        .map(|n| n.parse().ok())
+        .collect::<Option<_>>()
+        .filter(|v: &Vec<u64>| v.len() == 3)
+        .unwrap_or_else(|| {
+            exit_with_error(&format!(
+                "Version \"{}\" in {} is not of the form MAJOR.MINOR.PATCH",
+                version, METADATA_FILE
+            ))
+        });
+    let (major, minor, patch) = (numbers[0], numbers[1], numbers[2]);
+    match part {
+        Part::Major => format!("{}.0.0", major + 1),
+        Part::Minor => format!("{}.{}.0", major, minor + 1),
+        Part::Patch => format!("{}.{}.{}", major, minor, patch + 1),
+    }
+}
+
+/// Add an entry above the existing entries of the changelog.
+fn add_changelog_entry(path: &Path, version: &str, message: &str) {
+    let date = Local::today().format("%Y-%m-%d").to_string();
+    let entry = format!("## v{} - {}\n{}\n\n", version, date, message.trim());
+    let changelog = read_to_string(path).unwrap_or_else(|_| "# Changelog\n\n".to_owned());
+    let content = match changelog.find("\n## ") {
+        Some(i) => format!("{}{}{}", &changelog[..=i], entry, &changelog[i + 1..]),
+        None => format!("{}\n\n{}", changelog.trim_end(), entry),
+    };
+    filesystem::write(path, content)
+        .unwrap_or_else(|_| panic!("Error when writing file {:?}", path))
+}
+
+/// Replace the first top-level `version = "..."` line of a TOML file, if the file exists.
+fn update_toml_version(path: &Path, version: &str) -> bool {
+    let content = match read_to_string(path) {
+        Ok(content) => content,
+        Err(_) => return false,
+    };
+    let mut found = false;
+    let mut lines: Vec<String> = Vec::new();
+    for line in content.lines() {
+        if !found && line.starts_with("version") && line.contains('=') {
+            found = true;
+            lines.push(format!("version = \"{}\"", version));
+        } else {
+            lines.push(line.to_owned());
+        }
+    }
+    if found {
+        filesystem::write(path, lines.join("\n") + "\n")
+            .unwrap_or_else(|_| panic!("Error when writing file {:?}", path));
+    }
+    found
+}
+
+pub fn run(args: BumpArgs) {
+    let root = find_project_root();
+    let mut metadata = Metadata::load(&root);
+    let old_version = metadata.version.clone();
+    let version = bumped_version(&old_version, args.part);
+    metadata.version = version.clone();
+    metadata.save(&root);
+    add_changelog_entry(&root.join(CHANGELOG_FILE), &version, &args.message);
+
+    let mut changed = vec![METADATA_FILE, CHANGELOG_FILE];
+    for file in ["pyproject.toml", "Project.toml"] {
+        if update_toml_version(&root.join(file), &version) {
+            changed.push(file);
+        }
+    }
+    if args.tag {
+        commit_and_tag(
+            &root,
+            &changed,
+            &format!("Version {}\n\n{}", version, args.message),
+            &format!("v{}", version),
+            &args.message,
+        );
+    }
+    println!("Bumped version from {} to {}", old_version, version);
+}
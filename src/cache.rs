@@ -0,0 +1,381 @@
+use chrono::Local;
+use clap::{Args, Subcommand};
+use git2::Repository;
+use serde::{Deserialize, Serialize};
+
+use std::collections::BTreeMap;
+use std::fs::read_to_string;
+use std::path::{Path, PathBuf};
+
+use crate::data::{checksum_and_size, reflink, sha256};
+use crate::git::is_clean;
+use crate::lock::{lock, write_atomic};
+use crate::report::walk_files;
+use crate::trash::trash;
+use crate::warning::{warn, Code};
+use crate::{exit_with_error, find_project_root};
+
+/// Path of the index of cached artifacts, relative to the project root
+pub const CACHE_INDEX: &str = "cache/CACHE.toml";
+
+/// Code an artifact is taken to depend on, if none is given
+const DEFAULT_CODE: &str = "src";
+
+#[derive(Subcommand)]
+pub enum CacheCommand {
+    /// Make identical files in cache/ share their storage with copy-on-write clones
+    Dedup,
+
+    /// Record which code and inputs made files in cache/, to tell when they're stale
+    Record(RecordArgs),
+
+    /// Check that files in cache/ are up to date. Exits with an error if any are stale
+    Check(CheckArgs),
+
+    /// Forget files in cache/ which no longer exist, and with --stale, remove stale files
+    Gc(GcArgs),
+}
+
+#[derive(Args)]
+pub struct RecordArgs {
+    /// Files or directories in cache/ to record
+    #[clap(required = true)]
+    files: Vec<PathBuf>,
+
+    /// Script or directory of code which made the files (default: src/). Can be given
+    /// multiple times
+    #[clap(long, value_name = "PATH", multiple_occurrences = true)]
+    code: Vec<PathBuf>,
+
+    /// Input the files were made from. Can be given multiple times
+    #[clap(long, value_name = "PATH", multiple_occurrences = true)]
+    input: Vec<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct CheckArgs {
+    /// Files or directories in cache/ to check
+    #[clap(required = true)]
+    files: Vec<PathBuf>,
+
+    /// Print nothing, only exit with an error if any file is stale
+    #[clap(short, long)]
+    quiet: bool,
+}
+
+#[derive(Args)]
+pub struct GcArgs {
+    /// Move the files whose code or inputs changed since they were made to the trash
+    #[clap(long)]
+    stale: bool,
+
+    /// Only list what would be removed
+    #[clap(long)]
+    dry_run: bool,
+}
+
+/// The record of how a file in cache/ was made.
+#[derive(Serialize, Deserialize)]
+pub struct CacheEntry {
+    /// Path relative to the project root
+    pub path: String,
+    /// Date and time it was recorded in RFC 3339 format
+    pub recorded: String,
+    /// The git commit HEAD was at, and whether there were uncommitted changes
+    pub commit: Option<String>,
+    pub dirty: bool,
+    /// Checksums of the code which made it, by path relative to the project root. For
+    /// directories, the checksum of the checksums of its files, as in the data manifest
+    pub code: BTreeMap<String, String>,
+    /// Checksums of its inputs, by path relative to the project root
+    pub inputs: BTreeMap<String, String>,
+}
+
+/// The index of the files in cache/, with how each was made.
+#[derive(Default, Serialize, Deserialize)]
+pub struct CacheIndex {
+    #[serde(default)]
+    pub entry: Vec<CacheEntry>,
+}
+
+impl CacheIndex {
+    /// Load the index of the project at `root`. A missing index counts as empty.
+    /// To update it, lock it with `lock::lock` first, so concurrent updates aren't lost.
+    pub fn load(root: &Path) -> CacheIndex {
+        let path = root.join(CACHE_INDEX);
+        match read_to_string(&path) {
+            Err(_) => CacheIndex::default(),
+            Ok(content) => toml::from_str(&content)
+                .unwrap_or_else(|e| exit_with_error(&format!("Could not parse {:?}: {}", path, e))),
+        }
+    }
+
+    pub fn save(&self, root: &Path) {
+        let path = root.join(CACHE_INDEX);
+        let content = toml::to_string_pretty(self).expect("Error when serializing cache index");
+        write_atomic(&path, &content)
+            .unwrap_or_else(|_| panic!("Error when writing file {:?}", path))
+    }
+
+    /// Add `entry`, replacing any existing entry with the same path.
+    fn insert(&mut self, entry: CacheEntry) {
+        self.entry.retain(|e| e.path != entry.path);
+        self.entry.push(entry);
+        self.entry.sort_by(|a, b| a.path.cmp(&b.path));
+    }
+}
+
+/// Get the checksums of `paths`, relative to `root`.
+fn checksums(root: &Path, paths: &[String]) -> BTreeMap<String, String> {
+    paths
+        .iter()
+        .map(|path| {
+            let (checksum, _) = checksum_and_size(&root.join(path))
+                .unwrap_or_else(|_| exit_with_error(&format!("Could not read {}", path)));
+            (path.clone(), checksum)
+        })
+        .collect()
+}
+
+/// Record that the files `outputs` in cache/ were made by `code` from `inputs`, all given
+/// relative to `root`. If no code is given, the outputs are taken to depend on all of src/.
+pub fn record(root: &Path, outputs: &[String], code: &[String], inputs: &[String]) {
+    let (commit, dirty) = match Repository::discover(root) {
+        Ok(repo) => (
+            repo.head()
+                .ok()
+                .and_then(|head| Some(head.target()?.to_string())),
+            !is_clean(&repo),
+        ),
+        Err(_) => (None, false),
+    };
+    let code = if code.is_empty() && root.join(DEFAULT_CODE).exists() {
+        checksums(root, &[DEFAULT_CODE.to_owned()])
+    } else {
+        checksums(root, code)
+    };
+    let inputs = checksums(root, inputs);
+    let recorded = Local::now().to_rfc3339();
+    let _lock = lock(&root.join(CACHE_INDEX));
+    let mut index = CacheIndex::load(root);
+    for output in outputs {
+        index.insert(CacheEntry {
+            path: output.clone(),
+            recorded: recorded.clone(),
+            commit: commit.clone(),
+            dirty,
+            code: code.clone(),
+            inputs: inputs.clone(),
+        });
+    }
+    index.save(root);
+}
+
+/// Get why the file of `entry` is stale: Which of its code and inputs changed or were removed
+/// since it was made. Empty if it's up to date.
+fn changes(root: &Path, entry: &CacheEntry) -> Vec<String> {
+    entry
+        .code
+        .iter()
+        .chain(&entry.inputs)
+        .filter_map(
+            |(path, recorded)| match checksum_and_size(&root.join(path)) {
+                Err(_) => Some(format!("{} was removed", path)),
+                Ok((checksum, _)) if checksum != *recorded => Some(format!("{} changed", path)),
+                Ok(_) => None,
+            },
+        )
+        .collect()
+}
+
+/// Get the recorded files in cache/ of the project at `root` whose code or inputs changed.
+pub fn stale_files(root: &Path) -> Vec<String> {
+    CacheIndex::load(root)
+        .entry
+        .iter()
+        .filter(|entry| root.join(&entry.path).exists() && !changes(root, entry).is_empty())
+        .map(|entry| entry.path.clone())
+        .collect()
+}
+
+/// Get `path`, given on the command line, relative to `root`. It must be in cache/.
+fn cache_path(root: &Path, path: &Path) -> String {
+    let absolute = std::path::absolute(path).unwrap_or_else(|_| path.to_owned());
+    let relative = absolute
+        .strip_prefix(root)
+        .map(Path::to_owned)
+        .unwrap_or_else(|_| path.to_owned());
+    if !relative.starts_with("cache") || relative == Path::new(CACHE_INDEX) {
+        exit_with_error(&format!("{:?} is not a file in cache/", path))
+    }
+    relative.to_string_lossy().into_owned()
+}
+
+/// Get `paths`, given on the command line, relative to `root`.
+fn project_paths(root: &Path, paths: &[PathBuf]) -> Vec<String> {
+    paths
+        .iter()
+        .map(|path| {
+            let absolute = std::path::absolute(path).unwrap_or_else(|_| path.clone());
+            match absolute.strip_prefix(root) {
+                Ok(relative) => relative.to_string_lossy().into_owned(),
+                Err(_) => exit_with_error(&format!("{:?} is not in the project", path)),
+            }
+        })
+        .collect()
+}
+
+fn record_command(args: RecordArgs) {
+    let root = find_project_root();
+    let files: Vec<String> = args.files.iter().map(|f| cache_path(&root, f)).collect();
+    for file in &files {
+        if !root.join(file).exists() {
+            exit_with_error(&format!("{} does not exist", file))
+        }
+    }
+    record(
+        &root,
+        &files,
+        &project_paths(&root, &args.code),
+        &project_paths(&root, &args.input),
+    );
+    println!("Recorded {} files in {}", files.len(), CACHE_INDEX);
+}
+
+fn check(args: CheckArgs) {
+    let root = find_project_root();
+    let index = CacheIndex::load(&root);
+    let mut n_stale = 0;
+    for file in args.files.iter().map(|f| cache_path(&root, f)) {
+        let problems = match index.entry.iter().find(|entry| entry.path == file) {
+            None => vec![format!("it is not recorded in {}", CACHE_INDEX)],
+            Some(_) if !root.join(&file).exists() => vec!["it does not exist".to_owned()],
+            Some(entry) => changes(&root, entry),
+        };
+        if problems.is_empty() {
+            if !args.quiet {
+                println!("{} is up to date", file);
+            }
+        } else {
+            n_stale += 1;
+            if !args.quiet {
+                println!("{} is stale: {}", file, problems.join(", "));
+            }
+        }
+    }
+    if n_stale > 0 {
+        std::process::exit(1)
+    }
+}
+
+fn gc(args: GcArgs) {
+    let root = find_project_root();
+    let _lock = lock(&root.join(CACHE_INDEX));
+    let mut index = CacheIndex::load(&root);
+    if index.entry.is_empty() {
+        println!("No files recorded in {}", CACHE_INDEX);
+        return;
+    }
+    let mut missing = Vec::new();
+    let mut stale = Vec::new();
+    for entry in &index.entry {
+        if !root.join(&entry.path).exists() {
+            missing.push(entry.path.clone());
+        } else if args.stale {
+            let changes = changes(&root, entry);
+            if !changes.is_empty() {
+                println!("{} is stale: {}", entry.path, changes.join(", "));
+                stale.push(entry.path.clone());
+            }
+        }
+    }
+    if args.dry_run {
+        println!(
+            "Would forget {} missing and remove {} stale files",
+            missing.len(),
+            stale.len()
+        );
+        return;
+    }
+    if !stale.is_empty() {
+        let paths: Vec<PathBuf> = stale.iter().map(|path| root.join(path)).collect();
+        let id = trash(&root, &paths);
+        println!(
+            "Moved {} stale files to the trash. Restore them with `investigate trash restore {}`",
+            stale.len(),
+            id
+        );
+    }
+    index
+        .entry
+        .retain(|entry| !missing.contains(&entry.path) && !stale.contains(&entry.path));
+    index.save(&root);
+    println!("Forgot {} files which no longer exist", missing.len());
+}
+
+/// Replace every file in cache/ that is identical to an earlier file by a reflink clone of it,
+/// so they share storage. Only works on filesystems with reflinks, like btrfs, XFS and APFS.
+fn dedup() {
+    let root = find_project_root();
+    let cache = root.join("cache");
+    if !cache.is_dir() {
+        exit_with_error("Project has no cache/ directory")
+    }
+    // Only files of the same size can be identical, so only those need to be hashed
+    let mut by_size: BTreeMap<u64, Vec<PathBuf>> = BTreeMap::new();
+    for file in walk_files(&cache) {
+        let metadata = match file.symlink_metadata() {
+            Ok(metadata) if metadata.is_file() && metadata.len() > 0 => metadata,
+            _ => continue,
+        };
+        by_size.entry(metadata.len()).or_default().push(file);
+    }
+    let mut n_files = 0;
+    let mut n_bytes = 0;
+    for (size, files) in by_size.into_iter().filter(|(_, files)| files.len() > 1) {
+        let mut by_checksum: BTreeMap<String, Vec<PathBuf>> = BTreeMap::new();
+        for file in files {
+            let checksum = sha256(&file)
+                .unwrap_or_else(|_| exit_with_error(&format!("Could not read {:?}", file)));
+            by_checksum.entry(checksum).or_default().push(file);
+        }
+        for files in by_checksum.values() {
+            let (original, duplicates) = files.split_first().expect("Error when grouping files");
+            for duplicate in duplicates {
+                // Clone next to the duplicate, then replace it, so it's never missing
+                let mut temporary = duplicate.clone().into_os_string();
+                temporary.push(".investigate-dedup");
+                let temporary = PathBuf::from(temporary);
+                if !reflink(original, &temporary) {
+                    if n_files == 0 {
+                        exit_with_error(
+                            "The filesystem of cache/ does not support reflinks, \
+                            so no files were deduplicated",
+                        )
+                    }
+                    warn(Code::NotCopied, &format!("Could not clone {:?}", original));
+                    continue;
+                }
+                std::fs::rename(&temporary, duplicate)
+                    .unwrap_or_else(|_| panic!("Error when replacing file {:?}", duplicate));
+                n_files += 1;
+                n_bytes += size;
+            }
+        }
+    }
+    // Files cloned by an earlier run are counted again, since sharing can't be detected
+    println!(
+        "Deduplicated {} files in cache/, sharing up to {:.1} MB",
+        n_files,
+        n_bytes as f64 / 1e6
+    );
+}
+
+pub fn run(command: CacheCommand) {
+    match command {
+        CacheCommand::Dedup => dedup(),
+        CacheCommand::Record(args) => record_command(args),
+        CacheCommand::Check(args) => check(args),
+        CacheCommand::Gc(args) => gc(args),
+    }
+}
@@ -0,0 +1,283 @@
+use chrono::Local;
+use clap::Args;
+use git2::Repository;
+use uuid::Uuid;
+
+use std::path::{Path, PathBuf};
+
+use crate::author::get_author_email;
+use crate::config::Config;
+use crate::contributor::write_authors;
+use crate::decisions::DECISIONS_FILE;
+use crate::figure::FIGURES_FILE;
+use crate::finalize::FINALIZED_FILE;
+use crate::git::relative_to_workdir;
+use crate::metadata::{Contributor, Metadata, METADATA_FILE};
+use crate::new::{write, Existing, Language, IGNORED_DIRECTORIES};
+use crate::permissions::Permissions;
+use crate::rename::{rename_files, replace_toml_value};
+use crate::results::SNAPSHOT_FILE;
+use crate::run::RUNS_FILE;
+use crate::storage::check_space;
+use crate::templates::Templates;
+use crate::todo::TODO_FILE;
+use crate::trash::TRASH_DIR;
+use crate::warning::{warn, Code};
+use crate::{exit_with_error, registry, shell, tools};
+
+/// Files recording the history of a project, which a project derived from it starts without
+const HISTORY_FILES: [&str; 8] = [
+    METADATA_FILE,
+    "CHANGELOG.md",
+    DECISIONS_FILE,
+    RUNS_FILE,
+    FINALIZED_FILE,
+    SNAPSHOT_FILE,
+    FIGURES_FILE,
+    TODO_FILE,
+];
+
+#[derive(Args)]
+pub struct CloneArgs {
+    /// Path of the project to derive the new one from
+    existing: PathBuf,
+
+    /// Path of the new project. Its name is the last component of the path
+    new: PathBuf,
+
+    /// Do not initialize a git repository
+    #[clap(long)]
+    no_git: bool,
+}
+
+/// Files to copy from the project at `root`.
+struct Source {
+    root: PathBuf,
+    /// The git repository of the project, used to leave out ignored files
+    repo: Option<Repository>,
+}
+
+impl Source {
+    /// Check if `relative`, a path in the project, should be copied. The contents of the data
+    /// directories are left out, except their READMEs, as are ignored files and the project's
+    /// history.
+    fn is_copied(&self, relative: &Path, is_dir: bool) -> bool {
+        let first = relative
+            .components()
+            .next()
+            .map(|c| c.as_os_str().to_string_lossy().into_owned())
+            .unwrap_or_default();
+        if first == ".git" || first == TRASH_DIR {
+            return false;
+        }
+        if IGNORED_DIRECTORIES.contains(&first.as_str()) && relative.components().count() > 1 {
+            return relative.components().count() == 2
+                && relative.file_name().is_some_and(|name| name == "README.md");
+        }
+        if HISTORY_FILES.iter().any(|file| relative == Path::new(file)) {
+            return false;
+        }
+        let Some(repo) = &self.repo else {
+            return true;
+        };
+        let Some(in_repo) = relative_to_workdir(repo, &self.root.join(relative)) else {
+            return true;
+        };
+        // Directories are matched by patterns like "site/", which need the trailing slash
+        let in_repo = if is_dir { in_repo.join("") } else { in_repo };
+        !repo.is_path_ignored(&in_repo).unwrap_or(false)
+    }
+
+    /// Copy the directory `relative` of the project to `destination`, returning the number of
+    /// files copied.
+    fn copy(&self, relative: &Path, destination: &Path) -> usize {
+        let mut n_copied = 0;
+        let mut entries: Vec<_> = self
+            .root
+            .join(relative)
+            .read_dir()
+            .into_iter()
+            .flatten()
+            .flatten()
+            .collect();
+        entries.sort_by_key(|entry| entry.file_name());
+        for entry in entries {
+            let relative = relative.join(entry.file_name());
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+            if !self.is_copied(&relative, file_type.is_dir()) {
+                continue;
+            }
+            let to = destination.join(&relative);
+            if file_type.is_dir() {
+                std::fs::create_dir_all(&to)
+                    .unwrap_or_else(|_| panic!("Error when creating directory {:?}", to));
+                n_copied += self.copy(&relative, destination);
+            } else if file_type.is_symlink() {
+                copy_symlink(&entry.path(), &to);
+            } else {
+                std::fs::copy(entry.path(), &to)
+                    .unwrap_or_else(|_| panic!("Error when copying {:?}", entry.path()));
+                n_copied += 1;
+            }
+        }
+        n_copied
+    }
+}
+
+#[cfg(unix)]
+fn copy_symlink(from: &Path, to: &Path) {
+    let target =
+        std::fs::read_link(from).unwrap_or_else(|_| panic!("Error when reading link {:?}", from));
+    std::os::unix::fs::symlink(&target, to)
+        .unwrap_or_else(|_| panic!("Error when creating link {:?}", to));
+}
+
+#[cfg(not(unix))]
+fn copy_symlink(from: &Path, _to: &Path) {
+    warn(
+        Code::NotCopied,
+        &format!("Not copying the symbolic link {:?}", from),
+    );
+}
+
+/// Get the metadata of a project derived from the one with `metadata`: the same settings, but
+/// with its own name, UUID and authors, and none of the history.
+fn derived_metadata(metadata: &Metadata, name: &str, config: &Config) -> Metadata {
+    let today = Local::today().format("%Y-%m-%d").to_string();
+    let author_email = get_author_email(config);
+    Metadata {
+        name: name.to_owned(),
+        uuid: Uuid::new_v4().hyphenated().to_string(),
+        created: today.clone(),
+        investigate_version: env!("CARGO_PKG_VERSION").to_owned(),
+        version: "0.1.0".to_owned(),
+        authors: author_email
+            .iter()
+            .map(|(name, mail)| format!("{} <{}>", name, mail))
+            .collect(),
+        environment: metadata.environment.as_ref().map(|_| name.to_owned()),
+        // The new project is for different data
+        data_remote: None,
+        description: None,
+        doi: None,
+        osf_node: None,
+        contributors: author_email
+            .iter()
+            .map(|(name, mail)| Contributor {
+                name: name.clone(),
+                email: Some(mail.clone()),
+                role: "lead".to_owned(),
+                since: today.clone(),
+                until: None,
+            })
+            .collect(),
+        reopened: Vec::new(),
+        sessions: Vec::new(),
+        dates: Vec::new(),
+        tools: tools::versions(),
+        ..metadata.clone()
+    }
+}
+
+/// Get the name of a project at `path`, the last component of the path.
+pub fn name_from_path(path: &Path) -> String {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or_else(|| {
+            exit_with_error(&format!(
+                "Project name {:?} is not a normal UTF-8 string",
+                path
+            ))
+        })
+        .to_owned()
+}
+
+/// Copy the project at `source_root` with `metadata` to `destination`, without its data and
+/// history, and rename the copy to `name`. Returns the metadata of the copy.
+pub fn derive(
+    source_root: &Path,
+    metadata: &Metadata,
+    destination: &Path,
+    name: &str,
+    config: &Config,
+) -> Metadata {
+    std::fs::create_dir_all(destination)
+        .unwrap_or_else(|_| panic!("Error when creating directory {:?}", destination));
+    let source = Source {
+        repo: Repository::discover(source_root).ok(),
+        root: source_root.to_owned(),
+    };
+    let n_copied = source.copy(Path::new(""), destination);
+    for directory in &metadata.directories {
+        let path = destination.join(directory);
+        std::fs::create_dir_all(&path)
+            .unwrap_or_else(|_| panic!("Error when creating directory {:?}", path));
+    }
+
+    // The copied metadata has the old name, which the files are renamed from
+    metadata.save(destination);
+    if let Err(message) = rename_files(destination, metadata, name) {
+        exit_with_error(&format!("Could not rename the copied project: {}", message))
+    }
+    let new_metadata = derived_metadata(metadata, name, config);
+    new_metadata.save(destination);
+    write_authors(destination, &new_metadata, &[], Existing::Force);
+    if metadata.language == Some(Language::Julia) {
+        let project = destination.join("Project.toml");
+        if let Ok(content) = std::fs::read_to_string(&project) {
+            write(
+                &project,
+                &replace_toml_value(&content, "uuid", &new_metadata.uuid),
+                Existing::Force,
+            );
+        }
+    }
+    let templates = Templates::new(Vec::new(), config);
+    write(
+        &destination.join("CHANGELOG.md"),
+        &templates.render(
+            "changelog",
+            &[("date", &Local::today().format("%Y-%m-%d").to_string())],
+        ),
+        Existing::Force,
+    );
+    println!(
+        "Copied {} files from {:?}, without its data, results and history",
+        n_copied, source_root
+    );
+    new_metadata
+}
+
+pub fn run(args: CloneArgs) {
+    let metadata = Metadata::load(&args.existing);
+    let destination = args.new;
+    let name = name_from_path(&destination);
+    if destination.exists() {
+        exit_with_error(&format!("Path {:?} already exists", destination))
+    }
+    let config = Config::load();
+    let permissions = Permissions::prepare(&config.permissions);
+    check_space(&destination, &config.storage);
+    let new_metadata = derive(&args.existing, &metadata, &destination, &name, &config);
+    if !args.no_git {
+        match Repository::init(&destination) {
+            Ok(_) => println!("Initialized git repository"),
+            Err(e) => warn(
+                Code::GitFailed,
+                &format!(
+                    "Could not initialize git repository: {}. Run `git init` later",
+                    e.message()
+                ),
+            ),
+        }
+    }
+    permissions.apply(&destination);
+    registry::register(&destination);
+    println!("Created project \"{}\" from \"{}\"", name, metadata.name);
+    if new_metadata.environment.is_some() || metadata.language == Some(Language::Julia) {
+        println!("Create its environment with `investigate env recreate`");
+    }
+    shell::request_cd(&destination);
+}
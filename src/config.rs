@@ -0,0 +1,484 @@
+use clap::{Args, Subcommand};
+use serde::Deserialize;
+use toml_edit::DocumentMut;
+
+use std::collections::BTreeMap;
+use std::fs::read_to_string;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::directories::DirectorySpec;
+use crate::metadata::METADATA_FILE;
+use crate::new::{Ci, Language};
+use crate::schema::{unknown_config_keys, validate, validate_content, FileKind};
+use crate::warning::{warn, Code};
+use crate::{exit_with_error, find_project_root_opt};
+
+/// Get the directory with the user's configuration and templates.
+pub fn config_dir() -> Option<PathBuf> {
+    let config_home = match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+    };
+    Some(config_home.join("investigate"))
+}
+
+/// Get the directory with the data investigate keeps about the user's projects.
+pub fn data_dir() -> Option<PathBuf> {
+    let data_home = match std::env::var_os("XDG_DATA_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var_os("HOME")?)
+            .join(".local")
+            .join("share"),
+    };
+    Some(data_home.join("investigate"))
+}
+
+/// Get the directory with the site-wide configuration and templates, managed by administrators
+/// of shared servers. It's `/etc/investigate`, unless `$INVESTIGATE_SITE_DIR` is set.
+pub fn site_dir() -> PathBuf {
+    match std::env::var_os("INVESTIGATE_SITE_DIR") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from("/etc/investigate"),
+    }
+}
+
+/// Settings for creating a new project. Every setting is optional,
+/// such that a profile only needs to specify what differs from the defaults.
+#[derive(Deserialize, Default, Clone)]
+pub struct Profile {
+    pub language: Option<Language>,
+    /// Directories to create instead of the default ones
+    pub directories: Option<Vec<DirectorySpec>>,
+    /// Directory with templates that take precedence over the user and built-in templates
+    pub templates: Option<PathBuf>,
+    pub scripts: Option<bool>,
+    pub docs: Option<bool>,
+    pub params: Option<bool>,
+    pub badges: Option<bool>,
+    pub index: Option<bool>,
+    pub dir_readmes: Option<bool>,
+    pub lfs: Option<bool>,
+    pub annex: Option<bool>,
+    pub protect_raw: Option<bool>,
+    /// Special remote for git-annex, as "NAME type=TYPE [KEY=VALUE ...]"
+    pub annex_remote: Option<String>,
+    /// Conda channels, in order of priority
+    pub channels: Option<Vec<String>>,
+    /// Packages to install in the project's environment
+    pub packages: Option<Vec<String>>,
+    pub ci: Option<Ci>,
+    pub assignment: Option<bool>,
+    pub benchmark: Option<bool>,
+    pub modulefile: Option<bool>,
+}
+
+impl Profile {
+    /// Get the settings of `self`, with settings from `other` taking precedence.
+    pub fn overlaid(&self, other: &Profile) -> Profile {
+        Profile {
+            language: other.language.or(self.language),
+            directories: other.directories.clone().or(self.directories.clone()),
+            templates: other.templates.clone().or(self.templates.clone()),
+            scripts: other.scripts.or(self.scripts),
+            docs: other.docs.or(self.docs),
+            params: other.params.or(self.params),
+            badges: other.badges.or(self.badges),
+            index: other.index.or(self.index),
+            dir_readmes: other.dir_readmes.or(self.dir_readmes),
+            lfs: other.lfs.or(self.lfs),
+            annex: other.annex.or(self.annex),
+            protect_raw: other.protect_raw.or(self.protect_raw),
+            annex_remote: other.annex_remote.clone().or(self.annex_remote.clone()),
+            channels: other.channels.clone().or(self.channels.clone()),
+            packages: other.packages.clone().or(self.packages.clone()),
+            ci: other.ci.or(self.ci),
+            assignment: other.assignment.or(self.assignment),
+            benchmark: other.benchmark.or(self.benchmark),
+            modulefile: other.modulefile.or(self.modulefile),
+        }
+    }
+}
+
+/// Fallback author information, used when it's not set in git config.
+#[derive(Deserialize, Default, Clone)]
+pub struct AuthorConfig {
+    pub name: Option<String>,
+    pub email: Option<String>,
+}
+
+/// How to notify the user when a run finishes, see `investigate run --notify`.
+#[derive(Deserialize, Default, Clone)]
+pub struct NotifyConfig {
+    /// Webhook URL, e.g. of Slack or Mattermost, which is sent the message as JSON
+    pub webhook: Option<String>,
+    /// Shell command which is given the message on standard input, e.g. `mail -s "Run" me@x.org`
+    pub command: Option<String>,
+}
+
+/// Which timestamped results directories to keep, see `investigate results prune`.
+#[derive(Deserialize, Default, Clone)]
+pub struct RetentionConfig {
+    /// Number of the newest timestamped directories in results/ to keep
+    pub keep_last: Option<usize>,
+    /// Keep directories referenced by registered figures, the paper or the frozen snapshot
+    pub keep_referenced: Option<bool>,
+}
+
+/// Ownership and permissions of new projects on shared storage, see `investigate new`.
+#[derive(Deserialize, Default, Clone)]
+pub struct PermissionsConfig {
+    /// Unix group which should own the project's files, by name or ID
+    pub group: Option<String>,
+    /// Set the setgid bit on directories, so files created in them later belong to their group
+    pub setgid: Option<bool>,
+    /// Octal umask to create files with, e.g. "002" for group-writable files
+    pub umask: Option<String>,
+}
+
+/// Where projects may be created, see `investigate new`.
+#[derive(Deserialize, Default, Clone)]
+pub struct StorageConfig {
+    /// Free space below which creating a project gives a warning, like "50 GB"
+    pub min_free: Option<String>,
+    /// Shell command printing the bytes left of the user's quota at the path `$1`, like "120G"
+    pub quota_command: Option<String>,
+    /// Other places to create projects, suggested when there is too little space
+    pub locations: Option<Vec<PathBuf>>,
+}
+
+/// Warnings to suppress, see `investigate warnings`.
+#[derive(Deserialize, Default, Clone)]
+pub struct WarningsConfig {
+    /// Codes of the warnings not to print, like "W017"
+    pub allow: Option<Vec<String>>,
+}
+
+/// The user's configuration file.
+#[derive(Deserialize, Default)]
+pub struct Config {
+    #[serde(default)]
+    pub author: AuthorConfig,
+    /// Settings used when no profile is given
+    #[serde(default)]
+    pub defaults: Profile,
+    /// Named profiles, which take precedence over the defaults
+    #[serde(default)]
+    pub profile: BTreeMap<String, Profile>,
+    /// Text blocks such as funding acknowledgements, available in templates as `{blocks.NAME}`
+    #[serde(default)]
+    pub blocks: BTreeMap<String, String>,
+    #[serde(default)]
+    pub notify: NotifyConfig,
+    #[serde(default)]
+    pub retention: RetentionConfig,
+    #[serde(default)]
+    pub permissions: PermissionsConfig,
+    #[serde(default)]
+    pub storage: StorageConfig,
+    #[serde(default)]
+    pub warnings: WarningsConfig,
+    /// Record which options new projects are created with, see `investigate stats`
+    pub stats: Option<bool>,
+}
+
+impl Config {
+    fn load_file(path: &Path) -> Config {
+        let content = match read_to_string(path) {
+            Err(_) => return Config::default(),
+            Ok(content) => content,
+        };
+        let config = toml::from_str(&content).unwrap_or_else(|e| {
+            exit_with_error(&format!("Could not parse config file {:?}: {}", path, e))
+        });
+        // Misspelled keys would otherwise be silently ignored
+        if let Ok(table) = toml::from_str(&content) {
+            for problem in unknown_config_keys(&table) {
+                warn(
+                    Code::ConfigProblem,
+                    &format!("In config file {:?}: {}", path, problem),
+                );
+            }
+        }
+        config
+    }
+
+    /// Get the settings of `self`, with settings from `other` taking precedence.
+    fn overlaid(mut self, other: Config) -> Config {
+        self.author.name = other.author.name.or(self.author.name);
+        self.author.email = other.author.email.or(self.author.email);
+        self.defaults = self.defaults.overlaid(&other.defaults);
+        for (name, profile) in other.profile {
+            let merged = match self.profile.get(&name) {
+                Some(existing) => existing.overlaid(&profile),
+                None => profile,
+            };
+            self.profile.insert(name, merged);
+        }
+        self.blocks.extend(other.blocks);
+        self.notify.webhook = other.notify.webhook.or(self.notify.webhook);
+        self.notify.command = other.notify.command.or(self.notify.command);
+        self.retention.keep_last = other.retention.keep_last.or(self.retention.keep_last);
+        self.retention.keep_referenced = other
+            .retention
+            .keep_referenced
+            .or(self.retention.keep_referenced);
+        self.permissions.group = other.permissions.group.or(self.permissions.group);
+        self.permissions.setgid = other.permissions.setgid.or(self.permissions.setgid);
+        self.permissions.umask = other.permissions.umask.or(self.permissions.umask);
+        self.storage.min_free = other.storage.min_free.or(self.storage.min_free);
+        self.storage.quota_command = other.storage.quota_command.or(self.storage.quota_command);
+        self.storage.locations = other.storage.locations.or(self.storage.locations);
+        self.warnings.allow = other.warnings.allow.or(self.warnings.allow);
+        self.stats = other.stats.or(self.stats);
+        self
+    }
+
+    /// The profiles built into investigate, which config files may override.
+    fn builtin() -> Config {
+        let teaching = Profile {
+            directories: Some(
+                ["src", "raw", "results"]
+                    .iter()
+                    .map(|name| DirectorySpec::Name(name.to_string()))
+                    .collect(),
+            ),
+            assignment: Some(true),
+            ..Profile::default()
+        };
+        // Benchmarks of tools are published as benchmarks rather than as papers
+        let benchmark = Profile {
+            directories: Some(
+                ["raw", "src", "tmp", "cache", "choices", "results"]
+                    .iter()
+                    .map(|name| DirectorySpec::Name(name.to_string()))
+                    .collect(),
+            ),
+            benchmark: Some(true),
+            ..Profile::default()
+        };
+        Config {
+            profile: BTreeMap::from([
+                ("benchmark".to_owned(), benchmark),
+                ("teaching".to_owned(), teaching),
+            ]),
+            ..Config::default()
+        }
+    }
+
+    /// Load the built-in profiles, overlaid with the site configuration,
+    /// overlaid with the user's configuration. Missing config files count as empty.
+    pub fn load() -> Config {
+        let site = Config::builtin().overlaid(Config::load_file(&site_dir().join("site.toml")));
+        match config_dir() {
+            Some(dir) => site.overlaid(Config::load_file(&dir.join("config.toml"))),
+            None => site,
+        }
+    }
+
+    /// Get the settings of the named profile, or the defaults if no name is given.
+    pub fn profile(&self, name: Option<&str>) -> Profile {
+        match name {
+            None => self.defaults.clone(),
+            Some(name) => match self.profile.get(name) {
+                Some(profile) => self.defaults.overlaid(profile),
+                None => exit_with_error(&format!(
+                    "No profile named \"{}\" in config file. Available profiles: {}",
+                    name,
+                    if self.profile.is_empty() {
+                        "(none)".to_owned()
+                    } else {
+                        self.profile.keys().cloned().collect::<Vec<_>>().join(", ")
+                    }
+                )),
+            },
+        }
+    }
+}
+
+#[derive(Subcommand)]
+pub enum ConfigCommand {
+    /// Print a setting of the config file, e.g. `defaults.language`
+    Get(GetArgs),
+
+    /// Change a setting of the config file, e.g. `investigate config set defaults.language julia`
+    Set(SetArgs),
+
+    /// Open the config file in $VISUAL or $EDITOR
+    Edit,
+
+    /// Check the config files, and the metadata of the current project, for mistakes
+    Check,
+}
+
+#[derive(Args)]
+pub struct GetArgs {
+    /// Key separated by dots, e.g. `profile.bioinfo.channels`
+    key: String,
+}
+
+#[derive(Args)]
+pub struct SetArgs {
+    /// Key separated by dots, e.g. `profile.bioinfo.channels`
+    key: String,
+
+    /// Value in TOML syntax, e.g. `true` or `["conda-forge", "bioconda"]`.
+    /// Other values are taken as strings
+    value: String,
+}
+
+/// Get the codes of the warnings allowed by the config files. Unlike `Config::load`, this
+/// neither warns nor exits on invalid files, since it's used when giving warnings.
+pub fn allowed_warnings() -> Vec<String> {
+    let mut files = vec![site_dir().join("site.toml")];
+    files.extend(config_dir().map(|dir| dir.join("config.toml")));
+    // The user's config takes precedence over the site's
+    files
+        .iter()
+        .rev()
+        .find_map(|path| {
+            let config: Config = toml::from_str(&read_to_string(path).ok()?).ok()?;
+            config.warnings.allow
+        })
+        .unwrap_or_default()
+        .iter()
+        .map(|id| id.trim().to_uppercase())
+        .collect()
+}
+
+/// Get the path of the user's config file.
+fn config_path() -> PathBuf {
+    config_dir()
+        .map(|dir| dir.join("config.toml"))
+        .unwrap_or_else(|| exit_with_error("Could not locate the config file, as $HOME is not set"))
+}
+
+fn load_document(path: &Path) -> DocumentMut {
+    read_to_string(path)
+        .unwrap_or_default()
+        .parse()
+        .unwrap_or_else(|e| {
+            exit_with_error(&format!("Could not parse config file {:?}: {}", path, e))
+        })
+}
+
+fn get(args: GetArgs) {
+    // The user's config takes precedence over the site's
+    for path in [config_path(), site_dir().join("site.toml")] {
+        let document = load_document(&path);
+        let item = args
+            .key
+            .split('.')
+            .try_fold(document.as_item(), |item, key| item.get(key));
+        if let Some(item) = item.filter(|item| !item.is_none()) {
+            match item.as_str() {
+                Some(string) => println!("{}", string),
+                None => println!("{}", item.to_string().trim()),
+            }
+            return;
+        }
+    }
+    exit_with_error(&format!("{} is not set", args.key))
+}
+
+fn set(args: SetArgs) {
+    let path = config_path();
+    let mut document = load_document(&path);
+    let value = args
+        .value
+        .parse::<toml_edit::Value>()
+        .unwrap_or_else(|_| toml_edit::Value::from(args.value.as_str()));
+    let keys: Vec<&str> = args.key.split('.').collect();
+    let (last, parents) = keys.split_last().expect("Error when splitting key");
+    let mut table = document.as_table_mut();
+    for key in parents {
+        let item = table.entry(key).or_insert_with(|| {
+            // Implicit tables like [profile] are only written as part of [profile.NAME]
+            let mut table = toml_edit::Table::new();
+            table.set_implicit(true);
+            toml_edit::Item::Table(table)
+        });
+        table = item
+            .as_table_mut()
+            .unwrap_or_else(|| exit_with_error(&format!("{} is not a table in {:?}", key, path)));
+    }
+    table.insert(last, toml_edit::value(value));
+    let content = document.to_string();
+    // Refuse to write a config file which would fail to load
+    let problems = validate_content(&content, FileKind::Config);
+    if !problems.is_empty() {
+        exit_with_error(&format!(
+            "Not setting {}: {}",
+            args.key,
+            problems.join("\n")
+        ))
+    }
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .unwrap_or_else(|_| panic!("Error when creating directory {:?}", parent));
+    }
+    std::fs::write(&path, content).unwrap_or_else(|_| panic!("Error when writing file {:?}", path));
+    println!("Set {} in {:?}", args.key, path);
+}
+
+fn edit() {
+    let path = config_path();
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .unwrap_or_else(|_| panic!("Error when creating directory {:?}", parent));
+    }
+    let editor = ["VISUAL", "EDITOR"]
+        .iter()
+        .find_map(|var| std::env::var(var).ok().filter(|editor| !editor.is_empty()))
+        .unwrap_or_else(|| "vi".to_owned());
+    // The editor may have arguments, e.g. "code --wait"
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg(format!("{} \"$1\"", editor))
+        .arg("sh")
+        .arg(&path)
+        .status()
+        .unwrap_or_else(|_| exit_with_error(&format!("Could not run editor {}", editor)));
+    if !status.success() {
+        exit_with_error(&format!("Editor {} failed", editor))
+    }
+    if path.is_file() {
+        for problem in validate(&path, FileKind::Config) {
+            warn(Code::ConfigProblem, &problem);
+        }
+    }
+}
+
+/// Check the config files and the metadata file of the current project, if any.
+fn check() {
+    let mut files = vec![(site_dir().join("site.toml"), FileKind::Config)];
+    files.extend(config_dir().map(|dir| (dir.join("config.toml"), FileKind::Config)));
+    files
+        .extend(find_project_root_opt().map(|root| (root.join(METADATA_FILE), FileKind::Metadata)));
+    let mut n_problems = 0;
+    for (path, kind) in files {
+        if !path.is_file() {
+            continue;
+        }
+        let problems = validate(&path, kind);
+        if problems.is_empty() {
+            println!("{}: OK", path.display());
+        }
+        for problem in &problems {
+            println!("{}: {}", path.display(), problem);
+        }
+        n_problems += problems.len();
+    }
+    if n_problems > 0 {
+        exit_with_error(&format!("Found {} problems", n_problems))
+    }
+}
+
+pub fn run(command: ConfigCommand) {
+    match command {
+        ConfigCommand::Get(args) => get(args),
+        ConfigCommand::Set(args) => set(args),
+        ConfigCommand::Edit => edit(),
+        ConfigCommand::Check => check(),
+    }
+}
@@ -0,0 +1,277 @@
+use chrono::Local;
+use clap::{Args, Subcommand};
+
+use std::path::Path;
+use std::process::Command;
+
+use crate::author::parse_author;
+use crate::metadata::{Contributor, Metadata, METADATA_FILE};
+use crate::new::{write, Existing};
+use crate::{exit_with_error, filesystem, find_project_root};
+
+/// Name of the file listing everyone who contributed to the project
+const AUTHORS_FILE: &str = "AUTHORS";
+
+/// Name of the file mapping the names and emails in the git history to canonical ones
+const MAILMAP_FILE: &str = ".mailmap";
+
+#[derive(Subcommand)]
+pub enum ContributorCommand {
+    /// Record a new contributor to the project
+    Add(AddArgs),
+
+    /// Record that a contributor left the project
+    Leave(LeaveArgs),
+
+    /// List the contributors to the project
+    List,
+
+    /// Update AUTHORS and .mailmap from the contributors and everyone in the git history
+    Sync,
+}
+
+#[derive(Args)]
+pub struct AddArgs {
+    /// Full name of the contributor
+    name: String,
+
+    #[clap(long)]
+    email: Option<String>,
+
+    /// Role in the project, e.g. "lead", "analyst" or "supervisor"
+    #[clap(long, default_value = "analyst")]
+    role: String,
+
+    /// Date the contributor joined, as YYYY-MM-DD (default: today)
+    #[clap(long)]
+    since: Option<String>,
+}
+
+#[derive(Args)]
+pub struct LeaveArgs {
+    /// Full name of the contributor
+    name: String,
+
+    /// Date the contributor left, as YYYY-MM-DD (default: today)
+    #[clap(long)]
+    date: Option<String>,
+}
+
+fn today() -> String {
+    Local::today().format("%Y-%m-%d").to_string()
+}
+
+fn check_date(date: &str) {
+    if chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d").is_err() {
+        exit_with_error(&format!("Date \"{}\" is not in YYYY-MM-DD format", date))
+    }
+}
+
+/// Get the contributors as a markdown list, current contributors first.
+pub fn contributor_list(contributors: &[Contributor]) -> String {
+    let (current, former): (Vec<_>, Vec<_>) = contributors.iter().partition(|c| c.until.is_none());
+    current
+        .into_iter()
+        .chain(former)
+        .map(|c| {
+            let email = c
+                .email
+                .as_ref()
+                .map(|email| format!(" <{}>", email))
+                .unwrap_or_default();
+            let until = c.until.as_deref().unwrap_or("present");
+            format!(
+                "- {}{}, {}, {} to {}",
+                c.name, email, c.role, c.since, until
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Get "NAME <EMAIL>", or just the name if there's no email.
+fn identity(name: &str, email: Option<&str>) -> String {
+    match email {
+        Some(email) => format!("{} <{}>", name, email),
+        None => name.to_owned(),
+    }
+}
+
+/// Write AUTHORS, listing the contributors followed by the other people in `history`, and
+/// add lines to .mailmap mapping the names and emails in `history` to canonical ones.
+/// `history` is the authors of the commits as (name, email), oldest first.
+/// Returns the people in the history who are not contributors.
+pub fn write_authors(
+    root: &Path,
+    metadata: &Metadata,
+    history: &[(String, String)],
+    existing: Existing,
+) -> Vec<(String, String)> {
+    let mailmap_path = root.join(MAILMAP_FILE);
+    let mut mailmap: Vec<String> = match filesystem::current().read_to_string(&mailmap_path) {
+        Ok(content) => content.lines().map(str::to_owned).collect(),
+        Err(_) => vec![
+            "# Maps the names and emails in the git history to canonical ones, see".to_owned(),
+            "# `git help gitmailmap`. Updated by `investigate contributor sync`.".to_owned(),
+        ],
+    };
+    let mut others: Vec<(String, String)> = Vec::new();
+    for (name, email) in history {
+        let same_email = |other: &str| other.eq_ignore_ascii_case(email);
+        let contributor = metadata
+            .contributors
+            .iter()
+            .find(|c| c.email.as_deref().is_some_and(same_email))
+            .or_else(|| {
+                metadata
+                    .contributors
+                    .iter()
+                    .find(|c| c.name.eq_ignore_ascii_case(name))
+            });
+        let canonical = match contributor {
+            Some(c) => (c.name.clone(), c.email.clone()),
+            None => match others
+                .iter()
+                .find(|(n, e)| same_email(e) || n.eq_ignore_ascii_case(name))
+            {
+                Some((n, e)) => (n.clone(), Some(e.clone())),
+                None => {
+                    others.push((name.clone(), email.clone()));
+                    continue;
+                }
+            },
+        };
+        let line = match &canonical.1 {
+            Some(canonical_email) if canonical.0 == *name && canonical_email == email => continue,
+            Some(canonical_email) => format!(
+                "{} {}",
+                identity(&canonical.0, Some(canonical_email)),
+                identity(name, Some(email))
+            ),
+            // Without a canonical email, only the name is mapped
+            None if canonical.0 == *name => continue,
+            None => identity(&canonical.0, Some(email)),
+        };
+        if !mailmap.contains(&line) {
+            mailmap.push(line);
+        }
+    }
+    let authors: Vec<String> = metadata
+        .contributors
+        .iter()
+        .map(|c| identity(&c.name, c.email.as_deref()))
+        .chain(
+            others
+                .iter()
+                .map(|(name, email)| identity(name, Some(email))),
+        )
+        .collect();
+    write(
+        &root.join(AUTHORS_FILE),
+        &format!(
+            "# Authors of {}: its contributors, and everyone else in the git history.\n\
+            # Updated by `investigate contributor sync`.\n{}\n",
+            metadata.name,
+            authors.join("\n")
+        ),
+        existing,
+    );
+    write(&mailmap_path, &(mailmap.join("\n") + "\n"), existing);
+    others
+}
+
+/// Get the authors and co-authors of the commits touching the project at `root`, as
+/// (name, email), oldest first, without duplicates.
+fn git_history(root: &Path) -> Vec<(String, String)> {
+    let output = Command::new("git")
+        .args([
+            "log",
+            "--reverse",
+            "--format=%an <%ae>%x00%(trailers:key=Co-authored-by,valueonly,separator=%x00)",
+            "--",
+            ".",
+        ])
+        .current_dir(root)
+        .output()
+        .unwrap_or_else(|_| exit_with_error("Could not run git"));
+    if !output.status.success() {
+        exit_with_error("Could not read the git history. Does the project have any commits?")
+    }
+    let mut people: Vec<(String, String)> = Vec::new();
+    for author in String::from_utf8_lossy(&output.stdout).split(['\0', '\n']) {
+        if let Some(person) = parse_author(author) {
+            if !people.contains(&person) {
+                people.push(person);
+            }
+        }
+    }
+    people
+}
+
+fn sync() {
+    let root = find_project_root();
+    let metadata = Metadata::load(&root);
+    let others = write_authors(&root, &metadata, &git_history(&root), Existing::Force);
+    println!("Updated {} and {}", AUTHORS_FILE, MAILMAP_FILE);
+    if !others.is_empty() {
+        println!("These people are in the git history, but not recorded as contributors:");
+        for (name, email) in &others {
+            println!("  {} <{}>", name, email);
+        }
+        println!("Record them with `investigate contributor add NAME --email EMAIL --role ROLE`");
+    }
+}
+
+fn add(args: AddArgs) {
+    let root = find_project_root();
+    let mut metadata = Metadata::load(&root);
+    let since = args.since.unwrap_or_else(today);
+    check_date(&since);
+    if metadata
+        .contributors
+        .iter()
+        .any(|c| c.name == args.name && c.until.is_none())
+    {
+        exit_with_error(&format!("{} is already a contributor", args.name))
+    }
+    metadata.contributors.push(Contributor {
+        name: args.name.clone(),
+        email: args.email,
+        role: args.role.clone(),
+        since,
+        until: None,
+    });
+    metadata.save(&root);
+    println!("Added {} as {} in {}", args.name, args.role, METADATA_FILE);
+}
+
+fn leave(args: LeaveArgs) {
+    let root = find_project_root();
+    let mut metadata = Metadata::load(&root);
+    let date = args.date.unwrap_or_else(today);
+    check_date(&date);
+    let contributor = metadata
+        .contributors
+        .iter_mut()
+        .find(|c| c.name == args.name && c.until.is_none())
+        .unwrap_or_else(|| exit_with_error(&format!("{} is not a current contributor", args.name)));
+    contributor.until = Some(date.clone());
+    metadata.save(&root);
+    println!("Recorded that {} left the project {}", args.name, date);
+}
+
+pub fn run(command: ContributorCommand) {
+    match command {
+        ContributorCommand::Add(args) => add(args),
+        ContributorCommand::Leave(args) => leave(args),
+        ContributorCommand::Sync => sync(),
+        ContributorCommand::List => {
+            let metadata = Metadata::load(&find_project_root());
+            if metadata.contributors.is_empty() {
+                println!("No contributors recorded");
+            } else {
+                println!("{}", contributor_list(&metadata.contributors));
+            }
+        }
+    }
+}
@@ -0,0 +1,441 @@
+use clap::Args;
+use git2::Repository;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, List, ListItem, ListState, Paragraph};
+use ratatui::{DefaultTerminal, Frame};
+
+use std::collections::BTreeMap;
+use std::fs::read_to_string;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+
+use crate::cache::stale_files;
+use crate::data::size;
+use crate::dates::approaching;
+use crate::decisions::DECISIONS_FILE;
+use crate::finalize::FINALIZED_FILE;
+use crate::git::is_clean;
+use crate::metadata::Metadata;
+use crate::registry;
+use crate::report::environment_section;
+use crate::run::{format_bytes, format_duration, load_runs};
+use crate::tmp::expired;
+use crate::todo::load_items;
+use crate::tools::{drift_from, versions, ToolVersion};
+use crate::{exit_with_error, find_project_root, is_plain};
+
+/// Number of recent runs and decisions to show
+const RECENT: usize = 10;
+
+#[derive(Args)]
+pub struct DashboardArgs {
+    /// Show all registered projects, instead of the current one
+    #[clap(long)]
+    all: bool,
+}
+
+/// A line of the dashboard, and whether it needs attention.
+pub struct Item {
+    pub text: String,
+    pub warning: bool,
+}
+
+impl Item {
+    fn ok(text: impl Into<String>) -> Item {
+        Item {
+            text: text.into(),
+            warning: false,
+        }
+    }
+
+    fn warning(text: impl Into<String>) -> Item {
+        Item {
+            text: text.into(),
+            warning: true,
+        }
+    }
+}
+
+/// What the dashboard shows about a project.
+pub struct Summary {
+    pub name: String,
+    pub root: PathBuf,
+    pub status: Vec<Item>,
+    /// Key dates which are approaching or have passed
+    pub dates: Vec<Item>,
+    /// Size in bytes of each top-level directory
+    pub usage: Vec<(String, u64)>,
+    pub runs: Vec<Item>,
+    pub decisions: Vec<String>,
+}
+
+impl Summary {
+    /// Get what needs attention: The warnings of the status, the key dates, and the failed runs.
+    pub fn warnings(&self) -> Vec<&str> {
+        self.status
+            .iter()
+            .chain(&self.dates)
+            .chain(&self.runs)
+            .filter(|item| item.warning)
+            .map(|item| item.text.as_str())
+            .collect()
+    }
+}
+
+fn git_status(root: &Path) -> Item {
+    let Ok(repo) = Repository::discover(root) else {
+        return Item::warning("Not in a git repository");
+    };
+    let branch = repo
+        .head()
+        .ok()
+        .and_then(|head| head.shorthand().map(str::to_owned))
+        .unwrap_or_else(|| "(no commits)".to_owned());
+    if is_clean(&repo) {
+        Item::ok(format!("Git: on {}, no uncommitted changes", branch))
+    } else {
+        Item::warning(format!("Git: on {}, with uncommitted changes", branch))
+    }
+}
+
+pub fn summarize(
+    root: &Path,
+    metadata: &Metadata,
+    installed: &BTreeMap<String, ToolVersion>,
+) -> Summary {
+    let mut status = vec![if root.join(FINALIZED_FILE).exists() {
+        Item::ok("Finalized")
+    } else {
+        Item::ok(format!("Active, created {}", metadata.created))
+    }];
+    status.push(git_status(root));
+    // The environment section is a Markdown list, which is reused without its heading
+    for line in environment_section(root, metadata).lines().skip(1) {
+        let text = line.trim_start_matches("- ");
+        if text.contains("missing") || text.contains("not locked") {
+            status.push(Item::warning(text));
+        } else {
+            status.push(Item::ok(text));
+        }
+    }
+    for warning in drift_from(&metadata.tools, installed) {
+        status.push(Item::warning(warning));
+    }
+    let n_expired = expired(root).len();
+    if n_expired > 0 {
+        status.push(Item::warning(format!(
+            "{} expired scratch directories in tmp/",
+            n_expired
+        )));
+    }
+    let n_stale = stale_files(root).len();
+    if n_stale > 0 {
+        status.push(Item::warning(format!("{} stale files in cache/", n_stale)));
+    }
+    let n_open = load_items(root).iter().filter(|item| !item.done).count();
+    if n_open > 0 {
+        status.push(Item::ok(format!(
+            "{} open items in the TODO list, see `investigate todo list`",
+            n_open
+        )));
+    }
+
+    let dates = approaching(metadata)
+        .into_iter()
+        .map(Item::warning)
+        .collect();
+
+    let usage = metadata
+        .directories
+        .iter()
+        .filter(|name| !name.contains('/'))
+        .map(|name| (name.clone(), size(&root.join(name))))
+        .collect();
+
+    let runs = load_runs(root)
+        .iter()
+        .rev()
+        .take(RECENT)
+        .map(|run| {
+            let text = format!(
+                "{}  {}  {:>8}  {}",
+                run.id,
+                run.started
+                    .get(..16)
+                    .unwrap_or(&run.started)
+                    .replace('T', " "),
+                run.wall_seconds
+                    .map(format_duration)
+                    .unwrap_or_else(|| "-".to_owned()),
+                run.command.join(" ")
+            );
+            match run.exit_code {
+                Some(0) => Item::ok(text),
+                Some(code) => Item::warning(format!("{}  (exit {})", text, code)),
+                None => Item::warning(format!("{}  (killed)", text)),
+            }
+        })
+        .collect();
+
+    let decisions_log = read_to_string(root.join(DECISIONS_FILE)).unwrap_or_default();
+    let decisions = decisions_log
+        .lines()
+        .rev()
+        .filter_map(|line| line.strip_prefix("## "))
+        .take(RECENT)
+        .map(str::to_owned)
+        .collect();
+
+    Summary {
+        name: metadata.name.clone(),
+        root: root.to_owned(),
+        status,
+        dates,
+        usage,
+        runs,
+        decisions,
+    }
+}
+
+fn item_line(item: &Item) -> ListItem<'_> {
+    let style = if item.warning {
+        Style::default().fg(Color::Yellow)
+    } else {
+        Style::default()
+    };
+    ListItem::new(Line::styled(item.text.as_str(), style))
+}
+
+/// Get the lines showing the disk usage of each directory, with bars relative to the largest.
+fn usage_lines(usage: &[(String, u64)], width: usize) -> Vec<Line<'_>> {
+    let largest = usage
+        .iter()
+        .map(|(_, bytes)| *bytes)
+        .max()
+        .unwrap_or(0)
+        .max(1);
+    let name_width = usage.iter().map(|(name, _)| name.len()).max().unwrap_or(0);
+    let bar_width = width.saturating_sub(name_width + 14);
+    usage
+        .iter()
+        .map(|(name, bytes)| {
+            let filled = (*bytes as f64 / largest as f64 * bar_width as f64).round() as usize;
+            Line::from(vec![
+                Span::raw(format!(
+                    "{:name_width$} {:>10} ",
+                    name,
+                    format_bytes(*bytes),
+                    name_width = name_width
+                )),
+                Span::styled("█".repeat(filled), Style::default().fg(Color::Cyan)),
+            ])
+        })
+        .collect()
+}
+
+fn draw_summary(frame: &mut Frame, area: Rect, summary: &Summary) {
+    let [header, top, bottom] = Layout::vertical([
+        Constraint::Length(1),
+        Constraint::Percentage(45),
+        Constraint::Fill(1),
+    ])
+    .areas(area);
+    let [status, usage] =
+        Layout::horizontal([Constraint::Percentage(55), Constraint::Fill(1)]).areas(top);
+    let [runs, decisions] =
+        Layout::horizontal([Constraint::Percentage(55), Constraint::Fill(1)]).areas(bottom);
+
+    let title = Line::from(vec![
+        Span::styled(
+            summary.name.as_str(),
+            Style::default().add_modifier(Modifier::BOLD),
+        ),
+        Span::raw(format!("  {}", summary.root.display())),
+    ]);
+    frame.render_widget(Paragraph::new(title), header);
+    frame.render_widget(
+        List::new(summary.status.iter().chain(&summary.dates).map(item_line))
+            .block(Block::bordered().title("Status")),
+        status,
+    );
+    let total: u64 = summary.usage.iter().map(|(_, bytes)| bytes).sum();
+    frame.render_widget(
+        Paragraph::new(usage_lines(
+            &summary.usage,
+            usage.width.saturating_sub(2) as usize,
+        ))
+        .block(Block::bordered().title(format!("Disk usage: {}", format_bytes(total)))),
+        usage,
+    );
+    let runs_list = if summary.runs.is_empty() {
+        List::new([ListItem::new("No runs recorded")])
+    } else {
+        List::new(summary.runs.iter().map(item_line))
+    };
+    frame.render_widget(
+        runs_list.block(Block::bordered().title("Recent runs")),
+        runs,
+    );
+    let decisions_list = if summary.decisions.is_empty() {
+        List::new([ListItem::new("No decisions recorded")])
+    } else {
+        List::new(summary.decisions.iter().map(|d| ListItem::new(d.as_str())))
+    };
+    frame.render_widget(
+        decisions_list.block(Block::bordered().title("Recent decisions")),
+        decisions,
+    );
+}
+
+fn draw(frame: &mut Frame, summaries: &[Summary], selected: &mut ListState) {
+    let [main, help] =
+        Layout::vertical([Constraint::Fill(1), Constraint::Length(1)]).areas(frame.area());
+    let keys = if summaries.len() > 1 {
+        "↑/↓: select project  r: refresh  q: quit"
+    } else {
+        "r: refresh  q: quit"
+    };
+    frame.render_widget(
+        Paragraph::new(keys).style(Style::default().add_modifier(Modifier::DIM)),
+        help,
+    );
+    if summaries.len() == 1 {
+        draw_summary(frame, main, &summaries[0]);
+        return;
+    }
+    let width = summaries.iter().map(|s| s.name.len()).max().unwrap_or(0) as u16 + 10;
+    let [projects, detail] =
+        Layout::horizontal([Constraint::Length(width), Constraint::Fill(1)]).areas(main);
+    let items = summaries.iter().map(|summary| {
+        let warnings = summary.warnings().len();
+        if warnings > 0 {
+            ListItem::new(Line::from(vec![
+                Span::raw(summary.name.as_str()),
+                Span::styled(
+                    format!(" ({})", warnings),
+                    Style::default().fg(Color::Yellow),
+                ),
+            ]))
+        } else {
+            ListItem::new(summary.name.as_str())
+        }
+    });
+    let list = List::new(items)
+        .block(Block::bordered().title("Projects"))
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    frame.render_stateful_widget(list, projects, selected);
+    if let Some(summary) = selected.selected().and_then(|i| summaries.get(i)) {
+        draw_summary(frame, detail, summary);
+    }
+}
+
+/// Print `items` as lines of plain text, labeled by whether they need attention.
+pub fn print_items<'a>(items: impl IntoIterator<Item = &'a Item>) {
+    for item in items {
+        let label = if item.warning { "warning" } else { "ok" };
+        println!("  {}: {}", label, item.text);
+    }
+}
+
+/// Print `summary` as lines of plain text, for --plain.
+fn print_plain(summary: &Summary) {
+    println!("== {} ({})", summary.name, summary.root.display());
+    println!("Status:");
+    print_items(summary.status.iter().chain(&summary.dates));
+    let total: u64 = summary.usage.iter().map(|(_, bytes)| bytes).sum();
+    println!("Disk usage: {}", format_bytes(total));
+    for (name, bytes) in &summary.usage {
+        println!("  {}: {}", name, format_bytes(*bytes));
+    }
+    println!("Recent runs:");
+    if summary.runs.is_empty() {
+        println!("  none");
+    }
+    for run in &summary.runs {
+        let label = if run.warning { "failed" } else { "ok" };
+        println!("  {}: {}", label, run.text);
+    }
+    println!("Recent decisions:");
+    if summary.decisions.is_empty() {
+        println!("  none");
+    }
+    for decision in &summary.decisions {
+        println!("  {}", decision);
+    }
+}
+
+/// Get the summaries of the projects at `roots`, skipping those that no longer exist.
+fn summarize_all(roots: &[PathBuf]) -> Vec<Summary> {
+    let installed = versions();
+    roots
+        .iter()
+        .filter_map(|root| Some(summarize(root, &Metadata::try_load(root)?, &installed)))
+        .collect()
+}
+
+fn run_terminal(
+    terminal: &mut DefaultTerminal,
+    roots: &[PathBuf],
+    mut summaries: Vec<Summary>,
+) -> std::io::Result<()> {
+    let mut selected = ListState::default().with_selected(Some(0));
+    loop {
+        terminal.draw(|frame| draw(frame, &summaries, &mut selected))?;
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+            KeyCode::Char('r') => {
+                summaries = summarize_all(roots);
+                // Warnings printed while summarizing would otherwise stay on the screen
+                terminal.clear()?;
+            }
+            KeyCode::Down | KeyCode::Char('j') => selected.select_next(),
+            KeyCode::Up | KeyCode::Char('k') => selected.select_previous(),
+            _ => (),
+        }
+        // Selecting past the last project stays on it
+        if selected.selected().is_some_and(|i| i >= summaries.len()) {
+            selected.select(Some(summaries.len().saturating_sub(1)));
+        }
+    }
+}
+
+pub fn run(args: DashboardArgs) {
+    let roots = if args.all {
+        registry::load()
+    } else {
+        let root = find_project_root();
+        // Fails with an error here, rather than inside the dashboard
+        Metadata::load(&root);
+        vec![root]
+    };
+    let summaries = summarize_all(&roots);
+    if summaries.is_empty() {
+        exit_with_error("No registered projects exist")
+    }
+    if is_plain() {
+        for summary in &summaries {
+            print_plain(summary);
+        }
+        return;
+    }
+    if !std::io::stdout().is_terminal() {
+        exit_with_error(
+            "The dashboard must be shown in a terminal. Use --plain to print it as text instead",
+        )
+    }
+    let mut terminal = ratatui::init();
+    let result = run_terminal(&mut terminal, &roots, summaries);
+    ratatui::restore();
+    if let Err(e) = result {
+        exit_with_error(&format!("Could not show the dashboard: {}", e))
+    }
+}
@@ -0,0 +1,533 @@
+use chrono::Local;
+use clap::{ArgEnum, Args, Subcommand};
+use git2::Repository;
+use indicatif::ProgressBar;
+use sha2::{Digest, Sha256};
+
+use std::fs::{create_dir_all, File};
+use std::path::{Component, Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use crate::author::author_string;
+use crate::config::Config;
+use crate::encrypt::{decrypt, encrypt, DecryptArgs, EncryptArgs};
+use crate::lock::lock;
+use crate::manifest::{DataEntry, Manifest, MANIFEST_FILE};
+use crate::metadata::{Metadata, METADATA_FILE};
+use crate::new::{write, Existing};
+use crate::progress::Steps;
+use crate::report::walk_files;
+use crate::templates::Templates;
+use crate::warning::{warn, Code};
+use crate::{exit_with_error, find_project_root, require_network};
+
+/// Directories copied to and from the data remote
+pub const DATA_DIRECTORIES: [&str; 2] = ["raw", "results"];
+
+#[derive(Subcommand)]
+pub enum DataCommand {
+    /// Add a data file or directory to raw/, and record its origin and checksum
+    Add(AddArgs),
+
+    /// Encrypt files in raw/ for the project's encryption recipients, and remove the originals
+    Encrypt(EncryptArgs),
+
+    /// Decrypt encrypted files in raw/
+    Decrypt(DecryptArgs),
+
+    /// Record where a restricted dataset lives, instead of copying it into raw/
+    Restrict(RestrictArgs),
+
+    /// Copy the data directories from the project's data remote
+    Fetch(SyncArgs),
+
+    /// Copy the data directories to the project's data remote
+    Push(SyncArgs),
+}
+
+/// How to add data to raw/ without copying it.
+#[derive(Copy, Clone, PartialEq, Eq, ArgEnum)]
+enum LinkMode {
+    /// A reflink if supported, else a hardlink, else a symlink
+    Auto,
+    /// A copy-on-write clone, on filesystems like btrfs, XFS and APFS
+    Reflink,
+    /// A hard link, if the data is on the same filesystem
+    Hardlink,
+    /// A symbolic link to the original location
+    Symlink,
+}
+
+#[derive(Args)]
+pub struct AddArgs {
+    /// File or directory to add
+    source: PathBuf,
+
+    /// Name in raw/ (default: the name of <SOURCE>)
+    #[clap(long)]
+    name: Option<String>,
+
+    /// Link to the data instead of copying it
+    #[clap(
+        long,
+        arg_enum,
+        value_parser,
+        value_name = "MODE",
+        min_values = 0,
+        require_equals = true,
+        default_missing_value = "auto"
+    )]
+    link: Option<LinkMode>,
+}
+
+#[derive(Args)]
+pub struct SyncArgs {
+    /// Directories to copy (default: raw and results)
+    directories: Vec<String>,
+}
+
+#[derive(Args)]
+pub struct RestrictArgs {
+    /// Name of the dataset
+    name: String,
+
+    /// Where the data is stored, e.g. a URI or path on secure storage
+    #[clap(long)]
+    location: String,
+
+    /// Email of the person or group who controls access to the data
+    #[clap(long)]
+    contact: String,
+
+    /// SHA-256 checksum of the data (default: computed if <LOCATION> is a local file)
+    #[clap(long)]
+    checksum: Option<String>,
+}
+
+pub fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Compute the hex-encoded SHA-256 checksum of the file at `path`.
+pub fn sha256(path: &Path) -> std::io::Result<String> {
+    sha256_with_progress(path, &ProgressBar::hidden())
+}
+
+/// Compute the hex-encoded SHA-256 checksum of the file at `path`, advancing `progress` by
+/// the bytes read.
+pub fn sha256_with_progress(path: &Path, progress: &ProgressBar) -> std::io::Result<String> {
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut progress.wrap_read(File::open(path)?), &mut hasher)?;
+    Ok(hex(&hasher.finalize()))
+}
+
+/// Get the total size of the file or directory at `path`.
+pub fn size(path: &Path) -> u64 {
+    if !path.is_dir() {
+        return path.metadata().map(|m| m.len()).unwrap_or(0);
+    }
+    walk_files(path)
+        .iter()
+        .filter_map(|file| file.metadata().ok())
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
+/// Compute the checksum and total size of the file or directory at `path`.
+/// The checksum of a directory is that of the list of checksums of its files.
+pub fn checksum_and_size(path: &Path) -> std::io::Result<(String, u64)> {
+    checksum_and_size_with_progress(path, &ProgressBar::hidden())
+}
+
+/// Like `checksum_and_size`, advancing `progress` by the bytes read.
+pub fn checksum_and_size_with_progress(
+    path: &Path,
+    progress: &ProgressBar,
+) -> std::io::Result<(String, u64)> {
+    if !path.is_dir() {
+        return Ok((
+            sha256_with_progress(path, progress)?,
+            path.metadata()?.len(),
+        ));
+    }
+    let mut list = String::new();
+    let mut size = 0;
+    for file in walk_files(path) {
+        let relative = file.strip_prefix(path).unwrap_or(&file);
+        let checksum = sha256_with_progress(&file, progress)?;
+        list.push_str(&format!("{}  {}\n", checksum, relative.display()));
+        size += file.metadata()?.len();
+    }
+    Ok((hex(&Sha256::digest(list.as_bytes())), size))
+}
+
+/// Clone `source` to `destination` with copy-on-write, if the filesystem supports it.
+pub fn reflink(source: &Path, destination: &Path) -> bool {
+    let flag = if cfg!(target_os = "macos") {
+        "-c"
+    } else {
+        "--reflink=always"
+    };
+    let cloned = Command::new("cp")
+        .args(["-R", flag])
+        .arg(source)
+        .arg(destination)
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success());
+    if !cloned {
+        // A failed clone may leave a partial copy behind
+        let _ = std::fs::remove_dir_all(destination).or_else(|_| std::fs::remove_file(destination));
+    }
+    cloned
+}
+
+pub fn symlink(source: &Path, destination: &Path) -> std::io::Result<()> {
+    #[cfg(unix)]
+    return std::os::unix::fs::symlink(source, destination);
+    #[cfg(windows)]
+    return if source.is_dir() {
+        std::os::windows::fs::symlink_dir(source, destination)
+    } else {
+        std::os::windows::fs::symlink_file(source, destination)
+    };
+}
+
+pub fn copy_recursive(source: &Path, destination: &Path) -> std::io::Result<()> {
+    if source.is_dir() {
+        create_dir_all(destination)?;
+        for entry in source.read_dir()? {
+            let entry = entry?;
+            copy_recursive(&entry.path(), &destination.join(entry.file_name()))?;
+        }
+        Ok(())
+    } else {
+        std::fs::copy(source, destination).map(|_| ())
+    }
+}
+
+/// Put `source` at `destination` as specified by `link`, and return the method used.
+fn ingest(
+    source: &Path,
+    destination: &Path,
+    link: Option<LinkMode>,
+) -> std::io::Result<&'static str> {
+    let error = |message: &str| std::io::Error::other(message.to_owned());
+    match link {
+        None => copy_recursive(source, destination).map(|_| "copy"),
+        Some(LinkMode::Symlink) => symlink(source, destination).map(|_| "symlink"),
+        Some(LinkMode::Hardlink) if source.is_dir() => {
+            Err(error("Directories cannot be hard linked"))
+        }
+        Some(LinkMode::Hardlink) => std::fs::hard_link(source, destination).map(|_| "hardlink"),
+        Some(LinkMode::Reflink) => match reflink(source, destination) {
+            true => Ok("reflink"),
+            false => Err(error("The filesystem does not support reflinks")),
+        },
+        Some(LinkMode::Auto) => {
+            if reflink(source, destination) {
+                Ok("reflink")
+            } else if !source.is_dir() && std::fs::hard_link(source, destination).is_ok() {
+                Ok("hardlink")
+            } else {
+                symlink(source, destination).map(|_| "symlink")
+            }
+        }
+    }
+}
+
+/// Warn if `path` in the project at `root` is ignored by git.
+fn warn_if_ignored(root: &Path, path: &Path, pattern: &str) {
+    // Projects from older versions of investigate ignore everything in raw/
+    if let Ok(repo) = Repository::discover(root) {
+        let ignored = repo
+            .workdir()
+            .and_then(|workdir| path.strip_prefix(workdir).ok())
+            .is_some_and(|relative| repo.is_path_ignored(relative).unwrap_or(false));
+        if ignored {
+            warn(
+                Code::DataIgnored,
+                &format!(
+                    "{:?} is ignored by git. Add the line \"!{}\" to \
+                    .gitignore, and change \"raw\" to \"raw/*\"",
+                    path, pattern
+                ),
+            )
+        }
+    }
+}
+
+fn add(args: AddArgs) {
+    let root = find_project_root();
+    let config = Config::load();
+    let source = args
+        .source
+        .canonicalize()
+        .unwrap_or_else(|_| exit_with_error(&format!("Could not find {:?}", args.source)));
+    let name = match args.name {
+        Some(name) => name,
+        None => source
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| exit_with_error("Could not determine name, use --name")),
+    };
+    let mut components = Path::new(&name).components();
+    if !matches!(
+        (components.next(), components.next()),
+        (Some(Component::Normal(_)), None)
+    ) || name.contains(['/', '\\'])
+    {
+        exit_with_error(&format!(
+            "Name \"{}\" must be the name of a file or directory in raw/, not a path",
+            name
+        ))
+    }
+    let raw = root.join("raw");
+    if !raw.is_dir() {
+        exit_with_error("Project has no raw/ directory")
+    }
+    let destination = raw.join(&name);
+    if destination.symlink_metadata().is_ok() {
+        exit_with_error(&format!("{:?} already exists", destination))
+    }
+    let mut steps = Steps::new();
+    let (checksum, size) = steps
+        .run("Checksum data", Some(size(&source)), |progress| {
+            checksum_and_size_with_progress(&source, progress)
+        })
+        .unwrap_or_else(|_| exit_with_error(&format!("Could not read {:?}", source)));
+    let method = ingest(&source, &destination, args.link).unwrap_or_else(|e| {
+        exit_with_error(&format!(
+            "Could not add {:?} to {:?}: {}",
+            source, destination, e
+        ))
+    });
+    let author = author_string(&config);
+    let _lock = lock(&root.join(MANIFEST_FILE));
+    let mut manifest = Manifest::load(&root);
+    manifest.insert(DataEntry {
+        path: name,
+        source: source.to_string_lossy().into_owned(),
+        method: method.to_owned(),
+        size,
+        sha256: checksum,
+        added: Local::today().format("%Y-%m-%d").to_string(),
+        author,
+        encrypted: None,
+    });
+    manifest.save(&root);
+    println!("Added {:?} to {:?} ({})", source, destination, method);
+    warn_if_ignored(&root, &root.join(MANIFEST_FILE), MANIFEST_FILE);
+}
+
+fn restrict(args: RestrictArgs) {
+    let root = find_project_root();
+    let config = Config::load();
+    let templates = Templates::new(Vec::new(), &config);
+    if args.name.is_empty() || args.name.contains('/') {
+        exit_with_error("Dataset name must be non-empty and cannot contain '/'")
+    }
+    let checksum = match args.checksum {
+        Some(checksum) => checksum,
+        None if Path::new(&args.location).is_file() => sha256(Path::new(&args.location))
+            .unwrap_or_else(|_| exit_with_error(&format!("Could not read {:?}", args.location))),
+        None => {
+            warn(
+                Code::NoChecksum,
+                &format!(
+                    "Could not compute checksum of {:?}, since it's not a local file. \
+                    Use --checksum to record it.",
+                    args.location
+                ),
+            );
+            "(unknown)".to_owned()
+        }
+    };
+    let author = author_string(&config);
+    let raw = root.join("raw");
+    if !raw.is_dir() {
+        exit_with_error("Project has no raw/ directory")
+    }
+    let path = raw.join(format!("{}.ACCESS.md", args.name));
+    if path.exists() {
+        exit_with_error(&format!("File {:?} already exists", path))
+    }
+    let content = templates.render(
+        "data_access",
+        &[
+            ("name", &args.name),
+            ("location", &args.location),
+            ("contact", &args.contact),
+            ("checksum", &checksum),
+            ("date", &Local::today().format("%Y-%m-%d").to_string()),
+            ("author", &author),
+        ],
+    );
+    write(&path, &content, Existing::Error);
+    println!("Wrote {:?}", path);
+    warn_if_ignored(&root, &path, "raw/*.ACCESS.md");
+}
+
+/// Get the command that copies `dir` from the remote, or to it if `push` is true.
+/// The remote is an S3 or Google Cloud Storage URI, an HTTP URL (fetch only),
+/// OSF storage as "osf://NODE" (push only), or anything else rclone understands,
+/// e.g. "myremote:path".
+pub fn sync_command(remote: &str, dir: &str, push: bool) -> Result<Vec<String>, String> {
+    let remote_dir = format!("{}/{}", remote.trim_end_matches('/'), dir);
+    let (from, to) = if push {
+        (dir.to_owned(), remote_dir.clone())
+    } else {
+        (remote_dir.clone(), dir.to_owned())
+    };
+    let command: Vec<&str> = if remote.starts_with("s3://") {
+        vec!["aws", "s3", "sync", &from, &to]
+    } else if remote.starts_with("gs://") {
+        vec!["gsutil", "-m", "rsync", "-r", &from, &to]
+    } else if let Some(node) = remote.strip_prefix("osf://") {
+        if !push {
+            return Err(format!(
+                "Cannot fetch from OSF remote {}. Download it with `osf -p {} clone`",
+                remote, node
+            ));
+        }
+        vec!["osf", "-p", node, "upload", "-r", "-U", dir, dir]
+    } else if remote.starts_with("http://") || remote.starts_with("https://") {
+        if push {
+            return Err(format!("Cannot push to HTTP remote {}", remote));
+        }
+        vec!["rclone", "copy", "--http-url", &remote_dir, ":http:", dir]
+    } else {
+        vec!["rclone", "copy", &from, &to]
+    };
+    Ok(command.into_iter().map(str::to_owned).collect())
+}
+
+/// Join `command` into a string which the shell splits back into the same words.
+pub fn shell_join(command: &[String]) -> String {
+    command
+        .iter()
+        .map(|word| {
+            if !word.is_empty()
+                && word
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "-_./:=@%+".contains(c))
+            {
+                word.clone()
+            } else {
+                format!("'{}'", word.replace('\'', "'\\''"))
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn sync(args: SyncArgs, push: bool) {
+    let root = find_project_root();
+    let metadata = Metadata::load(&root);
+    let remote = metadata.data_remote.unwrap_or_else(|| {
+        exit_with_error(&format!(
+            "Project has no data remote. Set data_remote in {}",
+            METADATA_FILE
+        ))
+    });
+    let directories = if args.directories.is_empty() {
+        DATA_DIRECTORIES.iter().map(|d| d.to_string()).collect()
+    } else {
+        args.directories
+    };
+    // Remotes without a scheme or an rclone remote name are local paths
+    if remote.contains(':') {
+        require_network(&format!("Copying data to and from remote {}", remote));
+    }
+    let mut steps = Steps::new();
+    for dir in &directories {
+        let command = sync_command(&remote, dir, push).unwrap_or_else(|e| exit_with_error(&e));
+        if !push {
+            create_dir_all(root.join(dir))
+                .unwrap_or_else(|_| panic!("Error when creating directory {:?}", dir));
+        }
+        println!("{}", shell_join(&command));
+        let name = format!("{} {}", if push { "Push" } else { "Fetch" }, dir);
+        // The output is only shown if the copy fails, so it doesn't garble the progress bar
+        let result = steps.run(&name, None, |_| {
+            let output = Command::new(&command[0])
+                .args(&command[1..])
+                .current_dir(&root)
+                .output()
+                .unwrap_or_else(|_| exit_with_error(&format!("Could not run {}", command[0])));
+            match output.status.success() {
+                true => Ok(()),
+                false => Err(output.stderr),
+            }
+        });
+        if let Err(stderr) = result {
+            eprint!("{}", String::from_utf8_lossy(&stderr));
+            exit_with_error(&format!("Could not copy {}", dir))
+        }
+    }
+    steps.summary();
+}
+
+pub fn run(command: DataCommand) {
+    match command {
+        DataCommand::Add(args) => add(args),
+        DataCommand::Encrypt(args) => encrypt(args),
+        DataCommand::Decrypt(args) => decrypt(args),
+        DataCommand::Restrict(args) => restrict(args),
+        DataCommand::Fetch(args) => sync(args, false),
+        DataCommand::Push(args) => sync(args, true),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn syncs_with_cloud_storage() {
+        assert_eq!(
+            sync_command("s3://bucket/proj/", "raw", false).unwrap(),
+            ["aws", "s3", "sync", "s3://bucket/proj/raw", "raw"]
+        );
+        assert_eq!(
+            sync_command("s3://bucket/proj", "raw", true).unwrap(),
+            ["aws", "s3", "sync", "raw", "s3://bucket/proj/raw"]
+        );
+        assert_eq!(
+            sync_command("gs://bucket/proj", "results", true).unwrap(),
+            [
+                "gsutil",
+                "-m",
+                "rsync",
+                "-r",
+                "results",
+                "gs://bucket/proj/results"
+            ]
+        );
+        assert_eq!(
+            sync_command("myremote:proj", "raw", false).unwrap(),
+            ["rclone", "copy", "myremote:proj/raw", "raw"]
+        );
+    }
+
+    #[test]
+    fn syncs_one_way_with_osf_and_http() {
+        assert_eq!(
+            sync_command("osf://abc12", "raw", true).unwrap(),
+            ["osf", "-p", "abc12", "upload", "-r", "-U", "raw", "raw"]
+        );
+        assert!(sync_command("osf://abc12", "raw", false).is_err());
+        assert_eq!(
+            sync_command("https://example.org/proj", "raw", false).unwrap(),
+            [
+                "rclone",
+                "copy",
+                "--http-url",
+                "https://example.org/proj/raw",
+                ":http:",
+                "raw"
+            ]
+        );
+        assert!(sync_command("https://example.org/proj", "raw", true).is_err());
+    }
+}
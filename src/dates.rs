@@ -0,0 +1,269 @@
+use chrono::{Duration, Local, NaiveDate, Utc};
+use clap::{Args, Subcommand};
+
+use std::path::PathBuf;
+
+use crate::metadata::{KeyDate, Metadata};
+use crate::warning::{warn, Code};
+use crate::{exit_with_error, find_project_root};
+
+/// Number of days before a key date it is flagged as approaching
+const WARN_DAYS: i64 = 30;
+
+#[derive(Subcommand)]
+pub enum DatesCommand {
+    /// Record a key date of the project, like the deadline for deleting the data
+    Add(AddArgs),
+
+    /// List the key dates of the project, and how far away they are
+    List,
+
+    /// Remove a key date
+    Remove(RemoveArgs),
+
+    /// Export the key dates as an iCalendar file, to import in a calendar
+    Ical(IcalArgs),
+}
+
+#[derive(Args)]
+pub struct AddArgs {
+    /// Name of the date, e.g. "data_deletion", "embargo_end" or "resubmission"
+    name: String,
+
+    /// The date, as YYYY-MM-DD
+    date: String,
+
+    /// What is due, e.g. "Delete raw/ as required by the data management plan"
+    #[clap(long)]
+    note: Option<String>,
+}
+
+#[derive(Args)]
+pub struct RemoveArgs {
+    /// Name of the date
+    name: String,
+}
+
+#[derive(Args)]
+pub struct IcalArgs {
+    /// Write the calendar to this file instead of stdout
+    #[clap(short, long)]
+    output: Option<PathBuf>,
+
+    /// Days before each date to be reminded, in the calendar
+    #[clap(long, default_value_t = 7)]
+    remind: i64,
+}
+
+fn parse_date(date: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()
+}
+
+fn today() -> NaiveDate {
+    Local::now().naive_local().date()
+}
+
+/// Describe when `key_date` is, relative to today, like "in 12 days" or "3 days ago".
+fn relative(key_date: &KeyDate) -> String {
+    let Some(date) = parse_date(&key_date.date) else {
+        return "invalid date".to_owned();
+    };
+    match (date - today()).num_days() {
+        0 => "today".to_owned(),
+        1 => "tomorrow".to_owned(),
+        days if days > 0 => format!("in {} days", days),
+        days => format!("{} days ago", -days),
+    }
+}
+
+/// Get the key dates of the project with `metadata` which are approaching or have passed,
+/// described as warnings.
+pub fn approaching(metadata: &Metadata) -> Vec<String> {
+    let today = today();
+    metadata
+        .dates
+        .iter()
+        .filter_map(|key_date| {
+            let Some(date) = parse_date(&key_date.date) else {
+                return Some(format!(
+                    "Key date {} has invalid date \"{}\"",
+                    key_date.name, key_date.date
+                ));
+            };
+            let days = (date - today).num_days();
+            if days < 0 {
+                Some(format!(
+                    "Key date {} passed {} days ago, on {}",
+                    key_date.name, -days, key_date.date
+                ))
+            } else if days <= WARN_DAYS {
+                Some(format!(
+                    "Key date {} is {}, on {}",
+                    key_date.name,
+                    relative(key_date),
+                    key_date.date
+                ))
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+fn add(args: AddArgs) {
+    let root = find_project_root();
+    let mut metadata = Metadata::load(&root);
+    if parse_date(&args.date).is_none() {
+        exit_with_error(&format!(
+            "Date \"{}\" is not in YYYY-MM-DD format",
+            args.date
+        ))
+    }
+    if metadata.dates.iter().any(|date| date.name == args.name) {
+        exit_with_error(&format!(
+            "The project already has a date named {}. Remove it first",
+            args.name
+        ))
+    }
+    let key_date = KeyDate {
+        name: args.name,
+        date: args.date,
+        note: args.note,
+    };
+    println!(
+        "Added {} on {}, {}",
+        key_date.name,
+        key_date.date,
+        relative(&key_date)
+    );
+    metadata.dates.push(key_date);
+    metadata.dates.sort_by(|a, b| a.date.cmp(&b.date));
+    metadata.save(&root);
+}
+
+fn list() {
+    let metadata = Metadata::load(&find_project_root());
+    if metadata.dates.is_empty() {
+        println!("No key dates recorded. Add one with `investigate dates add`");
+        return;
+    }
+    let width = metadata
+        .dates
+        .iter()
+        .map(|date| date.name.len())
+        .max()
+        .unwrap_or(0);
+    for key_date in &metadata.dates {
+        let line = format!(
+            "{:<width$}  {}  {:<14}  {}",
+            key_date.name,
+            key_date.date,
+            relative(key_date),
+            key_date.note.as_deref().unwrap_or_default(),
+            width = width
+        );
+        println!("{}", line.trim_end());
+    }
+}
+
+fn remove(args: RemoveArgs) {
+    let root = find_project_root();
+    let mut metadata = Metadata::load(&root);
+    let n_dates = metadata.dates.len();
+    metadata.dates.retain(|date| date.name != args.name);
+    if metadata.dates.len() == n_dates {
+        exit_with_error(&format!("The project has no date named {}", args.name))
+    }
+    metadata.save(&root);
+    println!("Removed {}", args.name);
+}
+
+/// Escape `text` for a text value of iCalendar.
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+/// Get the key dates of the project with `metadata` as an iCalendar file, with an all-day event
+/// for each date and a reminder `remind` days before.
+fn calendar(metadata: &Metadata, remind: i64) -> String {
+    let stamp = Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_owned(),
+        "VERSION:2.0".to_owned(),
+        format!("PRODID:-//investigate//{}//EN", env!("CARGO_PKG_VERSION")),
+    ];
+    for key_date in &metadata.dates {
+        let Some(date) = parse_date(&key_date.date) else {
+            warn(
+                Code::InvalidRecord,
+                &format!(
+                    "Skipping key date {} with invalid date \"{}\"",
+                    key_date.name, key_date.date
+                ),
+            );
+            continue;
+        };
+        lines.extend([
+            "BEGIN:VEVENT".to_owned(),
+            // Stable, so importing the file again updates the events instead of duplicating them
+            format!("UID:{}-{}@investigate", metadata.uuid, key_date.name),
+            format!("DTSTAMP:{}", stamp),
+            format!("DTSTART;VALUE=DATE:{}", date.format("%Y%m%d")),
+            format!(
+                "DTEND;VALUE=DATE:{}",
+                (date + Duration::days(1)).format("%Y%m%d")
+            ),
+            format!(
+                "SUMMARY:{}",
+                escape(&format!("{}: {}", metadata.name, key_date.name))
+            ),
+        ]);
+        if let Some(note) = &key_date.note {
+            lines.push(format!("DESCRIPTION:{}", escape(note)));
+        }
+        if remind > 0 {
+            lines.extend([
+                "BEGIN:VALARM".to_owned(),
+                "ACTION:DISPLAY".to_owned(),
+                format!(
+                    "DESCRIPTION:{}",
+                    escape(&format!("{} of {}", key_date.name, metadata.name))
+                ),
+                format!("TRIGGER:-P{}D", remind),
+                "END:VALARM".to_owned(),
+            ]);
+        }
+        lines.push("END:VEVENT".to_owned());
+    }
+    lines.push("END:VCALENDAR".to_owned());
+    // iCalendar requires CRLF line endings
+    lines.join("\r\n") + "\r\n"
+}
+
+fn ical(args: IcalArgs) {
+    let metadata = Metadata::load(&find_project_root());
+    if metadata.dates.is_empty() {
+        exit_with_error("No key dates recorded. Add them with `investigate dates add`")
+    }
+    let calendar = calendar(&metadata, args.remind);
+    match args.output {
+        None => print!("{}", calendar),
+        Some(path) => {
+            std::fs::write(&path, calendar)
+                .unwrap_or_else(|_| panic!("Error when writing file {:?}", path));
+            println!("Wrote {} key dates to {:?}", metadata.dates.len(), path);
+        }
+    }
+}
+
+pub fn run(command: DatesCommand) {
+    match command {
+        DatesCommand::Add(args) => add(args),
+        DatesCommand::List => list(),
+        DatesCommand::Remove(args) => remove(args),
+        DatesCommand::Ical(args) => ical(args),
+    }
+}
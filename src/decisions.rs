@@ -0,0 +1,27 @@
+use chrono::Local;
+
+use std::fs::read_to_string;
+use std::path::Path;
+
+use crate::filesystem;
+use crate::templates::Templates;
+
+/// Name of the decision log in the root of every project
+pub const DECISIONS_FILE: &str = "DECISIONS.md";
+
+/// Add an entry to the decision log of the project at `root`, creating the log if needed.
+pub fn record(root: &Path, templates: &Templates, title: &str, author: &str, text: &str) {
+    let path = root.join(DECISIONS_FILE);
+    let mut content = read_to_string(&path).unwrap_or_else(|_| templates.get("decisions"));
+    content.push_str(&templates.render(
+        "decision",
+        &[
+            ("date", &Local::today().format("%Y-%m-%d").to_string()),
+            ("title", title),
+            ("author", author),
+            ("text", text),
+        ],
+    ));
+    filesystem::write(&path, content)
+        .unwrap_or_else(|_| panic!("Error when writing file {:?}", path))
+}
@@ -0,0 +1,165 @@
+use clap::{Args, Subcommand};
+
+use std::fs::read_to_string;
+use std::path::{Path, PathBuf};
+
+use crate::new::{write, Existing};
+use crate::report::walk_files;
+use crate::results::{delimiter, parse_table};
+use crate::trash::trash;
+use crate::{exit_with_error, find_project_root};
+
+/// Suffix of a data dictionary, which replaces the extension of the table it describes.
+const DICTIONARY_SUFFIX: &str = ".dictionary.tsv";
+
+#[derive(Subcommand)]
+pub enum DictCommand {
+    /// Create a data dictionary describing the columns of a table in results/
+    New(NewArgs),
+}
+
+#[derive(Args)]
+pub struct NewArgs {
+    /// The table, as a path or as its name in results/, e.g. `counts` for results/counts.csv
+    table: PathBuf,
+
+    /// Overwrite an existing dictionary
+    #[clap(long)]
+    force: bool,
+}
+
+/// Get the path of the data dictionary of the table at `table`.
+fn dictionary_path(table: &Path) -> PathBuf {
+    let stem = table.file_stem().unwrap_or_default().to_string_lossy();
+    table.with_file_name(format!("{}{}", stem, DICTIONARY_SUFFIX))
+}
+
+/// Check if `path` is a table which should have a data dictionary.
+fn is_table(path: &Path) -> bool {
+    delimiter(path).is_some() && !path.to_string_lossy().ends_with(DICTIONARY_SUFFIX)
+}
+
+/// Get the names of the columns described by the data dictionary at `path`.
+fn described_columns(path: &Path) -> Option<Vec<String>> {
+    let content = read_to_string(path).ok()?;
+    Some(
+        parse_table(&content, '\t')
+            .into_iter()
+            .skip(1)
+            .filter_map(|row| row.into_iter().next())
+            .collect(),
+    )
+}
+
+/// Guess the type of a column from its values, ignoring missing values.
+fn column_type<'a>(values: impl Iterator<Item = &'a str>) -> &'static str {
+    let values: Vec<&str> = values
+        .map(str::trim)
+        .filter(|v| !["", "NA", "NaN", "nan", "null", "None"].contains(v))
+        .collect();
+    let all = |f: fn(&str) -> bool| !values.is_empty() && values.iter().all(|v| f(v));
+    if all(|v| v.parse::<i64>().is_ok()) {
+        "integer"
+    } else if all(|v| v.parse::<f64>().is_ok()) {
+        "number"
+    } else if all(|v| ["true", "false"].contains(&v.to_lowercase().as_str())) {
+        "boolean"
+    } else if all(|v| chrono::NaiveDate::parse_from_str(v, "%Y-%m-%d").is_ok()) {
+        "date"
+    } else {
+        "string"
+    }
+}
+
+/// Find the table given as `table`: a path, or the name of a table in results/.
+fn find_table(root: &Path, table: &Path) -> PathBuf {
+    if table.is_file() {
+        return table.to_owned();
+    }
+    ["csv", "tsv"]
+        .iter()
+        .map(|extension| root.join("results").join(table).with_extension(extension))
+        .find(|path| path.is_file())
+        .unwrap_or_else(|| exit_with_error(&format!("No table {:?} found", table)))
+}
+
+fn new(args: NewArgs) {
+    let root = find_project_root();
+    let table = find_table(&root, &args.table);
+    let delimiter = delimiter(&table)
+        .unwrap_or_else(|| exit_with_error(&format!("{:?} is not a .csv or .tsv table", table)));
+    let content = read_to_string(&table)
+        .unwrap_or_else(|_| exit_with_error(&format!("Could not read {:?}", table)));
+    let rows = parse_table(&content, delimiter);
+    let Some((header, records)) = rows.split_first() else {
+        exit_with_error(&format!("Table {:?} is empty", table))
+    };
+    let path = dictionary_path(&table);
+    if path.exists() && !args.force {
+        exit_with_error(&format!(
+            "{:?} already exists. Use --force to overwrite it",
+            path
+        ))
+    }
+    // Dictionaries of tables outside the project are overwritten, having no trash
+    let in_project = std::path::absolute(&path).is_ok_and(|path| path.starts_with(&root));
+    if path.exists() && in_project {
+        let id = trash(&root, std::slice::from_ref(&path));
+        println!("Moved the old dictionary to the trash as {}", id);
+    }
+    let mut lines = vec!["column\ttype\tunit\tdescription".to_owned()];
+    for (i, column) in header.iter().enumerate() {
+        let values = records
+            .iter()
+            .filter_map(|record| record.get(i).map(String::as_str));
+        lines.push(format!("{}\t{}\t\t", column, column_type(values)));
+    }
+    write(&path, &(lines.join("\n") + "\n"), Existing::Force);
+    println!(
+        "Wrote {:?} with the {} columns of the table. Fill in their units and descriptions",
+        path,
+        header.len()
+    );
+}
+
+/// Get the problems with the data dictionaries of the tables in results/: tables without a
+/// dictionary, and columns missing from their dictionary.
+pub fn problems(root: &Path) -> Vec<String> {
+    let mut problems = Vec::new();
+    for table in walk_files(&root.join("results")) {
+        if !is_table(&table) {
+            continue;
+        }
+        let relative = table.strip_prefix(root).unwrap_or(&table);
+        let Some(described) = described_columns(&dictionary_path(&table)) else {
+            problems.push(format!(
+                "Table {} has no data dictionary. Create it with `investigate dict new {}`",
+                relative.display(),
+                relative.display()
+            ));
+            continue;
+        };
+        let header = read_to_string(&table)
+            .ok()
+            .and_then(|content| parse_table(&content, delimiter(&table)?).into_iter().next())
+            .unwrap_or_default();
+        let missing: Vec<String> = header
+            .into_iter()
+            .filter(|column| !described.contains(column))
+            .collect();
+        if !missing.is_empty() {
+            problems.push(format!(
+                "Data dictionary of {} does not describe the columns {}",
+                relative.display(),
+                missing.join(", ")
+            ));
+        }
+    }
+    problems
+}
+
+pub fn run(command: DictCommand) {
+    match command {
+        DictCommand::New(args) => new(args),
+    }
+}
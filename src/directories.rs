@@ -0,0 +1,91 @@
+use serde::{Deserialize, Serialize};
+
+/// The directories created in a project by default, with their descriptions.
+const DEFAULT_DIRECTORIES: [(&str, &str); 7] = [
+    (
+        "raw",
+        "Raw data, e.g. experimental data, or data from external research groups.\n  \
+        Should not be modified at all.",
+    ),
+    (
+        "src",
+        "This directory contains code and scripts used to reproduce the results.\n  \
+        The file `main.py` or `<ModuleName>.jl` should produce all results using only the data\n  \
+        in directories `raw` and `choices`.",
+    ),
+    (
+        "tmp",
+        "Directory for throwaway analyses and intermediate results.\n  \
+        Anything in this directory should be able to be deleted with no big loss",
+    ),
+    (
+        "cache",
+        "Also for intermediate results, but for content that is troublesome\n  \
+        to recreate, e.g. results of long-running simuations or long-running computation",
+    ),
+    (
+        "choices",
+        "For files that are not raw files, but impossible to recreate automatically,\n  \
+        because they rely on humans (you!) making judgement calls.",
+    ),
+    (
+        "results",
+        "For final analytic results. The main script should write results to this\n  \
+        directory, primarily",
+    ),
+    (
+        "paper",
+        "For results related to submission of any papers, e.g. manuscripts or\n  \
+        publication-ready figures.",
+    ),
+];
+
+/// A directory in a project. The name may be a nested path like "results/figures".
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Directory {
+    pub name: String,
+    pub description: Option<String>,
+}
+
+/// A directory as given in the config file: Either just a name, or a name and description.
+#[derive(Clone, Deserialize)]
+#[serde(untagged)]
+pub enum DirectorySpec {
+    Name(String),
+    Full(Directory),
+}
+
+impl DirectorySpec {
+    /// Get the directory, using the default description for known directories without one.
+    pub fn resolve(&self) -> Directory {
+        let (name, description) = match self {
+            DirectorySpec::Name(name) => (name, None),
+            DirectorySpec::Full(dir) => (&dir.name, dir.description.clone()),
+        };
+        let name = name.trim_matches('/').to_owned();
+        let description = description.or_else(|| {
+            DEFAULT_DIRECTORIES
+                .iter()
+                .find(|(n, _)| *n == name)
+                .map(|(_, d)| d.to_string())
+        });
+        Directory { name, description }
+    }
+}
+
+pub fn default_directories() -> Vec<Directory> {
+    DEFAULT_DIRECTORIES
+        .iter()
+        .map(|(name, description)| Directory {
+            name: name.to_string(),
+            description: Some(description.to_string()),
+        })
+        .collect()
+}
+
+pub fn default_directory_names() -> Vec<String> {
+    DEFAULT_DIRECTORIES
+        .iter()
+        .map(|(name, _)| name.to_string())
+        .collect()
+}
@@ -0,0 +1,222 @@
+use chrono::Local;
+use clap::Args;
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::author::author_string;
+use crate::config::Config;
+use crate::data::sha256;
+use crate::lock::lock;
+use crate::manifest::{DataEntry, Manifest, MANIFEST_FILE};
+use crate::metadata::{Metadata, METADATA_FILE};
+use crate::warning::{warn, Code};
+use crate::{exit_with_error, find_project_root};
+
+#[derive(Args)]
+pub struct EncryptArgs {
+    /// Files in raw/ to encrypt
+    #[clap(required = true)]
+    files: Vec<PathBuf>,
+
+    /// Keep the unencrypted files
+    #[clap(long)]
+    keep: bool,
+}
+
+#[derive(Args)]
+pub struct DecryptArgs {
+    /// Encrypted files in raw/ to decrypt, with or without their .age or .gpg extension
+    #[clap(required = true)]
+    files: Vec<PathBuf>,
+
+    /// File with the age identity (private key) to decrypt with. Not needed for GPG
+    #[clap(short, long)]
+    identity: Option<PathBuf>,
+}
+
+/// The program used to encrypt data, determined by the kind of recipients.
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum Tool {
+    Age,
+    Gpg,
+}
+
+impl Tool {
+    fn extension(self) -> &'static str {
+        match self {
+            Tool::Age => "age",
+            Tool::Gpg => "gpg",
+        }
+    }
+}
+
+/// Get the tool which encrypts for `recipients`: age for age and SSH public keys, else GPG.
+fn tool(recipients: &[String]) -> Tool {
+    let is_age = |r: &String| r.starts_with("age1") || r.starts_with("ssh-");
+    if recipients.iter().all(is_age) {
+        Tool::Age
+    } else if !recipients.iter().any(is_age) {
+        Tool::Gpg
+    } else {
+        exit_with_error("Recipients cannot mix age or SSH keys with GPG keys")
+    }
+}
+
+fn run_tool(command: &mut Command, program: &str, path: &Path) {
+    let status = command
+        .status()
+        .unwrap_or_else(|_| exit_with_error(&format!("Could not run {}", program)));
+    if !status.success() {
+        exit_with_error(&format!("{} failed on {:?}", program, path))
+    }
+}
+
+/// Get `path` relative to raw/ in the project at `root`, or exit if it's not in raw/.
+fn relative_to_raw(root: &Path, path: &Path) -> String {
+    let raw = root.join("raw").canonicalize().unwrap_or_default();
+    let absolute = std::path::absolute(path).unwrap_or_else(|_| path.to_owned());
+    let parent = absolute
+        .parent()
+        .and_then(|parent| parent.canonicalize().ok())
+        .unwrap_or_default();
+    match parent
+        .join(absolute.file_name().unwrap_or_default())
+        .strip_prefix(&raw)
+    {
+        Ok(relative) => relative.to_string_lossy().into_owned(),
+        Err(_) => exit_with_error(&format!(
+            "{:?} is not in the project's raw/ directory",
+            path
+        )),
+    }
+}
+
+pub fn encrypt(args: EncryptArgs) {
+    let root = find_project_root();
+    let metadata = Metadata::load(&root);
+    if metadata.encryption_recipients.is_empty() {
+        exit_with_error(&format!(
+            "No encryption recipients. Add age or SSH public keys, or GPG key IDs, \
+            to encryption_recipients in {}",
+            METADATA_FILE
+        ))
+    }
+    let tool = tool(&metadata.encryption_recipients);
+    let _lock = lock(&root.join(MANIFEST_FILE));
+    let mut manifest = Manifest::load(&root);
+    for file in &args.files {
+        if !file.is_file() {
+            exit_with_error(&format!("{:?} is not a file", file))
+        }
+        let relative = relative_to_raw(&root, file);
+        let encrypted_name = format!("{}.{}", relative, tool.extension());
+        let encrypted = root.join("raw").join(&encrypted_name);
+        // The manifest records the checksum of the plaintext, which stays the same
+        // no matter who the data is encrypted for
+        let checksum =
+            sha256(file).unwrap_or_else(|_| exit_with_error(&format!("Could not read {:?}", file)));
+        let mut command = match tool {
+            Tool::Age => Command::new("age"),
+            Tool::Gpg => {
+                let mut command = Command::new("gpg");
+                command.args(["--batch", "--yes", "--encrypt"]);
+                command
+            }
+        };
+        for recipient in &metadata.encryption_recipients {
+            command.args(["-r", recipient]);
+        }
+        command.arg("-o").arg(&encrypted).arg(file);
+        run_tool(&mut command, tool.extension(), file);
+
+        let mut entry = match manifest.data.iter().find(|e| e.path == relative) {
+            Some(entry) => entry.clone(),
+            None => DataEntry {
+                path: relative.clone(),
+                source: std::path::absolute(file)
+                    .unwrap_or_else(|_| file.clone())
+                    .to_string_lossy()
+                    .into_owned(),
+                method: "in-place".to_owned(),
+                size: file.metadata().map(|m| m.len()).unwrap_or_default(),
+                sha256: checksum.clone(),
+                added: Local::today().format("%Y-%m-%d").to_string(),
+                author: author_string(&Config::load()),
+                encrypted: None,
+            },
+        };
+        if entry.sha256 != checksum {
+            warn(
+                Code::FileChanged,
+                &format!(
+                    "Checksum of {:?} differs from the one in the manifest. \
+                    Recording the new checksum",
+                    file
+                ),
+            );
+            entry.sha256 = checksum;
+        }
+        entry.encrypted = Some(encrypted_name);
+        manifest.insert(entry);
+        if !args.keep {
+            std::fs::remove_file(file)
+                .unwrap_or_else(|_| panic!("Error when removing file {:?}", file));
+        }
+        println!("Encrypted {:?} to {:?}", file, encrypted);
+    }
+    manifest.save(&root);
+}
+
+pub fn decrypt(args: DecryptArgs) {
+    let root = find_project_root();
+    let manifest = Manifest::load(&root);
+    for file in &args.files {
+        let (encrypted, plaintext) = match file.extension().and_then(|e| e.to_str()) {
+            Some("age" | "gpg") => (file.clone(), file.with_extension("")),
+            _ => {
+                let candidates = ["age", "gpg"].map(|extension| {
+                    let mut name = file.clone().into_os_string();
+                    name.push(format!(".{}", extension));
+                    PathBuf::from(name)
+                });
+                match candidates.into_iter().find(|c| c.is_file()) {
+                    Some(encrypted) => (encrypted, file.clone()),
+                    None => exit_with_error(&format!("No encrypted file found for {:?}", file)),
+                }
+            }
+        };
+        let is_age = encrypted.extension().is_some_and(|e| e == "age");
+        let mut command = if is_age {
+            let identity = args
+                .identity
+                .as_ref()
+                .unwrap_or_else(|| exit_with_error("Decrypting with age requires --identity"));
+            let mut command = Command::new("age");
+            command.arg("--decrypt").arg("-i").arg(identity);
+            command
+        } else {
+            let mut command = Command::new("gpg");
+            command.args(["--batch", "--yes", "--decrypt"]);
+            command
+        };
+        command.arg("-o").arg(&plaintext).arg(&encrypted);
+        run_tool(&mut command, if is_age { "age" } else { "gpg" }, &encrypted);
+
+        let relative = relative_to_raw(&root, &plaintext);
+        if let Some(entry) = manifest.data.iter().find(|e| e.path == relative) {
+            let checksum = sha256(&plaintext)
+                .unwrap_or_else(|_| exit_with_error(&format!("Could not read {:?}", plaintext)));
+            if checksum != entry.sha256 {
+                warn(
+                    Code::FileChanged,
+                    &format!(
+                        "Checksum of decrypted {:?} does not match the manifest",
+                        plaintext
+                    ),
+                );
+            }
+        }
+        println!("Decrypted {:?} to {:?}", encrypted, plaintext);
+    }
+}
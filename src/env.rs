@@ -0,0 +1,392 @@
+use clap::{Args, Subcommand};
+
+use std::collections::BTreeMap;
+use std::fs::read_to_string;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::metadata::Metadata;
+use crate::new::Language;
+use crate::progress::Steps;
+use crate::warning::{warn, Code};
+use crate::wsl;
+use crate::{exit_with_error, filesystem, find_project_root, is_offline};
+
+#[derive(Subcommand)]
+pub enum EnvCommand {
+    /// Delete the project's environment, and create it again from the spec files only
+    Recreate,
+
+    /// Compare a Conda environment to the project's spec file
+    Diff(DiffArgs),
+}
+
+#[derive(Args)]
+pub struct DiffArgs {
+    /// Name of the Conda environment (default: the active environment, else the project's)
+    name: Option<String>,
+
+    /// Write the differences to the spec file
+    #[clap(long)]
+    update_spec: bool,
+}
+
+/// A package in a Conda environment spec, e.g. "numpy>=1.20" or "pip: requests".
+#[derive(Clone, PartialEq, Eq)]
+pub struct Package {
+    pub name: String,
+    /// The version constraint, e.g. ">=1.20", if any
+    pub version: Option<String>,
+    /// Whether the package is installed by pip rather than Conda
+    pub pip: bool,
+}
+
+impl Package {
+    fn parse(spec: &str, pip: bool) -> Package {
+        // Remove channel prefix like "conda-forge::numpy"
+        let spec = spec.rsplit("::").next().unwrap_or(spec).trim();
+        match spec.find(['=', '<', '>', '!', '~', ' ', '[']) {
+            Some(i) => Package {
+                name: spec[..i].to_owned(),
+                version: Some(spec[i..].trim().to_owned()).filter(|v| !v.is_empty()),
+                pip,
+            },
+            None => Package {
+                name: spec.to_owned(),
+                version: None,
+                pip,
+            },
+        }
+    }
+
+    pub fn spec(&self) -> String {
+        format!("{}{}", self.name, self.version.as_deref().unwrap_or(""))
+    }
+
+    /// Get the package pinned to exactly `version`.
+    fn pinned(&self, version: &str) -> Package {
+        let operator = if self.pip { "==" } else { "=" };
+        Package {
+            version: Some(format!("{}{}", operator, version)),
+            ..self.clone()
+        }
+    }
+}
+
+/// Parse the `dependencies` section of a Conda environment file.
+pub fn parse_dependencies(text: &str) -> Vec<Package> {
+    let mut packages = Vec::new();
+    let mut in_dependencies = false;
+    let mut in_pip = false;
+    for line in text.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        // A new top-level key ends the dependencies section
+        if !line.starts_with([' ', '-']) {
+            in_dependencies = trimmed == "dependencies:";
+            in_pip = false;
+            continue;
+        }
+        if !in_dependencies {
+            continue;
+        }
+        let Some(item) = trimmed.strip_prefix('-') else {
+            continue;
+        };
+        let item = item.trim().trim_matches(['"', '\'']);
+        let indent = line.len() - line.trim_start().len();
+        if item == "pip:" {
+            in_pip = true;
+        } else if in_pip && indent > 2 {
+            packages.push(Package::parse(item, true));
+        } else {
+            in_pip = false;
+            packages.push(Package::parse(item, false));
+        }
+    }
+    packages
+}
+
+/// Get the environment variables which make Conda, pip and Julia's Pkg install packages only
+/// from their local caches, if --offline was given.
+pub fn offline_env() -> Vec<(&'static str, &'static str)> {
+    if is_offline() {
+        vec![
+            ("CONDA_OFFLINE", "true"),
+            ("PIP_NO_INDEX", "1"),
+            ("JULIA_PKG_OFFLINE", "true"),
+        ]
+    } else {
+        Vec::new()
+    }
+}
+
+/// Get the file the environment is created from: the lock file, if it exists.
+pub fn spec_file(root: &Path) -> Option<PathBuf> {
+    ["environment.lock.yml", "environment.yml"]
+        .iter()
+        .map(|name| root.join(name))
+        .find(|path| path.is_file())
+}
+
+/// Check if the Conda environment `name` of the project at `root` exists.
+pub fn conda_env_exists(root: &Path, name: &str) -> bool {
+    wsl::command("conda", root)
+        .args(["env", "list"])
+        .output()
+        .map(|output| {
+            String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .any(|line| line.split_whitespace().next() == Some(name))
+        })
+        .unwrap_or(false)
+}
+
+/// Get the packages explicitly installed in the Conda environment `name` of the project at
+/// `root`, if it exists.
+pub fn live_packages(root: &Path, name: &str) -> Option<Vec<Package>> {
+    if !conda_env_exists(root, name) {
+        return None;
+    }
+    let output = wsl::command("conda", root)
+        .args(["env", "export", "--from-history", "-n", name])
+        .output()
+        .ok()?;
+    output
+        .status
+        .success()
+        .then(|| parse_dependencies(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Get the installed version of every package in the Conda environment `name`.
+fn installed_versions(root: &Path, name: &str) -> BTreeMap<String, String> {
+    let output = wsl::command("conda", root)
+        .args(["list", "--json", "-n", name])
+        .output()
+        .unwrap_or_else(|_| exit_with_error("Could not run conda"));
+    let list: serde_json::Value = serde_json::from_slice(&output.stdout)
+        .unwrap_or_else(|_| exit_with_error("Could not parse output of conda list"));
+    list.as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|package| {
+            Some((
+                package.get("name")?.as_str()?.to_owned(),
+                package.get("version")?.as_str()?.to_owned(),
+            ))
+        })
+        .collect()
+}
+
+/// Replace the `dependencies` section of the Conda environment file `text` by `packages`.
+fn write_dependencies(text: &str, packages: &[Package]) -> String {
+    let mut section = vec!["dependencies:".to_owned()];
+    section.extend(
+        packages
+            .iter()
+            .filter(|p| !p.pip)
+            .map(|p| format!("  - {}", p.spec())),
+    );
+    if packages.iter().any(|p| p.pip) {
+        section.push("  - pip:".to_owned());
+        section.extend(
+            packages
+                .iter()
+                .filter(|p| p.pip)
+                .map(|p| format!("    - {}", p.spec())),
+        );
+    }
+    let mut lines: Vec<String> = Vec::new();
+    let mut in_dependencies = false;
+    let mut found = false;
+    for line in text.lines() {
+        if !line.trim().is_empty() && !line.starts_with([' ', '-', '#']) {
+            in_dependencies = line.starts_with("dependencies:");
+            if in_dependencies {
+                found = true;
+                lines.append(&mut section);
+            }
+        }
+        if !in_dependencies {
+            lines.push(line.to_owned());
+        }
+    }
+    if !found {
+        lines.append(&mut section);
+    }
+    lines.join("\n") + "\n"
+}
+
+fn diff(args: DiffArgs) {
+    let root = find_project_root();
+    let metadata = Metadata::load(&root);
+    let name = args
+        .name
+        .or_else(|| {
+            std::env::var("CONDA_DEFAULT_ENV")
+                .ok()
+                .filter(|e| e != "base")
+        })
+        .or(metadata.environment)
+        .unwrap_or_else(|| exit_with_error("No Conda environment given, active or in project"));
+    let spec = spec_file(&root)
+        .unwrap_or_else(|| exit_with_error("No environment.yml or environment.lock.yml found"));
+    let text = read_to_string(&spec)
+        .unwrap_or_else(|_| exit_with_error(&format!("Could not read {:?}", spec)));
+    let specified = parse_dependencies(&text);
+    let live = live_packages(&root, &name).unwrap_or_else(|| {
+        exit_with_error(&format!("Conda environment \"{}\" does not exist", name))
+    });
+    let versions = installed_versions(&root, &name);
+
+    let mut updated = Vec::new();
+    let mut changes = 0;
+    for package in &specified {
+        match versions.get(&package.name) {
+            None => {
+                println!("- {}", package.spec());
+                changes += 1;
+            }
+            Some(version) => {
+                // Only exact pins can be said to be violated
+                let pinned = package
+                    .version
+                    .as_deref()
+                    .and_then(|v| v.strip_prefix("==").or_else(|| v.strip_prefix('=')));
+                match pinned {
+                    Some(pin) if !version.starts_with(pin.trim_end_matches(['*', '.'])) => {
+                        println!("~ {} (installed: {})", package.spec(), version);
+                        changes += 1;
+                        updated.push(package.pinned(version));
+                    }
+                    _ => updated.push(package.clone()),
+                }
+            }
+        }
+    }
+    for package in &live {
+        if !specified.iter().any(|s| s.name == package.name) {
+            let added = match versions.get(&package.name) {
+                Some(version) => package.pinned(version),
+                None => package.clone(),
+            };
+            println!("+ {}", added.spec());
+            changes += 1;
+            updated.push(added);
+        }
+    }
+    if changes == 0 {
+        println!(
+            "Conda environment \"{}\" matches {:?}",
+            name,
+            spec.file_name().unwrap_or_default()
+        );
+    } else if args.update_spec {
+        filesystem::write(&spec, write_dependencies(&text, &updated))
+            .unwrap_or_else(|_| panic!("Error when writing file {:?}", spec));
+        println!("Updated {:?}", spec);
+    }
+}
+
+/// Run `command` as the step `name`, showing its output only if it fails.
+fn run_checked(steps: &mut Steps, name: &str, command: &mut Command, description: &str) {
+    let result = steps.run(name, None, |_| {
+        let output = command
+            .output()
+            .unwrap_or_else(|_| exit_with_error(&format!("Could not {}", description)));
+        match output.status.success() {
+            true => Ok(()),
+            false => Err(output.stderr),
+        }
+    });
+    if let Err(stderr) = result {
+        eprint!("{}", String::from_utf8_lossy(&stderr));
+        exit_with_error(&format!("Could not {}", description))
+    }
+}
+
+fn recreate_conda(root: &Path, name: &str, steps: &mut Steps) {
+    let spec = spec_file(root)
+        .unwrap_or_else(|| exit_with_error("No environment.yml or environment.lock.yml found"));
+    let text = read_to_string(&spec)
+        .unwrap_or_else(|_| exit_with_error(&format!("Could not read {:?}", spec)));
+    let specified = parse_dependencies(&text);
+    wsl::check(root);
+    match live_packages(root, name) {
+        None => println!("Conda environment \"{}\" does not exist", name),
+        Some(live) => {
+            let missing: Vec<_> = live
+                .iter()
+                .filter(|p| !specified.iter().any(|s| s.name == p.name))
+                .collect();
+            if !missing.is_empty() {
+                println!(
+                    "These packages are installed, but not in {:?}, and will be removed:",
+                    spec.file_name().unwrap_or_default()
+                );
+                for package in missing {
+                    println!("  {}", package.spec());
+                }
+            }
+            run_checked(
+                steps,
+                "Remove Conda environment",
+                wsl::command("conda", root).args(["env", "remove", "-y", "-n", name]),
+                &format!("remove Conda environment \"{}\"", name),
+            );
+        }
+    }
+    run_checked(
+        steps,
+        "Create Conda environment",
+        wsl::command("conda", root)
+            .args(["env", "create", "-n", name, "-f"])
+            // Relative to the project, as the path may be translated to one inside WSL
+            .arg(spec.file_name().unwrap_or_default())
+            .envs(offline_env()),
+        &format!("create Conda environment \"{}\"", name),
+    );
+    println!("Recreated Conda environment \"{}\" from {:?}", name, spec);
+}
+
+fn recreate_julia(root: &Path, steps: &mut Steps) {
+    // The packages are shared in the depot, so only the project's manifest can be recreated
+    if !root.join("Manifest.toml").is_file() {
+        warn(
+            Code::NotLocked,
+            "No Manifest.toml found, so package versions are resolved anew",
+        )
+    }
+    wsl::check(root);
+    run_checked(
+        steps,
+        "Instantiate Julia environment",
+        wsl::command("julia", root)
+            .arg("--project=.")
+            .args(["-e", "using Pkg; Pkg.instantiate(); Pkg.precompile()"])
+            .envs(offline_env()),
+        "instantiate Julia environment",
+    );
+    println!("Instantiated Julia environment from Project.toml and Manifest.toml");
+}
+
+fn recreate() {
+    let root = find_project_root();
+    let metadata = Metadata::load(&root);
+    let mut steps = Steps::new();
+    match (metadata.language, &metadata.environment) {
+        (_, Some(name)) => recreate_conda(&root, name, &mut steps),
+        (Some(Language::Julia), None) => recreate_julia(&root, &mut steps),
+        _ => exit_with_error("Project has no environment"),
+    }
+    steps.summary();
+}
+
+pub fn run(command: EnvCommand) {
+    match command {
+        EnvCommand::Recreate => recreate(),
+        EnvCommand::Diff(args) => diff(args),
+    }
+}
@@ -0,0 +1,78 @@
+use clap::Args;
+
+use std::ffi::OsString;
+use std::path::Path;
+use std::process::Command;
+
+use crate::metadata::Metadata;
+use crate::new::Language;
+use crate::wsl;
+use crate::{exit_with_error, find_project_root};
+
+#[derive(Args)]
+pub struct ExecArgs {
+    /// Command to run, given after `--`
+    #[clap(last = true, required = true)]
+    command: Vec<OsString>,
+}
+
+/// Get a command which runs `program` with `args` in the environment of the project at `root`:
+/// Its Conda environment, its Python virtual environment in `.venv`, or its Julia project.
+/// With --wsl on Windows, it runs inside WSL, like Conda and Julia when creating the project.
+pub fn project_command(root: &Path, program: &OsString, args: &[OsString]) -> Command {
+    let metadata = Metadata::load(root);
+    let venv = root.join(".venv");
+    if venv.is_dir() && wsl::is_used(root) {
+        // The PATH inside WSL is only known there, so its shell puts the environment first
+        let mut command = wsl::command("sh", root);
+        command
+            .args([
+                "-c",
+                r#"VIRTUAL_ENV="$PWD/.venv" PATH="$PWD/.venv/bin:$PATH" exec "$@""#,
+                "sh",
+            ])
+            .arg(program)
+            .args(args);
+        command
+    } else if venv.is_dir() {
+        let mut paths = vec![venv.join("bin")];
+        paths.extend(std::env::split_paths(
+            &std::env::var_os("PATH").unwrap_or_default(),
+        ));
+        let mut command = wsl::command(program, root);
+        command
+            .env("VIRTUAL_ENV", &venv)
+            .env(
+                "PATH",
+                std::env::join_paths(paths).expect("Error when joining PATH"),
+            )
+            .args(args);
+        command
+    } else if let Some(environment) = &metadata.environment {
+        let mut command = wsl::command("conda", root);
+        command
+            .args(["run", "--no-capture-output", "-n", environment])
+            .arg(program)
+            .args(args);
+        command
+    } else {
+        let mut command = wsl::command(program, root);
+        if metadata.language == Some(Language::Julia) {
+            wsl::env_path(&mut command, "JULIA_PROJECT", root);
+        }
+        command.args(args);
+        command
+    }
+}
+
+pub fn run(args: ExecArgs) {
+    let root = find_project_root();
+    let (program, rest) = args
+        .command
+        .split_first()
+        .expect("Error when parsing command");
+    let status = project_command(&root, program, rest)
+        .status()
+        .unwrap_or_else(|_| exit_with_error(&format!("Could not run {:?}", program)));
+    std::process::exit(status.code().unwrap_or(1))
+}
@@ -0,0 +1,231 @@
+use clap::{Args, Subcommand};
+use git2::{BranchType, Repository, WorktreeAddOptions, WorktreePruneOptions};
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::data::symlink;
+use crate::git::{is_clean, relative_to_workdir};
+use crate::{exit_with_error, find_project_root};
+
+/// Directory of the experiments' worktrees, relative to the project root
+const EXPERIMENTS_DIR: &str = "tmp/experiments";
+
+#[derive(Subcommand)]
+pub enum ExperimentCommand {
+    /// Start an experiment on a new branch, checked out in tmp/experiments/<NAME>
+    Start(NameArgs),
+
+    /// Merge an experiment into the current branch, and remove it
+    Finish(NameArgs),
+
+    /// Remove an experiment, and delete its branch
+    Discard(DiscardArgs),
+
+    /// List the experiments of the project
+    List,
+}
+
+#[derive(Args)]
+pub struct NameArgs {
+    /// Name of the experiment
+    name: String,
+}
+
+#[derive(Args)]
+pub struct DiscardArgs {
+    /// Name of the experiment
+    name: String,
+
+    /// Discard the experiment, even if it has uncommitted changes
+    #[clap(long)]
+    force: bool,
+}
+
+fn branch_name(name: &str) -> String {
+    format!("experiment/{}", name)
+}
+
+fn worktree_name(name: &str) -> String {
+    format!("experiment-{}", name)
+}
+
+fn open_repository(root: &Path) -> Repository {
+    Repository::discover(root)
+        .unwrap_or_else(|_| exit_with_error("Experiments require the project to be tracked by git"))
+}
+
+/// Get the root of the project inside the worktree of experiment `name`.
+fn experiment_root(repo: &Repository, root: &Path, name: &str) -> (PathBuf, PathBuf) {
+    let worktree = root.join(EXPERIMENTS_DIR).join(name);
+    let prefix = relative_to_workdir(repo, root).unwrap_or_default();
+    let project = worktree.join(prefix);
+    (worktree, project)
+}
+
+/// Link every file in raw/ of the project at `root` which is not in raw/ of the experiment.
+fn share_raw(root: &Path, experiment: &Path) {
+    let raw = experiment.join("raw");
+    std::fs::create_dir_all(&raw)
+        .unwrap_or_else(|_| panic!("Error when creating directory {:?}", raw));
+    for entry in root.join("raw").read_dir().into_iter().flatten().flatten() {
+        let link = raw.join(entry.file_name());
+        if link.symlink_metadata().is_err() {
+            symlink(&entry.path(), &link)
+                .unwrap_or_else(|_| panic!("Error when linking {:?}", link));
+        }
+    }
+}
+
+fn start(name: &str) {
+    let root = find_project_root();
+    if name.is_empty() || name.contains(['/', '\\']) || name.starts_with('.') {
+        exit_with_error(
+            "Experiment name must be non-empty, and cannot contain '/' or start with '.'",
+        )
+    }
+    let repo = open_repository(&root);
+    let head = repo
+        .head()
+        .and_then(|head| head.peel_to_commit())
+        .unwrap_or_else(|_| exit_with_error("Cannot start experiment: repository has no commits"));
+    if repo
+        .find_branch(&branch_name(name), BranchType::Local)
+        .is_ok()
+    {
+        exit_with_error(&format!("Branch {} already exists", branch_name(name)))
+    }
+    let (worktree, project) = experiment_root(&repo, &root, name);
+    if worktree.exists() {
+        exit_with_error(&format!("{:?} already exists", worktree))
+    }
+    std::fs::create_dir_all(root.join(EXPERIMENTS_DIR))
+        .unwrap_or_else(|_| panic!("Error when creating directory {}", EXPERIMENTS_DIR));
+    let branch = repo
+        .branch(&branch_name(name), &head, false)
+        .unwrap_or_else(|e| exit_with_error(&format!("Could not create branch: {}", e.message())));
+    repo.worktree(
+        &worktree_name(name),
+        &worktree,
+        Some(WorktreeAddOptions::new().reference(Some(branch.get()))),
+    )
+    .unwrap_or_else(|e| exit_with_error(&format!("Could not create worktree: {}", e.message())));
+    share_raw(&root, &project);
+    println!(
+        "Started experiment \"{}\" on branch {} in {}/{}, sharing raw/ with the project",
+        name,
+        branch_name(name),
+        EXPERIMENTS_DIR,
+        name
+    );
+}
+
+/// Remove the worktree and branch of experiment `name`.
+fn remove(repo: &Repository, root: &Path, name: &str) {
+    let (_, project) = experiment_root(repo, root, name);
+    // Remove the links to the shared data first, so it can't be deleted with the worktree
+    for entry in project
+        .join("raw")
+        .read_dir()
+        .into_iter()
+        .flatten()
+        .flatten()
+    {
+        if entry.file_type().is_ok_and(|t| t.is_symlink()) {
+            let _ = std::fs::remove_file(entry.path());
+        }
+    }
+    let worktree = repo
+        .find_worktree(&worktree_name(name))
+        .unwrap_or_else(|_| exit_with_error(&format!("No experiment named \"{}\"", name)));
+    worktree
+        .prune(Some(
+            WorktreePruneOptions::new().valid(true).working_tree(true),
+        ))
+        .unwrap_or_else(|e| {
+            exit_with_error(&format!("Could not remove worktree: {}", e.message()))
+        });
+    if let Ok(mut branch) = repo.find_branch(&branch_name(name), BranchType::Local) {
+        branch.delete().unwrap_or_else(|e| {
+            exit_with_error(&format!("Could not delete branch: {}", e.message()))
+        });
+    }
+}
+
+/// Open the repository of the worktree of experiment `name`.
+fn open_experiment(repo: &Repository, root: &Path, name: &str) -> Repository {
+    let (worktree, _) = experiment_root(repo, root, name);
+    Repository::open(&worktree)
+        .unwrap_or_else(|_| exit_with_error(&format!("No experiment named \"{}\"", name)))
+}
+
+fn finish(name: &str) {
+    let root = find_project_root();
+    let repo = open_repository(&root);
+    if !is_clean(&open_experiment(&repo, &root, name)) {
+        exit_with_error("The experiment has uncommitted changes. Commit or discard them first")
+    }
+    if !is_clean(&repo) {
+        exit_with_error("The project has uncommitted changes. Commit or discard them first")
+    }
+    let workdir = repo
+        .workdir()
+        .expect("Error when finding git working directory");
+    let status = Command::new("git")
+        .args(["merge", "--no-ff", "-m"])
+        .arg(format!("Merge experiment {}", name))
+        .arg(branch_name(name))
+        .current_dir(workdir)
+        .status()
+        .unwrap_or_else(|_| exit_with_error("Could not run git"));
+    if !status.success() {
+        exit_with_error(&format!(
+            "Could not merge {}. Resolve the conflicts and commit, then remove the experiment \
+            with `investigate experiment discard {}`",
+            branch_name(name),
+            name
+        ))
+    }
+    remove(&repo, &root, name);
+    println!("Merged and removed experiment \"{}\"", name);
+}
+
+fn discard(args: DiscardArgs) {
+    let root = find_project_root();
+    let repo = open_repository(&root);
+    if !args.force && !is_clean(&open_experiment(&repo, &root, &args.name)) {
+        exit_with_error("The experiment has uncommitted changes. Use --force to discard them")
+    }
+    remove(&repo, &root, &args.name);
+    println!(
+        "Discarded experiment \"{}\" and deleted branch {}",
+        args.name,
+        branch_name(&args.name)
+    );
+}
+
+fn list() {
+    let root = find_project_root();
+    let repo = open_repository(&root);
+    let worktrees = repo.worktrees().expect("Error when listing git worktrees");
+    let names: Vec<&str> = worktrees
+        .iter()
+        .flatten()
+        .filter_map(|worktree| worktree.strip_prefix("experiment-"))
+        .collect();
+    if names.is_empty() {
+        println!("No experiments");
+    }
+    for name in names {
+        println!("{}\t{}/{}", name, EXPERIMENTS_DIR, name);
+    }
+}
+
+pub fn run(command: ExperimentCommand) {
+    match command {
+        ExperimentCommand::Start(args) => start(&args.name),
+        ExperimentCommand::Finish(args) => finish(&args.name),
+        ExperimentCommand::Discard(args) => discard(args),
+        ExperimentCommand::List => list(),
+    }
+}
@@ -0,0 +1,427 @@
+use chrono::{Datelike, Local};
+use clap::{ArgEnum, Args, Subcommand};
+use serde_json::{json, Value};
+
+use uuid::Uuid;
+
+use std::path::PathBuf;
+use std::process::Command;
+
+use crate::author::parse_author;
+use crate::metadata::{Contributor, Metadata, METADATA_FILE};
+use crate::redact::{RedactionRules, REDACTION_FILE};
+use crate::report::walk_files;
+use crate::warning::{warn, Code};
+use crate::{capitalize, exit_with_error, find_project_root};
+
+#[derive(Subcommand)]
+pub enum ExportCommand {
+    /// Export the project's metadata for depositing it in a data repository
+    Metadata(MetadataArgs),
+
+    /// Write a .tar.gz archive of the results to share, optionally redacted
+    Archive(ArchiveArgs),
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, ArgEnum)]
+pub enum MetadataFormat {
+    /// DataCite XML, version 4
+    Datacite,
+    /// schema.org Dataset as JSON-LD
+    Jsonld,
+}
+
+#[derive(Args)]
+pub struct MetadataArgs {
+    #[clap(arg_enum, value_parser, long)]
+    format: MetadataFormat,
+
+    /// Write the metadata to this file instead of stdout
+    #[clap(short, long)]
+    output: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct ArchiveArgs {
+    /// Files and directories to archive, relative to the project root
+    #[clap(default_value = "results")]
+    paths: Vec<PathBuf>,
+
+    /// Apply the redaction rules in redaction.toml, to share results without identifying data
+    #[clap(long)]
+    redact: bool,
+
+    /// Path of the archive (default: NAME-VERSION-results.tar.gz next to the project)
+    #[clap(short, long)]
+    output: Option<PathBuf>,
+}
+
+/// A person, with the name split as repositories want it.
+pub struct Person {
+    pub name: String,
+    pub given: Option<String>,
+    pub family: String,
+    pub email: Option<String>,
+}
+
+impl Person {
+    fn new(name: &str, email: Option<String>) -> Person {
+        let name = name.trim();
+        // Repositories want family and given names separately, which can only be guessed
+        let (given, family) = match name.rsplit_once(' ') {
+            Some((given, family)) => (Some(given.trim().to_owned()), family.to_owned()),
+            None => (None, name.to_owned()),
+        };
+        Person {
+            name: name.to_owned(),
+            given,
+            family,
+            email,
+        }
+    }
+
+    /// Get the author given as "NAME <EMAIL>" or "NAME".
+    pub fn from_author(author: &str) -> Person {
+        match parse_author(author) {
+            Some((name, email)) => Person::new(&name, Some(email)),
+            None => Person::new(author, None),
+        }
+    }
+
+    fn from_contributor(contributor: &Contributor) -> Person {
+        Person::new(&contributor.name, contributor.email.clone())
+    }
+}
+
+/// Get the title of the project, e.g. "Rna expression" for "rna_expression".
+pub fn title(metadata: &Metadata) -> String {
+    capitalize(&metadata.name.replace(['_', '-'], " "))
+}
+
+/// Get the DataCite contributor type of the role of a contributor.
+fn contributor_type(role: &str) -> &'static str {
+    match role.to_lowercase().as_str() {
+        "lead" => "ProjectLeader",
+        "supervisor" => "Supervisor",
+        "data curator" | "curator" => "DataCurator",
+        _ => "ProjectMember",
+    }
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Get the DataCite XML of the project. Values only the repository knows, like the DOI if it
+/// isn't reserved yet, are DataCite's standard placeholders.
+fn datacite(metadata: &Metadata, title: &str, year: i32) -> String {
+    let element = |name: &str, text: &str| format!("<{0}>{1}</{0}>", name, escape_xml(text));
+    let mut lines = vec![
+        r#"<?xml version="1.0" encoding="UTF-8"?>"#.to_owned(),
+        concat!(
+            r#"<resource xmlns="http://datacite.org/schema/kernel-4" "#,
+            r#"xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance" "#,
+            r#"xsi:schemaLocation="http://datacite.org/schema/kernel-4 "#,
+            r#"http://schema.datacite.org/meta/kernel-4/metadata.xsd">"#
+        )
+        .to_owned(),
+        format!(
+            "  <identifier identifierType=\"DOI\">{}</identifier>",
+            escape_xml(metadata.doi.as_deref().unwrap_or("(:tba)"))
+        ),
+        "  <creators>".to_owned(),
+    ];
+    let person = |tag: &str, attributes: &str, person: &Person| {
+        let mut lines = vec![
+            format!("    <{}{}>", tag, attributes),
+            format!(
+                "      <{}Name nameType=\"Personal\">{}</{}Name>",
+                tag,
+                escape_xml(&match &person.given {
+                    Some(given) => format!("{}, {}", person.family, given),
+                    None => person.family.clone(),
+                }),
+                tag
+            ),
+        ];
+        if let Some(given) = &person.given {
+            lines.push(format!("      {}", element("givenName", given)));
+        }
+        lines.push(format!("      {}", element("familyName", &person.family)));
+        lines.push(format!("    </{}>", tag));
+        lines
+    };
+    for author in &metadata.authors {
+        lines.extend(person("creator", "", &Person::from_author(author)));
+    }
+    lines.push("  </creators>".to_owned());
+    lines.push("  <titles>".to_owned());
+    lines.push(format!("    {}", element("title", title)));
+    lines.push("  </titles>".to_owned());
+    lines.push("  <publisher>(:unav)</publisher>".to_owned());
+    lines.push(format!(
+        "  {}",
+        element("publicationYear", &year.to_string())
+    ));
+    lines.push(
+        r#"  <resourceType resourceTypeGeneral="Dataset">Research project</resourceType>"#
+            .to_owned(),
+    );
+    if !metadata.keywords.is_empty() {
+        lines.push("  <subjects>".to_owned());
+        for keyword in &metadata.keywords {
+            lines.push(format!("    {}", element("subject", keyword)));
+        }
+        lines.push("  </subjects>".to_owned());
+    }
+    if !metadata.contributors.is_empty() {
+        lines.push("  <contributors>".to_owned());
+        for contributor in &metadata.contributors {
+            let attributes = format!(
+                " contributorType=\"{}\"",
+                contributor_type(&contributor.role)
+            );
+            lines.extend(person(
+                "contributor",
+                &attributes,
+                &Person::from_contributor(contributor),
+            ));
+        }
+        lines.push("  </contributors>".to_owned());
+    }
+    lines.push("  <dates>".to_owned());
+    lines.push(format!(
+        "    <date dateType=\"Created\">{}</date>",
+        escape_xml(&metadata.created)
+    ));
+    lines.push("  </dates>".to_owned());
+    lines.push("  <alternateIdentifiers>".to_owned());
+    lines.push(format!(
+        "    <alternateIdentifier alternateIdentifierType=\"UUID\">{}</alternateIdentifier>",
+        escape_xml(&metadata.uuid)
+    ));
+    lines.push("  </alternateIdentifiers>".to_owned());
+    lines.push(format!("  {}", element("version", &metadata.version)));
+    if let Some(license) = &metadata.license {
+        lines.push("  <rightsList>".to_owned());
+        lines.push(format!(
+            "    <rights rightsURI=\"https://spdx.org/licenses/{0}.html\" \
+            rightsIdentifier=\"{0}\" rightsIdentifierScheme=\"SPDX\">{0}</rights>",
+            escape_xml(license)
+        ));
+        lines.push("  </rightsList>".to_owned());
+    }
+    if let Some(description) = &metadata.description {
+        lines.push("  <descriptions>".to_owned());
+        lines.push(format!(
+            "    <description descriptionType=\"Abstract\">{}</description>",
+            escape_xml(description)
+        ));
+        lines.push("  </descriptions>".to_owned());
+    }
+    lines.push("</resource>".to_owned());
+    lines.join("\n") + "\n"
+}
+
+fn person_jsonld(person: &Person) -> Value {
+    let mut value = json!({
+        "@type": "Person",
+        "name": person.name,
+        "familyName": person.family,
+    });
+    if let Some(given) = &person.given {
+        value["givenName"] = json!(given);
+    }
+    if let Some(email) = &person.email {
+        value["email"] = json!(email);
+    }
+    value
+}
+
+/// Get the schema.org JSON-LD of the project, describing it as a Dataset.
+fn jsonld(metadata: &Metadata, title: &str) -> String {
+    let mut dataset = json!({
+        "@context": "https://schema.org",
+        "@type": "Dataset",
+        "name": title,
+        "identifier": match &metadata.doi {
+            Some(doi) => format!("https://doi.org/{}", doi),
+            None => format!("urn:uuid:{}", metadata.uuid),
+        },
+        "version": metadata.version,
+        "dateCreated": metadata.created,
+        "creator": metadata
+            .authors
+            .iter()
+            .map(|author| person_jsonld(&Person::from_author(author)))
+            .collect::<Vec<_>>(),
+    });
+    if !metadata.contributors.is_empty() {
+        dataset["contributor"] = metadata
+            .contributors
+            .iter()
+            .map(|c| person_jsonld(&Person::from_contributor(c)))
+            .collect();
+    }
+    if let Some(description) = &metadata.description {
+        dataset["description"] = json!(description);
+    }
+    if !metadata.keywords.is_empty() {
+        dataset["keywords"] = json!(metadata.keywords);
+    }
+    if let Some(license) = &metadata.license {
+        dataset["license"] = json!(format!("https://spdx.org/licenses/{}.html", license));
+    }
+    serde_json::to_string_pretty(&dataset).expect("Error when serializing JSON-LD") + "\n"
+}
+
+fn export_metadata(args: MetadataArgs) {
+    let root = find_project_root();
+    let metadata = Metadata::load(&root);
+    // Repositories reject deposits without these, so point out where they're set
+    let missing: Vec<&str> = [
+        ("description", metadata.description.is_none()),
+        ("license", metadata.license.is_none()),
+        ("keywords", metadata.keywords.is_empty()),
+        ("authors", metadata.authors.is_empty()),
+    ]
+    .into_iter()
+    .filter_map(|(field, missing)| missing.then_some(field))
+    .collect();
+    if !missing.is_empty() {
+        warn(
+            Code::MissingMetadata,
+            &format!(
+                "No {} in {}. Most repositories require them",
+                missing.join(", "),
+                METADATA_FILE
+            ),
+        );
+    }
+    let title = title(&metadata);
+    let content = match args.format {
+        MetadataFormat::Datacite => datacite(&metadata, &title, Local::today().year()),
+        MetadataFormat::Jsonld => jsonld(&metadata, &title),
+    };
+    match args.output {
+        None => print!("{}", content),
+        Some(path) => {
+            std::fs::write(&path, content)
+                .unwrap_or_else(|_| exit_with_error(&format!("Could not write {:?}", path)));
+            println!("Wrote {:?}", path);
+        }
+    }
+}
+
+fn archive(args: ArchiveArgs) {
+    let root = find_project_root();
+    let metadata = Metadata::load(&root);
+    let rules = args.redact.then(|| RedactionRules::load(&root));
+    let name = format!(
+        "{}-{}-results{}",
+        metadata.name,
+        metadata.version,
+        if args.redact { "-redacted" } else { "" }
+    );
+    let output = args.output.unwrap_or_else(|| {
+        root.parent()
+            .unwrap_or(&root)
+            .join(format!("{}.tar.gz", name))
+    });
+    let mut files = Vec::new();
+    for path in &args.paths {
+        let full = root.join(path);
+        if full.is_dir() {
+            files.extend(walk_files(&full));
+        } else if full.is_file() {
+            files.push(full);
+        } else {
+            exit_with_error(&format!("{:?} does not exist in the project", path))
+        }
+    }
+
+    // The files are copied to a staging directory, so the redaction never touches the originals
+    let staging = std::env::temp_dir().join(format!("investigate-archive-{}", Uuid::new_v4()));
+    let top = staging.join(&name);
+    let mut report = Vec::new();
+    let (mut n_archived, mut n_excluded) = (0, 0);
+    for file in files {
+        let relative = file.strip_prefix(&root).unwrap_or(&file);
+        let shown = relative.to_string_lossy().replace('\\', "/");
+        if rules
+            .as_ref()
+            .is_some_and(|rules| rules.is_excluded(&shown))
+        {
+            report.push(format!("{}: left out", shown));
+            n_excluded += 1;
+            continue;
+        }
+        let copy = top.join(relative);
+        if let Some(parent) = copy.parent() {
+            std::fs::create_dir_all(parent)
+                .unwrap_or_else(|_| panic!("Error when creating directory {:?}", parent));
+        }
+        std::fs::copy(&file, &copy).unwrap_or_else(|_| panic!("Error when copying {:?}", file));
+        n_archived += 1;
+        if let Some(rules) = &rules {
+            match rules.redact(&copy) {
+                Ok(redaction) if !redaction.is_empty() => {
+                    report.push(format!("{}: {}", shown, redaction.describe()))
+                }
+                Ok(_) => (),
+                Err(message) => {
+                    // Copies which may still identify someone must not be left behind
+                    let _ = std::fs::remove_dir_all(&staging);
+                    exit_with_error(&format!("{}. No archive was written", message))
+                }
+            }
+        }
+    }
+    if rules.is_some() {
+        let path = top.join("REDACTION.txt");
+        let content = format!(
+            "Redacted by investigate {} on {}, following {}.\n\n{}\n",
+            env!("CARGO_PKG_VERSION"),
+            Local::today().format("%Y-%m-%d"),
+            REDACTION_FILE,
+            if report.is_empty() {
+                "Nothing matched the rules.".to_owned()
+            } else {
+                report.join("\n")
+            }
+        );
+        std::fs::write(&path, content)
+            .unwrap_or_else(|_| panic!("Error when writing file {:?}", path));
+    }
+    let status = Command::new("tar")
+        .arg("-czf")
+        .arg(&output)
+        .arg("-C")
+        .arg(&staging)
+        .arg(&name)
+        .status();
+    let _ = std::fs::remove_dir_all(&staging);
+    if !status.is_ok_and(|status| status.success()) {
+        exit_with_error(&format!("Could not write archive {:?}", output))
+    }
+    for line in &report {
+        println!("{}", line);
+    }
+    println!("Archived {} files to {:?}", n_archived, output);
+    if n_excluded > 0 {
+        println!(
+            "Left out {} files excluded by {}",
+            n_excluded, REDACTION_FILE
+        );
+    }
+}
+
+pub fn run(command: ExportCommand) {
+    match command {
+        ExportCommand::Metadata(args) => export_metadata(args),
+        ExportCommand::Archive(args) => archive(args),
+    }
+}
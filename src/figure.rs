@@ -0,0 +1,291 @@
+use chrono::Local;
+use clap::{ArgEnum, Args, Subcommand};
+use git2::{Repository, Status};
+use serde::{Deserialize, Serialize};
+
+use std::fs::read_to_string;
+use std::path::{Path, PathBuf};
+
+use crate::data::sha256;
+use crate::git::relative_to_workdir;
+use crate::warning::{warn, Code};
+use crate::{exit_with_error, filesystem, find_project_root};
+
+/// Name of the file recording the registered figures, in the project root
+pub const FIGURES_FILE: &str = "figures.toml";
+
+/// Kind of a registered artifact.
+#[derive(Copy, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Kind {
+    #[default]
+    Figure,
+    Table,
+}
+
+impl Kind {
+    /// Directory artifacts of this kind are copied to, relative to the project root
+    fn directory(self) -> &'static str {
+        match self {
+            Kind::Figure => "results/figures",
+            Kind::Table => "results/tables",
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Kind::Figure => "figure",
+            Kind::Table => "table",
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, ArgEnum)]
+pub enum SnippetFormat {
+    Latex,
+    Quarto,
+}
+
+#[derive(Subcommand)]
+pub enum FigureCommand {
+    /// Copy a plot into results/figures/, or a table into results/tables/,
+    /// under a stable name, and record where it came from
+    Register(RegisterArgs),
+
+    /// Print a snippet which includes a registered figure or table in the paper
+    Snippet(SnippetArgs),
+}
+
+#[derive(Args)]
+pub struct RegisterArgs {
+    /// The plot or table to register
+    path: PathBuf,
+
+    /// Caption of the figure or table
+    #[clap(long)]
+    caption: String,
+
+    /// Register a table, e.g. a LaTeX or Markdown table, instead of a figure
+    #[clap(long)]
+    table: bool,
+
+    /// Script which produced the figure
+    #[clap(long)]
+    script: Option<PathBuf>,
+
+    /// Stable name of the figure, used for its file name and label (default: from <PATH>)
+    #[clap(long)]
+    name: Option<String>,
+
+    /// Also print a snippet including the figure in this format
+    #[clap(arg_enum, value_parser, long)]
+    snippet: Option<SnippetFormat>,
+}
+
+#[derive(Args)]
+pub struct SnippetArgs {
+    /// Name of the registered figure or table
+    name: String,
+
+    #[clap(arg_enum, value_parser, long, default_value = "latex")]
+    format: SnippetFormat,
+}
+
+/// A figure or table registered with `investigate figure register`.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Figure {
+    /// Stable name, used as file name and label
+    pub name: String,
+    #[serde(default)]
+    pub kind: Kind,
+    /// Path relative to the project root
+    pub file: String,
+    pub caption: String,
+    /// Path of the producing script relative to the project root, if known
+    pub script: Option<String>,
+    /// The git commit HEAD was at when the figure was registered
+    pub commit: Option<String>,
+    /// Hex-encoded SHA-256 checksum of the file
+    pub sha256: String,
+    /// Date in YYYY-MM-DD format
+    pub registered: String,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct Figures {
+    #[serde(default)]
+    pub figure: Vec<Figure>,
+}
+
+impl Figures {
+    /// Load the registered figures of the project at `root`. A missing file counts as empty.
+    pub fn load(root: &Path) -> Figures {
+        let path = root.join(FIGURES_FILE);
+        match read_to_string(&path) {
+            Err(_) => Figures::default(),
+            Ok(content) => toml::from_str(&content)
+                .unwrap_or_else(|e| exit_with_error(&format!("Could not parse {:?}: {}", path, e))),
+        }
+    }
+
+    pub fn save(&self, root: &Path) {
+        let path = root.join(FIGURES_FILE);
+        let content = toml::to_string_pretty(self).expect("Error when serializing figures");
+        filesystem::write(&path, content)
+            .unwrap_or_else(|_| panic!("Error when writing file {:?}", path))
+    }
+
+    pub fn get(&self, name: &str) -> &Figure {
+        self.figure
+            .iter()
+            .find(|f| f.name == name)
+            .unwrap_or_else(|| exit_with_error(&format!("No figure or table named \"{}\"", name)))
+    }
+}
+
+/// Turn `name` into a name which is safe in file names, LaTeX labels and Quarto IDs.
+fn slugify(name: &str) -> String {
+    let slug: String = name
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect();
+    slug.split('-')
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// Get the snippet including `figure` in a document in paper/.
+/// Tables are included as they are, so they must be in the format of the document.
+pub fn snippet(figure: &Figure, format: SnippetFormat) -> String {
+    let path = format!("../{}", figure.file);
+    match (format, figure.kind) {
+        (SnippetFormat::Latex, Kind::Figure) => format!(
+            "\\begin{{figure}}\n  \\centering\n  \\includegraphics[width=\\linewidth]{{{}}}\n  \
+            \\caption{{{}}}\n  \\label{{fig:{}}}\n\\end{{figure}}",
+            path, figure.caption, figure.name
+        ),
+        (SnippetFormat::Latex, Kind::Table) => format!(
+            "\\begin{{table}}\n  \\centering\n  \\caption{{{}}}\n  \\label{{tab:{}}}\n  \
+            \\input{{{}}}\n\\end{{table}}",
+            figure.caption, figure.name, path
+        ),
+        (SnippetFormat::Quarto, Kind::Figure) => {
+            format!("![{}]({}){{#fig-{}}}", figure.caption, path, figure.name)
+        }
+        (SnippetFormat::Quarto, Kind::Table) => format!(
+            "::: {{#tbl-{}}}\n{{{{< include {} >}}}}\n\n{}\n:::",
+            figure.name, path, figure.caption
+        ),
+    }
+}
+
+/// Get the commit HEAD is at, warning if `script` has uncommitted changes.
+fn producing_commit(root: &Path, script: Option<&str>) -> Option<String> {
+    let repo = Repository::discover(root).ok()?;
+    let commit = repo.head().ok()?.target()?.to_string();
+    if let Some(script) = script {
+        let relative = relative_to_workdir(&repo, &root.join(script))?;
+        let status = repo.status_file(&relative).unwrap_or(Status::WT_NEW);
+        if !status.is_empty() && !status.is_ignored() {
+            warn(
+                Code::FileChanged,
+                &format!(
+                    "{} has uncommitted changes, so commit {} did not produce the figure",
+                    script,
+                    &commit[..8]
+                ),
+            );
+        }
+    }
+    Some(commit)
+}
+
+fn register(args: RegisterArgs) {
+    let root = find_project_root();
+    if !args.path.is_file() {
+        exit_with_error(&format!("{:?} is not a file", args.path))
+    }
+    let name = slugify(&match args.name {
+        Some(name) => name,
+        None => args
+            .path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+    });
+    if name.is_empty() {
+        exit_with_error("Name must contain letters or digits")
+    }
+    let script = args.script.map(|script| {
+        let absolute = script
+            .canonicalize()
+            .unwrap_or_else(|_| exit_with_error(&format!("Could not find {:?}", script)));
+        absolute
+            .strip_prefix(root.canonicalize().unwrap_or_else(|_| root.clone()))
+            .unwrap_or_else(|_| exit_with_error("The script must be inside the project"))
+            .to_string_lossy()
+            .into_owned()
+    });
+    let extension = args
+        .path
+        .extension()
+        .map(|e| format!(".{}", e.to_string_lossy()))
+        .unwrap_or_default();
+    let kind = if args.table {
+        Kind::Table
+    } else {
+        Kind::Figure
+    };
+    let file = format!("{}/{}{}", kind.directory(), name, extension);
+    let destination = root.join(&file);
+    std::fs::create_dir_all(root.join(kind.directory()))
+        .unwrap_or_else(|_| panic!("Error when creating directory {}", kind.directory()));
+    let same_file = args.path.canonicalize().ok() == destination.canonicalize().ok();
+    if !same_file {
+        std::fs::copy(&args.path, &destination)
+            .unwrap_or_else(|_| exit_with_error(&format!("Could not copy {:?}", args.path)));
+    }
+    let figure = Figure {
+        name: name.clone(),
+        kind,
+        file,
+        caption: args.caption,
+        commit: producing_commit(&root, script.as_deref()),
+        script,
+        sha256: sha256(&destination)
+            .unwrap_or_else(|_| exit_with_error(&format!("Could not read {:?}", destination))),
+        registered: Local::today().format("%Y-%m-%d").to_string(),
+    };
+    let mut figures = Figures::load(&root);
+    // Updated figures keep their place, which is their order in the paper
+    let existing = figures.figure.iter_mut().find(|f| f.name == name);
+    let existed = existing.is_some();
+    match existing {
+        Some(existing) => *existing = figure.clone(),
+        None => figures.figure.push(figure.clone()),
+    }
+    figures.save(&root);
+    println!(
+        "{} {} \"{}\" as {} in {}",
+        if existed { "Updated" } else { "Registered" },
+        kind.name(),
+        name,
+        figure.file,
+        FIGURES_FILE
+    );
+    if let Some(format) = args.snippet {
+        println!("{}", snippet(&figure, format));
+    }
+}
+
+pub fn run(command: FigureCommand) {
+    match command {
+        FigureCommand::Register(args) => register(args),
+        FigureCommand::Snippet(args) => {
+            let figures = Figures::load(&find_project_root());
+            println!("{}", snippet(figures.get(&args.name), args.format));
+        }
+    }
+}
@@ -0,0 +1,360 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::io::{Error, ErrorKind, Result, Write};
+use std::path::{Component, Path, PathBuf};
+use std::rc::Rc;
+
+/// Where the files of a project are written. Code creating projects goes through this, instead
+/// of `std::fs`, so projects can be created elsewhere than on the local disk.
+pub trait Filesystem {
+    fn exists(&self, path: &Path) -> bool;
+    fn is_dir(&self, path: &Path) -> bool;
+    fn read_to_string(&self, path: &Path) -> Result<String>;
+    /// Get the paths of the entries of the directory at `path`, in no particular order.
+    fn read_dir(&self, path: &Path) -> Result<Vec<PathBuf>>;
+    fn write(&self, path: &Path, contents: &[u8]) -> Result<()>;
+    /// Add `contents` to the end of the file at `path`, creating it if it doesn't exist.
+    fn append(&self, path: &Path, contents: &[u8]) -> Result<()>;
+    /// Move the file at `from` to `to`, replacing any file there.
+    fn rename(&self, from: &Path, to: &Path) -> Result<()>;
+    /// Create the directory at `path`, whose parent must exist.
+    fn create_dir(&self, path: &Path) -> Result<()>;
+    /// Create the directory at `path`, and any missing parents.
+    fn create_dir_all(&self, path: &Path) -> Result<()>;
+    /// Make the file at `path` executable, if the filesystem supports it.
+    fn set_executable(&self, path: &Path) -> Result<()>;
+}
+
+/// The local disk.
+pub struct Local;
+
+impl Filesystem for Local {
+    fn exists(&self, path: &Path) -> bool {
+        path.exists()
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        path.is_dir()
+    }
+
+    fn read_to_string(&self, path: &Path) -> Result<String> {
+        std::fs::read_to_string(path)
+    }
+
+    fn read_dir(&self, path: &Path) -> Result<Vec<PathBuf>> {
+        path.read_dir()?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect()
+    }
+
+    fn write(&self, path: &Path, contents: &[u8]) -> Result<()> {
+        std::fs::write(path, contents)
+    }
+
+    fn append(&self, path: &Path, contents: &[u8]) -> Result<()> {
+        std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?
+            .write_all(contents)
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> Result<()> {
+        std::fs::rename(from, to)
+    }
+
+    fn create_dir(&self, path: &Path) -> Result<()> {
+        std::fs::create_dir(path)
+    }
+
+    fn create_dir_all(&self, path: &Path) -> Result<()> {
+        std::fs::create_dir_all(path)
+    }
+
+    #[cfg(unix)]
+    fn set_executable(&self, path: &Path) -> Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755))
+    }
+
+    #[cfg(not(unix))]
+    fn set_executable(&self, _path: &Path) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// An entry written to a `Memory` filesystem.
+enum Entry {
+    Directory,
+    File { contents: Vec<u8>, executable: bool },
+}
+
+/// A filesystem kept in memory, on top of the local disk: What is written is kept in memory,
+/// while reading falls back to the disk for what was not written. Nothing is changed on disk,
+/// which makes it useful for trying out what would be written.
+#[derive(Default)]
+pub struct Memory {
+    entries: RefCell<BTreeMap<PathBuf, Entry>>,
+}
+
+/// Get `path` without `.` components, so the same path is always the same key.
+fn normalize(path: &Path) -> PathBuf {
+    path.components()
+        .filter(|component| *component != Component::CurDir)
+        .collect()
+}
+
+impl Memory {
+    pub fn new() -> Memory {
+        Memory::default()
+    }
+
+    /// Get the paths written, in order, each with what it is: "directory", "file" or
+    /// "executable".
+    pub fn written(&self) -> Vec<(PathBuf, &'static str)> {
+        self.entries
+            .borrow()
+            .iter()
+            .map(|(path, entry)| {
+                let kind = match entry {
+                    Entry::Directory => "directory",
+                    Entry::File {
+                        executable: true, ..
+                    } => "executable",
+                    Entry::File { .. } => "file",
+                };
+                (path.clone(), kind)
+            })
+            .collect()
+    }
+
+    fn parent_is_dir(&self, path: &Path) -> bool {
+        match path.parent() {
+            None => true,
+            Some(parent) if parent.as_os_str().is_empty() => true,
+            Some(parent) => self.is_dir(parent),
+        }
+    }
+}
+
+impl Filesystem for Memory {
+    fn exists(&self, path: &Path) -> bool {
+        self.entries.borrow().contains_key(&normalize(path)) || path.exists()
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        match self.entries.borrow().get(&normalize(path)) {
+            Some(entry) => matches!(entry, Entry::Directory),
+            None => path.is_dir(),
+        }
+    }
+
+    fn read_to_string(&self, path: &Path) -> Result<String> {
+        match self.entries.borrow().get(&normalize(path)) {
+            Some(Entry::File { contents, .. }) => String::from_utf8(contents.clone())
+                .map_err(|e| Error::new(ErrorKind::InvalidData, e)),
+            Some(Entry::Directory) => Err(Error::new(ErrorKind::IsADirectory, "Is a directory")),
+            None => std::fs::read_to_string(path),
+        }
+    }
+
+    fn read_dir(&self, path: &Path) -> Result<Vec<PathBuf>> {
+        if !self.is_dir(path) {
+            return Err(Error::new(ErrorKind::NotFound, "No such directory"));
+        }
+        let directory = normalize(path);
+        let mut paths: Vec<PathBuf> = Local.read_dir(path).unwrap_or_default();
+        for written in self.entries.borrow().keys() {
+            let is_child = written.parent() == Some(directory.as_path());
+            if is_child && !paths.iter().any(|p| normalize(p) == *written) {
+                paths.push(path.join(written.file_name().unwrap_or_default()));
+            }
+        }
+        Ok(paths)
+    }
+
+    fn write(&self, path: &Path, contents: &[u8]) -> Result<()> {
+        if !self.parent_is_dir(path) {
+            return Err(Error::new(ErrorKind::NotFound, "No such directory"));
+        }
+        if self.is_dir(path) {
+            return Err(Error::new(ErrorKind::IsADirectory, "Is a directory"));
+        }
+        self.entries.borrow_mut().insert(
+            normalize(path),
+            Entry::File {
+                contents: contents.to_vec(),
+                executable: false,
+            },
+        );
+        Ok(())
+    }
+
+    fn append(&self, path: &Path, contents: &[u8]) -> Result<()> {
+        let mut existing = match self.entries.borrow().get(&normalize(path)) {
+            Some(Entry::File { contents, .. }) => contents.clone(),
+            Some(Entry::Directory) => {
+                return Err(Error::new(ErrorKind::IsADirectory, "Is a directory"))
+            }
+            None => std::fs::read(path).unwrap_or_default(),
+        };
+        existing.extend_from_slice(contents);
+        self.write(path, &existing)
+    }
+
+    /// Files on disk are copied, as nothing on disk is changed.
+    fn rename(&self, from: &Path, to: &Path) -> Result<()> {
+        let entry = self.entries.borrow_mut().remove(&normalize(from));
+        match entry {
+            Some(Entry::File {
+                contents,
+                executable,
+            }) => {
+                self.write(to, &contents)?;
+                if executable {
+                    self.set_executable(to)?;
+                }
+                Ok(())
+            }
+            Some(Entry::Directory) => {
+                self.entries
+                    .borrow_mut()
+                    .insert(normalize(from), Entry::Directory);
+                Err(Error::new(ErrorKind::IsADirectory, "Is a directory"))
+            }
+            None => self.write(to, &std::fs::read(from)?),
+        }
+    }
+
+    fn create_dir(&self, path: &Path) -> Result<()> {
+        if self.exists(path) {
+            return Err(Error::new(ErrorKind::AlreadyExists, "File exists"));
+        }
+        if !self.parent_is_dir(path) {
+            return Err(Error::new(ErrorKind::NotFound, "No such directory"));
+        }
+        self.entries
+            .borrow_mut()
+            .insert(normalize(path), Entry::Directory);
+        Ok(())
+    }
+
+    fn create_dir_all(&self, path: &Path) -> Result<()> {
+        if self.is_dir(path) {
+            return Ok(());
+        }
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            self.create_dir_all(parent)?;
+        }
+        self.create_dir(path)
+    }
+
+    fn set_executable(&self, path: &Path) -> Result<()> {
+        match self.entries.borrow_mut().get_mut(&normalize(path)) {
+            Some(Entry::File { executable, .. }) => {
+                *executable = true;
+                Ok(())
+            }
+            _ => Err(Error::new(ErrorKind::NotFound, "No such file")),
+        }
+    }
+}
+
+thread_local! {
+    /// The filesystem files are written to on this thread
+    static CURRENT: RefCell<Rc<dyn Filesystem>> = RefCell::new(Rc::new(Local));
+}
+
+/// Get the filesystem files are written to: The local disk, unless `with` says otherwise.
+pub fn current() -> Rc<dyn Filesystem> {
+    CURRENT.with(|current| current.borrow().clone())
+}
+
+/// Call `f` with files written to `filesystem` instead of the current one, on this thread.
+pub fn with<T>(filesystem: Rc<dyn Filesystem>, f: impl FnOnce() -> T) -> T {
+    let previous = CURRENT.with(|current| current.replace(filesystem));
+    let result = f();
+    CURRENT.with(|current| current.replace(previous));
+    result
+}
+
+/// Write `contents` to the file at `path` on the current filesystem, like `std::fs::write`.
+pub fn write(path: impl AsRef<Path>, contents: impl AsRef<[u8]>) -> Result<()> {
+    current().write(path.as_ref(), contents.as_ref())
+}
+
+/// Create the directory at `path` and its missing parents on the current filesystem, like
+/// `std::fs::create_dir_all`.
+pub fn create_dir_all(path: impl AsRef<Path>) -> Result<()> {
+    current().create_dir_all(path.as_ref())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A directory which does not exist on disk, so everything in it is in memory.
+    fn missing_dir() -> PathBuf {
+        std::env::temp_dir().join(format!("investigate-test-{}", uuid::Uuid::new_v4()))
+    }
+
+    #[test]
+    fn memory_keeps_writes_off_disk() {
+        let memory = Memory::new();
+        let dir = missing_dir();
+        let file = dir.join("sub").join("file.txt");
+        assert!(memory.write(&file, b"text").is_err());
+        memory.create_dir_all(&dir.join("sub")).unwrap();
+        memory.write(&file, b"text").unwrap();
+        memory.set_executable(&file).unwrap();
+        assert!(memory.is_dir(&dir));
+        assert_eq!(memory.read_to_string(&file).unwrap(), "text");
+        assert_eq!(memory.read_dir(&dir).unwrap(), vec![dir.join("sub")]);
+        assert_eq!(
+            memory.written(),
+            vec![
+                (dir.clone(), "directory"),
+                (dir.join("sub"), "directory"),
+                (file, "executable"),
+            ]
+        );
+        assert!(!dir.exists());
+    }
+
+    #[test]
+    fn memory_appends_and_renames() {
+        let memory = Memory::new();
+        let dir = missing_dir();
+        memory.create_dir(&dir).unwrap();
+        let (log, moved) = (dir.join("log"), dir.join("moved"));
+        memory.append(&log, b"one\n").unwrap();
+        memory.append(&log, b"two\n").unwrap();
+        memory.rename(&log, &moved).unwrap();
+        assert!(!memory.exists(&log));
+        assert_eq!(memory.read_to_string(&moved).unwrap(), "one\ntwo\n");
+        assert!(!dir.exists());
+    }
+
+    #[test]
+    fn memory_reads_from_disk() {
+        let memory = Memory::new();
+        let manifest = Path::new(env!("CARGO_MANIFEST_DIR")).join("Cargo.toml");
+        assert!(memory.exists(&manifest));
+        assert!(memory
+            .read_to_string(&manifest)
+            .unwrap()
+            .contains("[package]"));
+        assert!(memory
+            .create_dir(Path::new(env!("CARGO_MANIFEST_DIR")))
+            .is_err());
+    }
+
+    #[test]
+    fn with_restores_the_filesystem() {
+        let memory = Rc::new(Memory::new());
+        let file = missing_dir();
+        with(memory.clone(), || write(&file, "text")).unwrap();
+        assert_eq!(memory.written(), vec![(file.clone(), "file")]);
+        assert!(!current().exists(&file));
+    }
+}
@@ -0,0 +1,241 @@
+use chrono::Local;
+use clap::Args;
+use git2::Repository;
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::author::author_string;
+use crate::config::Config;
+use crate::data::{checksum_and_size_with_progress, sha256_with_progress, size, DATA_DIRECTORIES};
+use crate::git::{commit_and_tag, commit_project_files, is_clean};
+use crate::manifest::{Manifest, MANIFEST_FILE};
+use crate::metadata::Metadata;
+use crate::new::{write, Existing};
+use crate::progress::Steps;
+use crate::report::walk_files;
+use crate::templates::Templates;
+use crate::warning::{warn, Code};
+use crate::{exit_with_error, find_project_root};
+
+/// Marker file in the root of finalized projects
+pub const FINALIZED_FILE: &str = "FINALIZED";
+
+#[derive(Args)]
+pub struct FinalizeArgs {
+    /// Tag the commit of the finalized project "finalized"
+    #[clap(long)]
+    tag: bool,
+
+    /// Write a .tar.gz archive of the project next to its directory
+    #[clap(long)]
+    archive: bool,
+}
+
+/// Set whether all files and directories in `path` are read-only, recursively. Links are left
+/// alone, as the permissions of the file they link to are shared with its original location.
+pub fn set_readonly(path: &Path, readonly: bool) {
+    if path.is_dir() {
+        for entry in path.read_dir().into_iter().flatten().flatten() {
+            set_readonly(&entry.path(), readonly);
+        }
+    }
+    if let Ok(metadata) = path.symlink_metadata() {
+        if metadata.file_type().is_symlink() {
+            return;
+        }
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            // A hard link, like those made by `data add --link`
+            if metadata.is_file() && metadata.nlink() > 1 {
+                if readonly {
+                    warn(
+                        Code::PermissionsFailed,
+                        &format!(
+                            "Not making {:?} read-only, as it's a hard link to a file \
+                            outside the project too",
+                            path
+                        ),
+                    );
+                }
+                return;
+            }
+        }
+        let mut permissions = metadata.permissions();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            // Only the owner regains write permission
+            let mode = permissions.mode();
+            permissions.set_mode(if readonly {
+                mode & !0o222
+            } else {
+                mode | 0o200
+            });
+        }
+        #[cfg(not(unix))]
+        permissions.set_readonly(readonly);
+        std::fs::set_permissions(path, permissions)
+            .unwrap_or_else(|_| panic!("Error when setting permissions of {:?}", path));
+    }
+}
+
+/// Checksums of all files in the data directories, in the format of `sha256sum`.
+fn data_checksums(root: &Path, steps: &mut Steps) -> String {
+    let total = DATA_DIRECTORIES
+        .iter()
+        .map(|dir| size(&root.join(dir)))
+        .sum();
+    let lines: Result<Vec<String>, PathBuf> = steps.run("Checksum data", Some(total), |progress| {
+        let mut lines = Vec::new();
+        for dir in DATA_DIRECTORIES {
+            for file in walk_files(&root.join(dir)) {
+                let checksum = sha256_with_progress(&file, progress).map_err(|_| file.clone())?;
+                let relative = file.strip_prefix(root).unwrap_or(&file);
+                lines.push(format!("{}  {}", checksum, relative.display()));
+            }
+        }
+        Ok(lines)
+    });
+    lines
+        .unwrap_or_else(|file| exit_with_error(&format!("Could not read {:?}", file)))
+        .join("\n")
+}
+
+/// Check that the data listed in the manifest is still in raw/ with the checksum it was added
+/// with, and exit with an error listing the data which isn't.
+fn verify_manifest(root: &Path, steps: &mut Steps) {
+    let raw = root.join("raw");
+    let manifest = Manifest::load(root);
+    let entries: Vec<_> = manifest
+        .data
+        .iter()
+        // Encrypted data can only be checked once decrypted
+        .filter(|entry| {
+            raw.join(&entry.path).exists()
+                || !entry
+                    .encrypted
+                    .as_ref()
+                    .is_some_and(|e| raw.join(e).is_file())
+        })
+        .collect();
+    let total = entries
+        .iter()
+        .map(|entry| size(&raw.join(&entry.path)))
+        .sum();
+    let problems: Result<Vec<String>, PathBuf> =
+        steps.run("Verify manifest", Some(total), |progress| {
+            let mut problems = Vec::new();
+            for entry in entries {
+                let path = raw.join(&entry.path);
+                if !path.exists() {
+                    problems.push(format!("raw/{} is missing", entry.path));
+                    continue;
+                }
+                let (checksum, _) =
+                    checksum_and_size_with_progress(&path, progress).map_err(|_| path.clone())?;
+                if checksum != entry.sha256 {
+                    problems.push(format!(
+                        "raw/{} has changed checksum since it was added",
+                        entry.path
+                    ));
+                }
+            }
+            Ok(problems)
+        });
+    let problems =
+        problems.unwrap_or_else(|path| exit_with_error(&format!("Could not read {:?}", path)));
+    if !problems.is_empty() {
+        exit_with_error(&format!(
+            "Data doesn't match {}:\n{}",
+            MANIFEST_FILE,
+            problems.join("\n")
+        ))
+    }
+}
+
+/// Get the path of the archive of the project written by `investigate finalize --archive`.
+pub fn archive_path(root: &Path, metadata: &Metadata) -> PathBuf {
+    let parent = root.parent().unwrap_or(root);
+    parent.join(format!("{}-{}.tar.gz", metadata.name, metadata.version))
+}
+
+fn archive(root: &Path, metadata: &Metadata, steps: &mut Steps) {
+    let parent = root.parent().unwrap_or(root);
+    let dirname = root.file_name().unwrap_or_default();
+    let archive = archive_path(root, metadata);
+    let status = steps.run("Archive project", None, |_| {
+        match Command::new("tar")
+            .arg("-czf")
+            .arg(&archive)
+            .arg("-C")
+            .arg(parent)
+            .arg(dirname)
+            .status()
+        {
+            Ok(status) if status.success() => Ok(()),
+            _ => Err(()),
+        }
+    });
+    match status {
+        Ok(()) => println!("Archived project to {:?}", archive),
+        Err(()) => warn(
+            Code::ArchiveFailed,
+            &format!("Could not archive project to {:?}", archive),
+        ),
+    }
+}
+
+pub fn run(args: FinalizeArgs) {
+    let root = find_project_root();
+    let metadata = Metadata::load(&root);
+    if root.join(FINALIZED_FILE).exists() {
+        exit_with_error("Project is already finalized")
+    }
+    let repo = Repository::discover(&root)
+        .unwrap_or_else(|_| exit_with_error("Only projects tracked by git can be finalized"));
+    if !is_clean(&repo) {
+        exit_with_error("Project has uncommitted changes. Commit or discard them first")
+    }
+    let commit = repo
+        .head()
+        .ok()
+        .and_then(|head| head.target())
+        .unwrap_or_else(|| exit_with_error("Project has no commits"));
+    let config = Config::load();
+    let author = author_string(&config);
+
+    let mut steps = Steps::new();
+    verify_manifest(&root, &mut steps);
+    let templates = Templates::new(Vec::new(), &config);
+    let content = templates.render(
+        "finalized",
+        &[
+            ("date", &Local::today().format("%Y-%m-%d").to_string()),
+            ("author", &author),
+            ("version", &metadata.version),
+            ("commit", &commit.to_string()),
+            ("checksums", &data_checksums(&root, &mut steps)),
+        ],
+    );
+    write(&root.join(FINALIZED_FILE), &content, Existing::Error);
+    let message = format!("Finalize project at version {}", metadata.version);
+    if args.tag {
+        let tag = commit_and_tag(&root, &[FINALIZED_FILE], &message, "finalized", &message);
+        println!("Tagged finalized project as {}", tag);
+    } else {
+        commit_project_files(&root, &[FINALIZED_FILE], &message);
+    }
+    for dir in DATA_DIRECTORIES {
+        set_readonly(&root.join(dir), true);
+    }
+    println!(
+        "Finalized project. {} are now read-only",
+        DATA_DIRECTORIES.join(" and ")
+    );
+    if args.archive {
+        archive(&root, &metadata, &mut steps);
+    }
+    steps.summary();
+}
@@ -0,0 +1,296 @@
+use clap::Args;
+use git2::{Oid, Repository};
+
+use std::fs::read_to_string;
+use std::path::Path;
+
+use crate::author::PLACEHOLDER_NAME;
+use crate::data::{checksum_and_size, sha256, DATA_DIRECTORIES};
+use crate::dictionary;
+use crate::env::spec_file;
+use crate::finalize::FINALIZED_FILE;
+use crate::find_project_root;
+use crate::git::relative_to_workdir;
+use crate::lock::lock;
+use crate::manifest::{Manifest, MANIFEST_FILE};
+use crate::metadata::{Metadata, METADATA_FILE};
+use crate::new::IGNORED_DIRECTORIES;
+use crate::report::walk_files;
+use crate::tmp;
+use crate::tools;
+use crate::warning::{warn, Code};
+
+#[derive(Args)]
+pub struct FsckArgs {
+    /// Repair problems where it's safe: recreate missing directories, remove manifest entries
+    /// of deleted data, and stop tracking .env
+    #[clap(long)]
+    fix: bool,
+
+    /// Also verify the checksums of the data, which may be slow for large datasets
+    #[clap(long)]
+    checksums: bool,
+}
+
+/// The problems found, and how many of them were repaired.
+#[derive(Default)]
+struct Problems {
+    found: usize,
+    fixed: usize,
+}
+
+impl Problems {
+    fn report(&mut self, problem: &str) {
+        println!("{}", problem);
+        self.found += 1;
+    }
+
+    fn fixed(&mut self, problem: &str) {
+        println!("{} (fixed)", problem);
+        self.found += 1;
+        self.fixed += 1;
+    }
+}
+
+fn check_directories(root: &Path, metadata: &Metadata, fix: bool, problems: &mut Problems) {
+    for dir in &metadata.directories {
+        let path = root.join(dir);
+        if path.is_dir() {
+            continue;
+        }
+        let problem = format!(
+            "Directory {} is listed in {}, but is missing",
+            dir, METADATA_FILE
+        );
+        if fix && std::fs::create_dir_all(&path).is_ok() {
+            problems.fixed(&problem);
+        } else {
+            problems.report(&problem);
+        }
+    }
+    if metadata.environment.is_some() && spec_file(root).is_none() {
+        problems.report("Project has a Conda environment, but no environment.yml");
+    }
+}
+
+fn check_manifest(root: &Path, fix: bool, checksums: bool, problems: &mut Problems) {
+    let _lock = lock(&root.join(MANIFEST_FILE));
+    let mut manifest = Manifest::load(root);
+    let raw = root.join("raw");
+    let mut deleted = Vec::new();
+    for entry in &manifest.data {
+        let path = raw.join(&entry.path);
+        let encrypted = entry.encrypted.as_ref().map(|name| raw.join(name));
+        if path.symlink_metadata().is_err() && encrypted.as_ref().is_some_and(|e| e.is_file()) {
+            // Encrypted data can only be checked once decrypted
+            continue;
+        }
+        if path.symlink_metadata().is_err() {
+            let problem = format!(
+                "raw/{} is listed in {}, but was deleted",
+                entry.path, MANIFEST_FILE
+            );
+            if fix {
+                deleted.push(entry.path.clone());
+                problems.fixed(&problem);
+            } else {
+                problems.report(&problem);
+            }
+            continue;
+        }
+        if !path.exists() {
+            problems.report(&format!(
+                "raw/{} links to {}, which no longer exists",
+                entry.path, entry.source
+            ));
+            continue;
+        }
+        // Directories are only checked by their checksum, since computing their size means
+        // reading every file anyway
+        if path.is_file() && path.metadata().map(|m| m.len()).ok() != Some(entry.size) {
+            problems.report(&format!(
+                "raw/{} has changed size since it was added",
+                entry.path
+            ));
+        } else if checksums
+            && checksum_and_size(&path).ok().map(|(checksum, _)| checksum)
+                != Some(entry.sha256.clone())
+        {
+            problems.report(&format!(
+                "raw/{} has changed checksum since it was added",
+                entry.path
+            ));
+        }
+    }
+    if !deleted.is_empty() {
+        manifest.data.retain(|entry| !deleted.contains(&entry.path));
+        manifest.save(root);
+    }
+}
+
+fn check_finalized(root: &Path, checksums: bool, problems: &mut Problems) {
+    let Ok(content) = read_to_string(root.join(FINALIZED_FILE)) else {
+        return;
+    };
+    // The commit the project was finalized at must still be in the history
+    let commit = content.split_whitespace().find_map(|word| {
+        let word = word.trim_end_matches(['.', ',']);
+        (word.len() == 40)
+            .then(|| Oid::from_str(word).ok())
+            .flatten()
+    });
+    if let (Some(commit), Ok(repo)) = (commit, Repository::discover(root)) {
+        if repo.find_commit(commit).is_err() {
+            problems.report(&format!(
+                "{} refers to commit {}, which is not in the repository",
+                FINALIZED_FILE, commit
+            ));
+        }
+    }
+    let listed: Vec<(&str, &str)> = content
+        .lines()
+        .filter_map(|line| line.split_once("  "))
+        .filter(|(checksum, _)| {
+            checksum.len() == 64 && checksum.chars().all(|c| c.is_ascii_hexdigit())
+        })
+        .collect();
+    for (checksum, file) in &listed {
+        let path = root.join(file);
+        if !path.is_file() {
+            problems.report(&format!(
+                "{} was deleted after the project was finalized",
+                file
+            ));
+        } else if checksums && sha256(&path).ok().as_deref() != Some(*checksum) {
+            problems.report(&format!(
+                "{} was changed after the project was finalized",
+                file
+            ));
+        }
+    }
+    for dir in DATA_DIRECTORIES {
+        for path in walk_files(&root.join(dir)) {
+            let relative = path.strip_prefix(root).unwrap_or(&path).to_string_lossy();
+            if !listed.iter().any(|(_, file)| *file == relative) {
+                problems.report(&format!(
+                    "{} was added after the project was finalized",
+                    relative
+                ));
+            }
+        }
+    }
+}
+
+/// Check that the file of secrets .env is not tracked by git.
+fn check_secrets(root: &Path, fix: bool, problems: &mut Problems) {
+    let Ok(repo) = Repository::discover(root) else {
+        return;
+    };
+    let Some(relative) = relative_to_workdir(&repo, root).map(|dir| dir.join(".env")) else {
+        return;
+    };
+    let mut index = repo.index().expect("Error when reading git index");
+    if index.get_path(&relative, 0).is_none() {
+        return;
+    }
+    let problem = ".env with secrets is tracked by git. Secrets that were committed must be \
+        considered leaked, and should be replaced"
+        .to_owned();
+    // Only untrack the file, so the secrets remain available to the code
+    if fix && index.remove_path(&relative).is_ok() && index.write().is_ok() {
+        problems.fixed(&problem);
+    } else {
+        problems.report(&problem);
+    }
+}
+
+/// Check that no generated file still has the placeholder author, used when the author was
+/// unknown. Data and other untracked directories are not searched.
+fn check_placeholders(root: &Path, problems: &mut Problems) {
+    let files = root
+        .read_dir()
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name().is_some_and(|name| {
+                !name.to_string_lossy().starts_with('.')
+                    && !IGNORED_DIRECTORIES.iter().any(|dir| name == *dir)
+            })
+        })
+        .flat_map(|path| match path.is_dir() {
+            true => walk_files(&path),
+            false => vec![path],
+        });
+    for file in files {
+        // Generated files are small, and reading large ones would be slow
+        if file.metadata().map(|m| m.len()).unwrap_or(u64::MAX) > 1 << 20 {
+            continue;
+        }
+        if read_to_string(&file).is_ok_and(|content| content.contains(PLACEHOLDER_NAME)) {
+            problems.report(&format!(
+                "{} has the placeholder author {}. Replace it with the author",
+                file.strip_prefix(root).unwrap_or(&file).display(),
+                PLACEHOLDER_NAME
+            ));
+        }
+    }
+}
+
+/// Check that every table in results/ has a data dictionary describing its columns.
+fn check_dictionaries(root: &Path, problems: &mut Problems) {
+    for problem in dictionary::problems(root) {
+        problems.report(&problem);
+    }
+}
+
+/// Warn about external tools whose versions differ significantly from those the project was
+/// created with. This is no problem with the project itself, but may explain one.
+fn check_tools(metadata: &Metadata) {
+    for warning in tools::drift(&metadata.tools) {
+        warn(Code::ToolDrift, &warning);
+    }
+}
+
+/// Warn about expired scratch directories in tmp/, which are only removed by
+/// `investigate tmp gc`.
+fn check_scratch(root: &Path) {
+    for scratch in tmp::expired(root) {
+        let name = scratch.path.strip_prefix(root).unwrap_or(&scratch.path);
+        warn(
+            Code::ExpiredScratch,
+            &format!(
+                "{} expired on {}. Remove it with `investigate tmp gc`",
+                name.display(),
+                scratch.expires
+            ),
+        );
+    }
+}
+
+pub fn run(args: FsckArgs) {
+    let root = find_project_root();
+    let metadata = Metadata::load(&root);
+    let mut problems = Problems::default();
+    check_directories(&root, &metadata, args.fix, &mut problems);
+    check_manifest(&root, args.fix, args.checksums, &mut problems);
+    check_finalized(&root, args.checksums, &mut problems);
+    check_secrets(&root, args.fix, &mut problems);
+    check_dictionaries(&root, &mut problems);
+    check_placeholders(&root, &mut problems);
+    check_tools(&metadata);
+    check_scratch(&root);
+    let remaining = problems.found - problems.fixed;
+    if problems.found == 0 {
+        println!("No problems found");
+    } else {
+        println!(
+            "Found {} problems, fixed {}",
+            problems.found, problems.fixed
+        );
+    }
+    if remaining > 0 {
+        std::process::exit(1)
+    }
+}
@@ -0,0 +1,236 @@
+use git2::{IndexAddOption, Oid, Repository, Signature};
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use crate::exit_with_error;
+
+/// Get the git repository that `path` would be created in, if any.
+/// A repository at `path` itself is not considered to contain it.
+pub fn containing_repository(path: &Path) -> Option<Repository> {
+    let path = std::path::absolute(path).ok()?;
+    if path.join(".git").exists() {
+        return None;
+    }
+    Repository::discover(path.parent()?).ok()
+}
+
+/// Check if the working tree of `repo` has no uncommitted changes, ignoring ignored files.
+pub fn is_clean(repo: &Repository) -> bool {
+    repo.statuses(None)
+        .map(|statuses| statuses.iter().all(|s| s.status().is_ignored()))
+        .unwrap_or(false)
+}
+
+/// Get `path` relative to the working directory of `repo`.
+pub fn relative_to_workdir(repo: &Repository, path: &Path) -> Option<PathBuf> {
+    let workdir = repo.workdir()?.canonicalize().ok()?;
+    let path = path.canonicalize().ok()?;
+    path.strip_prefix(workdir).ok().map(Path::to_path_buf)
+}
+
+/// Run `program` with `args`, giving it `input` on stdin, and return its stdout.
+fn run_with_input(program: &str, args: &[&str], input: &str) -> Result<String, git2::Error> {
+    let fail = |e: String| {
+        git2::Error::from_str(&format!("Could not sign commit with {}: {}", program, e))
+    };
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| fail(e.to_string()))?;
+    child
+        .stdin
+        .take()
+        .expect("Error when opening stdin of child process")
+        .write_all(input.as_bytes())
+        .map_err(|e| fail(e.to_string()))?;
+    let output = child.wait_with_output().map_err(|e| fail(e.to_string()))?;
+    if !output.status.success() {
+        return Err(fail(
+            String::from_utf8_lossy(&output.stderr).trim().to_owned(),
+        ));
+    }
+    String::from_utf8(output.stdout).map_err(|e| fail(e.to_string()))
+}
+
+/// Sign the commit `content` the same way git would, using the settings
+/// `gpg.format`, `user.signingkey` and `gpg.<FORMAT>.program` from git config.
+fn sign_commit(
+    repo: &Repository,
+    content: &str,
+    committer: &Signature,
+) -> Result<String, git2::Error> {
+    let config = repo.config()?;
+    let format = config
+        .get_string("gpg.format")
+        .unwrap_or_else(|_| "openpgp".to_owned());
+    let key = config.get_string("user.signingkey").ok();
+    let program = |key: &str, default: &str| {
+        config
+            .get_string(key)
+            .unwrap_or_else(|_| default.to_owned())
+    };
+    match format.as_str() {
+        "ssh" => {
+            let key = key.ok_or_else(|| {
+                git2::Error::from_str("Signing with SSH requires user.signingkey to be set")
+            })?;
+            // The key is either a path, or the public key itself
+            let key_path = match key.strip_prefix("key::") {
+                Some(literal) => {
+                    let path = std::env::temp_dir()
+                        .join(format!("investigate-signingkey-{}.pub", std::process::id()));
+                    std::fs::write(&path, literal)
+                        .map_err(|e| git2::Error::from_str(&e.to_string()))?;
+                    path
+                }
+                None => match key.strip_prefix("~/") {
+                    Some(rest) => {
+                        PathBuf::from(std::env::var_os("HOME").unwrap_or_default()).join(rest)
+                    }
+                    None => PathBuf::from(&key),
+                },
+            };
+            let key_path_str = key_path.to_string_lossy().into_owned();
+            let signature = run_with_input(
+                &program("gpg.ssh.program", "ssh-keygen"),
+                &["-Y", "sign", "-n", "git", "-f", &key_path_str],
+                content,
+            );
+            if key.starts_with("key::") {
+                let _ = std::fs::remove_file(&key_path);
+            }
+            signature
+        }
+        format => {
+            let program = if format == "x509" {
+                program("gpg.x509.program", "gpgsm")
+            } else {
+                program("gpg.openpgp.program", &program("gpg.program", "gpg"))
+            };
+            // Like git, default to the key of the committer
+            let key = key.unwrap_or_else(|| committer.to_string());
+            run_with_input(&program, &["--status-fd=2", "-bsau", &key], content)
+        }
+    }
+}
+
+/// Commit the current index of `repo` on HEAD. The commit is signed if `sign` is true,
+/// or if `commit.gpgsign` is set in git config.
+pub fn commit_index(repo: &Repository, message: &str, sign: bool) -> Result<Oid, git2::Error> {
+    let signature = repo.signature()?;
+    let mut index = repo.index()?;
+    let tree = repo.find_tree(index.write_tree()?)?;
+    let parent = repo.head().ok().and_then(|h| h.peel_to_commit().ok());
+    let parents = parent.as_ref().into_iter().collect::<Vec<_>>();
+    let sign = sign || repo.config()?.get_bool("commit.gpgsign").unwrap_or(false);
+    if !sign {
+        return repo.commit(
+            Some("HEAD"),
+            &signature,
+            &signature,
+            message,
+            &tree,
+            &parents,
+        );
+    }
+    let buffer = repo.commit_create_buffer(&signature, &signature, message, &tree, &parents)?;
+    let content = buffer
+        .as_str()
+        .ok_or_else(|| git2::Error::from_str("Commit is not valid UTF-8"))?;
+    let gpg_signature = sign_commit(repo, content, &signature)?;
+    let oid = repo.commit_signed(content, &gpg_signature, None)?;
+    // Unlike `commit`, `commit_signed` does not move HEAD, which may point to an unborn branch
+    let head = repo.find_reference("HEAD")?;
+    let branch = head.symbolic_target().unwrap_or("HEAD").to_owned();
+    let first_line = message.lines().next().unwrap_or_default();
+    repo.reference(&branch, oid, true, &format!("commit: {}", first_line))?;
+    Ok(oid)
+}
+
+/// Add all files matching `pathspecs` to the index of `repo`, including deletions,
+/// then commit on HEAD.
+pub fn commit_paths<T: AsRef<str>>(
+    repo: &Repository,
+    pathspecs: &[T],
+    message: &str,
+    sign: bool,
+) -> Result<Oid, git2::Error> {
+    let mut index = repo.index()?;
+    index.add_all(
+        pathspecs.iter().map(|p| p.as_ref()),
+        IndexAddOption::DEFAULT,
+        None,
+    )?;
+    index.update_all(pathspecs.iter().map(|p| p.as_ref()), None)?;
+    index.write()?;
+    commit_index(repo, message, sign)
+}
+
+/// Get the name of the tag `name` of the project at `root`.
+/// Tags of subprojects are prefixed by their path, so they don't clash.
+fn project_tag_name(repo: &Repository, root: &Path, name: &str) -> String {
+    match relative_to_workdir(repo, root) {
+        Some(prefix) if !prefix.as_os_str().is_empty() => {
+            format!("{}/{}", prefix.to_string_lossy(), name)
+        }
+        _ => name.to_owned(),
+    }
+}
+
+/// Create the annotated tag `tag` of the project at `root` on `target`.
+fn tag(repo: &Repository, root: &Path, target: Oid, tag: &str, message: &str) -> String {
+    let signature = repo.signature().unwrap_or_else(|_| {
+        exit_with_error("Cannot tag: git user.name and user.email are not set")
+    });
+    let target = repo
+        .find_object(target, None)
+        .expect("Error when reading git commit");
+    let name = project_tag_name(repo, root, tag);
+    repo.tag(&name, &target, &signature, message, false)
+        .unwrap_or_else(|e| exit_with_error(&format!("Could not create tag: {}", e.message())));
+    name
+}
+
+/// Commit `files` of the project at `root`, which may be a subproject.
+pub fn commit_project_files(root: &Path, files: &[&str], message: &str) -> Oid {
+    let repo = Repository::discover(root)
+        .unwrap_or_else(|_| exit_with_error("Cannot commit: project is not a git repository"));
+    let prefix = relative_to_workdir(&repo, root).unwrap_or_default();
+    let pathspecs: Vec<String> = files
+        .iter()
+        .map(|file| prefix.join(file).to_string_lossy().into_owned())
+        .collect();
+    commit_paths(&repo, &pathspecs, message, false)
+        .unwrap_or_else(|e| exit_with_error(&format!("Could not commit: {}", e.message())))
+}
+
+/// Commit `files` in the project at `root`, and tag the commit with `tag`.
+/// Returns the full name of the tag.
+pub fn commit_and_tag(
+    root: &Path,
+    files: &[&str],
+    commit_message: &str,
+    tag_name: &str,
+    tag_message: &str,
+) -> String {
+    let commit_id = commit_project_files(root, files, commit_message);
+    let repo = Repository::discover(root).expect("Error when opening git repository");
+    tag(&repo, root, commit_id, tag_name, tag_message)
+}
+
+/// Tag the current HEAD of the project at `root` with `tag`. Returns the full name of the tag.
+pub fn tag_head(root: &Path, tag_name: &str, message: &str) -> String {
+    let repo = Repository::discover(root)
+        .unwrap_or_else(|_| exit_with_error("Cannot tag: project is not a git repository"));
+    let head = repo
+        .head()
+        .ok()
+        .and_then(|head| head.target())
+        .unwrap_or_else(|| exit_with_error("Cannot tag: repository has no commits"));
+    tag(&repo, root, head, tag_name, message)
+}
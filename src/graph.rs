@@ -0,0 +1,126 @@
+use clap::{ArgEnum, Args};
+
+use std::collections::BTreeSet;
+
+use crate::figure::Figures;
+use crate::run::{load_runs, RUNS_FILE};
+use crate::{exit_with_error, find_project_root};
+
+#[derive(Copy, Clone, PartialEq, Eq, ArgEnum)]
+pub enum GraphFormat {
+    Dot,
+    Mermaid,
+}
+
+#[derive(Args)]
+pub struct GraphArgs {
+    #[clap(arg_enum, value_parser, long, default_value = "dot")]
+    format: GraphFormat,
+}
+
+#[derive(Copy, Clone)]
+enum NodeKind {
+    Input,
+    Script,
+    Output,
+}
+
+fn node_kind(node: &str) -> NodeKind {
+    if node.starts_with("raw/") || node.starts_with("choices/") {
+        NodeKind::Input
+    } else if node.starts_with("results/") || node.starts_with("cache/") {
+        NodeKind::Output
+    } else {
+        NodeKind::Script
+    }
+}
+
+fn to_dot(nodes: &BTreeSet<String>, edges: &BTreeSet<(String, String)>) -> String {
+    let quote = |node: &str| format!("\"{}\"", node.replace('\\', "\\\\").replace('"', "\\\""));
+    let mut lines = vec![
+        "digraph provenance {".to_owned(),
+        "  rankdir=LR;".to_owned(),
+    ];
+    for node in nodes {
+        let shape = match node_kind(node) {
+            NodeKind::Input => "cylinder",
+            NodeKind::Script => "box",
+            NodeKind::Output => "note",
+        };
+        lines.push(format!("  {} [shape={}];", quote(node), shape));
+    }
+    for (from, to) in edges {
+        lines.push(format!("  {} -> {};", quote(from), quote(to)));
+    }
+    lines.push("}".to_owned());
+    lines.join("\n")
+}
+
+fn to_mermaid(nodes: &BTreeSet<String>, edges: &BTreeSet<(String, String)>) -> String {
+    // Mermaid IDs can't contain most punctuation, so nodes are numbered
+    let nodes: Vec<&String> = nodes.iter().collect();
+    let id = |node: &String| format!("n{}", nodes.binary_search(&node).unwrap_or_default());
+    let mut lines = vec!["flowchart LR".to_owned()];
+    for node in &nodes {
+        let label = node.replace('"', "#quot;");
+        let shape = match node_kind(node) {
+            NodeKind::Input => format!("[(\"{}\")]", label),
+            NodeKind::Script => format!("[\"{}\"]", label),
+            NodeKind::Output => format!("[/\"{}\"/]", label),
+        };
+        lines.push(format!("  {}{}", id(node), shape));
+    }
+    for (from, to) in edges {
+        lines.push(format!("  {} --> {}", id(from), id(to)));
+    }
+    lines.join("\n")
+}
+
+pub fn run(args: GraphArgs) {
+    let root = find_project_root();
+    let mut edges: BTreeSet<(String, String)> = BTreeSet::new();
+    // Failed runs may have written partial outputs, which are not results
+    for run in load_runs(&root)
+        .into_iter()
+        .filter(|run| run.exit_code == Some(0))
+    {
+        let scripts = if run.scripts.is_empty() {
+            vec![run.command.join(" ")]
+        } else {
+            run.scripts
+        };
+        for script in &scripts {
+            edges.extend(
+                run.inputs
+                    .iter()
+                    .map(|input| (input.clone(), script.clone())),
+            );
+            edges.extend(
+                run.outputs
+                    .iter()
+                    .map(|output| (script.clone(), output.clone())),
+            );
+        }
+    }
+    for figure in Figures::load(&root).figure {
+        if let Some(script) = figure.script {
+            edges.insert((script, figure.file));
+        }
+    }
+    if edges.is_empty() {
+        exit_with_error(&format!(
+            "No provenance recorded. Run the analysis with `investigate run`, \
+            which records it in {}",
+            RUNS_FILE
+        ))
+    }
+    let nodes: BTreeSet<String> = edges
+        .iter()
+        .flat_map(|(from, to)| [from.clone(), to.clone()])
+        .collect();
+    let graph = match args.format {
+        GraphFormat::Dot => to_dot(&nodes, &edges),
+        GraphFormat::Mermaid => to_mermaid(&nodes, &edges),
+    };
+    println!("{}", graph);
+}
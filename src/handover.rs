@@ -0,0 +1,184 @@
+use chrono::{Local, TimeZone};
+use clap::Args;
+use git2::Repository;
+
+use std::fs::read_to_string;
+use std::path::{Path, PathBuf};
+
+use crate::author::{get_author_email, warn_unknown_author, PLACEHOLDER_NAME};
+use crate::config::Config;
+use crate::contributor::contributor_list;
+use crate::env::spec_file;
+use crate::git::is_clean;
+use crate::metadata::Metadata;
+use crate::new::{convert_name_to_module, write, Existing, Language};
+use crate::report::{environment_section, walk_files};
+use crate::templates::Templates;
+use crate::trash::trash;
+use crate::{exit_with_error, find_project_root};
+
+/// Directories searched for TODO and FIXME comments
+const CODE_DIRECTORIES: [&str; 4] = ["src", "scripts", "notebooks", "test"];
+
+#[derive(Args)]
+pub struct HandoverArgs {
+    /// File to write the handover document to
+    #[clap(short, long, default_value = "HANDOVER.md")]
+    output: PathBuf,
+
+    /// Overwrite the output file if it exists
+    #[clap(long)]
+    force: bool,
+}
+
+/// The steps to reproduce the results of the project at `root`, as a numbered list.
+fn how_to_run(root: &Path, metadata: &Metadata) -> String {
+    let mut steps = Vec::new();
+    if metadata.data_remote.is_some() {
+        steps.push(
+            "Download the data with `investigate data fetch`, or `./sync_data.sh fetch`".to_owned(),
+        );
+    }
+    if let Some(name) = &metadata.environment {
+        let spec = spec_file(root)
+            .and_then(|path| path.file_name().map(|n| n.to_string_lossy().into_owned()))
+            .unwrap_or_else(|| "environment.yml".to_owned());
+        steps.push(format!(
+            "Create the environment with `conda env create -f {}`, \
+            and activate it with `conda activate {}`",
+            spec, name
+        ));
+    } else if metadata.language == Some(Language::Julia) {
+        steps.push(
+            "Install the packages with `julia --project=. -e 'using Pkg; Pkg.instantiate()'`"
+                .to_owned(),
+        );
+    }
+    if root.join("Makefile").is_file() {
+        steps.push("Run the analysis with `make`".to_owned());
+    } else if root.join("src").join("main.py").is_file() {
+        steps.push("Run the analysis with `python src/main.py`".to_owned());
+    } else if metadata.language == Some(Language::Julia) {
+        let module = convert_name_to_module(&metadata.name);
+        steps.push(format!(
+            "Run the analysis with `julia --project=. -e 'using {0}; {0}.julia_main()'`",
+            module
+        ));
+    }
+    if steps.is_empty() {
+        return "TODO: Describe how to run the analysis.".to_owned();
+    }
+    steps
+        .iter()
+        .enumerate()
+        .map(|(i, step)| format!("{}. {}", i + 1, step))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn state(root: &Path) -> String {
+    let Ok(repo) = Repository::discover(root) else {
+        return "The project is not tracked by git.".to_owned();
+    };
+    let mut lines = Vec::new();
+    if let Ok(commit) = repo.head().and_then(|head| head.peel_to_commit()) {
+        let date = Local
+            .timestamp(commit.time().seconds(), 0)
+            .format("%Y-%m-%d");
+        lines.push(format!(
+            "- Last commit: {} \"{}\" by {}",
+            date,
+            commit.summary().unwrap_or_default(),
+            commit.author().name().unwrap_or("unknown")
+        ));
+    }
+    lines.push(if is_clean(&repo) {
+        "- All changes are committed".to_owned()
+    } else {
+        "- There are uncommitted changes, which should be committed or discarded".to_owned()
+    });
+    if let Ok(remote) = repo.find_remote("origin") {
+        lines.push(format!(
+            "- Git remote: {}",
+            remote.url().unwrap_or_default()
+        ));
+    }
+    lines.join("\n")
+}
+
+/// Find TODO and FIXME comments in the code of the project at `root`.
+fn todos(root: &Path) -> String {
+    let mut items = Vec::new();
+    for dir in CODE_DIRECTORIES {
+        for file in walk_files(&root.join(dir)) {
+            // Skip binary files
+            let Ok(content) = read_to_string(&file) else {
+                continue;
+            };
+            let relative = file.strip_prefix(root).unwrap_or(&file);
+            for (i, line) in content.lines().enumerate() {
+                if line.contains("TODO") || line.contains("FIXME") {
+                    items.push(format!(
+                        "- `{}:{}`: {}",
+                        relative.display(),
+                        i + 1,
+                        line.trim()
+                    ));
+                }
+            }
+        }
+    }
+    if items.is_empty() {
+        "No TODO or FIXME comments were found in the code.".to_owned()
+    } else {
+        format!(
+            "These TODO and FIXME comments were found in the code:\n{}",
+            items.join("\n")
+        )
+    }
+}
+
+pub fn run(args: HandoverArgs) {
+    let root = find_project_root();
+    let metadata = Metadata::load(&root);
+    let config = Config::load();
+    let templates = Templates::new(Vec::new(), &config);
+    let path = root.join(&args.output);
+    if path.exists() && !args.force {
+        exit_with_error(&format!(
+            "{:?} already exists. Use --force to overwrite it",
+            path
+        ))
+    }
+    if path.exists() {
+        let id = trash(&root, std::slice::from_ref(&path));
+        println!("Moved the old {:?} to the trash as {}", args.output, id);
+    }
+    let author = get_author_email(&config)
+        .map(|(name, _)| name)
+        .unwrap_or_else(|| {
+            warn_unknown_author();
+            PLACEHOLDER_NAME.to_owned()
+        });
+    let contributors = if metadata.contributors.is_empty() {
+        "No contributors recorded. Add them with `investigate contributor add`.".to_owned()
+    } else {
+        contributor_list(&metadata.contributors)
+    };
+    let content = templates.render(
+        "handover",
+        &[
+            ("name", &metadata.name),
+            ("date", &Local::today().format("%Y-%m-%d").to_string()),
+            ("author", &author),
+            ("version", &metadata.version),
+            ("contributors", &contributors),
+            ("how_to_run", &how_to_run(&root, &metadata)),
+            ("environment", &environment_section(&root, &metadata)),
+            ("state", &state(&root)),
+            ("todos", &todos(&root)),
+        ],
+    );
+    write(&path, &content, Existing::Force);
+    println!("Wrote {:?}", path);
+}
@@ -0,0 +1,170 @@
+use chrono::{DateTime, Local};
+use clap::Args;
+use git2::Repository;
+use serde::Serialize;
+
+use std::path::Path;
+use std::time::SystemTime;
+
+use crate::find_project_root;
+use crate::metadata::Metadata;
+use crate::new::Language;
+use crate::warning::{emitted, Emitted};
+
+#[derive(Args)]
+pub struct InfoArgs {
+    /// Print the information as JSON
+    #[clap(long)]
+    json: bool,
+}
+
+#[derive(Serialize)]
+struct DirectoryInfo {
+    name: String,
+    /// Last modification time of any file in the directory, if it has any files
+    last_modified: Option<String>,
+}
+
+#[derive(Serialize)]
+struct Info {
+    name: String,
+    path: String,
+    uuid: String,
+    created: String,
+    investigate_version: String,
+    version: String,
+    language: Option<Language>,
+    authors: Vec<String>,
+    environment: Option<String>,
+    git_remote: Option<String>,
+    data_remote: Option<String>,
+    seed: Option<u32>,
+    directories: Vec<DirectoryInfo>,
+    /// Warnings given while gathering the information
+    warnings: Vec<Emitted>,
+}
+
+/// Get the latest modification time of any file in `dir`, recursively.
+fn last_modified(dir: &Path) -> Option<SystemTime> {
+    let mut latest = None;
+    for entry in dir.read_dir().ok()?.flatten() {
+        let path = entry.path();
+        let modified = if path.is_dir() {
+            last_modified(&path)
+        } else {
+            entry.metadata().ok().and_then(|m| m.modified().ok())
+        };
+        latest = latest.max(modified);
+    }
+    latest
+}
+
+fn git_remote(root: &Path) -> Option<String> {
+    let repo = Repository::discover(root).ok()?;
+    let remotes = repo.remotes().ok()?;
+    // Prefer "origin", but use any remote if it doesn't exist
+    let name = if remotes.iter().any(|r| r == Some("origin")) {
+        "origin"
+    } else {
+        remotes.get(0)?
+    };
+    let remote = repo.find_remote(name).ok()?;
+    remote.url().map(|url| url.to_owned())
+}
+
+fn print_human(info: &Info) {
+    let none = || "(none)".to_owned();
+    println!("Name:                {}", info.name);
+    println!("Path:                {}", info.path);
+    println!("UUID:                {}", info.uuid);
+    println!("Created:             {}", info.created);
+    println!(
+        "Created with:        investigate {}",
+        info.investigate_version
+    );
+    println!("Version:             {}", info.version);
+    println!(
+        "Language:            {}",
+        match info.language {
+            Some(Language::Python) => "Python".to_owned(),
+            Some(Language::Julia) => "Julia".to_owned(),
+            None => none(),
+        }
+    );
+    println!(
+        "Authors:             {}",
+        if info.authors.is_empty() {
+            none()
+        } else {
+            info.authors.join(", ")
+        }
+    );
+    println!(
+        "Environment:         {}",
+        info.environment.clone().unwrap_or_else(none)
+    );
+    println!(
+        "Git remote:          {}",
+        info.git_remote.clone().unwrap_or_else(none)
+    );
+    println!(
+        "Data remote:         {}",
+        info.data_remote.clone().unwrap_or_else(none)
+    );
+    println!(
+        "Seed:                {}",
+        info.seed.map(|s| s.to_string()).unwrap_or_else(none)
+    );
+    println!("Last modified:");
+    for dir in &info.directories {
+        println!(
+            "  {:<18} {}",
+            dir.name.clone() + ":",
+            dir.last_modified
+                .clone()
+                .unwrap_or_else(|| "(empty)".to_owned())
+        );
+    }
+}
+
+pub fn run(args: InfoArgs) {
+    let root = find_project_root();
+    let metadata = Metadata::load(&root);
+    let directories = metadata
+        .directories
+        .iter()
+        .filter(|dir| root.join(dir).is_dir())
+        .map(|dir| DirectoryInfo {
+            name: dir.clone(),
+            last_modified: last_modified(&root.join(dir)).map(|time| {
+                DateTime::<Local>::from(time)
+                    .format("%Y-%m-%d %H:%M")
+                    .to_string()
+            }),
+        })
+        .collect();
+    let info = Info {
+        name: metadata.name,
+        path: root.to_string_lossy().into_owned(),
+        uuid: metadata.uuid,
+        created: metadata.created,
+        investigate_version: metadata.investigate_version,
+        version: metadata.version,
+        language: metadata.language,
+        authors: metadata.authors,
+        environment: metadata.environment,
+        git_remote: git_remote(&root),
+        data_remote: metadata.data_remote,
+        seed: metadata.seed,
+        directories,
+        warnings: emitted(),
+    };
+    if args.json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&info).expect("Error when serializing to JSON")
+        );
+    } else {
+        print_human(&info);
+    }
+}
@@ -0,0 +1,222 @@
+use clap::Subcommand;
+use toml_edit::{value, Array, DocumentMut, Item, Table};
+
+use std::collections::BTreeMap;
+use std::fs::read_to_string;
+use std::path::{Path, PathBuf};
+
+use crate::author::PLACEHOLDER_NAME;
+use crate::config::Config;
+use crate::metadata::Metadata;
+use crate::new::{make_ci, make_docs, write, Ci, Existing, Language};
+use crate::templates::Templates;
+use crate::{exit_with_error, filesystem, find_project_root};
+
+/// UUID of the Test standard library, which packages depend on only for their tests.
+const TEST_UUID: &str = "8dfed614-e22c-5e08-85e1-65c5234f0b40";
+
+#[derive(Subcommand)]
+pub enum JuliaCommand {
+    /// Turn the project into a package: add tests, docs, compat bounds and GitHub Actions
+    Packageify,
+}
+
+/// Get the resolved version of each package in the Manifest.toml at `path`, and the Julia
+/// version it was resolved with, if any.
+pub fn manifest_versions(path: &Path) -> (BTreeMap<String, String>, Option<String>) {
+    let manifest: toml::Table = match read_to_string(path) {
+        Ok(content) => toml::from_str(&content)
+            .unwrap_or_else(|e| exit_with_error(&format!("Could not parse {:?}: {}", path, e))),
+        Err(_) => return (BTreeMap::new(), None),
+    };
+    let julia_version = manifest
+        .get("julia_version")
+        .and_then(toml::Value::as_str)
+        .map(str::to_owned);
+    // Manifests of Julia 1.7 and later list the packages under [deps]
+    let packages = match manifest.get("deps").and_then(toml::Value::as_table) {
+        Some(deps) => deps,
+        None => &manifest,
+    };
+    let versions = packages
+        .iter()
+        .filter_map(|(name, entries)| {
+            let version = entries.as_array()?.first()?.get("version")?.as_str()?;
+            Some((name.clone(), version.to_owned()))
+        })
+        .collect();
+    (versions, julia_version)
+}
+
+/// Get the compat bound allowing all versions compatible with `version` under semver,
+/// e.g. "1.4" for 1.4.2 and "0.3" for 0.3.1.
+fn compat_bound(version: &str) -> String {
+    let mut parts = version.split('.');
+    match (parts.next(), parts.next()) {
+        (Some(major), Some(minor)) => format!("{}.{}", major, minor),
+        _ => version.to_owned(),
+    }
+}
+
+fn table<'a>(project: &'a mut DocumentMut, name: &str) -> &'a mut Table {
+    project
+        .entry(name)
+        .or_insert_with(|| Item::Table(Table::new()))
+        .as_table_mut()
+        .unwrap_or_else(|| exit_with_error(&format!("[{}] in Project.toml is not a table", name)))
+}
+
+/// Add compat bounds for Julia and for the dependencies that don't have one, and the Test
+/// standard library as a test-only dependency. Returns the bounds added.
+fn update_project(project: &mut DocumentMut, manifest: &Path) -> Vec<String> {
+    let (versions, julia_version) = manifest_versions(manifest);
+    let deps: Vec<String> = project
+        .get("deps")
+        .and_then(Item::as_table)
+        .map(|deps| deps.iter().map(|(name, _)| name.to_owned()).collect())
+        .unwrap_or_default();
+    let compat = table(project, "compat");
+    let mut added = Vec::new();
+    if !compat.contains_key("julia") {
+        let bound = julia_version
+            .as_deref()
+            .map_or("1".to_owned(), compat_bound);
+        compat["julia"] = value(&bound);
+        added.push(format!("julia = \"{}\"", bound));
+    }
+    // Standard libraries have no version in the manifest, and need no bound
+    for dep in deps {
+        if let (false, Some(version)) = (compat.contains_key(&dep), versions.get(&dep)) {
+            let bound = compat_bound(version);
+            compat[dep.as_str()] = value(&bound);
+            added.push(format!("{} = \"{}\"", dep, bound));
+        }
+    }
+    compat.sort_values();
+    let extras = table(project, "extras");
+    if !extras.contains_key("Test") {
+        extras["Test"] = value(TEST_UUID);
+    }
+    let targets = table(project, "targets");
+    if !targets.contains_key("test") {
+        let mut test = Array::new();
+        test.push("Test");
+        targets["test"] = value(test);
+    }
+    added
+}
+
+fn packageify() {
+    let root = find_project_root();
+    if Metadata::load(&root).language != Some(Language::Julia) {
+        exit_with_error("Only Julia projects can be turned into Julia packages")
+    }
+    let project_path = root.join("Project.toml");
+    let mut project: DocumentMut = read_to_string(&project_path)
+        .unwrap_or_else(|_| exit_with_error("Could not read Project.toml"))
+        .parse()
+        .unwrap_or_else(|e| exit_with_error(&format!("Could not parse Project.toml: {}", e)));
+    let module_name = project
+        .get("name")
+        .and_then(Item::as_str)
+        .unwrap_or_else(|| exit_with_error("Project.toml has no name"))
+        .to_owned();
+    if !root
+        .join("src")
+        .join(format!("{}.jl", module_name))
+        .is_file()
+    {
+        exit_with_error(&format!(
+            "A package must define its module {} in src/{}.jl",
+            module_name, module_name
+        ))
+    }
+    let author = project
+        .get("authors")
+        .and_then(Item::as_array)
+        .and_then(|authors| authors.get(0))
+        .and_then(|author| author.as_str())
+        .unwrap_or(PLACEHOLDER_NAME)
+        .to_owned();
+
+    let added = update_project(&mut project, &root.join("Manifest.toml"));
+    filesystem::write(&project_path, project.to_string())
+        .unwrap_or_else(|_| panic!("Error when writing file {:?}", project_path));
+    for bound in &added {
+        println!("Added compat bound {} to Project.toml", bound);
+    }
+
+    let templates = &Templates::new(Vec::new(), &Config::load());
+    let workflows = root.join(".github").join("workflows");
+    let files: Vec<PathBuf> = [
+        root.join("test").join("runtests.jl"),
+        root.join("docs").join("make.jl"),
+        workflows.join("ci.yml"),
+        workflows.join("CompatHelper.yml"),
+        workflows.join("TagBot.yml"),
+    ]
+    .into_iter()
+    .filter(|path| !path.exists())
+    .collect();
+    let test_dir = root.join("test");
+    filesystem::create_dir_all(&test_dir)
+        .unwrap_or_else(|_| panic!("Error when creating directory {:?}", test_dir));
+    write(
+        &test_dir.join("runtests.jl"),
+        &templates.render("runtests_julia", &[("module_name", &module_name)]),
+        Existing::Merge,
+    );
+    make_docs(
+        &root,
+        templates,
+        Language::Julia,
+        &module_name,
+        &module_name,
+        &author,
+        Existing::Merge,
+    );
+    make_ci(
+        &root,
+        templates,
+        Ci::Github,
+        Language::Julia,
+        &module_name,
+        Existing::Merge,
+    );
+    for (name, file) in [
+        ("compathelper_julia", "CompatHelper.yml"),
+        ("tagbot_julia", "TagBot.yml"),
+    ] {
+        write(
+            &workflows.join(file),
+            &templates.render(name, &[]),
+            Existing::Merge,
+        );
+    }
+    for path in files {
+        println!("Wrote {:?}", path.strip_prefix(&root).unwrap_or(&path));
+    }
+
+    println!(
+        "\nTo finish the package:\n  \
+        * Move code specific to this analysis, like `setup` and the directory constants,\n    \
+        out of src/{}.jl, e.g. into scripts/\n  \
+        * Export the public functions, document them with docstrings, and test them in test/",
+        module_name
+    );
+    if !["LICENSE", "LICENSE.md"]
+        .iter()
+        .any(|name| root.join(name).is_file())
+    {
+        println!("  * Add a LICENSE file. Registered packages must have an open source license");
+    }
+    println!(
+        "  * Register the package with Registrator: https://github.com/JuliaRegistries/Registrator.jl"
+    );
+}
+
+pub fn run(command: JuliaCommand) {
+    match command {
+        JuliaCommand::Packageify => packageify(),
+    }
+}
@@ -0,0 +1,67 @@
+use std::fs::{File, OpenOptions};
+use std::path::{Path, PathBuf};
+
+use crate::filesystem;
+
+/// An exclusive lock shared between processes, released when dropped.
+pub struct FileLock {
+    _file: File,
+}
+
+/// Get the hidden lock file `.NAME.lock` next to `path`.
+fn lock_path(path: &Path) -> PathBuf {
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    path.with_file_name(format!(".{}.lock", name))
+}
+
+#[cfg(unix)]
+fn lock_file(file: &File) -> std::io::Result<()> {
+    use std::os::unix::io::AsRawFd;
+    // SAFETY: The file descriptor is valid for as long as `file` is
+    if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX) } == 0 {
+        Ok(())
+    } else {
+        Err(std::io::Error::last_os_error())
+    }
+}
+
+#[cfg(not(unix))]
+fn lock_file(_file: &File) -> std::io::Result<()> {
+    Ok(())
+}
+
+/// Lock `path` against changes by other processes, waiting until they release it.
+/// The lock is held on a separate lock file, so `path` itself may be replaced.
+pub fn lock(path: &Path) -> FileLock {
+    let lock_path = lock_path(path);
+    if let Some(parent) = lock_path.parent() {
+        std::fs::create_dir_all(parent)
+            .unwrap_or_else(|_| panic!("Error when creating directory {:?}", parent));
+    }
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&lock_path)
+        .unwrap_or_else(|_| panic!("Error when opening lock file {:?}", lock_path));
+    lock_file(&file).unwrap_or_else(|_| panic!("Error when locking {:?}", lock_path));
+    FileLock { _file: file }
+}
+
+/// Write `content` to `path`, such that readers see either the old or the new content,
+/// never a partial write.
+pub fn write_atomic(path: &Path, content: &str) -> std::io::Result<()> {
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let temporary = path.with_file_name(format!(".{}.tmp-{}", name, std::process::id()));
+    let filesystem = filesystem::current();
+    filesystem.write(&temporary, content.as_bytes())?;
+    filesystem.rename(&temporary, path).inspect_err(|_| {
+        let _ = std::fs::remove_file(&temporary);
+    })
+}
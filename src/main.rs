@@ -1,18 +1,108 @@
-use chrono::Local;
-use clap::{ArgEnum, Parser};
-use git2::Repository;
-use uuid::Uuid;
+use clap::{Parser, Subcommand};
 
-use std::ffi::OsString;
-use std::fs::create_dir;
-use std::path::Path;
-use std::process::Command;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
 
-const DIRECTORIES: [&str; 7] = ["src", "raw", "results", "paper", "tmp", "cache", "choices"];
+mod all;
+mod author;
+mod bib;
+mod bump;
+mod cache;
+mod clone;
+mod config;
+mod contributor;
+mod dashboard;
+mod data;
+mod dates;
+mod decisions;
+mod dictionary;
+mod directories;
+mod encrypt;
+mod env;
+mod exec;
+mod experiment;
+mod export;
+mod figure;
+mod filesystem;
+mod finalize;
+mod fsck;
+mod git;
+mod graph;
+mod handover;
+mod info;
+mod julia;
+mod lock;
+mod manifest;
+mod metadata;
+mod new;
+mod notify;
+mod paper;
+mod paths;
+mod permissions;
+mod progress;
+mod publish;
+mod readme;
+mod redact;
+mod registry;
+mod rename;
+mod reopen;
+mod report;
+mod results;
+mod run;
+mod schema;
+mod self_update;
+mod shell;
+mod software;
+mod ssh;
+mod stats;
+mod status;
+mod storage;
+mod templates;
+mod timesheet;
+mod tmp;
+mod todo;
+mod tools;
+mod trash;
+mod tree;
+mod verify;
+mod warning;
+mod watch;
+mod wsl;
 
-fn write(path: &Path, string: &str) {
-    std::fs::write(path, string.as_bytes())
-        .unwrap_or_else(|_| panic!("Error when creating file {:?}", path))
+fn exit_with_error(message: &str) -> ! {
+    eprintln!("Error: {}", message);
+    std::process::exit(1)
+}
+
+/// Whether network access is forbidden, set by --offline.
+static OFFLINE: AtomicBool = AtomicBool::new(false);
+
+fn is_offline() -> bool {
+    OFFLINE.load(Ordering::Relaxed)
+}
+
+/// Exit with an error if network access is forbidden, since `action` needs it.
+fn require_network(action: &str) {
+    if is_offline() {
+        exit_with_error(&format!(
+            "{} requires network access, which --offline forbids",
+            action
+        ))
+    }
+}
+
+/// Whether output should be plain text without spinners, box-drawing or color, set by --plain.
+static PLAIN: AtomicBool = AtomicBool::new(false);
+
+fn is_plain() -> bool {
+    PLAIN.load(Ordering::Relaxed)
+}
+
+/// Whether Conda and Julia run inside WSL on Windows, set by --wsl.
+static IN_WSL: AtomicBool = AtomicBool::new(false);
+
+fn run_in_wsl() -> bool {
+    IN_WSL.load(Ordering::Relaxed)
 }
 
 fn capitalize(s: &str) -> String {
@@ -27,214 +117,289 @@ fn capitalize(s: &str) -> String {
     }
 }
 
-fn make_readme(path: &Path, project_name: &str, author: &Option<String>) {
-    let date = Local::today().format("%Y-%m-%d").to_string();
-
-    // Add top of Readme
-    let mut content = format!(
-        include_str!("../templates/readme"),
-        project_name = project_name,
-        author = match author {
-            None => "".to_owned(),
-            Some(name) => format!("Author: {}\n", name),
-        },
-        date = date
-    );
-
-    // Add directory content, taken from main README.md...
-    let readme = include_str!("../README.md");
-    let mut lines = readme.lines();
-    // ... From first "# Directory structure" ...
-    for line in lines.by_ref() {
-        if line.trim() == "## Directory structure" {
-            content.push_str(line);
-            content.push('\n');
-            break;
-        }
-    }
-    // ... to next header.
-    for line in lines {
-        if line.starts_with("## ") {
-            break;
-        }
-        content.push_str(line);
-        content.push('\n');
-    }
-    write(path, &content)
+/// Find the root of the project containing the current directory, if any.
+fn find_project_root_opt() -> Option<PathBuf> {
+    let cwd = std::env::current_dir()
+        .unwrap_or_else(|_| exit_with_error("Could not get current directory"));
+    cwd.ancestors()
+        .find(|dir| dir.join(metadata::METADATA_FILE).is_file())
+        .map(|dir| dir.to_owned())
 }
 
-fn convert_name_to_module(project_name: &str) -> String {
-    // Splits by dash or underscore, then capitalize each chunk before joining.
-    project_name
-        .split(|c| c == '_' || c == '-')
-        .map(capitalize)
-        .collect()
+/// Find the root of the project containing the current directory.
+fn find_project_root() -> PathBuf {
+    find_project_root_opt().unwrap_or_else(|| {
+        exit_with_error(&format!(
+            "Not inside a project: No {} file found in this or any parent directory",
+            metadata::METADATA_FILE
+        ))
+    })
 }
 
-fn make_julia_project(path: &Path, module_name: &str, author_email: &Option<(String, String)>) {
-    let author_string = match author_email {
-        None => "Unknown author".to_owned(),
-        Some((name, mail)) => format!("{} <{}>", &name, &mail),
-    };
-    let uuid = Uuid::new_v4().hyphenated().to_string();
-    let content = format!(
-        include_str!("../templates/project"),
-        module_name = module_name,
-        uuid_str = uuid,
-        author = author_string
-    );
-    write(path, &content)
-}
+#[derive(Parser)]
+#[clap(version, author, about)]
+struct Options {
+    /// Never access the network. Fail if that's required, and use only local package caches
+    #[clap(long, global = true, env = "INVESTIGATE_OFFLINE")]
+    offline: bool,
 
-fn conda_create(project_name: &str) {
-    match Command::new("conda")
-        .args(["create", "-n", project_name, "-y"])
-        .output()
-    {
-        Ok(_) => println!("Created Conda environment \"{}\"", &project_name),
-        Err(_) => eprintln!(
-            "Warning: Could not create Conda environment \"{}\"",
-            &project_name
-        ),
-    }
-}
+    /// Print plain text, without spinners, box-drawing characters or color, for screen readers
+    /// and logs
+    #[clap(long, global = true, env = "INVESTIGATE_PLAIN")]
+    plain: bool,
 
-fn make_conda_yml(path: &Path, project_name: &str) {
-    let prefix = match std::env::var("CONDA_PREFIX") {
-        Err(_) => {
-            eprintln!("Warning: Could not get env variable $CONDA_PREFIX. Not writing \"environment.yml\" file.");
-            return;
-        }
-        Ok(x) => x,
-    };
-    let prefix_path = Path::new(&prefix).join("envs").join(project_name);
-    write(
-        &path.join("environment.yml"),
-        &format!(
-            include_str!("../templates/environment"),
-            name = project_name,
-            prefix_path = prefix_path.to_str().unwrap()
-        ),
-    );
-}
+    /// On Windows, run Conda and Julia inside WSL, in the distribution the project is in, or the
+    /// default one for projects on Windows drives
+    #[clap(long, global = true, env = "INVESTIGATE_WSL")]
+    wsl: bool,
 
-fn make_dirs(path: &Path) {
-    create_dir(path)
-        .unwrap_or_else(|_| panic!("Error when creating main project directory: {:?}", path));
-    for subdir in DIRECTORIES {
-        create_dir(path.join(subdir))
-            .unwrap_or_else(|_| panic!("Error when creating sub-directory: {:?}", path));
-    }
-}
+    /// Don't print warnings with this code, like W017. Can be given multiple times
+    #[clap(
+        long,
+        global = true,
+        multiple_occurrences = true,
+        use_value_delimiter = true,
+        env = "INVESTIGATE_ALLOW",
+        value_name = "CODE"
+    )]
+    allow: Vec<String>,
 
-fn get_author_email() -> Option<(String, String)> {
-    let mut name = None;
-    let mut email = None;
-    let config = git2::Config::open_default().ok()?;
-    for maybe_entry in &config.entries(Some("user")).ok()? {
-        let entry = maybe_entry.ok()?;
-        let entryname = entry.name()?;
-        let value = entry.value()?;
-        if entryname == "user.name" {
-            name = Some(value.to_owned())
-        } else if entryname == "user.email" {
-            email = Some(value.to_owned())
-        }
-    }
-    Some((name?, email?))
+    #[clap(subcommand)]
+    command: Commands,
 }
 
-#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ArgEnum)]
-enum Language {
-    Python,
-    Julia,
-}
+#[derive(Subcommand)]
+enum Commands {
+    /// Create a new project
+    New(new::NewArgs),
 
-#[derive(Parser)]
-#[clap(version, author, about)]
-struct Options {
-    /// Path to project dir to create
-    dirname: OsString, // if None, try to init current dir
+    /// Increment the version of the current project and add a changelog entry
+    Bump(bump::BumpArgs),
+
+    /// Print metadata of the current project
+    Info(info::InfoArgs),
+
+    /// Rename the current project, its directory, environment and code
+    Rename(rename::RenameArgs),
+
+    /// Create a new project from the code, environment and settings of an existing one,
+    /// without its data, results and history
+    CloneFrom(clone::CloneArgs),
+
+    /// Manage the data of the current project
+    #[clap(subcommand)]
+    Data(data::DataCommand),
+
+    /// Manage the environment of the current project
+    #[clap(subcommand)]
+    Env(env::EnvCommand),
+
+    /// Tools for Julia projects
+    #[clap(subcommand)]
+    Julia(julia::JuliaCommand),
+
+    /// Manage the cache/ directory of the current project
+    #[clap(subcommand)]
+    Cache(cache::CacheCommand),
 
-    /// Main programming language
-    #[clap(arg_enum, value_parser, short, long)]
-    language: Option<Language>,
+    /// Run a command in the project root, with the project's environment activated
+    Exec(exec::ExecArgs),
 
-    /// Project name (default: same as <DIRNAME>)
-    #[clap(short, long)]
-    name: Option<String>,
+    /// Run a command in the project's environment, and record the run in runs.jsonl
+    Run(run::RunArgs),
+
+    /// Show the runs recorded by `investigate run`
+    #[clap(subcommand)]
+    Runs(run::RunsCommand),
+
+    /// Record the versions of the software used, for the paper's methods section
+    #[clap(subcommand)]
+    Software(software::SoftwareCommand),
+
+    /// Write a helper for scripts with the paths of the project's directories, like src/paths.py
+    Paths(paths::PathsArgs),
+
+    /// Print what needs attention in the project, and the open items of its TODO list
+    Status,
+
+    /// Show the status, recent runs, disk usage and decisions of the project, or of all projects
+    Dashboard(dashboard::DashboardArgs),
+
+    /// Run a command in each registered project, like `investigate all status`
+    All(all::AllArgs),
+
+    /// Print the project's files and directories, with their sizes, descriptions and whether
+    /// git and the data manifest cover them
+    Tree(tree::TreeArgs),
+
+    /// Run a Makefile or justfile target, or a recorded run, again whenever src/ changes
+    Watch(watch::WatchArgs),
+
+    /// Print a graph of which scripts made which results from which data, as recorded by runs
+    Graph(graph::GraphArgs),
+
+    /// Try out variants of the analysis on separate branches, checked out in tmp/experiments/
+    #[clap(subcommand)]
+    Experiment(experiment::ExperimentCommand),
+
+    /// Mark the current project as done, and make its data read-only
+    Finalize(finalize::FinalizeArgs),
+
+    /// Check the consistency of the current project's metadata, data manifest and records
+    Fsck(fsck::FsckArgs),
+
+    /// Check that the generated code, Makefile and paper of the current project work
+    VerifyBuild,
+
+    /// Make a finalized project writable again, recording why
+    Reopen(reopen::ReopenArgs),
+
+    /// Manage the people who work or worked on the current project
+    #[clap(subcommand)]
+    Contributor(contributor::ContributorCommand),
+
+    /// Write a handover document for whoever takes over the current project
+    Handover(handover::HandoverArgs),
+
+    /// Describe the columns of result tables in data dictionaries
+    #[clap(subcommand)]
+    Dict(dictionary::DictCommand),
+
+    /// Compare results, and check them against a frozen snapshot
+    #[clap(subcommand)]
+    Results(results::ResultsCommand),
+
+    /// Manage scratch directories in tmp/, which expire after some days
+    #[clap(subcommand)]
+    Tmp(tmp::TmpCommand),
+
+    /// Track the time spent on the project, in work sessions
+    #[clap(subcommand)]
+    Time(timesheet::TimeCommand),
+
+    /// Keep a list of follow-ups to the analysis in TODO.md
+    #[clap(subcommand)]
+    Todo(todo::TodoCommand),
+
+    /// Record deadlines like data deletion and embargo end, and export them to a calendar
+    #[clap(subcommand)]
+    Dates(dates::DatesCommand),
+
+    /// List the codes of the warnings, which can be allowed with --allow or the config
+    Warnings,
+
+    /// Restore or delete what destructive commands moved to the project's trash
+    #[clap(subcommand)]
+    Trash(trash::TrashCommand),
+
+    /// Register figures in results/figures/, and include them in the paper
+    #[clap(subcommand)]
+    Figure(figure::FigureCommand),
+
+    /// Keep the paper in paper/ in sync with the results
+    #[clap(subcommand)]
+    Paper(paper::PaperCommand),
+
+    /// Manage the bibliography of the paper in paper/
+    #[clap(subcommand)]
+    Bib(bib::BibCommand),
+
+    /// Export the project's metadata in the formats of data repositories
+    #[clap(subcommand)]
+    Export(export::ExportCommand),
+
+    /// Publish the project in a data repository
+    #[clap(subcommand)]
+    Publish(publish::PublishCommand),
+
+    /// Write a reproducibility report of the current project, e.g. for supplementary materials
+    Report(report::ReportArgs),
+
+    /// Inspect the built-in templates, and copy them to customize them
+    #[clap(subcommand)]
+    Templates(templates::TemplatesCommand),
+
+    /// Read, change and check the configuration
+    #[clap(subcommand)]
+    Config(config::ConfigCommand),
+
+    /// Show local stats of the projects you have created, if enabled in the config
+    Stats,
+
+    /// Print a snippet for the shell's config showing the current project in the prompt
+    ShellHook(shell::HookArgs),
+
+    /// Print a shell function for the shell's config making `investigate new` cd into the project
+    ShellInit(shell::InitArgs),
+
+    /// Update investigate to the latest release on GitHub
+    SelfUpdate(self_update::SelfUpdateArgs),
+
+    /// Manage the README of the current project
+    #[clap(subcommand)]
+    Readme(readme::ReadmeCommand),
 }
 
 fn main() {
     let args = Options::parse();
-    let path = Path::new(&args.dirname);
-    let project_name = if let Some(name) = args.name {
-        name
-    } else {
-        args.dirname
-            .to_str()
-            .unwrap_or_else(|| {
-                eprint!(
-                    "Error: Project name {:?} is not a normal UTF-8 string",
-                    args.dirname
-                );
-                std::process::exit(1)
-            })
-            .to_owned()
-    };
-    if project_name.is_empty() {
-        eprint!("Error: Project name cannot be empty");
-        std::process::exit(1)
+    OFFLINE.store(args.offline, Ordering::Relaxed);
+    PLAIN.store(args.plain, Ordering::Relaxed);
+    IN_WSL.store(args.wsl, Ordering::Relaxed);
+    if args.plain {
+        // Commands run by investigate, like Conda, and investigate itself in `investigate all`,
+        // inherit the setting
+        std::env::set_var("NO_COLOR", "1");
+        std::env::set_var("INVESTIGATE_PLAIN", "true");
     }
-    let capitalized_project = capitalize(&project_name);
-    make_dirs(path);
-    Repository::init(&path).expect("Error when initializing git repo:");
-    let author_email = get_author_email();
-    if author_email.is_none() {
-        eprintln!(
-            "Warning: Could not extract author name and email from global git config.\n\
-            Set name and mail with:\n\
-            git config --global user.name \"FIRST_NAME LAST_NAME\"\n\
-            git config --global user.mail \"EXAMPLE@EMAIL.COM\"\n"
-        )
-    }
-    let author = author_email.as_ref().map(|x| x.0.clone());
-
-    // .gitignore
-    let python_gitignore = match args.language {
-        Some(Language::Python) => "__pycache__",
-        _ => "",
-    };
-    write(
-        &path.join(".gitignore"),
-        &format!(
-            include_str!("../templates/gitignore"),
-            python_gitignore = python_gitignore
-        ),
-    );
-
-    // Readme
-    make_readme(&path.join("README.md"), &capitalized_project, &author);
-
-    // Extra Python/Julia specifics
-    if let Some(language) = args.language {
-        match language {
-            Language::Julia => {
-                let module_name = convert_name_to_module(&project_name);
-                write(
-                    &path.join("src").join(module_name.clone() + ".jl"),
-                    include_str!("../templates/main"),
-                );
-                make_julia_project(&path.join("Project.toml"), &module_name, &author_email);
-            }
-            Language::Python => {
-                write(
-                    &path.join("src").join("main.py"),
-                    include_str!("../templates/main"),
-                );
-                conda_create(&project_name);
-                make_conda_yml(path, &project_name);
-            }
-        }
+    warning::allow(args.allow);
+    match args.command {
+        Commands::New(args) => new::run(args),
+        Commands::Bump(args) => bump::run(args),
+        Commands::Info(args) => info::run(args),
+        Commands::Rename(args) => rename::run(args),
+        Commands::CloneFrom(args) => clone::run(args),
+        Commands::Data(command) => data::run(command),
+        Commands::Env(command) => env::run(command),
+        Commands::Julia(command) => julia::run(command),
+        Commands::Cache(command) => cache::run(command),
+        Commands::Exec(args) => exec::run(args),
+        Commands::Run(args) => run::run(args),
+        Commands::Runs(command) => run::run_runs(command),
+        Commands::Graph(args) => graph::run(args),
+        Commands::Experiment(command) => experiment::run(command),
+        Commands::Finalize(args) => finalize::run(args),
+        Commands::Fsck(args) => fsck::run(args),
+        Commands::VerifyBuild => verify::run(),
+        Commands::Tree(args) => tree::run(args),
+        Commands::Status => status::run(),
+        Commands::Dashboard(args) => dashboard::run(args),
+        Commands::All(args) => all::run(args),
+        Commands::Paths(args) => paths::run(args),
+        Commands::Watch(args) => watch::run(args),
+        Commands::Software(command) => software::run(command),
+        Commands::Reopen(args) => reopen::run(args),
+        Commands::Contributor(command) => contributor::run(command),
+        Commands::Handover(args) => handover::run(args),
+        Commands::Dict(command) => dictionary::run(command),
+        Commands::Results(command) => results::run(command),
+        Commands::Tmp(command) => tmp::run(command),
+        Commands::Time(command) => timesheet::run(command),
+        Commands::Todo(command) => todo::run(command),
+        Commands::Dates(command) => dates::run(command),
+        Commands::Warnings => warning::run(),
+        Commands::Trash(command) => trash::run(command),
+        Commands::Figure(command) => figure::run(command),
+        Commands::Paper(command) => paper::run(command),
+        Commands::Bib(command) => bib::run(command),
+        Commands::Report(args) => report::run(args),
+        Commands::Export(command) => export::run(command),
+        Commands::Publish(command) => publish::run(command),
+        Commands::Readme(command) => readme::run(command),
+        Commands::Templates(command) => templates::run(command),
+        Commands::Config(command) => config::run(command),
+        Commands::Stats => stats::run(),
+        Commands::ShellHook(args) => shell::run_hook(args),
+        Commands::ShellInit(args) => shell::run_init(args),
+        Commands::SelfUpdate(args) => self_update::run(args),
     }
 }
@@ -0,0 +1,68 @@
+use serde::{Deserialize, Serialize};
+
+use std::fs::read_to_string;
+use std::path::Path;
+
+use crate::exit_with_error;
+use crate::lock::write_atomic;
+
+/// Path of the manifest of added data files, relative to the project root
+pub const MANIFEST_FILE: &str = "raw/MANIFEST.toml";
+
+/// A file or directory added to raw/ with `investigate data add`.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct DataEntry {
+    /// Path relative to raw/
+    pub path: String,
+    /// Absolute path of the original data
+    pub source: String,
+    /// How the data was added: "copy", "symlink", "hardlink" or "reflink",
+    /// or "in-place" if it was put in raw/ without investigate
+    pub method: String,
+    /// Size in bytes
+    pub size: u64,
+    /// Hex-encoded SHA-256 checksum. For directories, the checksum of the list of
+    /// checksums of its files, as printed by `sha256sum`
+    pub sha256: String,
+    /// Date in YYYY-MM-DD format
+    pub added: String,
+    /// Who added the data, as "NAME <EMAIL>"
+    pub author: String,
+    /// Path of the encrypted data relative to raw/, if encrypted with `investigate data encrypt`.
+    /// The checksum is always that of the unencrypted data.
+    pub encrypted: Option<String>,
+}
+
+/// The manifest of the data added to raw/.
+#[derive(Default, Serialize, Deserialize)]
+pub struct Manifest {
+    #[serde(default)]
+    pub data: Vec<DataEntry>,
+}
+
+impl Manifest {
+    /// Load the manifest of the project at `root`. A missing manifest counts as empty.
+    /// To update it, lock it with `lock::lock` first, so concurrent updates aren't lost.
+    pub fn load(root: &Path) -> Manifest {
+        let path = root.join(MANIFEST_FILE);
+        match read_to_string(&path) {
+            Err(_) => Manifest::default(),
+            Ok(content) => toml::from_str(&content)
+                .unwrap_or_else(|e| exit_with_error(&format!("Could not parse {:?}: {}", path, e))),
+        }
+    }
+
+    pub fn save(&self, root: &Path) {
+        let path = root.join(MANIFEST_FILE);
+        let content = toml::to_string_pretty(self).expect("Error when serializing manifest");
+        write_atomic(&path, &content)
+            .unwrap_or_else(|_| panic!("Error when writing file {:?}", path))
+    }
+
+    /// Add `entry`, replacing any existing entry with the same path.
+    pub fn insert(&mut self, entry: DataEntry) {
+        self.data.retain(|e| e.path != entry.path);
+        self.data.push(entry);
+        self.data.sort_by(|a, b| a.path.cmp(&b.path));
+    }
+}
@@ -0,0 +1,143 @@
+use serde::{Deserialize, Serialize};
+
+use std::collections::BTreeMap;
+use std::fs::read_to_string;
+use std::path::Path;
+
+use crate::directories::default_directory_names;
+use crate::new::Language;
+use crate::tools::ToolVersion;
+use crate::{exit_with_error, filesystem};
+
+/// Name of the metadata file in the root of every project
+pub const METADATA_FILE: &str = ".investigate.toml";
+
+/// A reopening of a finalized project, see `investigate reopen`.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Reopening {
+    /// Date in YYYY-MM-DD format
+    pub date: String,
+    /// Who reopened the project, as "NAME <EMAIL>"
+    pub author: String,
+    pub reason: String,
+}
+
+/// A person who works or worked on the project, see `investigate contributor`.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Contributor {
+    pub name: String,
+    pub email: Option<String>,
+    /// Role in the project, e.g. "lead", "analyst" or "supervisor"
+    pub role: String,
+    /// Date the contributor joined, in YYYY-MM-DD format
+    pub since: String,
+    /// Date the contributor left, in YYYY-MM-DD format, if they did
+    pub until: Option<String>,
+}
+
+/// A session of work on the project, see `investigate time`.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct WorkSession {
+    /// Local time in YYYY-MM-DDTHH:MM:SS format
+    pub start: String,
+    /// Local time the session stopped, or None if it's running
+    pub end: Option<String>,
+    /// Who worked, as "NAME <EMAIL>"
+    pub author: String,
+    pub note: Option<String>,
+}
+
+/// A key date of the project, like a deadline from the data management plan, see
+/// `investigate dates`.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct KeyDate {
+    /// Name of the date, e.g. "embargo_end"
+    pub name: String,
+    /// Date in YYYY-MM-DD format
+    pub date: String,
+    pub note: Option<String>,
+}
+
+/// The contents of the project's metadata file.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Metadata {
+    /// Project name, as given when created
+    pub name: String,
+    pub uuid: String,
+    /// Date of creation in YYYY-MM-DD format
+    pub created: String,
+    /// Version of investigate that created the project
+    pub investigate_version: String,
+    /// Version of the analysis itself, see `investigate bump`
+    pub version: String,
+    pub language: Option<Language>,
+    /// Authors as "NAME <EMAIL>"
+    #[serde(default)]
+    pub authors: Vec<String>,
+    /// Name of the Conda environment, if any
+    pub environment: Option<String>,
+    /// Directories created with the project
+    #[serde(default = "default_directory_names")]
+    pub directories: Vec<String>,
+    /// Remote location of the data directories, see `investigate data fetch`
+    pub data_remote: Option<String>,
+    /// Seed for the random number generators in the project's code
+    pub seed: Option<u32>,
+    /// Who data is encrypted for by `investigate data encrypt`:
+    /// age or SSH public keys, or GPG key IDs
+    #[serde(default)]
+    pub encryption_recipients: Vec<String>,
+    /// Short description of the project, used when depositing it, see `investigate export`
+    pub description: Option<String>,
+    /// SPDX identifier of the license, e.g. "CC-BY-4.0"
+    pub license: Option<String>,
+    #[serde(default)]
+    pub keywords: Vec<String>,
+    /// DOI of the published project, e.g. reserved by `investigate publish zenodo`
+    pub doi: Option<String>,
+    /// ID of the project's OSF project or component, see `investigate publish osf`
+    pub osf_node: Option<String>,
+    /// People who work or worked on the project. This and the following fields must be last,
+    /// since TOML tables come after all other values.
+    #[serde(default)]
+    pub contributors: Vec<Contributor>,
+    /// Each time the project was reopened after being finalized
+    #[serde(default)]
+    pub reopened: Vec<Reopening>,
+    /// Work sessions, for reporting the time spent on the project
+    #[serde(default)]
+    pub sessions: Vec<WorkSession>,
+    /// Deadlines and other key dates, by date
+    #[serde(default)]
+    pub dates: Vec<KeyDate>,
+    /// Versions of the external tools installed when the project was created
+    #[serde(default)]
+    pub tools: BTreeMap<String, ToolVersion>,
+}
+
+impl Metadata {
+    pub fn load(root: &Path) -> Metadata {
+        let path = root.join(METADATA_FILE);
+        let content = read_to_string(&path)
+            .unwrap_or_else(|_| exit_with_error(&format!("Could not read {:?}", path)));
+        toml::from_str(&content)
+            .unwrap_or_else(|e| exit_with_error(&format!("Could not parse {:?}: {}", path, e)))
+    }
+
+    /// Load the metadata of the project at `root`, if it is one. Unlike `load`, this doesn't
+    /// exit on errors, since registered projects may have been moved or deleted.
+    pub fn try_load(root: &Path) -> Option<Metadata> {
+        toml::from_str(&read_to_string(root.join(METADATA_FILE)).ok()?).ok()
+    }
+
+    pub fn to_toml(&self) -> String {
+        toml::to_string_pretty(self).expect("Error when serializing metadata")
+    }
+
+    pub fn save(&self, root: &Path) {
+        let path = root.join(METADATA_FILE);
+        filesystem::current()
+            .write(&path, self.to_toml().as_bytes())
+            .unwrap_or_else(|_| panic!("Error when writing file {:?}", path))
+    }
+}
@@ -0,0 +1,1855 @@
+use chrono::Local;
+use clap::{ArgEnum, Args};
+use git2::Repository;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::rc::Rc;
+use std::thread;
+use std::time::Instant;
+
+use crate::author::{get_author_email, warn_unknown_author, PLACEHOLDER_EMAIL, PLACEHOLDER_NAME};
+use crate::clone::name_from_path;
+use crate::config::{Config, Profile};
+use crate::contributor::write_authors;
+use crate::data::{shell_join, sync_command, DATA_DIRECTORIES};
+use crate::directories::{default_directories, Directory};
+use crate::env::offline_env;
+use crate::filesystem::Memory;
+use crate::git::{commit_index, commit_paths, containing_repository, relative_to_workdir};
+use crate::metadata::{Contributor, Metadata, METADATA_FILE};
+use crate::paths::{constant_name, directory_constants, make_paths};
+use crate::permissions::Permissions;
+use crate::progress::Steps;
+use crate::ssh::{self, Ssh, Target};
+use crate::storage::check_space;
+use crate::templates::Templates;
+use crate::trash::Trashing;
+use crate::warning::{warn, Code};
+use crate::wsl;
+use crate::{
+    capitalize, exit_with_error, filesystem, is_offline, readme, registry, require_network, shell,
+    stats, tools,
+};
+
+/// Directories whose content is not tracked by git
+pub const IGNORED_DIRECTORIES: [&str; 4] = ["results", "tmp", "cache", "raw"];
+
+/// Data dictionary of the long table of benchmark results, results/metrics.tsv
+const BENCHMARK_DICTIONARY: &str = "metrics.dictionary.tsv";
+
+/// Extensions of binary files, such as figures and documents
+const BINARY_EXTENSIONS: [&str; 8] = ["png", "jpg", "jpeg", "gif", "pdf", "svgz", "docx", "xlsx"];
+
+/// Extensions of binary data files, which are stored in Git LFS with --lfs
+const DATA_EXTENSIONS: [&str; 13] = [
+    "h5", "hdf5", "parquet", "feather", "npy", "npz", "rds", "RData", "pkl", "jld2", "bam", "gz",
+    "zip",
+];
+
+/// What to do when the project directory already exists.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum Existing {
+    /// Refuse to touch an existing directory
+    Error,
+    /// Overwrite all generated files
+    Force,
+    /// Only add files and directories that are missing
+    Merge,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ArgEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Language {
+    Python,
+    Julia,
+}
+
+/// Where to run continuous integration of the project.
+#[derive(Copy, Clone, PartialEq, Eq, ArgEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Ci {
+    Github,
+    Gitlab,
+}
+
+/// How to create a project inside an existing git repository.
+#[derive(Copy, Clone, PartialEq, Eq, ArgEnum)]
+pub enum Subproject {
+    /// Add the project to the existing repository in a dedicated commit
+    Commit,
+    /// Give the project its own repository, and add it as a git submodule
+    Submodule,
+    /// Give the project its own, independent repository
+    Nested,
+}
+
+#[derive(Args)]
+pub struct NewArgs {
+    /// Path to project dir to create, or [user@]host:path to create it on another machine over
+    /// SSH. Start a local path with a colon in it with ./
+    #[clap(required_unless_present = "from-spec")]
+    dirname: Option<OsString>,
+
+    /// Create every project described in this TOML file, instead of a single project
+    #[clap(long, value_name = "FILE", conflicts_with_all = &["dirname", "name"])]
+    from_spec: Option<PathBuf>,
+
+    /// Main programming language
+    #[clap(arg_enum, value_parser, short, long, env = "INVESTIGATE_LANGUAGE")]
+    pub language: Option<Language>,
+
+    /// Use settings from this profile in the config file
+    #[clap(short, long, env = "INVESTIGATE_PROFILE")]
+    profile: Option<String>,
+
+    /// Split code into library code in src/ and numbered analysis scripts in scripts/
+    #[clap(long, env = "INVESTIGATE_SCRIPTS")]
+    pub scripts: bool,
+
+    /// Add a documentation scaffold in docs/ (mkdocs for Python, Documenter.jl for Julia)
+    #[clap(long, env = "INVESTIGATE_DOCS")]
+    docs: bool,
+
+    /// Add analysis parameters in config/, with code that loads and validates them
+    #[clap(long, env = "INVESTIGATE_PARAMS")]
+    params: bool,
+
+    /// Add license, language and DOI badges to the README
+    #[clap(long, env = "INVESTIGATE_BADGES")]
+    pub badges: bool,
+
+    /// Add a table of scripts and notebooks to the README, see `investigate readme refresh`
+    #[clap(long, env = "INVESTIGATE_INDEX")]
+    pub index: bool,
+
+    /// Add a README.md to each directory explaining what belongs there
+    #[clap(long, env = "INVESTIGATE_DIR_READMES")]
+    dir_readmes: bool,
+
+    /// Add an ASSIGNMENT.md for students, and an answers/ directory for the reference solution,
+    /// which is not tracked by git
+    #[clap(long, env = "INVESTIGATE_ASSIGNMENT")]
+    assignment: bool,
+
+    /// Add a benchmarks/ directory with a matrix of the tools and versions compared, and a
+    /// data dictionary of the tool×dataset×metric table in results/
+    #[clap(long, env = "INVESTIGATE_BENCHMARK")]
+    benchmark: bool,
+
+    /// Add a CI workflow which creates the environment, then lints and tests the code
+    #[clap(arg_enum, value_parser, long, env = "INVESTIGATE_CI")]
+    ci: Option<Ci>,
+
+    /// Add a module file for Lmod and Environment Modules, which sets the paths of the project
+    /// and activates its environment with `module load`
+    #[clap(long, env = "INVESTIGATE_MODULEFILE")]
+    modulefile: bool,
+
+    /// Store binary data files in Git LFS
+    #[clap(long, env = "INVESTIGATE_LFS")]
+    lfs: bool,
+
+    /// Store raw/ and large files in results/ in git-annex, with the special remote from config
+    #[clap(long, conflicts_with = "lfs", env = "INVESTIGATE_ANNEX")]
+    annex: bool,
+
+    /// Install a git pre-commit hook which keeps raw/ append-only: it rejects commits changing
+    /// or deleting data in raw/, or adding data not recorded in raw/MANIFEST.toml
+    #[clap(long, env = "INVESTIGATE_PROTECT_RAW")]
+    protect_raw: bool,
+
+    /// Don't create the Conda environment, e.g. in CI. It can be created later from
+    /// environment.yml
+    #[clap(long, env = "INVESTIGATE_NO_ENV")]
+    no_env: bool,
+
+    /// Don't initialize a git repository, e.g. where git is unavailable. The project can be put
+    /// under version control later with `git init`
+    #[clap(long, conflicts_with = "subproject", env = "INVESTIGATE_NO_GIT")]
+    no_git: bool,
+
+    /// If <DIRNAME> is inside a git repository: How to add the project to it
+    #[clap(
+        long,
+        arg_enum,
+        value_parser,
+        value_name = "MODE",
+        min_values = 0,
+        require_equals = true,
+        default_missing_value = "commit",
+        env = "INVESTIGATE_SUBPROJECT"
+    )]
+    subproject: Option<Subproject>,
+
+    /// Sign the initial commit and those made by --subproject, even if commit.gpgsign is not
+    /// set in git config
+    #[clap(long, env = "INVESTIGATE_SIGN")]
+    sign: bool,
+
+    /// Conda channel to install packages from, in order of priority. May be given multiple times
+    #[clap(
+        short,
+        long,
+        value_name = "CHANNEL",
+        env = "INVESTIGATE_CHANNELS",
+        value_delimiter = ','
+    )]
+    channel: Vec<String>,
+
+    /// Package to install in the project's environment, e.g. "numpy>=1.20". May be given multiple times
+    #[clap(
+        long = "package",
+        value_name = "PACKAGE",
+        env = "INVESTIGATE_PACKAGES",
+        value_delimiter = ';'
+    )]
+    packages: Vec<String>,
+
+    /// Remote location of the data in raw/ and results/, e.g. s3://bucket/prefix
+    #[clap(long, value_name = "URI", env = "INVESTIGATE_DATA_REMOTE")]
+    data_remote: Option<String>,
+
+    /// Seed for random number generators in the generated code (default: random)
+    #[clap(long, env = "INVESTIGATE_SEED")]
+    seed: Option<u32>,
+
+    /// Project name (default: same as <DIRNAME>)
+    #[clap(short, long)]
+    name: Option<String>,
+
+    /// If <DIRNAME> exists, overwrite generated files
+    #[clap(long, conflicts_with = "merge")]
+    force: bool,
+
+    /// If <DIRNAME> exists, only add missing files and directories
+    #[clap(long)]
+    merge: bool,
+
+    /// Only print the files and directories which would be created, without creating anything,
+    /// creating environments or running git
+    #[clap(long)]
+    dry_run: bool,
+
+    /// Settings of a project in a --from-spec file, which take precedence over the profile
+    #[clap(skip)]
+    overrides: Box<Profile>,
+
+    /// Host the project is created on over SSH, if <DIRNAME> is remote
+    #[clap(skip)]
+    remote: Option<String>,
+}
+
+/// A project in a file given to --from-spec.
+#[derive(Deserialize)]
+struct ProjectSpec {
+    /// Path to the project, relative to the spec file
+    path: PathBuf,
+    /// Project name (default: the last component of the path)
+    name: Option<String>,
+    profile: Option<String>,
+    seed: Option<u32>,
+    data_remote: Option<String>,
+    #[serde(flatten)]
+    settings: Profile,
+}
+
+/// A file given to --from-spec.
+#[derive(Deserialize)]
+struct Spec {
+    /// Settings shared by all projects, which take precedence over the profile
+    #[serde(default)]
+    defaults: Profile,
+    #[serde(default)]
+    project: Vec<ProjectSpec>,
+}
+
+pub fn write(path: &Path, string: &str, existing: Existing) {
+    let fs = filesystem::current();
+    if existing == Existing::Merge && fs.exists(path) {
+        return;
+    }
+    fs.write(path, string.as_bytes())
+        .unwrap_or_else(|_| panic!("Error when creating file {:?}", path))
+}
+
+fn make_dir(path: &Path) {
+    let fs = filesystem::current();
+    if !fs.is_dir(path) {
+        fs.create_dir(path)
+            .unwrap_or_else(|_| panic!("Error when creating sub-directory: {:?}", path));
+    }
+}
+
+pub fn convert_name_to_module(project_name: &str) -> String {
+    // Splits by dash or underscore, then capitalize each chunk before joining.
+    project_name.split(['_', '-']).map(capitalize).collect()
+}
+
+pub fn convert_name_to_package(project_name: &str) -> String {
+    // Python packages are lowercase and cannot contain dashes
+    project_name.to_lowercase().replace('-', "_")
+}
+
+/// Get the name of the console command of a Python project, e.g. "rna-expression".
+pub fn convert_name_to_command(project_name: &str) -> String {
+    project_name.to_lowercase().replace('_', "-")
+}
+
+fn make_pyproject(
+    path: &Path,
+    templates: &Templates,
+    package_name: &str,
+    command_name: &str,
+    (name, mail): (&str, &str),
+    existing: Existing,
+) {
+    let authors = format!("{{ name = \"{}\", email = \"{}\" }}", name, mail);
+    let content = templates.render(
+        "pyproject",
+        &[
+            ("package_name", package_name),
+            ("command_name", command_name),
+            ("authors", &authors),
+        ],
+    );
+    write(path, &content, existing)
+}
+
+fn make_scripts(
+    path: &Path,
+    templates: &Templates,
+    language: Language,
+    module_name: &str,
+    existing: Existing,
+) {
+    let scripts_dir = path.join("scripts");
+    make_dir(&scripts_dir);
+    let (extension, script) = match language {
+        Language::Julia => (
+            "jl",
+            templates.render("script_julia", &[("module_name", module_name)]),
+        ),
+        Language::Python => (
+            "py",
+            templates.render("script_python", &[("package_name", module_name)]),
+        ),
+    };
+    write(
+        &scripts_dir.join(format!("01_example.{}", extension)),
+        &script,
+        existing,
+    );
+}
+
+pub fn make_docs(
+    path: &Path,
+    templates: &Templates,
+    language: Language,
+    project_name: &str,
+    module_name: &str,
+    author: &str,
+    existing: Existing,
+) {
+    let docs_dir = path.join("docs");
+    let index = templates.render("docs_index", &[("project_name", project_name)]);
+    make_dir(&docs_dir);
+    match language {
+        Language::Julia => {
+            let src_dir = docs_dir.join("src");
+            make_dir(&src_dir);
+            write(&src_dir.join("index.md"), &index, existing);
+            write(
+                &docs_dir.join("make.jl"),
+                &templates.render(
+                    "docs_make_julia",
+                    &[
+                        ("module_name", module_name),
+                        ("project_name", project_name),
+                        ("author", author),
+                    ],
+                ),
+                existing,
+            );
+            write(
+                &docs_dir.join("Project.toml"),
+                &templates.get("docs_project_julia"),
+                existing,
+            );
+        }
+        Language::Python => {
+            write(&docs_dir.join("index.md"), &index, existing);
+            write(
+                &path.join("mkdocs.yml"),
+                &templates.render(
+                    "mkdocs",
+                    &[("project_name", project_name), ("author", author)],
+                ),
+                existing,
+            );
+        }
+    }
+}
+
+fn make_makefile(
+    path: &Path,
+    templates: &Templates,
+    language: Language,
+    scripts: bool,
+    docs: bool,
+    existing: Existing,
+) {
+    let mut phony = Vec::new();
+    let mut targets = Vec::new();
+    if scripts {
+        let (extension, interpreter) = match language {
+            Language::Julia => ("jl", "julia --project=."),
+            Language::Python => ("py", "python"),
+        };
+        phony.push("all");
+        targets.push(templates.render(
+            "makefile_scripts",
+            &[("extension", extension), ("interpreter", interpreter)],
+        ));
+    }
+    if docs {
+        phony.push("docs");
+        targets.push(templates.get(match language {
+            Language::Julia => "makefile_docs_julia",
+            Language::Python => "makefile_docs_python",
+        }));
+    }
+    let content = format!(".PHONY: {}\n\n{}", phony.join(" "), targets.join("\n"));
+    write(&path.join("Makefile"), &content, existing)
+}
+
+pub fn make_ci(
+    path: &Path,
+    templates: &Templates,
+    ci: Ci,
+    language: Language,
+    project_name: &str,
+    existing: Existing,
+) {
+    let language_name = match language {
+        Language::Julia => "julia",
+        Language::Python => "python",
+    };
+    let (ci_name, ci_path) = match ci {
+        Ci::Github => {
+            let workflows = path.join(".github").join("workflows");
+            filesystem::current()
+                .create_dir_all(&workflows)
+                .unwrap_or_else(|_| panic!("Error when creating sub-directory: {:?}", workflows));
+            ("github", workflows.join("ci.yml"))
+        }
+        Ci::Gitlab => ("gitlab", path.join(".gitlab-ci.yml")),
+    };
+    write(
+        &ci_path,
+        &templates.render(
+            &format!("ci_{}_{}", ci_name, language_name),
+            &[("name", project_name)],
+        ),
+        existing,
+    );
+}
+
+/// UUID of the TOML standard library, which loads the parameters of Julia projects.
+const TOML_UUID: &str = "fa267f1f-6049-4f14-aa54-33bafae1ed76";
+
+/// Add config/params.yaml, and the module `module` in `code_dir` which loads it.
+fn make_python_params(
+    path: &Path,
+    templates: &Templates,
+    code_dir: &Path,
+    module: &str,
+    existing: Existing,
+) {
+    let config_dir = path.join("config");
+    make_dir(&config_dir);
+    let code_path = code_dir.join("params.py");
+    let code_file = code_path.strip_prefix(path).unwrap_or(&code_path);
+    write(
+        &config_dir.join("params.yaml"),
+        &templates.render(
+            "params_yaml",
+            &[("code_file", &code_file.to_string_lossy())],
+        ),
+        existing,
+    );
+    // The number of directories between params.py and the project root
+    let parents = (code_file.components().count() - 1).to_string();
+    write(
+        &code_path,
+        &templates.render(
+            "params_python",
+            &[("module", module), ("parents", &parents)],
+        ),
+        existing,
+    );
+}
+
+/// Add config/params.toml, and src/params.jl which loads it, to the Julia project at `path`.
+fn make_julia_params(path: &Path, templates: &Templates, module_name: &str, existing: Existing) {
+    let config_dir = path.join("config");
+    make_dir(&config_dir);
+    write(
+        &config_dir.join("params.toml"),
+        &templates.get("params_toml"),
+        existing,
+    );
+    write(
+        &path.join("src").join("params.jl"),
+        &templates.render("params_julia", &[("module_name", module_name)]),
+        existing,
+    );
+    let project_path = path.join("Project.toml");
+    let mut project: toml_edit::DocumentMut = filesystem::current()
+        .read_to_string(&project_path)
+        .unwrap_or_else(|_| panic!("Error when reading file {:?}", project_path))
+        .parse()
+        .unwrap_or_else(|_| panic!("Error when parsing file {:?}", project_path));
+    let deps = project
+        .entry("deps")
+        .or_insert_with(toml_edit::table)
+        .as_table_mut()
+        .expect("Error when reading [deps] of Project.toml");
+    if !deps.contains_key("TOML") {
+        deps["TOML"] = toml_edit::value(TOML_UUID);
+        deps.sort_values();
+        write(&project_path, &project.to_string(), Existing::Force);
+    }
+}
+
+fn make_julia_project(
+    path: &Path,
+    templates: &Templates,
+    module_name: &str,
+    uuid: &str,
+    (name, mail): (&str, &str),
+    existing: Existing,
+) {
+    // Keep the UUID of an existing project
+    if existing == Existing::Merge && filesystem::current().exists(path) {
+        return;
+    }
+    let author_string = format!("{} <{}>", name, mail);
+    let content = templates.render(
+        "project",
+        &[
+            ("module_name", module_name),
+            ("uuid_str", uuid),
+            ("author", &author_string),
+        ],
+    );
+    write(path, &content, existing)
+}
+
+/// Get a command running `program` with `args` in the project at `path`, on `remote` over SSH if
+/// given.
+fn command(remote: Option<&str>, program: &str, args: &[String], path: &Path) -> Command {
+    match remote {
+        None => {
+            let mut command = wsl::command(program, path);
+            command.args(args);
+            command
+        }
+        Some(host) => ssh::command(host, program, args, path),
+    }
+}
+
+/// Get the command creating the Conda environment `project_name` of the project at `path`, on
+/// `remote` if given.
+fn conda_command(
+    remote: Option<&str>,
+    path: &Path,
+    project_name: &str,
+    channels: &[String],
+    packages: &[String],
+) -> Command {
+    let mut args: Vec<String> = vec![
+        "create".into(),
+        "-n".into(),
+        project_name.into(),
+        "-y".into(),
+    ];
+    for channel in channels {
+        args.extend(["-c".to_owned(), channel.clone()]);
+    }
+    args.extend_from_slice(packages);
+    command(remote, "conda", &args, path)
+}
+
+/// Create the Conda environment with `command`, returning what to report when done. It runs in
+/// the background, so it reports nothing itself, which would garble the progress bar.
+fn conda_create(mut command: Command, project_name: &str) -> Result<String, String> {
+    let start = Instant::now();
+    match command.envs(offline_env()).output() {
+        Ok(output) if output.status.success() => Ok(format!(
+            "Created Conda environment \"{}\" in {:.1}s",
+            &project_name,
+            start.elapsed().as_secs_f64()
+        )),
+        _ if is_offline() => Err(format!(
+            "Could not create Conda environment \"{}\" from the local package cache. \
+            Create it later with `investigate env recreate`",
+            &project_name
+        )),
+        _ => Err(format!(
+            "Could not create Conda environment \"{}\"",
+            &project_name
+        )),
+    }
+}
+
+/// Add `packages` to the Julia project at `path`, on `remote` if given.
+fn julia_add_packages(path: &Path, packages: &[String], remote: Option<&str>) {
+    let start = Instant::now();
+    let names: Vec<String> = packages.iter().map(|p| format!("{:?}", p)).collect();
+    let args = [
+        "--project=.".to_owned(),
+        "-e".to_owned(),
+        format!("using Pkg; Pkg.add([{}])", names.join(", ")),
+    ];
+    match command(remote, "julia", &args, path)
+        .envs(offline_env())
+        .output()
+    {
+        Ok(output) if output.status.success() => println!(
+            "Added Julia packages in {:.1}s",
+            start.elapsed().as_secs_f64()
+        ),
+        _ => warn(
+            Code::EnvironmentFailed,
+            &format!("Could not add Julia packages {}", packages.join(", ")),
+        ),
+    }
+}
+
+/// Initialize a git repository at `path`, on `remote` if given.
+fn git_init(path: &Path, remote: Option<&str>) -> Result<(), String> {
+    let Some(host) = remote else {
+        return Repository::init(path)
+            .map(|_| ())
+            .map_err(|e| e.message().to_owned());
+    };
+    match ssh::command(host, "git", &["init".to_owned()], path).output() {
+        Ok(output) if output.status.success() => Ok(()),
+        Ok(output) => Err(String::from_utf8_lossy(&output.stderr).trim().to_owned()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// Get the directory Conda is installed in, which has the environments in envs/, on `remote`
+/// if given.
+fn conda_prefix(path: &Path, remote: Option<&str>) -> Option<String> {
+    match remote {
+        None => std::env::var("CONDA_PREFIX").ok(),
+        // $CONDA_PREFIX is only set in interactive shells, so ask conda where it's installed
+        Some(host) => ssh::command(host, "conda", &["info".into(), "--base".into()], path)
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_owned()),
+    }
+}
+
+fn make_conda_yml(
+    path: &Path,
+    templates: &Templates,
+    project_name: &str,
+    (channels, packages): (&[String], &[String]),
+    remote: Option<&str>,
+    existing: Existing,
+) {
+    let Some(prefix) = conda_prefix(path, remote) else {
+        let message = match remote {
+            None => "Could not get env variable $CONDA_PREFIX.".to_owned(),
+            Some(host) => format!("Could not find the Conda installation on {}.", host),
+        };
+        warn(
+            Code::EnvironmentFailed,
+            &format!("{} Not writing \"environment.yml\" file.", message),
+        );
+        return;
+    };
+    let prefix_path = Path::new(&prefix).join("envs").join(project_name);
+    let prefix_path = prefix_path.to_str().unwrap_or_else(|| {
+        exit_with_error(&format!(
+            "Conda environment path {:?} is not a normal UTF-8 string",
+            prefix_path
+        ))
+    });
+    let channel_list = if channels.is_empty() {
+        "  - defaults".to_owned()
+    } else {
+        channels
+            .iter()
+            .map(|channel| format!("  - {}", channel))
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+    let dependencies = if packages.is_empty() {
+        " []".to_owned()
+    } else {
+        packages
+            .iter()
+            .map(|package| format!("\n  - {}", package))
+            .collect()
+    };
+    write(
+        &path.join("environment.yml"),
+        &templates.render(
+            "environment",
+            &[
+                ("name", project_name),
+                ("channels", &channel_list),
+                ("prefix_path", prefix_path),
+                ("dependencies", &dependencies),
+            ],
+        ),
+        existing,
+    );
+}
+
+fn make_dirs(path: &Path, directories: &[Directory], existing: Existing) {
+    let fs = filesystem::current();
+    if fs.exists(path) {
+        if existing == Existing::Error {
+            exit_with_error(&format!(
+                "Path {:?} already exists.\n\
+                Use --force to overwrite generated files, or --merge to only add missing files.",
+                path
+            ))
+        }
+        if !fs.is_dir(path) {
+            exit_with_error(&format!("Path {:?} exists and is not a directory", path))
+        }
+    } else {
+        fs.create_dir(path)
+            .unwrap_or_else(|_| panic!("Error when creating main project directory: {:?}", path));
+    }
+    for subdir in directories {
+        let subpath = path.join(&subdir.name);
+        fs.create_dir_all(&subpath)
+            .unwrap_or_else(|_| panic!("Error when creating sub-directory: {:?}", subpath));
+    }
+}
+
+/// Write a module file to modulefiles/, setting the paths of the project and activating its
+/// environment, for HPC sites where `module load` is how environments are activated.
+fn make_modulefile(
+    path: &Path,
+    templates: &Templates,
+    project_name: &str,
+    args: &NewArgs,
+    directories: &[Directory],
+    existing: Existing,
+) {
+    let directory_variables: Vec<String> = directories
+        .iter()
+        .map(|directory| {
+            format!(
+                "setenv {} $root/{}",
+                constant_name(directory),
+                directory.name
+            )
+        })
+        .collect();
+    let environment = match args.language {
+        None => String::new(),
+        Some(Language::Julia) => {
+            "\n\n# The Julia environment of the project\nsetenv JULIA_PROJECT $root".to_owned()
+        }
+        Some(Language::Python) => {
+            let environment = conda_prefix(path, args.remote.as_deref())
+                .map(|prefix| Path::new(&prefix).join("envs").join(project_name))
+                .map_or_else(
+                    || format!("<Conda installation>/envs/{}", project_name),
+                    |prefix| prefix.display().to_string(),
+                );
+            format!(
+                "\n\n# The Conda environment of the project, made from environment.yml\n\
+                set environment {{{}}}\n\
+                setenv CONDA_PREFIX $environment\n\
+                setenv CONDA_DEFAULT_ENV {}\n\
+                prepend-path PATH $environment/bin",
+                environment, project_name
+            )
+        }
+    };
+    make_dir(&path.join("modulefiles"));
+    write(
+        &path.join("modulefiles").join(project_name),
+        &templates.render(
+            "modulefile",
+            &[
+                ("name", project_name),
+                ("directories", &directory_variables.join("\n")),
+                ("seed", &args.seed.unwrap_or_default().to_string()),
+                ("environment", &environment),
+            ],
+        ),
+        existing,
+    );
+}
+
+fn make_gitattributes(
+    path: &Path,
+    templates: &Templates,
+    lfs: bool,
+    annex: bool,
+    existing: Existing,
+) {
+    let data_attributes = if lfs {
+        "filter=lfs diff=lfs merge=lfs -text"
+    } else {
+        "binary"
+    };
+    let binary: Vec<String> = BINARY_EXTENSIONS
+        .iter()
+        .map(|ext| format!("*.{} binary", ext))
+        .chain(
+            DATA_EXTENSIONS
+                .iter()
+                .map(|ext| format!("*.{} {}", ext, data_attributes)),
+        )
+        .collect();
+    let annex = if annex {
+        "\n\n# Store raw data and results larger than 1 MB in git-annex, and everything else in git\n\
+        * annex.largefiles=nothing\n\
+        raw/** annex.largefiles=anything\n\
+        results/** annex.largefiles=(largerthan=1mb)"
+    } else {
+        ""
+    };
+    write(
+        &path.join(".gitattributes"),
+        &templates.render(
+            "gitattributes",
+            &[("binary", &binary.join("\n")), ("annex", annex)],
+        ),
+        existing,
+    );
+}
+
+/// Initialize git-annex in the repository at `path`, and add the special remote `remote`,
+/// given as "NAME type=TYPE [KEY=VALUE ...]".
+fn annex_init(path: &Path, remote: Option<&str>) {
+    let run = |args: &[&str]| {
+        Command::new("git")
+            .arg("annex")
+            .args(args)
+            .current_dir(path)
+            .output()
+            .is_ok_and(|output| output.status.success())
+    };
+    if !run(&["init"]) {
+        warn(
+            Code::AnnexFailed,
+            "Could not initialize git-annex. Is git-annex installed?",
+        );
+        return;
+    }
+    println!("Initialized git-annex");
+    if let Some(remote) = remote {
+        let args: Vec<&str> = ["initremote"]
+            .into_iter()
+            .chain(remote.split_whitespace())
+            .collect();
+        if run(&args) {
+            println!("Added git-annex special remote {}", remote);
+        } else {
+            warn(
+                Code::AnnexFailed,
+                &format!("Could not add git-annex special remote \"{}\"", remote),
+            );
+        }
+    }
+}
+
+/// Install the pre-commit hook which keeps raw/ append-only in the repository at `path`.
+fn install_raw_hook(path: &Path, templates: &Templates) {
+    let Ok(repo) = Repository::open(path) else {
+        // The hook checks paths relative to the repository, so it only works in its root
+        warn(
+            Code::HookNotInstalled,
+            "Not installing the raw/ hook, as the project has no repository of its own",
+        );
+        return;
+    };
+    let hook = repo.path().join("hooks").join("pre-commit");
+    let content = templates.render("pre_commit_raw", &[]);
+    if filesystem::current()
+        .read_to_string(&hook)
+        .is_ok_and(|existing| existing != content)
+    {
+        warn(
+            Code::HookNotInstalled,
+            &format!("Not installing the raw/ hook, as {:?} already exists", hook),
+        );
+        return;
+    }
+    write(&hook, &content, Existing::Force);
+    filesystem::current()
+        .set_executable(&hook)
+        .unwrap_or_else(|_| panic!("Error when making {:?} executable", hook));
+    println!("Installed pre-commit hook keeping raw/ append-only");
+}
+
+/// Write a shell script to fetch and push the data, for those without investigate installed.
+fn make_sync_script(path: &Path, templates: &Templates, remote: &str, existing: Existing) {
+    let commands = |push: bool| -> String {
+        DATA_DIRECTORIES
+            .iter()
+            .map(|dir| match sync_command(remote, dir, push) {
+                Ok(command) => format!("        {}", shell_join(&command)),
+                Err(e) => format!("        echo \"{}\" >&2\n        exit 1", e),
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+    let script_path = path.join("sync_data.sh");
+    write(
+        &script_path,
+        &templates.render(
+            "sync_data",
+            &[("fetch", &commands(false)), ("push", &commands(true))],
+        ),
+        existing,
+    );
+    filesystem::current()
+        .set_executable(&script_path)
+        .unwrap_or_else(|_| panic!("Error when setting permissions of {:?}", script_path));
+}
+
+/// Write a README.md into each directory, from the template "dir_readme_<NAME>" if it exists,
+/// else from the generic "dir_readme" template.
+fn make_dir_readmes(
+    path: &Path,
+    templates: &Templates,
+    directories: &[Directory],
+    existing: Existing,
+) {
+    for directory in directories {
+        let specific = format!("dir_readme_{}", directory.name.replace('/', "_"));
+        let name = if templates.has(&specific) {
+            &specific
+        } else {
+            "dir_readme"
+        };
+        let description = directory.description.clone().unwrap_or_default();
+        let content = templates.render(
+            name,
+            &[("name", &directory.name), ("description", &description)],
+        );
+        write(
+            &path.join(&directory.name).join("README.md"),
+            &content,
+            existing,
+        );
+    }
+}
+
+fn make_metadata(
+    path: &Path,
+    project_name: &str,
+    uuid: &str,
+    args: &NewArgs,
+    author_email: &Option<(String, String)>,
+    directories: &[Directory],
+    existing: Existing,
+) {
+    // Keep the UUID and version of an existing project
+    if existing == Existing::Merge && filesystem::current().exists(&path.join(METADATA_FILE)) {
+        return;
+    }
+    let metadata = Metadata {
+        name: project_name.to_owned(),
+        uuid: uuid.to_owned(),
+        created: Local::today().format("%Y-%m-%d").to_string(),
+        investigate_version: env!("CARGO_PKG_VERSION").to_owned(),
+        version: "0.1.0".to_owned(),
+        language: args.language,
+        authors: author_email
+            .iter()
+            .map(|(name, mail)| format!("{} <{}>", name, mail))
+            .collect(),
+        environment: (args.language == Some(Language::Python)).then(|| project_name.to_owned()),
+        directories: directories.iter().map(|d| d.name.clone()).collect(),
+        data_remote: args.data_remote.clone(),
+        seed: args.seed,
+        encryption_recipients: Vec::new(),
+        description: None,
+        license: None,
+        keywords: Vec::new(),
+        doi: None,
+        osf_node: None,
+        contributors: author_email
+            .iter()
+            .map(|(name, mail)| Contributor {
+                name: name.clone(),
+                email: Some(mail.clone()),
+                role: "lead".to_owned(),
+                since: Local::today().format("%Y-%m-%d").to_string(),
+                until: None,
+            })
+            .collect(),
+        reopened: Vec::new(),
+        sessions: Vec::new(),
+        dates: Vec::new(),
+        // The project is used with the tools of the machine it's on. Getting their versions
+        // runs them, which a dry run mustn't
+        tools: match &args.remote {
+            _ if args.dry_run => Default::default(),
+            Some(host) => tools::versions_on(host),
+            None => tools::versions(),
+        },
+    };
+    metadata.save(path);
+    write_authors(path, &metadata, &[], existing);
+}
+
+/// Commit the files of the new project at `path` to its new repository.
+fn initial_commit(path: &Path, project_name: &str, sign: bool) {
+    let repo = Repository::open(path).expect("Error when opening git repo");
+    match commit_paths(
+        &repo,
+        &["."],
+        &format!("Create project {}", project_name),
+        sign,
+    ) {
+        Ok(_) => println!("Committed project files"),
+        Err(e) => warn(
+            Code::GitFailed,
+            &format!("Could not make the initial commit: {}", e.message()),
+        ),
+    }
+}
+
+/// Commit the new project at `path` to the repository `repo` containing it.
+fn add_to_parent_repo(
+    repo: &Repository,
+    path: &Path,
+    project_name: &str,
+    subproject: Subproject,
+    sign: bool,
+) {
+    let relative =
+        relative_to_workdir(repo, path).expect("Error when finding project path in git repository");
+    let relative = relative.to_string_lossy();
+    let result = match subproject {
+        Subproject::Nested => return,
+        Subproject::Commit => commit_paths(
+            repo,
+            &[relative.as_ref()],
+            &format!("Add project {}", project_name),
+            sign,
+        ),
+        Subproject::Submodule => {
+            // A submodule must point to a commit in the project's own repository
+            let project_repo = Repository::open(path).expect("Error when opening git repo");
+            if let Err(e) = commit_paths(
+                &project_repo,
+                &["."],
+                &format!("Create project {}", project_name),
+                sign,
+            ) {
+                warn(
+                    Code::GitFailed,
+                    &format!(
+                        "Could not commit project, so it was not added as a submodule: {}",
+                        e.message()
+                    ),
+                );
+                return;
+            }
+            let workdir = repo
+                .workdir()
+                .expect("Error when getting git working directory");
+            match Command::new("git")
+                .current_dir(workdir)
+                .args(["submodule", "add", &format!("./{}", relative), &relative])
+                .output()
+            {
+                Ok(output) if output.status.success() => commit_index(
+                    repo,
+                    &format!("Add project {} as submodule", project_name),
+                    sign,
+                ),
+                _ => {
+                    warn(Code::GitFailed, "Could not add project as a git submodule");
+                    return;
+                }
+            }
+        }
+    };
+    if let Err(e) = result {
+        warn(
+            Code::GitFailed,
+            &format!(
+                "Could not commit project to git repository: {}",
+                e.message()
+            ),
+        )
+    }
+}
+
+/// Create every project in the spec file at `path`.
+/// Flags given on the command line apply to every project.
+fn create_from_spec(path: &Path, args: NewArgs) {
+    let content = std::fs::read_to_string(path)
+        .unwrap_or_else(|_| exit_with_error(&format!("Could not read spec file {:?}", path)));
+    let spec: Spec = toml::from_str(&content).unwrap_or_else(|e| {
+        exit_with_error(&format!("Could not parse spec file {:?}: {}", path, e))
+    });
+    if spec.project.is_empty() {
+        exit_with_error(&format!("Spec file {:?} has no [[project]] entries", path))
+    }
+    let base = path.parent().unwrap_or(Path::new(""));
+    let n_projects = spec.project.len();
+    for (i, project) in spec.project.into_iter().enumerate() {
+        let dirname = base.join(&project.path);
+        println!("Creating project {}/{}: {:?}", i + 1, n_projects, dirname);
+        let name = project.name.or_else(|| {
+            project
+                .path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+        });
+        create(NewArgs {
+            dirname: Some(dirname.into_os_string()),
+            from_spec: None,
+            language: args.language,
+            profile: project.profile.or_else(|| args.profile.clone()),
+            scripts: args.scripts,
+            docs: args.docs,
+            params: args.params,
+            badges: args.badges,
+            index: args.index,
+            dir_readmes: args.dir_readmes,
+            assignment: args.assignment,
+            benchmark: args.benchmark,
+            ci: args.ci,
+            modulefile: args.modulefile,
+            lfs: args.lfs,
+            protect_raw: args.protect_raw,
+            annex: args.annex,
+            no_env: args.no_env,
+            no_git: args.no_git,
+            subproject: args.subproject,
+            sign: args.sign,
+            channel: args.channel.clone(),
+            packages: args.packages.clone(),
+            data_remote: project.data_remote.or_else(|| args.data_remote.clone()),
+            seed: project.seed.or(args.seed),
+            name,
+            force: args.force,
+            merge: args.merge,
+            dry_run: args.dry_run,
+            overrides: Box::new(spec.defaults.overlaid(&project.settings)),
+            remote: None,
+        });
+    }
+}
+
+/// Print what `create` would write with `args`, writing to memory instead of the disk.
+fn dry_run(args: NewArgs) {
+    let memory = Rc::new(Memory::new());
+    filesystem::with(memory.clone(), || match args.from_spec.clone() {
+        Some(path) => create_from_spec(&path, args),
+        None => create(args),
+    });
+    println!("Would create:");
+    for (path, kind) in memory.written() {
+        let suffix = match kind {
+            "directory" => "/",
+            "executable" => " (executable)",
+            _ => "",
+        };
+        println!("  {}{}", path.display(), suffix);
+    }
+}
+
+/// Create the project in `target` on another machine, with `args`.
+fn create_remote(mut args: NewArgs, target: Target) {
+    require_network("Creating a project on another machine");
+    if args.dry_run {
+        exit_with_error("--dry-run can't be used with a project on another machine")
+    }
+    let ssh = Rc::new(Ssh::connect(&target));
+    // The name defaults to the last component of the path, not the whole of <DIRNAME>
+    args.name = args.name.or_else(|| Some(name_from_path(&target.path)));
+    args.dirname = Some(target.path.clone().into_os_string());
+    args.remote = Some(target.host.clone());
+    filesystem::with(ssh, || create(args));
+    println!(
+        "Created project at {}:{}",
+        target.host,
+        target.path.display()
+    );
+}
+
+pub fn run(args: NewArgs) {
+    if let Some(target) = args.dirname.as_deref().and_then(Target::parse) {
+        return create_remote(args, target);
+    }
+    if args.dry_run {
+        return dry_run(args);
+    }
+    match args.from_spec.clone() {
+        Some(path) => create_from_spec(&path, args),
+        None => {
+            let dirname = args.dirname.clone().expect("Error when parsing arguments");
+            create(args);
+            shell::request_cd(Path::new(&dirname));
+        }
+    }
+}
+
+/// Create the project, with the files it overwrites with --force moved to its trash first.
+fn create(args: NewArgs) {
+    let dirname = match &args.dirname {
+        Some(dirname) if args.force && !args.dry_run => dirname.clone(),
+        _ => return create_files(args),
+    };
+    let trashing = Rc::new(Trashing::new(filesystem::current(), Path::new(&dirname)));
+    filesystem::with(trashing.clone(), || create_files(args));
+    if let Some(id) = trashing.id() {
+        println!(
+            "Moved the overwritten files to the trash. Restore them with \
+            `investigate trash restore {}`",
+            id
+        );
+    }
+}
+
+fn create_files(mut args: NewArgs) {
+    // Command-line flags take precedence over the profile, which takes precedence over defaults
+    let config = Config::load();
+    let permissions = Permissions::prepare(&config.permissions);
+    let profile = config
+        .profile(args.profile.as_deref())
+        .overlaid(&args.overrides);
+    args.language = args.language.or(profile.language);
+    args.scripts |= profile.scripts.unwrap_or(false);
+    args.docs |= profile.docs.unwrap_or(false);
+    args.params |= profile.params.unwrap_or(false);
+    args.badges |= profile.badges.unwrap_or(false);
+    args.index |= profile.index.unwrap_or(false);
+    args.dir_readmes |= profile.dir_readmes.unwrap_or(false);
+    args.lfs |= profile.lfs.unwrap_or(false);
+    args.protect_raw |= profile.protect_raw.unwrap_or(false);
+    args.annex |= profile.annex.unwrap_or(false);
+    if args.lfs && args.annex {
+        exit_with_error("Data cannot be stored in both Git LFS and git-annex")
+    }
+    if args.remote.is_some() && (args.annex || args.protect_raw || args.subproject.is_some()) {
+        exit_with_error(
+            "--annex, --protect-raw and --subproject can't be used with a project on another machine",
+        )
+    }
+    if args.no_git && (args.lfs || args.annex || args.protect_raw) {
+        exit_with_error(
+            "--lfs, --annex and --protect-raw need git, so they can't be used with --no-git",
+        )
+    }
+    args.assignment |= profile.assignment.unwrap_or(false);
+    args.benchmark |= profile.benchmark.unwrap_or(false);
+    args.modulefile |= profile.modulefile.unwrap_or(false);
+    if args.channel.is_empty() {
+        args.channel = profile.channels.unwrap_or_default();
+    }
+    if args.packages.is_empty() {
+        args.packages = profile.packages.unwrap_or_default();
+    }
+    args.ci = args.ci.or(profile.ci);
+    if args.language.is_none() && (args.scripts || args.docs || args.params || args.ci.is_some()) {
+        exit_with_error("--scripts, --docs, --params and --ci require a language to be set")
+    }
+    if args.params && args.language == Some(Language::Python) {
+        // Needed by the generated code to load and validate the parameters
+        for package in ["pydantic", "pyyaml"] {
+            let name =
+                |spec: &String| spec.split(['<', '>', '=', '!', '~', ' ']).next() == Some(package);
+            if !args.packages.iter().any(name) {
+                args.packages.push(package.to_owned());
+            }
+        }
+    }
+    // A directory special remote is the only kind which is not accessed over the network
+    if args.annex
+        && profile
+            .annex_remote
+            .as_deref()
+            .is_some_and(|remote| !remote.contains("type=directory"))
+    {
+        require_network("Adding the git-annex special remote of the profile")
+    }
+    let mut directories: Vec<Directory> = match profile.directories {
+        Some(specs) => specs.iter().map(|spec| spec.resolve()).collect(),
+        None => default_directories(),
+    };
+    if args.assignment && !directories.iter().any(|d| d.name == "answers") {
+        directories.push(Directory {
+            name: "answers".to_owned(),
+            description: Some(
+                "Reference solution to the assignment. Not tracked by git, so it's not\n  \
+                handed out with the project."
+                    .to_owned(),
+            ),
+        });
+    }
+    if args.benchmark && !directories.iter().any(|d| d.name == "benchmarks") {
+        directories.push(Directory {
+            name: "benchmarks".to_owned(),
+            description: Some(
+                "Setup of the benchmark: the tools and versions compared, in tools.tsv,\n  \
+                and how they are run on each dataset."
+                    .to_owned(),
+            ),
+        });
+    }
+    let templates = &Templates::new(profile.templates.into_iter().collect(), &config);
+
+    let dirname = args.dirname.clone().expect("Error when parsing arguments");
+    let path = Path::new(&dirname);
+    let project_name = if let Some(name) = &args.name {
+        name.clone()
+    } else {
+        dirname
+            .to_str()
+            .unwrap_or_else(|| {
+                exit_with_error(&format!(
+                    "Project name {:?} is not a normal UTF-8 string",
+                    dirname
+                ))
+            })
+            .to_owned()
+    };
+    if project_name.is_empty() {
+        exit_with_error("Project name cannot be empty")
+    }
+    let existing = if args.force {
+        Existing::Force
+    } else if args.merge {
+        Existing::Merge
+    } else {
+        Existing::Error
+    };
+    let capitalized_project = capitalize(&project_name);
+    let parent_repo = (!args.no_git && args.remote.is_none())
+        .then(|| containing_repository(path))
+        .flatten();
+    let subproject = match (&parent_repo, args.subproject) {
+        (None, Some(_)) => {
+            warn(
+                Code::GitFailed,
+                &format!(
+                    "{:?} is not inside a git repository, ignoring --subproject",
+                    path
+                ),
+            );
+            None
+        }
+        (Some(repo), None) => exit_with_error(&format!(
+            "Path {:?} is inside the git repository at {:?}.\n\
+            Use --subproject to add the project to that repository in a dedicated commit,\n\
+            --subproject=submodule to add it as a git submodule, or\n\
+            --subproject=nested to create an independent repository inside it.",
+            path,
+            repo.workdir().unwrap_or_else(|| repo.path())
+        )),
+        (_, subproject) => subproject,
+    };
+    if !path.exists() && !args.dry_run && args.remote.is_none() {
+        check_space(path, &config.storage);
+    }
+    if !args.dry_run && args.remote.is_none() {
+        wsl::check(path);
+    }
+    make_dirs(path, &directories, existing);
+    let start = Instant::now();
+
+    // The slow steps run in the background while the files are written.
+    // An existing environment.yml means the environment was made on a previous run
+    let made_env =
+        existing == Existing::Merge && filesystem::current().exists(&path.join("environment.yml"));
+    let conda =
+        (args.language == Some(Language::Python) && !args.no_env && !args.dry_run && !made_env)
+            .then(|| {
+                let command = conda_command(
+                    args.remote.as_deref(),
+                    path,
+                    &project_name,
+                    &args.channel,
+                    &args.packages,
+                );
+                let name = project_name.clone();
+                thread::spawn(move || conda_create(command, &name))
+            });
+    let git_init = (!args.no_git
+        && !args.dry_run
+        && subproject != Some(Subproject::Commit)
+        && match args.remote {
+            None => Repository::open(path).is_err(),
+            Some(_) => !filesystem::current().exists(&path.join(".git")),
+        })
+    .then(|| {
+        let (path, remote) = (path.to_owned(), args.remote.clone());
+        thread::spawn(move || git_init(&path, remote.as_deref()))
+    });
+    // The metadata has no authors if unknown, but generated files have marked placeholders
+    let author_email = get_author_email(&config);
+    if author_email.is_none() {
+        warn_unknown_author();
+    }
+    let (author, mail) = author_email
+        .clone()
+        .unwrap_or_else(|| (PLACEHOLDER_NAME.to_owned(), PLACEHOLDER_EMAIL.to_owned()));
+    // Files added to an existing project use its UUID and seed, as its metadata is kept
+    let kept = (existing == Existing::Merge)
+        .then(|| filesystem::current().read_to_string(&path.join(METADATA_FILE)))
+        .and_then(|content| toml::from_str::<Metadata>(&content.ok()?).ok());
+    let uuid = kept
+        .as_ref()
+        .map(|metadata| metadata.uuid.clone())
+        .unwrap_or_else(|| Uuid::new_v4().hyphenated().to_string());
+    // Keep the seed below 2^31, so every language can represent it
+    args.seed = kept
+        .and_then(|metadata| metadata.seed)
+        .or(args.seed)
+        .or_else(|| Some(Uuid::new_v4().as_u128() as u32 >> 1));
+    let seed = args.seed.unwrap_or_default().to_string();
+    make_metadata(
+        path,
+        &project_name,
+        &uuid,
+        &args,
+        &author_email,
+        &directories,
+        existing,
+    );
+    write(
+        &path.join("CHANGELOG.md"),
+        &templates.render(
+            "changelog",
+            &[("date", &Local::today().format("%Y-%m-%d").to_string())],
+        ),
+        existing,
+    );
+
+    // .gitignore
+    let mut language_gitignore = Vec::new();
+    if args.language == Some(Language::Python) {
+        language_gitignore.push("__pycache__");
+    }
+    if args.docs {
+        language_gitignore.push(match args.language {
+            Some(Language::Python) => "site",
+            _ => "docs/build",
+        });
+    }
+    // Contents of these directories are not tracked by git, except their READMEs
+    // and the access documentation of restricted datasets
+    // With git-annex, the data is tracked, but its content is stored in the annex
+    let ignored_directories: Vec<String> = IGNORED_DIRECTORIES
+        .iter()
+        .filter(|dir| !(args.annex && DATA_DIRECTORIES.contains(dir)))
+        .chain(args.assignment.then_some(&"answers"))
+        .map(|dir| {
+            let mut lines = format!("{}/*", dir);
+            if args.dir_readmes {
+                lines.push_str(&format!("\n!{}/README.md", dir));
+            }
+            if *dir == "raw" {
+                lines.push_str("\n!raw/*.ACCESS.md\n!raw/MANIFEST.toml");
+            }
+            if *dir == "results" && args.benchmark {
+                lines.push_str(&format!("\n!results/{}", BENCHMARK_DICTIONARY));
+            }
+            lines
+        })
+        .collect();
+    let gitignore = templates.render(
+        "gitignore",
+        &[
+            ("ignored_directories", &ignored_directories.join("\n")),
+            ("language_gitignore", &language_gitignore.join("\n")),
+        ],
+    );
+    // Without language patterns, the last line of the template is empty
+    write(
+        &path.join(".gitignore"),
+        &format!("{}\n", gitignore.trim_end()),
+        existing,
+    );
+    make_gitattributes(path, templates, args.lfs, args.annex, existing);
+    write(
+        &path.join(".env.example"),
+        &templates.render("env_example", &[]),
+        existing,
+    );
+    write(
+        &path.join(".envrc"),
+        &templates.render("envrc", &[("seed", &seed)]),
+        existing,
+    );
+    if let Some(remote) = &args.data_remote {
+        make_sync_script(path, templates, remote, existing);
+    }
+    if args.dir_readmes {
+        make_dir_readmes(path, templates, &directories, existing);
+    }
+    if args.assignment {
+        let author_suffix = match &author_email {
+            Some((author, _)) => format!(" by {}", author),
+            None => String::new(),
+        };
+        write(
+            &path.join("ASSIGNMENT.md"),
+            &templates.render(
+                "assignment",
+                &[
+                    ("name", &capitalized_project),
+                    ("date", &Local::today().format("%Y-%m-%d").to_string()),
+                    ("author_suffix", &author_suffix),
+                ],
+            ),
+            existing,
+        );
+    }
+    if args.benchmark {
+        write(
+            &path.join("benchmarks").join("tools.tsv"),
+            &templates.render("benchmark_tools", &[]),
+            existing,
+        );
+        write(
+            &path.join("results").join(BENCHMARK_DICTIONARY),
+            &templates.render("benchmark_metrics", &[]),
+            existing,
+        );
+    }
+
+    // Extra Python/Julia specifics
+    if let Some(language) = args.language {
+        let directory_constants = directory_constants(&directories, language.into());
+        make_paths(path, templates, &directories, language.into(), existing);
+        match language {
+            Language::Julia => {
+                let module_name = convert_name_to_module(&project_name);
+                write(
+                    &path.join("src").join(module_name.clone() + ".jl"),
+                    &templates.render(
+                        "main_julia",
+                        &[
+                            ("module_name", &module_name),
+                            ("directory_constants", &directory_constants),
+                            ("seed", &seed),
+                            (
+                                "params",
+                                if args.params {
+                                    "\ninclude(\"params.jl\")\n"
+                                } else {
+                                    ""
+                                },
+                            ),
+                        ],
+                    ),
+                    existing,
+                );
+                make_julia_project(
+                    &path.join("Project.toml"),
+                    templates,
+                    &module_name,
+                    &uuid,
+                    (&author, &mail),
+                    existing,
+                );
+                if args.params {
+                    make_julia_params(path, templates, &module_name, existing);
+                }
+                if args.scripts {
+                    make_scripts(path, templates, language, &module_name, existing);
+                }
+                if args.docs {
+                    make_docs(
+                        path,
+                        templates,
+                        language,
+                        &capitalized_project,
+                        &module_name,
+                        &author,
+                        existing,
+                    );
+                }
+            }
+            Language::Python => {
+                if args.scripts {
+                    // Library code is an installable package, and scripts replace main.py
+                    let package_name = convert_name_to_package(&project_name);
+                    let package_dir = path.join("src").join(&package_name);
+                    make_dir(&package_dir);
+                    write(
+                        &package_dir.join("__init__.py"),
+                        &templates.render(
+                            "package_python",
+                            &[
+                                ("directory_constants", &directory_constants),
+                                ("seed", &seed),
+                            ],
+                        ),
+                        existing,
+                    );
+                    let command_name = convert_name_to_command(&project_name);
+                    write(
+                        &package_dir.join("cli.py"),
+                        &templates.render("cli_python", &[("command_name", &command_name)]),
+                        existing,
+                    );
+                    make_pyproject(
+                        &path.join("pyproject.toml"),
+                        templates,
+                        &package_name,
+                        &command_name,
+                        (&author, &mail),
+                        existing,
+                    );
+                    make_scripts(path, templates, language, &package_name, existing);
+                    if args.params {
+                        let module = format!("{}.params", package_name);
+                        make_python_params(path, templates, &package_dir, &module, existing);
+                    }
+                } else {
+                    write(
+                        &path.join("src").join("main.py"),
+                        &templates.render(
+                            "main_python",
+                            &[
+                                ("directory_constants", &directory_constants),
+                                ("seed", &seed),
+                            ],
+                        ),
+                        existing,
+                    );
+                }
+                if args.params && !args.scripts {
+                    make_python_params(path, templates, &path.join("src"), "params", existing);
+                }
+                make_conda_yml(
+                    path,
+                    templates,
+                    &project_name,
+                    (&args.channel, &args.packages),
+                    args.remote.as_deref(),
+                    existing,
+                );
+                if args.docs {
+                    make_docs(
+                        path,
+                        templates,
+                        language,
+                        &capitalized_project,
+                        &convert_name_to_package(&project_name),
+                        &author,
+                        existing,
+                    );
+                }
+            }
+        }
+        if let Some(ci) = args.ci {
+            make_ci(path, templates, ci, language, &project_name, existing);
+        }
+        if args.scripts || args.docs {
+            make_makefile(path, templates, language, args.scripts, args.docs, existing);
+        }
+    }
+
+    if args.modulefile {
+        make_modulefile(
+            path,
+            templates,
+            &project_name,
+            &args,
+            &directories,
+            existing,
+        );
+    }
+
+    // Readme comes last, so its file index sees all the generated files
+    readme::make_readme(
+        path,
+        templates,
+        &capitalized_project,
+        &author,
+        &args,
+        &directories,
+        existing,
+    );
+    if args.dry_run {
+        return;
+    }
+    println!(
+        "Created project files in {:.1}s",
+        start.elapsed().as_secs_f64()
+    );
+    let mut initialized = false;
+    if let Some(git_init) = git_init {
+        match git_init.join().expect("Error when initializing git repo") {
+            Ok(()) => {
+                println!("Initialized git repository");
+                initialized = true;
+            }
+            Err(e) => warn(
+                Code::GitFailed,
+                &format!(
+                    "Could not initialize git repository: {}. Run `git init` later",
+                    e
+                ),
+            ),
+        }
+    }
+    if args.annex {
+        annex_init(path, profile.annex_remote.as_deref());
+    }
+    if args.protect_raw {
+        install_raw_hook(path, templates);
+    }
+    if let Some(conda) = conda {
+        let result = Steps::new().run("Create Conda environment", None, |_| {
+            conda.join().expect("Error when creating Conda environment")
+        });
+        match result {
+            Ok(message) => println!("{}", message),
+            Err(warning) => warn(Code::EnvironmentFailed, &warning),
+        }
+    }
+    if args.language == Some(Language::Julia) && !args.packages.is_empty() {
+        julia_add_packages(path, &args.packages, args.remote.as_deref());
+    }
+    // The group, permissions and registry are of this machine
+    if args.remote.is_none() {
+        permissions.apply(path);
+        registry::register(path);
+    }
+    let options: Vec<String> = [
+        ("scripts", args.scripts),
+        ("docs", args.docs),
+        ("params", args.params),
+        ("badges", args.badges),
+        ("index", args.index),
+        ("dir_readmes", args.dir_readmes),
+        ("assignment", args.assignment),
+        ("benchmark", args.benchmark),
+        ("ci", args.ci.is_some()),
+        ("modulefile", args.modulefile),
+        ("lfs", args.lfs),
+        ("annex", args.annex),
+        ("protect_raw", args.protect_raw),
+        ("subproject", subproject.is_some()),
+        ("data_remote", args.data_remote.is_some()),
+        ("packages", !args.packages.is_empty()),
+    ]
+    .iter()
+    .filter(|(_, enabled)| *enabled)
+    .map(|(option, _)| option.to_string())
+    .collect();
+    stats::record_project(&config, args.language, args.profile.clone(), options);
+    // A submodule's initial commit is made when it's added to the parent repository
+    if initialized && args.remote.is_none() && subproject != Some(Subproject::Submodule) {
+        initial_commit(path, &project_name, args.sign);
+    }
+    if let (Some(repo), Some(subproject)) = (parent_repo, subproject) {
+        add_to_parent_repo(&repo, path, &project_name, subproject, args.sign);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filesystem::Filesystem;
+    use clap::Parser;
+
+    #[derive(Parser)]
+    struct Cli {
+        #[clap(flatten)]
+        args: NewArgs,
+    }
+
+    /// Create the project "proj" with the command-line arguments `args` in memory, returning its
+    /// path, which does not exist on disk, and the paths written.
+    fn create_in_memory(args: &[&str]) -> (PathBuf, Rc<Memory>) {
+        let path = std::env::temp_dir().join(format!("investigate-test-{}", Uuid::new_v4()));
+        let path_arg = path.to_str().unwrap();
+        let cli = Cli::parse_from(
+            [
+                "investigate",
+                path_arg,
+                "--name",
+                "proj",
+                "--dry-run",
+                "--no-git",
+            ]
+            .iter()
+            .chain(args),
+        );
+        let memory = Rc::new(Memory::new());
+        filesystem::with(memory.clone(), || create(cli.args));
+        assert!(!path.exists());
+        (path, memory)
+    }
+
+    fn written_files(path: &Path, memory: &Memory) -> Vec<String> {
+        memory
+            .written()
+            .into_iter()
+            .filter(|(_, kind)| *kind != "directory")
+            .map(|(file, _)| file.strip_prefix(path).unwrap().display().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn creates_julia_project() {
+        let (path, memory) = create_in_memory(&["--language", "julia", "--scripts"]);
+        let files = written_files(&path, &memory);
+        for file in [
+            ".gitignore",
+            METADATA_FILE,
+            "CHANGELOG.md",
+            "Project.toml",
+            "README.md",
+            "scripts/01_example.jl",
+            "src/Paths.jl",
+            "src/Proj.jl",
+        ] {
+            assert!(
+                files.iter().any(|f| f == file),
+                "{} not in {:?}",
+                file,
+                files
+            );
+        }
+        assert!(memory.is_dir(&path.join("raw")));
+        let metadata = memory.read_to_string(&path.join(METADATA_FILE)).unwrap();
+        assert!(metadata.contains("language = \"julia\""));
+    }
+
+    #[test]
+    fn creates_modulefile_and_sync_script() {
+        let (path, memory) =
+            create_in_memory(&["--modulefile", "--data-remote", "s3://bucket/proj"]);
+        let modulefile = memory
+            .read_to_string(&path.join("modulefiles").join("proj"))
+            .unwrap();
+        assert!(modulefile.starts_with("#%Module"));
+        assert!(modulefile.contains("setenv RAW_DIR $root/raw"));
+        assert!(memory
+            .written()
+            .contains(&(path.join("sync_data.sh"), "executable")));
+    }
+}
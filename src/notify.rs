@@ -0,0 +1,56 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use crate::config::NotifyConfig;
+use crate::is_offline;
+use crate::warning::{warn, Code};
+
+/// Check if `config` has any way to notify the user.
+pub fn can_notify(config: &NotifyConfig) -> bool {
+    config.webhook.is_some() || config.command.is_some()
+}
+
+/// Send `message` to the webhook and the command in `config`, warning if either fails.
+pub fn notify(config: &NotifyConfig, message: &str) {
+    if config.webhook.is_some() && is_offline() {
+        warn(
+            Code::NotifyFailed,
+            "Not posting notification to webhook, since --offline was given",
+        );
+    } else if let Some(webhook) = &config.webhook {
+        let body = serde_json::json!({ "text": message }).to_string();
+        let success = Command::new("curl")
+            .args(["-sSf", "-X", "POST", "-H", "Content-Type: application/json"])
+            .arg("--data-binary")
+            .arg(&body)
+            .arg(webhook)
+            .stdout(Stdio::null())
+            .status()
+            .is_ok_and(|status| status.success());
+        if !success {
+            warn(Code::NotifyFailed, "Could not post notification to webhook");
+        }
+    }
+    if let Some(command) = &config.command {
+        let success = Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .stdin(Stdio::piped())
+            .spawn()
+            .and_then(|mut child| {
+                child
+                    .stdin
+                    .take()
+                    .expect("Error when opening stdin of notify command")
+                    .write_all(message.as_bytes())?;
+                child.wait()
+            })
+            .is_ok_and(|status| status.success());
+        if !success {
+            warn(
+                Code::NotifyFailed,
+                &format!("Notify command \"{}\" failed", command),
+            );
+        }
+    }
+}
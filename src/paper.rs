@@ -0,0 +1,113 @@
+use clap::{Args, Subcommand};
+
+use std::path::Path;
+
+use crate::data::sha256;
+use crate::figure::{snippet, Figures, Kind, SnippetFormat, FIGURES_FILE};
+use crate::report::walk_files;
+use crate::warning::{warn, Code};
+use crate::{exit_with_error, filesystem, find_project_root};
+
+#[derive(Subcommand)]
+pub enum PaperCommand {
+    /// Regenerate the file in paper/ which includes all registered figures and tables, in order
+    Sync(SyncArgs),
+}
+
+#[derive(Args)]
+pub struct SyncArgs {
+    /// Format of the paper (default: quarto if paper/ has .qmd files, else latex)
+    #[clap(arg_enum, value_parser, long)]
+    format: Option<SnippetFormat>,
+}
+
+/// Get the file in paper/ which includes the registered artifacts in the given format.
+fn includes_file(format: SnippetFormat) -> &'static str {
+    match format {
+        // Quarto doesn't render files starting with an underscore on their own
+        SnippetFormat::Quarto => "paper/_artifacts.qmd",
+        SnippetFormat::Latex => "paper/artifacts.tex",
+    }
+}
+
+fn sync(root: &Path, args: SyncArgs) {
+    let figures = Figures::load(root);
+    if figures.figure.is_empty() {
+        exit_with_error(&format!(
+            "No figures or tables registered in {}. Use `investigate figure register`",
+            FIGURES_FILE
+        ))
+    }
+    let format = args.format.unwrap_or_else(|| {
+        let has_quarto = walk_files(&root.join("paper"))
+            .iter()
+            .any(|file| file.extension().is_some_and(|e| e == "qmd"));
+        if has_quarto {
+            SnippetFormat::Quarto
+        } else {
+            SnippetFormat::Latex
+        }
+    });
+    // A file that changed since registration may no longer match its caption
+    for figure in &figures.figure {
+        match sha256(&root.join(&figure.file)) {
+            Err(_) => warn(Code::FileChanged, &format!("{} is missing", figure.file)),
+            Ok(checksum) if checksum != figure.sha256 => warn(
+                Code::FileChanged,
+                &format!(
+                    "{} changed since it was registered. \
+                    Register it again to update its record",
+                    figure.file
+                ),
+            ),
+            Ok(_) => (),
+        }
+    }
+    let comment = match format {
+        SnippetFormat::Latex => "%",
+        SnippetFormat::Quarto => "<!--",
+    };
+    let mut sections = vec![format!(
+        "{} Generated by `investigate paper sync` from {}. Do not edit.{}",
+        comment,
+        FIGURES_FILE,
+        if format == SnippetFormat::Quarto {
+            " -->"
+        } else {
+            ""
+        }
+    )];
+    // Figures and tables are numbered separately, each in the order they were registered
+    for kind in [Kind::Figure, Kind::Table] {
+        sections.extend(
+            figures
+                .figure
+                .iter()
+                .filter(|figure| figure.kind == kind)
+                .map(|figure| snippet(figure, format)),
+        );
+    }
+    let path = root.join(includes_file(format));
+    filesystem::create_dir_all(root.join("paper"))
+        .unwrap_or_else(|_| panic!("Error when creating directory {:?}", root.join("paper")));
+    filesystem::write(&path, sections.join("\n\n") + "\n")
+        .unwrap_or_else(|_| panic!("Error when writing file {:?}", path));
+    let n_figures = figures
+        .figure
+        .iter()
+        .filter(|f| f.kind == Kind::Figure)
+        .count();
+    println!(
+        "Wrote {} figures and {} tables to {}",
+        n_figures,
+        figures.figure.len() - n_figures,
+        includes_file(format)
+    );
+}
+
+pub fn run(command: PaperCommand) {
+    let root = find_project_root();
+    match command {
+        PaperCommand::Sync(args) => sync(&root, args),
+    }
+}
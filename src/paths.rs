@@ -0,0 +1,143 @@
+use clap::{ArgEnum, Args};
+
+use std::path::Path;
+
+use crate::config::Config;
+use crate::directories::{Directory, DirectorySpec};
+use crate::metadata::Metadata;
+use crate::new::{write, Existing, Language};
+use crate::templates::Templates;
+use crate::{exit_with_error, filesystem, find_project_root};
+
+/// A language a helper with the paths of the project's directories can be generated for.
+#[derive(Copy, Clone, PartialEq, Eq, ArgEnum)]
+pub enum PathsLanguage {
+    Python,
+    Julia,
+    R,
+}
+
+impl From<Language> for PathsLanguage {
+    fn from(language: Language) -> PathsLanguage {
+        match language {
+            Language::Python => PathsLanguage::Python,
+            Language::Julia => PathsLanguage::Julia,
+        }
+    }
+}
+
+impl PathsLanguage {
+    /// Get the helper's path relative to the project root, and its template.
+    fn file(self) -> (&'static str, &'static str) {
+        match self {
+            PathsLanguage::Python => ("src/paths.py", "paths_python"),
+            PathsLanguage::Julia => ("src/Paths.jl", "paths_julia"),
+            PathsLanguage::R => ("R/paths.R", "paths_r"),
+        }
+    }
+}
+
+#[derive(Args)]
+pub struct PathsArgs {
+    /// Language to write the helper for (default: the project's language). Can be given
+    /// multiple times
+    #[clap(arg_enum, value_parser, long, multiple_occurrences = true)]
+    language: Vec<PathsLanguage>,
+}
+
+/// Get the name of the constant with the path of `directory`, like `RAW_DIR` for raw/.
+pub fn constant_name(directory: &Directory) -> String {
+    directory
+        .name
+        .to_uppercase()
+        .replace(|c: char| !c.is_ascii_alphanumeric(), "_")
+        + "_DIR"
+}
+
+/// Get the code defining a constant like `RAW_DIR` with the path of each directory,
+/// relative to the constant `PROJECT_DIR`.
+pub fn directory_constants(directories: &[Directory], language: PathsLanguage) -> String {
+    directories
+        .iter()
+        .map(|directory| {
+            let constant = constant_name(directory);
+            let components: Vec<String> = directory
+                .name
+                .split('/')
+                .map(|c| format!("\"{}\"", c))
+                .collect();
+            match language {
+                PathsLanguage::Python => {
+                    format!("{} = PROJECT_DIR / {}", constant, components.join(" / "))
+                }
+                PathsLanguage::Julia => format!(
+                    "const {} = joinpath(PROJECT_DIR, {})",
+                    constant,
+                    components.join(", ")
+                ),
+                PathsLanguage::R => format!(
+                    "{} <- file.path(PROJECT_DIR, {})",
+                    constant,
+                    components.join(", ")
+                ),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Write the helper defining the paths of `directories`, which finds the project root by
+/// looking for the metadata file, so scripts using it work from any working directory.
+pub fn make_paths(
+    path: &Path,
+    templates: &Templates,
+    directories: &[Directory],
+    language: PathsLanguage,
+    existing: Existing,
+) {
+    let (file, template) = language.file();
+    let exports: Vec<String> = directories.iter().map(constant_name).collect();
+    let file = path.join(file);
+    if let Some(parent) = file.parent() {
+        filesystem::current()
+            .create_dir_all(parent)
+            .unwrap_or_else(|_| panic!("Error when creating directory {:?}", parent));
+    }
+    write(
+        &file,
+        &templates.render(
+            template,
+            &[
+                (
+                    "directory_constants",
+                    &directory_constants(directories, language),
+                ),
+                ("exports", &exports.join(", ")),
+            ],
+        ),
+        existing,
+    );
+}
+
+pub fn run(args: PathsArgs) {
+    let root = find_project_root();
+    let metadata = Metadata::load(&root);
+    let languages = if args.language.is_empty() {
+        match metadata.language {
+            Some(language) => vec![language.into()],
+            None => exit_with_error("Project has no language. Give one with --language"),
+        }
+    } else {
+        args.language
+    };
+    let directories: Vec<Directory> = metadata
+        .directories
+        .iter()
+        .map(|name| DirectorySpec::Name(name.clone()).resolve())
+        .collect();
+    let templates = Templates::new(Vec::new(), &Config::load());
+    for language in languages {
+        make_paths(&root, &templates, &directories, language, Existing::Force);
+        println!("Wrote {}", language.file().0);
+    }
+}
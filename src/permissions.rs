@@ -0,0 +1,158 @@
+use git2::Repository;
+
+use std::path::Path;
+
+use crate::config::PermissionsConfig;
+use crate::exit_with_error;
+use crate::warning::{warn, Code};
+
+/// The setgid bit, which makes files created in a directory belong to the directory's group
+#[cfg(unix)]
+const SETGID: u32 = 0o2000;
+
+/// Parse an octal umask like "002" or "0o022".
+pub fn parse_umask(text: &str) -> Option<u32> {
+    let digits = text.strip_prefix("0o").unwrap_or(text);
+    u32::from_str_radix(digits, 8)
+        .ok()
+        .filter(|mask| *mask <= 0o777)
+}
+
+/// Ownership and permissions to give new projects, from the `[permissions]` section of the config.
+pub struct Permissions {
+    /// Name and ID of the group to own the files
+    group: Option<(String, u32)>,
+    setgid: bool,
+}
+
+/// Get the ID of the unix group named `group`, which may also be given as an ID.
+#[cfg(unix)]
+fn group_id(group: &str) -> Option<u32> {
+    if let Ok(gid) = group.parse() {
+        return Some(gid);
+    }
+    let name = std::ffi::CString::new(group).ok()?;
+    // SAFETY: getgrnam returns null or a pointer to a static group entry, which is read at once
+    let entry = unsafe { libc::getgrnam(name.as_ptr()) };
+    if entry.is_null() {
+        None
+    } else {
+        Some(unsafe { (*entry).gr_gid })
+    }
+}
+
+#[cfg(not(unix))]
+fn group_id(_group: &str) -> Option<u32> {
+    None
+}
+
+#[cfg(unix)]
+fn set_umask(mask: u32) {
+    // SAFETY: umask can't fail, and only changes the mode new files are created with
+    unsafe { libc::umask(mask as libc::mode_t) };
+}
+
+#[cfg(not(unix))]
+fn set_umask(_mask: u32) {}
+
+impl Permissions {
+    /// Check the permissions settings, and set the umask, so every file created from now on,
+    /// also by git and Conda, gets the configured mode. Run this before creating any files, so
+    /// a misconfigured group doesn't leave a half-made project.
+    pub fn prepare(config: &PermissionsConfig) -> Permissions {
+        if cfg!(not(unix))
+            && (config.group.is_some() || config.setgid.is_some() || config.umask.is_some())
+        {
+            warn(
+                Code::PermissionsFailed,
+                "The [permissions] settings are only supported on unix",
+            );
+        }
+        if let Some(umask) = &config.umask {
+            let mask = parse_umask(umask).unwrap_or_else(|| {
+                exit_with_error(&format!(
+                    "Invalid umask \"{}\" in the config file. Give it in octal, like \"002\"",
+                    umask
+                ))
+            });
+            set_umask(mask);
+        }
+        let group = config.group.as_ref().and_then(|group| {
+            let gid = group_id(group);
+            if gid.is_none() && cfg!(unix) {
+                exit_with_error(&format!("No unix group named \"{}\"", group))
+            }
+            Some((group.clone(), gid?))
+        });
+        Permissions {
+            group,
+            setgid: cfg!(unix) && config.setgid.unwrap_or(false),
+        }
+    }
+
+    /// Give the files of the project at `path` to the configured group, and set the setgid bit on
+    /// its directories. Git is told to keep the repository writable by the group.
+    pub fn apply(&self, path: &Path) {
+        if self.group.is_none() && !self.setgid {
+            return;
+        }
+        let mut n_failed = 0;
+        self.apply_to(path, &mut n_failed);
+        if n_failed > 0 {
+            warn(
+                Code::PermissionsFailed,
+                &format!(
+                    "Could not set the group or permissions of {} files in {:?}",
+                    n_failed, path
+                ),
+            );
+        }
+        if let Ok(repo) = Repository::open(path) {
+            let result = repo
+                .config()
+                .and_then(|mut config| config.set_str("core.sharedRepository", "group"));
+            if let Err(e) = result {
+                warn(
+                    Code::PermissionsFailed,
+                    &format!(
+                        "Could not make the git repository shared with the group: {}",
+                        e.message()
+                    ),
+                );
+            }
+        }
+        if let Some((group, _)) = &self.group {
+            println!("Gave the project's files to group {}", group);
+        }
+    }
+
+    #[cfg(unix)]
+    fn apply_to(&self, path: &Path, n_failed: &mut usize) {
+        use std::os::unix::fs::{lchown, PermissionsExt};
+        // Symlinks are not followed, so only the project's own files are changed
+        let Ok(metadata) = path.symlink_metadata() else {
+            *n_failed += 1;
+            return;
+        };
+        if let Some((_, gid)) = self.group {
+            if lchown(path, None, Some(gid)).is_err() {
+                *n_failed += 1;
+            }
+        }
+        if !metadata.is_dir() {
+            return;
+        }
+        if self.setgid {
+            let mode = metadata.permissions().mode() | SETGID;
+            if std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode)).is_err() {
+                *n_failed += 1;
+            }
+        }
+        for entry in path.read_dir().into_iter().flatten().flatten() {
+            self.apply_to(&entry.path(), n_failed);
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn apply_to(&self, _path: &Path, _n_failed: &mut usize) {}
+}
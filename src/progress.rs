@@ -0,0 +1,116 @@
+use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
+
+use std::io::IsTerminal;
+use std::time::{Duration, Instant};
+
+use crate::is_plain;
+
+/// A step of a long operation, as shown in the summary.
+struct Step {
+    name: String,
+    elapsed: Duration,
+    ok: bool,
+}
+
+/// The steps of a long operation, each shown with a progress bar while it runs, and listed
+/// in a summary table when the operation is done. When stdout is not a terminal, e.g. in logs,
+/// or with --plain, the bars are replaced by a line when each step starts and ends.
+pub struct Steps {
+    steps: Vec<Step>,
+    interactive: bool,
+}
+
+impl Steps {
+    pub fn new() -> Steps {
+        Steps {
+            steps: Vec::new(),
+            interactive: std::io::stdout().is_terminal() && !is_plain(),
+        }
+    }
+
+    fn bar(&self, name: &str, total: Option<u64>) -> ProgressBar {
+        if !self.interactive {
+            println!("{}...", name);
+            return ProgressBar::hidden();
+        }
+        let bar = match total {
+            Some(total) => ProgressBar::with_draw_target(Some(total), ProgressDrawTarget::stdout())
+                .with_style(
+                    ProgressStyle::with_template(
+                        "{msg} [{bar:30}] {bytes}/{total_bytes} ({eta} left)",
+                    )
+                    .expect("Error in progress bar template")
+                    .progress_chars("=> "),
+                ),
+            None => ProgressBar::with_draw_target(None, ProgressDrawTarget::stdout()).with_style(
+                ProgressStyle::with_template("{spinner} {msg} ({elapsed})")
+                    .expect("Error in progress bar template"),
+            ),
+        };
+        bar.set_message(name.to_owned());
+        bar.enable_steady_tick(Duration::from_millis(100));
+        bar
+    }
+
+    /// Run the step `name` by calling `f` with its progress bar. If `total` is given, the
+    /// bar shows the progress towards that many bytes, as `f` increments it, with an ETA.
+    /// Else it's a spinner showing the time spent.
+    pub fn run<T, E>(
+        &mut self,
+        name: &str,
+        total: Option<u64>,
+        f: impl FnOnce(&ProgressBar) -> Result<T, E>,
+    ) -> Result<T, E> {
+        let bar = self.bar(name, total);
+        let start = Instant::now();
+        let result = f(&bar);
+        let elapsed = start.elapsed();
+        bar.finish_and_clear();
+        let ok = result.is_ok();
+        if !self.interactive {
+            println!(
+                "{} {} in {:.1}s",
+                name,
+                if ok { "done" } else { "failed" },
+                elapsed.as_secs_f64()
+            );
+        }
+        self.steps.push(Step {
+            name: name.to_owned(),
+            elapsed,
+            ok,
+        });
+        result
+    }
+
+    /// Print the table of the steps run, if there was more than one.
+    pub fn summary(&self) {
+        if self.steps.len() < 2 {
+            return;
+        }
+        let width = self
+            .steps
+            .iter()
+            .map(|step| step.name.chars().count())
+            .max()
+            .unwrap_or(0)
+            .max("Step".len());
+        println!("{:width$}  {:>8}  Status", "Step", "Time", width = width);
+        for step in &self.steps {
+            println!(
+                "{:width$}  {:>7.1}s  {}",
+                step.name,
+                step.elapsed.as_secs_f64(),
+                if step.ok { "ok" } else { "failed" },
+                width = width
+            );
+        }
+        let total: Duration = self.steps.iter().map(|step| step.elapsed).sum();
+        println!(
+            "{:width$}  {:>7.1}s",
+            "Total",
+            total.as_secs_f64(),
+            width = width
+        );
+    }
+}
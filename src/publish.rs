@@ -0,0 +1,436 @@
+use chrono::Local;
+use clap::{Args, Subcommand};
+use serde_json::{json, Value};
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use crate::config::Config;
+use crate::export::{title, Person};
+use crate::finalize::archive_path;
+use crate::metadata::{Metadata, METADATA_FILE};
+use crate::new::{write, Existing};
+use crate::readme::set_doi;
+use crate::templates::Templates;
+use crate::warning::{warn, Code};
+use crate::{exit_with_error, find_project_root, require_network};
+
+/// Name of the citation file, in the Citation File Format read by GitHub and Zenodo
+const CITATION_FILE: &str = "CITATION.cff";
+
+#[derive(Subcommand)]
+pub enum PublishCommand {
+    /// Upload the project's archive to a draft deposition on Zenodo, and reserve its DOI.
+    /// Needs a Zenodo API token with the deposit scopes in $ZENODO_TOKEN
+    Zenodo(ZenodoArgs),
+
+    /// Create an OSF project, or a component of an existing one, with the project's metadata.
+    /// Needs an OSF personal access token with the osf.full_write scope in $OSF_TOKEN
+    Osf(OsfArgs),
+}
+
+#[derive(Args)]
+pub struct ZenodoArgs {
+    /// Archive to upload (default: the archive written by `investigate finalize --archive`)
+    #[clap(long)]
+    archive: Option<PathBuf>,
+
+    /// Use the Zenodo sandbox at sandbox.zenodo.org, e.g. to try it out
+    #[clap(long)]
+    sandbox: bool,
+}
+
+#[derive(Args)]
+pub struct OsfArgs {
+    /// Create a component of this OSF project, given by its ID, instead of a new project
+    #[clap(long, value_name = "NODE")]
+    parent: Option<String>,
+
+    /// Make the project public right away instead of private
+    #[clap(long)]
+    public: bool,
+
+    /// Make the project's OSF storage the data remote, so `investigate data push results`
+    /// uploads results/ to it
+    #[clap(long)]
+    sync_results: bool,
+}
+
+/// Names of the licenses on OSF, which it identifies by name rather than SPDX identifier.
+const OSF_LICENSES: [(&str, &str); 7] = [
+    ("CC-BY-4.0", "CC-By Attribution 4.0 International"),
+    ("CC0-1.0", "CC0 1.0 Universal"),
+    ("MIT", "MIT License"),
+    ("Apache-2.0", "Apache License 2.0"),
+    ("BSD-3-Clause", "BSD 3-Clause \"New\"/\"Revised\" License"),
+    ("GPL-3.0-only", "GNU General Public License (GPL) 3.0"),
+    ("GPL-3.0-or-later", "GNU General Public License (GPL) 3.0"),
+];
+
+/// Send a request to the API, with a JSON body or a file to upload, and get the response.
+fn api_request(
+    method: &str,
+    url: &str,
+    token: &str,
+    body: Option<&Value>,
+    upload: Option<&Path>,
+) -> Value {
+    let mut command = Command::new("curl");
+    // The token is passed on stdin, so other users can't see it in the process list
+    command.args(["-sS", "--fail-with-body", "-X", method, "-K", "-"]);
+    if let Some(body) = body {
+        command
+            .args(["-H", "Content-Type: application/json", "--data-binary"])
+            .arg(body.to_string());
+    }
+    if let Some(path) = upload {
+        command.arg("--upload-file").arg(path);
+    }
+    let mut child = command
+        .arg(url)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap_or_else(|_| exit_with_error("Could not run curl"));
+    child
+        .stdin
+        .take()
+        .expect("Error when opening stdin of curl")
+        .write_all(format!("header = \"Authorization: Bearer {}\"\n", token).as_bytes())
+        .expect("Error when writing to curl");
+    let output = child
+        .wait_with_output()
+        .unwrap_or_else(|_| exit_with_error("Could not run curl"));
+    if !output.status.success() {
+        exit_with_error(&format!(
+            "Request to {} failed: {} {}",
+            url,
+            String::from_utf8_lossy(&output.stderr).trim(),
+            String::from_utf8_lossy(&output.stdout).trim()
+        ))
+    }
+    serde_json::from_slice(&output.stdout)
+        .unwrap_or_else(|_| exit_with_error(&format!("Could not parse the response of {}", url)))
+}
+
+/// Get the metadata of the deposition, in Zenodo's format.
+fn deposition_metadata(metadata: &Metadata) -> Value {
+    let title = title(metadata);
+    let creators: Vec<Value> = metadata
+        .authors
+        .iter()
+        .map(|author| {
+            let person = Person::from_author(author);
+            let name = match person.given {
+                Some(given) => format!("{}, {}", person.family, given),
+                None => person.family,
+            };
+            json!({ "name": name })
+        })
+        .collect();
+    let mut deposition = json!({
+        "upload_type": "dataset",
+        "title": title,
+        "creators": creators,
+        "description": metadata.description.as_deref().unwrap_or(&title),
+        "access_right": "open",
+        "version": metadata.version,
+        "prereserve_doi": true,
+    });
+    if let Some(license) = &metadata.license {
+        deposition["license"] = json!(license.to_lowercase());
+    }
+    if !metadata.keywords.is_empty() {
+        deposition["keywords"] = json!(metadata.keywords);
+    }
+    json!({ "metadata": deposition })
+}
+
+/// Write the DOI into CITATION.cff, creating it if it doesn't exist.
+fn write_citation(root: &Path, metadata: &Metadata, doi: &str) {
+    let path = root.join(CITATION_FILE);
+    let content = match std::fs::read_to_string(&path) {
+        Ok(content) => {
+            let doi_line = format!("doi: \"{}\"", doi);
+            let mut found = false;
+            let mut lines: Vec<String> = content
+                .lines()
+                .map(|line| {
+                    if line.starts_with("doi:") {
+                        found = true;
+                        doi_line.clone()
+                    } else {
+                        line.to_owned()
+                    }
+                })
+                .collect();
+            if !found {
+                lines.push(doi_line);
+            }
+            lines.join("\n") + "\n"
+        }
+        Err(_) => {
+            let authors: Vec<String> = metadata
+                .authors
+                .iter()
+                .map(|author| {
+                    let person = Person::from_author(author);
+                    let mut entry = format!("  - family-names: \"{}\"", person.family);
+                    if let Some(given) = person.given {
+                        entry.push_str(&format!("\n    given-names: \"{}\"", given));
+                    }
+                    entry
+                })
+                .collect();
+            Templates::new(Vec::new(), &Config::load()).render(
+                "citation",
+                &[
+                    ("title", &title(metadata)),
+                    ("version", &metadata.version),
+                    ("doi", doi),
+                    ("date", &Local::today().format("%Y-%m-%d").to_string()),
+                    ("authors", &authors.join("\n")),
+                ],
+            )
+        }
+    };
+    write(&path, &content, Existing::Force);
+}
+
+fn zenodo(args: ZenodoArgs) {
+    let root = find_project_root();
+    let mut metadata = Metadata::load(&root);
+    if let Some(doi) = &metadata.doi {
+        exit_with_error(&format!(
+            "Project already has the DOI {}. Publish new versions on Zenodo instead",
+            doi
+        ))
+    }
+    if metadata.authors.is_empty() {
+        exit_with_error(&format!(
+            "Zenodo requires authors, but there are none in {}",
+            METADATA_FILE
+        ))
+    }
+    if metadata.license.is_none() {
+        warn(
+            Code::MissingMetadata,
+            &format!(
+                "No license in {}, so Zenodo's default is used",
+                METADATA_FILE
+            ),
+        );
+    }
+    let archive = args
+        .archive
+        .unwrap_or_else(|| archive_path(&root, &metadata));
+    if !archive.is_file() {
+        exit_with_error(&format!(
+            "No archive found at {:?}. Create it with `investigate finalize --archive`",
+            archive
+        ))
+    }
+    require_network("Publishing on Zenodo");
+    let token = std::env::var("ZENODO_TOKEN")
+        .ok()
+        .filter(|token| !token.is_empty())
+        .unwrap_or_else(|| {
+            exit_with_error("Set $ZENODO_TOKEN to a Zenodo API token with the deposit:write scope")
+        });
+    let host = if args.sandbox {
+        "https://sandbox.zenodo.org"
+    } else {
+        "https://zenodo.org"
+    };
+
+    let deposition = api_request(
+        "POST",
+        &format!("{}/api/deposit/depositions", host),
+        &token,
+        Some(&deposition_metadata(&metadata)),
+        None,
+    );
+    let field = |pointer: &str| {
+        deposition
+            .pointer(pointer)
+            .and_then(Value::as_str)
+            .map(str::to_owned)
+            .unwrap_or_else(|| exit_with_error("Unexpected response from Zenodo"))
+    };
+    let doi = field("/metadata/prereserve_doi/doi");
+    let bucket = field("/links/bucket");
+    let link = field("/links/html");
+    println!(
+        "Created draft deposition {} with the reserved DOI {}",
+        link, doi
+    );
+
+    let filename = archive.file_name().unwrap_or_default().to_string_lossy();
+    println!("Uploading {}", filename);
+    api_request(
+        "PUT",
+        &format!("{}/{}", bucket, filename),
+        &token,
+        None,
+        Some(&archive),
+    );
+
+    metadata.doi = Some(doi.clone());
+    metadata.save(&root);
+    write_citation(&root, &metadata, &doi);
+    set_doi(&root, &doi);
+    println!(
+        "Wrote the DOI to {}, {} and README.md. Commit them, then review and publish the \
+        deposition on Zenodo",
+        METADATA_FILE, CITATION_FILE
+    );
+}
+
+/// Get the ID of the license on OSF with the SPDX identifier `license`, if OSF has it.
+fn osf_license(license: &str, token: &str) -> Option<String> {
+    let (_, name) = OSF_LICENSES
+        .iter()
+        .find(|(spdx, _)| spdx.eq_ignore_ascii_case(license))?;
+    let licenses = api_request(
+        "GET",
+        "https://api.osf.io/v2/licenses/?page%5Bsize%5D=100",
+        token,
+        None,
+        None,
+    );
+    licenses
+        .get("data")?
+        .as_array()?
+        .iter()
+        .find(|l| l.pointer("/attributes/name").and_then(Value::as_str) == Some(name))?
+        .get("id")?
+        .as_str()
+        .map(str::to_owned)
+}
+
+fn osf(args: OsfArgs) {
+    let root = find_project_root();
+    let mut metadata = Metadata::load(&root);
+    if let Some(node) = &metadata.osf_node {
+        exit_with_error(&format!(
+            "Project already has the OSF project https://osf.io/{}",
+            node
+        ))
+    }
+    require_network("Publishing on OSF");
+    let token = std::env::var("OSF_TOKEN")
+        .ok()
+        .filter(|token| !token.is_empty())
+        .unwrap_or_else(|| {
+            exit_with_error("Set $OSF_TOKEN to an OSF personal access token with osf.full_write")
+        });
+    let mut attributes = json!({
+        // OSF calls analyses of an existing project "analysis" components
+        "category": if args.parent.is_some() { "analysis" } else { "project" },
+        "title": title(&metadata),
+        "public": args.public,
+        "tags": metadata.keywords,
+    });
+    if let Some(description) = &metadata.description {
+        attributes["description"] = json!(description);
+    }
+    let url = match &args.parent {
+        Some(parent) => format!("https://api.osf.io/v2/nodes/{}/children/", parent),
+        None => "https://api.osf.io/v2/nodes/".to_owned(),
+    };
+    let node = api_request(
+        "POST",
+        &url,
+        &token,
+        Some(&json!({ "data": { "type": "nodes", "attributes": attributes } })),
+        None,
+    );
+    let field = |pointer: &str| {
+        node.pointer(pointer)
+            .and_then(Value::as_str)
+            .map(str::to_owned)
+            .unwrap_or_else(|| exit_with_error("Unexpected response from OSF"))
+    };
+    let id = field("/data/id");
+    let link = field("/data/links/html");
+    println!(
+        "Created OSF {} {}",
+        if args.parent.is_some() {
+            "component"
+        } else {
+            "project"
+        },
+        link
+    );
+
+    match &metadata.license {
+        None => warn(
+            Code::MissingMetadata,
+            &format!("No license in {}, so none is set on OSF", METADATA_FILE),
+        ),
+        Some(license) => match osf_license(license, &token) {
+            None => warn(
+                Code::MissingMetadata,
+                &format!(
+                    "License {} is not known to investigate on OSF. Set it on OSF instead",
+                    license
+                ),
+            ),
+            Some(license_id) => {
+                let holders: Vec<String> = metadata
+                    .authors
+                    .iter()
+                    .map(|author| Person::from_author(author).name)
+                    .collect();
+                api_request(
+                    "PATCH",
+                    &format!("https://api.osf.io/v2/nodes/{}/", id),
+                    &token,
+                    Some(&json!({ "data": {
+                        "type": "nodes",
+                        "id": id,
+                        "attributes": { "node_license": {
+                            "year": Local::today().format("%Y").to_string(),
+                            "copyright_holders": holders,
+                        }},
+                        "relationships": { "license": {
+                            "data": { "type": "licenses", "id": license_id },
+                        }},
+                    }})),
+                    None,
+                );
+                println!("Set its license to {}", license);
+            }
+        },
+    }
+
+    metadata.osf_node = Some(id.clone());
+    if args.sync_results {
+        if let Some(remote) = &metadata.data_remote {
+            warn(
+                Code::RemoteReplaced,
+                &format!("Replacing the data remote {}", remote),
+            );
+        }
+        metadata.data_remote = Some(format!("osf://{}", id));
+        println!(
+            "Set the data remote to its OSF storage. Upload results/ with \
+            `investigate data push results`, which uses osfclient"
+        );
+    }
+    metadata.save(&root);
+    // OSF contributors are OSF users, which can't be found from a name and email
+    println!(
+        "Wrote the OSF project to {}. Add the other authors as contributors on OSF",
+        METADATA_FILE
+    );
+}
+
+pub fn run(command: PublishCommand) {
+    match command {
+        PublishCommand::Zenodo(args) => zenodo(args),
+        PublishCommand::Osf(args) => osf(args),
+    }
+}
@@ -0,0 +1,250 @@
+use chrono::Local;
+use clap::Subcommand;
+
+use std::fs::read_to_string;
+use std::path::{Path, PathBuf};
+
+use crate::directories::Directory;
+use crate::new::{write, Existing, Language, NewArgs};
+use crate::templates::{variables, Templates};
+use crate::{exit_with_error, filesystem, find_project_root};
+
+// The file index in the README is placed between these markers,
+// so it can be regenerated without touching the rest of the README.
+const INDEX_START: &str = "<!-- investigate:index:start -->";
+const INDEX_END: &str = "<!-- investigate:index:end -->";
+
+/// Badge added by --badges, until the project has a DOI
+const DOI_PLACEHOLDER: &str =
+    "[![DOI](https://img.shields.io/badge/DOI-TODO-blue)](https://doi.org/TODO)";
+
+/// Directories searched for files to put in the file index
+const INDEX_DIRECTORIES: [&str; 3] = ["src", "scripts", "notebooks"];
+
+/// Extensions of files put in the file index
+const INDEX_EXTENSIONS: [&str; 6] = ["py", "jl", "R", "ipynb", "sh", "qmd"];
+
+#[derive(Subcommand)]
+pub enum ReadmeCommand {
+    /// Regenerate the table of scripts and notebooks in README.md
+    Refresh,
+}
+
+fn badges(language: Option<Language>) -> String {
+    let mut badges =
+        vec!["![License](https://img.shields.io/badge/license-TODO-lightgrey)".to_owned()];
+    if let Some(language) = language {
+        let (name, color) = match language {
+            Language::Python => ("Python", "3776AB"),
+            Language::Julia => ("Julia", "9558B2"),
+        };
+        badges.push(format!(
+            "![Language](https://img.shields.io/badge/language-{}-{})",
+            name, color
+        ));
+    }
+    badges.push(DOI_PLACEHOLDER.to_owned());
+    badges.join(" ") + "\n\n"
+}
+
+/// Add a badge linking to `doi` to the README of the project at `root`, replacing the
+/// placeholder badge if there is one, else below the title.
+pub fn set_doi(root: &Path, doi: &str) {
+    let path = root.join("README.md");
+    let Ok(content) = read_to_string(&path) else {
+        return;
+    };
+    let badge = format!(
+        "[![DOI](https://zenodo.org/badge/DOI/{0}.svg)](https://doi.org/{0})",
+        doi
+    );
+    let content = if content.contains(DOI_PLACEHOLDER) {
+        content.replace(DOI_PLACEHOLDER, &badge)
+    } else if content.contains(&format!("https://doi.org/{}", doi)) {
+        return;
+    } else {
+        match content.split_once('\n') {
+            Some((title, rest)) if title.starts_with("# ") => {
+                format!("{}\n{}\n\n{}", title, badge, rest.trim_start_matches('\n'))
+            }
+            _ => format!("{}\n\n{}", badge, content),
+        }
+    };
+    write(&path, &content, Existing::Force);
+}
+
+pub fn make_readme(
+    path: &Path,
+    templates: &Templates,
+    project_name: &str,
+    author: &str,
+    args: &NewArgs,
+    directories: &[Directory],
+    existing: Existing,
+) {
+    let date = Local::today().format("%Y-%m-%d").to_string();
+
+    // Add top of Readme
+    let badges = if args.badges {
+        badges(args.language)
+    } else {
+        "".to_owned()
+    };
+    let author = format!("Author: {}\n", author);
+
+    // The directories are described from the directory config, in its order, and their
+    // section from a template, so it can be moved or translated
+    let list: Vec<String> = directories
+        .iter()
+        .map(|directory| match &directory.description {
+            Some(description) => format!("* `{}`: {}", directory.name, description),
+            None => format!("* `{}`", directory.name),
+        })
+        .collect();
+    let section = templates.render("readme_directories", &[("directories", &list.join("\n"))]);
+    let mut content = templates.render(
+        "readme",
+        &[
+            ("project_name", project_name),
+            ("badges", &badges),
+            ("author", &author),
+            ("date", &date),
+            ("directories", section.trim_end()),
+        ],
+    );
+    // README templates made before the section was a variable end before it
+    if !variables(&templates.get("readme"))
+        .iter()
+        .any(|v| v == "directories")
+    {
+        content.push_str(&section);
+    }
+    if args.scripts {
+        content.push('\n');
+        content.push_str(&templates.get("readme_scripts"));
+    }
+    if args.index {
+        content.push_str("\n## Files\n");
+        content.push_str(&file_index(path));
+    }
+    write(&path.join("README.md"), &content, existing)
+}
+
+/// Get a one-line description of a file from its first comment or docstring.
+fn describe(path: &Path) -> Option<String> {
+    let text = filesystem::current().read_to_string(path).ok()?;
+    let line = if path.extension().is_some_and(|e| e == "ipynb") {
+        describe_notebook(&text)?
+    } else {
+        let mut lines = text.lines().map(str::trim).filter(|l| !l.is_empty());
+        let mut line = lines.next()?;
+        if line.starts_with("#!") {
+            line = lines.next()?;
+        }
+        if let Some(docstring) = line.strip_prefix("\"\"\"").or(line.strip_prefix("'''")) {
+            // Docstring may start on the line after the quotes
+            if docstring.is_empty() {
+                lines.next()?
+            } else {
+                docstring
+            }
+            .trim_end_matches(['"', '\''])
+            .to_owned()
+        } else {
+            line.strip_prefix('#')?.trim_start_matches('#').to_owned()
+        }
+    };
+    // Only keep the first sentence
+    let line = line.trim();
+    let sentence = match line.find(". ") {
+        Some(i) => &line[..=i],
+        None => line,
+    };
+    (!sentence.is_empty()).then(|| sentence.to_owned())
+}
+
+/// Get the first line of the first markdown cell of a Jupyter notebook.
+fn describe_notebook(text: &str) -> Option<String> {
+    let json: serde_json::Value = serde_json::from_str(text).ok()?;
+    let cell = json
+        .get("cells")?
+        .as_array()?
+        .iter()
+        .find(|c| c.get("cell_type").and_then(|t| t.as_str()) == Some("markdown"))?;
+    let source = match cell.get("source")? {
+        serde_json::Value::String(s) => s.clone(),
+        serde_json::Value::Array(v) => v.iter().filter_map(|s| s.as_str()).collect(),
+        _ => return None,
+    };
+    let line = source.lines().map(str::trim).find(|l| !l.is_empty())?;
+    Some(line.trim_start_matches('#').to_owned())
+}
+
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    let fs = filesystem::current();
+    let Ok(paths) = fs.read_dir(dir) else {
+        return;
+    };
+    for path in paths {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        if name.starts_with('.') || name == "__pycache__" {
+            continue;
+        }
+        if fs.is_dir(&path) {
+            collect_files(&path, files)
+        } else if path
+            .extension()
+            .is_some_and(|e| INDEX_EXTENSIONS.iter().any(|x| e == *x))
+        {
+            files.push(path)
+        }
+    }
+}
+
+/// Create the file index, including its start and end markers.
+fn file_index(root: &Path) -> String {
+    let mut files = Vec::new();
+    for dir in INDEX_DIRECTORIES {
+        collect_files(&root.join(dir), &mut files);
+    }
+    files.sort();
+    let mut index = String::from(INDEX_START);
+    index.push('\n');
+    if files.is_empty() {
+        index.push_str("No scripts or notebooks yet.\n");
+    } else {
+        index.push_str("| File | Description |\n|------|-------------|\n");
+        for file in files {
+            let relative = file.strip_prefix(root).unwrap().to_string_lossy();
+            let description = describe(&file).unwrap_or_default().replace('|', "\\|");
+            index.push_str(&format!("| [`{0}`]({0}) | {1} |\n", relative, description));
+        }
+    }
+    index.push_str(INDEX_END);
+    index.push('\n');
+    index
+}
+
+fn refresh() {
+    let root = find_project_root();
+    let path = root.join("README.md");
+    let readme = read_to_string(&path)
+        .unwrap_or_else(|_| exit_with_error(&format!("Could not read {:?}", path)));
+    let index = file_index(&root);
+    let content = match (readme.find(INDEX_START), readme.find(INDEX_END)) {
+        (Some(start), Some(end)) if start < end => {
+            let mut rest = &readme[end + INDEX_END.len()..];
+            rest = rest.strip_prefix('\n').unwrap_or(rest);
+            format!("{}{}{}", &readme[..start], index, rest)
+        }
+        _ => format!("{}\n\n## Files\n{}", readme.trim_end(), index),
+    };
+    write(&path, &content, Existing::Force);
+    println!("Refreshed file index in {:?}", path);
+}
+
+pub fn run(command: ReadmeCommand) {
+    match command {
+        ReadmeCommand::Refresh => refresh(),
+    }
+}
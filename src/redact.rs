@@ -0,0 +1,174 @@
+use serde::Deserialize;
+
+use std::fs::read_to_string;
+use std::path::Path;
+use std::process::Command;
+
+use crate::exit_with_error;
+use crate::results::{delimiter, matches_pattern, parse_table};
+
+/// File in the project root with the rules of `investigate export archive --redact`
+pub const REDACTION_FILE: &str = "redaction.toml";
+
+/// Extensions of images which may carry EXIF and other identifying metadata
+const IMAGE_EXTENSIONS: [&str; 8] = ["jpg", "jpeg", "png", "tif", "tiff", "heic", "webp", "gif"];
+
+/// What to remove from files before they are shared. Patterns use `*` for any characters.
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct RedactionRules {
+    /// Columns of CSV and TSV files to drop, by patterns of their header, ignoring case
+    #[serde(default)]
+    pub drop_columns: Vec<String>,
+    /// Files to leave out, by patterns of their path relative to the project
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    /// Lines to remove from text files, by their start, like "@RG" in SAM headers
+    #[serde(default)]
+    pub strip_lines: Vec<String>,
+    /// Remove EXIF and other metadata from images, with exiftool
+    #[serde(default)]
+    pub strip_metadata: bool,
+}
+
+/// What was removed from a file.
+#[derive(Default)]
+pub struct Redaction {
+    pub columns: Vec<String>,
+    pub lines: usize,
+    pub metadata: bool,
+}
+
+impl Redaction {
+    pub fn is_empty(&self) -> bool {
+        self.columns.is_empty() && self.lines == 0 && !self.metadata
+    }
+
+    pub fn describe(&self) -> String {
+        let mut parts = Vec::new();
+        if !self.columns.is_empty() {
+            parts.push(format!("dropped columns {}", self.columns.join(", ")));
+        }
+        if self.lines > 0 {
+            parts.push(format!("removed {} lines", self.lines));
+        }
+        if self.metadata {
+            parts.push("stripped image metadata".to_owned());
+        }
+        parts.join(", ")
+    }
+}
+
+/// Quote `field` for a delimited table, if it needs it.
+pub fn quote_field(field: &str, delimiter: char) -> String {
+    if field.contains([delimiter, '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+impl RedactionRules {
+    /// Load the rules of the project at `root`, exiting if there are none.
+    pub fn load(root: &Path) -> RedactionRules {
+        let path = root.join(REDACTION_FILE);
+        let content = read_to_string(&path).unwrap_or_else(|_| {
+            exit_with_error(&format!(
+                "No redaction rules. Write them in {} in the project root",
+                REDACTION_FILE
+            ))
+        });
+        let rules: RedactionRules = toml::from_str(&content)
+            .unwrap_or_else(|e| exit_with_error(&format!("Could not parse {:?}: {}", path, e)));
+        if rules.strip_metadata
+            && !Command::new("exiftool")
+                .arg("-ver")
+                .output()
+                .is_ok_and(|output| output.status.success())
+        {
+            exit_with_error("strip_metadata needs exiftool, which could not be run")
+        }
+        rules
+    }
+
+    /// Check if the file at `relative`, relative to the project, should be left out.
+    pub fn is_excluded(&self, relative: &str) -> bool {
+        self.exclude
+            .iter()
+            .any(|pattern| matches_pattern(pattern, relative))
+    }
+
+    fn drops_column(&self, name: &str) -> bool {
+        let name = name.trim().to_lowercase();
+        self.drop_columns
+            .iter()
+            .any(|pattern| matches_pattern(&pattern.to_lowercase(), &name))
+    }
+
+    /// Redact the copy of a file at `path`, in place.
+    pub fn redact(&self, path: &Path) -> Result<Redaction, String> {
+        let mut redaction = Redaction::default();
+        let extension = path
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        if self.strip_metadata && IMAGE_EXTENSIONS.contains(&extension.as_str()) {
+            let status = Command::new("exiftool")
+                .args(["-q", "-all=", "-overwrite_original"])
+                .arg(path)
+                .status();
+            if !status.is_ok_and(|status| status.success()) {
+                return Err(format!("Could not strip the metadata of {:?}", path));
+            }
+            redaction.metadata = true;
+            return Ok(redaction);
+        }
+        // Binary files can't have lines or columns removed
+        let Ok(content) = read_to_string(path) else {
+            return Ok(redaction);
+        };
+        let mut kept: Vec<&str> = Vec::new();
+        for line in content.lines() {
+            if self
+                .strip_lines
+                .iter()
+                .any(|start| line.starts_with(start.as_str()))
+            {
+                redaction.lines += 1;
+            } else {
+                kept.push(line);
+            }
+        }
+        let mut content = kept.join("\n");
+        if !content.is_empty() {
+            content.push('\n');
+        }
+        if let Some(delimiter) = delimiter(path) {
+            let table = parse_table(&content, delimiter);
+            let header = table.first().cloned().unwrap_or_default();
+            let dropped: Vec<usize> = (0..header.len())
+                .filter(|&j| self.drops_column(&header[j]))
+                .collect();
+            if !dropped.is_empty() {
+                redaction.columns = dropped.iter().map(|&j| header[j].clone()).collect();
+                content = table
+                    .iter()
+                    .map(|row| {
+                        let fields: Vec<String> = row
+                            .iter()
+                            .enumerate()
+                            .filter(|(j, _)| !dropped.contains(j))
+                            .map(|(_, field)| quote_field(field, delimiter))
+                            .collect();
+                        fields.join(&delimiter.to_string()) + "\n"
+                    })
+                    .collect();
+            }
+        }
+        if !redaction.is_empty() {
+            std::fs::write(path, content)
+                .map_err(|e| format!("Could not write {:?}: {}", path, e))?;
+        }
+        Ok(redaction)
+    }
+}
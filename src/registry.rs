@@ -0,0 +1,79 @@
+use std::fs::{create_dir_all, read_to_string};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use crate::config::data_dir;
+use crate::filesystem::{self, Local};
+use crate::lock::{lock, write_atomic};
+use crate::warning::{warn, Code};
+
+/// Get the path of the registry file, which lists the paths of all projects created by investigate.
+fn registry_path() -> Option<PathBuf> {
+    Some(data_dir()?.join("registry"))
+}
+
+/// Load the absolute paths of all registered projects.
+pub fn load() -> Vec<PathBuf> {
+    registry_path()
+        .and_then(|path| read_to_string(path).ok())
+        .map(|content| {
+            content
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .map(PathBuf::from)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn save(projects: &[PathBuf]) {
+    let path = match registry_path() {
+        Some(path) => path,
+        None => {
+            warn(
+                Code::NoRegistry,
+                "Could not locate the project registry, as $HOME is not set",
+            );
+            return;
+        }
+    };
+    if let Some(parent) = path.parent() {
+        create_dir_all(parent)
+            .unwrap_or_else(|_| panic!("Error when creating directory {:?}", parent));
+    }
+    let mut content = String::new();
+    for project in projects {
+        content.push_str(&project.to_string_lossy());
+        content.push('\n');
+    }
+    // The registry is of this machine, also when the project is created on another
+    filesystem::with(Rc::new(Local), || write_atomic(&path, &content))
+        .unwrap_or_else(|_| panic!("Error when writing file {:?}", path))
+}
+
+/// Add a project to the registry, if it's not already there.
+pub fn register(project: &Path) {
+    let project = project
+        .canonicalize()
+        .unwrap_or_else(|_| project.to_owned());
+    let _lock = registry_path().map(|path| lock(&path));
+    let mut projects = load();
+    if !projects.contains(&project) {
+        projects.push(project);
+        save(&projects)
+    }
+}
+
+/// Change the path of a registered project. Returns whether the project was registered.
+pub fn replace(old: &Path, new: &Path) -> bool {
+    let _lock = registry_path().map(|path| lock(&path));
+    let mut projects = load();
+    match projects.iter_mut().find(|p| p.as_path() == old) {
+        Some(project) => {
+            *project = new.to_owned();
+            save(&projects);
+            true
+        }
+        None => false,
+    }
+}
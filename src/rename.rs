@@ -0,0 +1,398 @@
+use clap::Args;
+
+use std::fs::read_to_string;
+use std::path::{Path, PathBuf};
+
+use crate::env::conda_env_exists;
+use crate::metadata::{Metadata, METADATA_FILE};
+use crate::new::{
+    convert_name_to_command, convert_name_to_module, convert_name_to_package, Language,
+};
+use crate::warning::{warn, Code};
+use crate::{capitalize, exit_with_error, filesystem, find_project_root, registry, wsl};
+
+#[derive(Args)]
+pub struct RenameArgs {
+    /// New name of the project
+    new_name: String,
+
+    /// Only print what would be changed
+    #[clap(long)]
+    dry_run: bool,
+
+    /// Do not rename the project directory itself
+    #[clap(long)]
+    keep_dir: bool,
+}
+
+/// A single, reversible step of a rename.
+enum Action {
+    Edit {
+        path: PathBuf,
+        old: String,
+        new: String,
+    },
+    Move {
+        from: PathBuf,
+        to: PathBuf,
+    },
+    CondaRename {
+        root: PathBuf,
+        from: String,
+        to: String,
+    },
+    Registry {
+        from: PathBuf,
+        to: PathBuf,
+    },
+}
+
+impl Action {
+    fn describe(&self) -> String {
+        match self {
+            Action::Edit { path, .. } => format!("Edit {:?}", path),
+            Action::Move { from, to } => format!("Move {:?} to {:?}", from, to),
+            Action::CondaRename { from, to, .. } => {
+                format!("Rename Conda environment \"{}\" to \"{}\"", from, to)
+            }
+            Action::Registry { from, to } => {
+                format!("Change registered path {:?} to {:?}", from, to)
+            }
+        }
+    }
+
+    fn apply(&self) -> Result<(), String> {
+        match self {
+            Action::Edit { path, new, .. } => filesystem::write(path, new)
+                .map_err(|e| format!("Could not write {:?}: {}", path, e)),
+            Action::Move { from, to } => std::fs::rename(from, to)
+                .map_err(|e| format!("Could not move {:?} to {:?}: {}", from, to, e)),
+            Action::CondaRename { root, from, to } => conda_rename(root, from, to),
+            Action::Registry { from, to } => {
+                registry::replace(from, to);
+                Ok(())
+            }
+        }
+    }
+
+    fn undo(&self) {
+        let result = match self {
+            Action::Edit { path, old, .. } => filesystem::write(path, old)
+                .map_err(|e| format!("Could not restore {:?}: {}", path, e)),
+            Action::Move { from, to } => std::fs::rename(to, from)
+                .map_err(|e| format!("Could not move {:?} back to {:?}: {}", to, from, e)),
+            Action::CondaRename { root, from, to } => conda_rename(root, to, from),
+            Action::Registry { from, to } => {
+                registry::replace(to, from);
+                Ok(())
+            }
+        };
+        if let Err(message) = result {
+            warn(Code::UndoFailed, &message)
+        }
+    }
+}
+
+fn conda_rename(root: &Path, from: &str, to: &str) -> Result<(), String> {
+    match wsl::command("conda", root)
+        .args(["rename", "-n", from, to])
+        .output()
+    {
+        Ok(output) if output.status.success() => Ok(()),
+        _ => Err(format!(
+            "Could not rename Conda environment \"{}\" to \"{}\"",
+            from, to
+        )),
+    }
+}
+
+/// Replace all occurrences of the identifier `old` which are not part of a longer identifier.
+fn replace_identifier(content: &str, old: &str, new: &str) -> String {
+    let is_ident = |c: char| c.is_alphanumeric() || c == '_';
+    let mut result = String::with_capacity(content.len());
+    let mut rest = content;
+    while let Some(i) = rest.find(old) {
+        let before = rest[..i].chars().next_back();
+        let after = rest[i + old.len()..].chars().next();
+        result.push_str(&rest[..i]);
+        if before.is_some_and(is_ident) || after.is_some_and(is_ident) {
+            result.push_str(old);
+        } else {
+            result.push_str(new);
+        }
+        rest = &rest[i + old.len()..];
+    }
+    result.push_str(rest);
+    result
+}
+
+/// Add an edit of the file at `path` to `actions`, if the file exists and would change.
+fn plan_edit(actions: &mut Vec<Action>, path: PathBuf, edit: impl Fn(&str) -> String) {
+    if let Ok(old) = read_to_string(&path) {
+        let new = edit(&old);
+        if new != old {
+            actions.push(Action::Edit { path, old, new })
+        }
+    }
+}
+
+/// Add an edit of every file with the given extension in `dir` to `actions`.
+fn plan_dir_edits(
+    actions: &mut Vec<Action>,
+    dir: &Path,
+    extension: &str,
+    edit: impl Fn(&str) -> String,
+) {
+    let mut paths: Vec<PathBuf> = match dir.read_dir() {
+        Ok(entries) => entries.flatten().map(|e| e.path()).collect(),
+        Err(_) => return,
+    };
+    paths.sort();
+    for path in paths {
+        if path.extension().is_some_and(|e| e == extension) {
+            plan_edit(actions, path, &edit)
+        }
+    }
+}
+
+/// Replace the value of the first line of a TOML file setting `key`.
+pub fn replace_toml_value(content: &str, key: &str, value: &str) -> String {
+    let mut found = false;
+    let mut result = String::with_capacity(content.len());
+    for line in content.lines() {
+        let is_key = line.split_once('=').is_some_and(|(k, _)| k.trim() == key);
+        if !found && is_key {
+            found = true;
+            result.push_str(&format!("{} = \"{}\"", key, value));
+        } else {
+            result.push_str(line);
+        }
+        result.push('\n');
+    }
+    result
+}
+
+/// Plan the edits and moves of the files in the project at `root` which rename it to `new_name`.
+fn plan_files(root: &Path, metadata: &Metadata, new_name: &str) -> Vec<Action> {
+    let old_name = metadata.name.as_str();
+    let mut actions = Vec::new();
+
+    let mut new_metadata = metadata.clone();
+    new_metadata.name = new_name.to_owned();
+    if new_metadata.environment.as_deref() == Some(old_name) {
+        new_metadata.environment = Some(new_name.to_owned());
+    }
+    plan_edit(&mut actions, root.join(METADATA_FILE), |_| {
+        new_metadata.to_toml()
+    });
+
+    // The capitalized name is used as title in the README and docs
+    let replace_title =
+        |content: &str| content.replace(&capitalize(old_name), &capitalize(new_name));
+
+    plan_edit(&mut actions, root.join("README.md"), |content| {
+        let old_title = format!("# {}", capitalize(old_name));
+        match content.strip_prefix(&old_title) {
+            Some(rest) if rest.starts_with('\n') => format!("# {}{}", capitalize(new_name), rest),
+            _ => content.to_owned(),
+        }
+    });
+
+    match metadata.language {
+        Some(Language::Julia) => {
+            let old_module = convert_name_to_module(old_name);
+            let new_module = convert_name_to_module(new_name);
+            let replace = |content: &str| replace_identifier(content, &old_module, &new_module);
+            let old_file = root.join("src").join(old_module.clone() + ".jl");
+            plan_edit(&mut actions, old_file.clone(), replace);
+            plan_edit(&mut actions, root.join("src").join("params.jl"), replace);
+            if old_file.exists() {
+                actions.push(Action::Move {
+                    from: old_file,
+                    to: root.join("src").join(new_module.clone() + ".jl"),
+                });
+            }
+            plan_edit(&mut actions, root.join("Project.toml"), |content| {
+                replace_toml_value(content, "name", &new_module)
+            });
+            plan_dir_edits(&mut actions, &root.join("scripts"), "jl", replace);
+            plan_edit(&mut actions, root.join("docs").join("make.jl"), |content| {
+                replace(&replace_title(content))
+            });
+            plan_edit(
+                &mut actions,
+                root.join("docs").join("src").join("index.md"),
+                replace_title,
+            );
+        }
+        Some(Language::Python) => {
+            let old_package = convert_name_to_package(old_name);
+            let new_package = convert_name_to_package(new_name);
+            let replace = |content: &str| replace_identifier(content, &old_package, &new_package);
+            let old_dir = root.join("src").join(&old_package);
+            let (old_command, new_command) = (
+                convert_name_to_command(old_name),
+                convert_name_to_command(new_name),
+            );
+            plan_edit(&mut actions, old_dir.join("cli.py"), |content| {
+                replace_identifier(content, &old_command, &new_command)
+            });
+            plan_edit(&mut actions, old_dir.join("params.py"), replace);
+            if old_dir.is_dir() {
+                actions.push(Action::Move {
+                    from: old_dir,
+                    to: root.join("src").join(&new_package),
+                });
+            }
+            let old_script = format!("{} = \"{}.cli:main\"", old_command, old_package);
+            let new_script = format!("{} = \"{}.cli:main\"", new_command, new_package);
+            plan_edit(&mut actions, root.join("pyproject.toml"), |content| {
+                replace_toml_value(content, "name", &new_package).replace(&old_script, &new_script)
+            });
+            plan_dir_edits(&mut actions, &root.join("scripts"), "py", replace);
+            plan_edit(&mut actions, root.join("mkdocs.yml"), replace_title);
+            plan_edit(
+                &mut actions,
+                root.join("docs").join("index.md"),
+                replace_title,
+            );
+            for ci_file in [
+                root.join(".github").join("workflows").join("ci.yml"),
+                root.join(".gitlab-ci.yml"),
+            ] {
+                plan_edit(&mut actions, ci_file, |content| {
+                    replace_identifier(content, old_name, new_name)
+                });
+            }
+            plan_edit(&mut actions, root.join("environment.yml"), |content| {
+                content
+                    .lines()
+                    .map(|line| {
+                        if line.trim() == format!("name: {}", old_name) {
+                            format!("name: {}", new_name)
+                        } else if line.starts_with("prefix:") && line.ends_with(old_name) {
+                            format!("{}{}", &line[..line.len() - old_name.len()], new_name)
+                        } else {
+                            line.to_owned()
+                        }
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            });
+        }
+        None => (),
+    }
+    actions
+}
+
+/// Rename the project at `root` to `new_name` in its files only, leaving its directory, Conda
+/// environment and registration alone, as for a copy of a project.
+pub fn rename_files(root: &Path, metadata: &Metadata, new_name: &str) -> Result<(), String> {
+    for action in plan_files(root, metadata, new_name) {
+        action.apply()?;
+    }
+    Ok(())
+}
+
+fn plan(root: &Path, metadata: &Metadata, args: &RenameArgs) -> Vec<Action> {
+    let old_name = metadata.name.as_str();
+    let new_name = args.new_name.as_str();
+    let mut actions = plan_files(root, metadata, new_name);
+    if metadata.language == Some(Language::Python) && conda_env_exists(root, old_name) {
+        actions.push(Action::CondaRename {
+            root: root.to_owned(),
+            from: old_name.to_owned(),
+            to: new_name.to_owned(),
+        });
+    }
+
+    // Renaming the directory must come last, since the above paths are inside it
+    let mut new_root = root.to_owned();
+    if !args.keep_dir && root.file_name().is_some_and(|n| n == old_name) {
+        new_root = root.with_file_name(new_name);
+        actions.push(Action::Move {
+            from: root.to_owned(),
+            to: new_root.clone(),
+        });
+    }
+    if registry::load().iter().any(|p| p == root) {
+        actions.push(Action::Registry {
+            from: root.to_owned(),
+            to: new_root,
+        });
+    }
+    actions
+}
+
+pub fn run(args: RenameArgs) {
+    let root = find_project_root();
+    let metadata = Metadata::load(&root);
+    if args.new_name.is_empty() {
+        exit_with_error("Project name cannot be empty")
+    }
+    // The name becomes the name of the project's directory, so it must be a single component
+    if args.new_name == "." || args.new_name == ".." || args.new_name.contains(['/', '\\']) {
+        exit_with_error(&format!(
+            "Project name \"{}\" cannot be a path; give only a name",
+            args.new_name
+        ))
+    }
+    if args.new_name == metadata.name {
+        exit_with_error(&format!("Project is already named \"{}\"", metadata.name))
+    }
+    let actions = plan(&root, &metadata, &args);
+    for action in &actions {
+        if let Action::Move { to, .. } = action {
+            if to.exists() {
+                exit_with_error(&format!("Cannot rename: {:?} already exists", to))
+            }
+        }
+    }
+    if args.dry_run {
+        println!(
+            "Renaming \"{}\" to \"{}\" would:",
+            metadata.name, args.new_name
+        );
+        for action in &actions {
+            println!("  {}", action.describe());
+        }
+        return;
+    }
+    for (i, action) in actions.iter().enumerate() {
+        if let Err(message) = action.apply() {
+            // Undo everything done so far, so the project is never left half-renamed
+            for done in actions[..i].iter().rev() {
+                done.undo();
+            }
+            exit_with_error(&format!("{}. No changes were made.", message))
+        }
+    }
+    println!(
+        "Renamed project \"{}\" to \"{}\"",
+        metadata.name, args.new_name
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replaces_whole_identifiers_only() {
+        assert_eq!(
+            replace_identifier("import rna_seq\nrna_seq.run()", "rna_seq", "atac"),
+            "import atac\natac.run()"
+        );
+        assert_eq!(
+            replace_identifier("rna_seq_utils, my_rna_seq, rna_seq2", "rna_seq", "atac"),
+            "rna_seq_utils, my_rna_seq, rna_seq2"
+        );
+        assert_eq!(
+            replace_identifier("\"rna_seq\" (rna_seq)", "rna_seq", "atac"),
+            "\"atac\" (atac)"
+        );
+        // Identifiers may contain letters other than ASCII
+        assert_eq!(replace_identifier("proj projé", "proj", "new"), "new projé");
+        assert_eq!(replace_identifier("", "proj", "new"), "");
+    }
+}
@@ -0,0 +1,65 @@
+use chrono::Local;
+use clap::Args;
+
+use crate::author::author_string;
+use crate::config::Config;
+use crate::data::DATA_DIRECTORIES;
+use crate::decisions::{self, DECISIONS_FILE};
+use crate::finalize::{set_readonly, FINALIZED_FILE};
+use crate::git::{commit_project_files, tag_head};
+use crate::metadata::{Metadata, Reopening, METADATA_FILE};
+use crate::templates::Templates;
+use crate::{exit_with_error, find_project_root};
+
+#[derive(Args)]
+pub struct ReopenArgs {
+    /// Why the project is reopened, e.g. "Reanalysis requested by reviewer 2"
+    #[clap(short, long)]
+    message: String,
+}
+
+pub fn run(args: ReopenArgs) {
+    let root = find_project_root();
+    let mut metadata = Metadata::load(&root);
+    if !root.join(FINALIZED_FILE).exists() {
+        exit_with_error("Project is not finalized")
+    }
+    let config = Config::load();
+    let templates = Templates::new(Vec::new(), &config);
+    let author = author_string(&config);
+
+    // Mark the finalized state before anything changes
+    let tag = tag_head(
+        &root,
+        &format!("pre-reopen-{}", metadata.reopened.len() + 1),
+        &format!("State before reopening: {}", args.message),
+    );
+    for dir in DATA_DIRECTORIES {
+        set_readonly(&root.join(dir), false);
+    }
+    std::fs::remove_file(root.join(FINALIZED_FILE))
+        .unwrap_or_else(|_| panic!("Error when removing {}", FINALIZED_FILE));
+    metadata.reopened.push(Reopening {
+        date: Local::today().format("%Y-%m-%d").to_string(),
+        author: author.clone(),
+        reason: args.message.clone(),
+    });
+    metadata.save(&root);
+    decisions::record(
+        &root,
+        &templates,
+        "Project reopened",
+        &author,
+        &format!("{}\n\nThe finalized state is tagged {}.", args.message, tag),
+    );
+    commit_project_files(
+        &root,
+        &[FINALIZED_FILE, METADATA_FILE, DECISIONS_FILE],
+        &format!("Reopen project\n\n{}", args.message),
+    );
+    println!(
+        "Reopened project. The finalized state is tagged {}, and {} are writable again",
+        tag,
+        DATA_DIRECTORIES.join(" and ")
+    );
+}
@@ -0,0 +1,303 @@
+use chrono::{Local, TimeZone};
+use clap::{ArgEnum, Args};
+use git2::Repository;
+
+use std::fs::read_to_string;
+use std::path::{Path, PathBuf};
+
+use crate::data::sha256;
+use crate::decisions::DECISIONS_FILE;
+use crate::env::spec_file;
+use crate::git::is_clean;
+use crate::metadata::Metadata;
+use crate::new::Language;
+use crate::{exit_with_error, find_project_root};
+
+#[derive(Copy, Clone, PartialEq, Eq, ArgEnum)]
+enum Format {
+    Markdown,
+    Html,
+}
+
+#[derive(Args)]
+pub struct ReportArgs {
+    /// Format of the report
+    #[clap(arg_enum, value_parser, long, default_value = "markdown")]
+    format: Format,
+
+    /// Write the report to this file instead of stdout
+    #[clap(short, long)]
+    output: Option<PathBuf>,
+
+    /// Maximum number of git commits to include
+    #[clap(long, default_value = "20")]
+    commits: usize,
+
+    /// Don't compute checksums of the raw data, which may be slow for large datasets
+    #[clap(long)]
+    no_checksums: bool,
+}
+
+/// Get all files in `dir` recursively, except hidden files, in sorted order.
+pub fn walk_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let mut entries: Vec<PathBuf> = match dir.read_dir() {
+        Ok(entries) => entries.flatten().map(|e| e.path()).collect(),
+        Err(_) => return files,
+    };
+    entries.sort();
+    for path in entries {
+        let hidden = path
+            .file_name()
+            .is_some_and(|n| n.to_string_lossy().starts_with('.'));
+        if hidden {
+            continue;
+        }
+        if path.is_dir() {
+            files.extend(walk_files(&path));
+        } else {
+            files.push(path);
+        }
+    }
+    files
+}
+
+fn metadata_section(metadata: &Metadata) -> String {
+    let mut lines = vec![
+        "## Project".to_owned(),
+        format!("- Name: {}", metadata.name),
+        format!("- UUID: {}", metadata.uuid),
+        format!("- Created: {}", metadata.created),
+        format!("- Version: {}", metadata.version),
+        format!(
+            "- Created with: investigate {}",
+            metadata.investigate_version
+        ),
+    ];
+    if !metadata.authors.is_empty() {
+        lines.push(format!("- Authors: {}", metadata.authors.join(", ")));
+    }
+    if let Some(seed) = metadata.seed {
+        lines.push(format!("- Seed: {}", seed));
+    }
+    if let Some(remote) = &metadata.data_remote {
+        lines.push(format!("- Data remote: {}", remote));
+    }
+    lines.join("\n")
+}
+
+/// The entries of a log like the changelog, without its preamble, under the header `title`.
+fn log_section(root: &Path, file: &str, title: &str) -> Option<String> {
+    let log = read_to_string(root.join(file)).ok()?;
+    let start = log.find("\n## ")?;
+    // Demote the log's headers below the report's section header
+    let entries = log[start + 1..].replace("\n## ", "\n### ");
+    Some(format!("## {}\n#{}", title, entries.trim_end()))
+}
+
+pub fn environment_section(root: &Path, metadata: &Metadata) -> String {
+    let mut lines = vec!["## Environment".to_owned()];
+    if let Some(name) = &metadata.environment {
+        lines.push(format!("- Conda environment: {}", name));
+        let spec = spec_file(root);
+        let lock = root.join("environment.lock.yml");
+        lines.push(match spec {
+            None => "- Spec file: missing".to_owned(),
+            Some(spec) if spec == lock => "- Spec file: environment.lock.yml (locked)".to_owned(),
+            Some(_) => "- Spec file: environment.yml (not locked)".to_owned(),
+        });
+    }
+    if metadata.language == Some(Language::Julia) {
+        lines.push(if root.join("Manifest.toml").is_file() {
+            "- Julia: Project.toml and Manifest.toml (locked)".to_owned()
+        } else {
+            "- Julia: Project.toml (not locked, Manifest.toml is missing)".to_owned()
+        });
+    }
+    if lines.len() == 1 {
+        lines.push("No environment.".to_owned());
+    }
+    lines.join("\n")
+}
+
+fn raw_data_section(root: &Path, checksums: bool) -> String {
+    let raw = root.join("raw");
+    let files = walk_files(&raw);
+    let mut lines = vec!["## Raw data".to_owned()];
+    let (restricted, data): (Vec<_>, Vec<_>) = files
+        .iter()
+        .partition(|f| f.to_string_lossy().ends_with(".ACCESS.md"));
+    if data.is_empty() {
+        lines.push("No raw data files.".to_owned());
+    } else {
+        lines.push("| File | Size (bytes) | SHA-256 |".to_owned());
+        lines.push("|---|---|---|".to_owned());
+        for file in data {
+            let size = file.metadata().map(|m| m.len()).unwrap_or_default();
+            let checksum = if checksums {
+                sha256(file).unwrap_or_else(|_| "(unreadable)".to_owned())
+            } else {
+                "".to_owned()
+            };
+            lines.push(format!(
+                "| {} | {} | {} |",
+                file.strip_prefix(&raw).unwrap_or(file).display(),
+                size,
+                checksum
+            ));
+        }
+    }
+    if !restricted.is_empty() {
+        lines.push(String::new());
+        lines.push("Restricted datasets, documented in:".to_owned());
+        for file in restricted {
+            lines.push(format!(
+                "- raw/{}",
+                file.strip_prefix(&raw).unwrap_or(file).display()
+            ));
+        }
+    }
+    lines.join("\n")
+}
+
+fn git_section(root: &Path, max_commits: usize) -> String {
+    let mut lines = vec!["## Git history".to_owned()];
+    let repo = match Repository::discover(root) {
+        Ok(repo) => repo,
+        Err(_) => {
+            lines.push("Not a git repository.".to_owned());
+            return lines.join("\n");
+        }
+    };
+    let clean = is_clean(&repo);
+    lines.push(format!(
+        "Working tree: {}",
+        if clean {
+            "clean"
+        } else {
+            "has uncommitted changes"
+        }
+    ));
+    lines.push(String::new());
+    let mut walk = match repo.revwalk() {
+        Ok(walk) => walk,
+        Err(_) => return lines.join("\n"),
+    };
+    if walk.push_head().is_err() {
+        lines.push("No commits.".to_owned());
+        return lines.join("\n");
+    }
+    for oid in walk.flatten().take(max_commits) {
+        let commit = repo
+            .find_commit(oid)
+            .expect("Error when reading git commit");
+        let date = Local
+            .timestamp(commit.time().seconds(), 0)
+            .format("%Y-%m-%d");
+        lines.push(format!(
+            "- `{}` {} {}",
+            &oid.to_string()[..8],
+            date,
+            commit.summary().unwrap_or_default()
+        ));
+    }
+    lines.join("\n")
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Convert the subset of markdown used in the report to HTML.
+fn to_html(title: &str, markdown: &str) -> String {
+    let mut body = Vec::new();
+    let mut in_list = false;
+    let mut in_table = false;
+    for line in markdown.lines() {
+        let is_item = line.starts_with("- ");
+        let is_row = line.starts_with('|');
+        if in_list && !is_item {
+            body.push("</ul>".to_owned());
+            in_list = false;
+        }
+        if in_table && !is_row {
+            body.push("</table>".to_owned());
+            in_table = false;
+        }
+        let line = escape_html(line);
+        if let Some(item) = line.strip_prefix("- ") {
+            if !in_list {
+                body.push("<ul>".to_owned());
+                in_list = true;
+            }
+            body.push(format!("<li>{}</li>", item));
+        } else if is_row {
+            let cells: Vec<&str> = line.trim_matches('|').split('|').map(str::trim).collect();
+            if cells.iter().all(|c| c.chars().all(|ch| ch == '-')) {
+                continue;
+            }
+            let tag = if in_table { "td" } else { "th" };
+            if !in_table {
+                body.push("<table>".to_owned());
+                in_table = true;
+            }
+            let cells: Vec<String> = cells
+                .iter()
+                .map(|c| format!("<{0}>{1}</{0}>", tag, c))
+                .collect();
+            body.push(format!("<tr>{}</tr>", cells.join("")));
+        } else if let Some(header) = line.strip_prefix("### ") {
+            body.push(format!("<h3>{}</h3>", header));
+        } else if let Some(header) = line.strip_prefix("## ") {
+            body.push(format!("<h2>{}</h2>", header));
+        } else if let Some(header) = line.strip_prefix("# ") {
+            body.push(format!("<h1>{}</h1>", header));
+        } else if !line.trim().is_empty() {
+            body.push(format!("<p>{}</p>", line));
+        }
+    }
+    if in_list {
+        body.push("</ul>".to_owned());
+    }
+    if in_table {
+        body.push("</table>".to_owned());
+    }
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n\
+        </head>\n<body>\n{}\n</body>\n</html>\n",
+        escape_html(title),
+        body.join("\n")
+    )
+}
+
+pub fn run(args: ReportArgs) {
+    let root = find_project_root();
+    let metadata = Metadata::load(&root);
+    let title = format!("Reproducibility report: {}", metadata.name);
+    let mut sections = vec![
+        format!(
+            "# {}\nGenerated {} by investigate {}.",
+            title,
+            Local::now().format("%Y-%m-%d %H:%M"),
+            env!("CARGO_PKG_VERSION")
+        ),
+        metadata_section(&metadata),
+    ];
+    sections.extend(log_section(&root, "CHANGELOG.md", "Milestones"));
+    sections.extend(log_section(&root, DECISIONS_FILE, "Decisions"));
+    sections.push(environment_section(&root, &metadata));
+    sections.push(raw_data_section(&root, !args.no_checksums));
+    sections.push(git_section(&root, args.commits));
+    let markdown = sections.join("\n\n") + "\n";
+    let report = match args.format {
+        Format::Markdown => markdown,
+        Format::Html => to_html(&title, &markdown),
+    };
+    match args.output {
+        None => print!("{}", report),
+        Some(path) => std::fs::write(&path, report)
+            .unwrap_or_else(|_| exit_with_error(&format!("Could not write {:?}", path))),
+    }
+}
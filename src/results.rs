@@ -0,0 +1,805 @@
+use chrono::Local;
+use clap::{Args, Subcommand};
+use git2::Repository;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use std::collections::BTreeSet;
+use std::fs::read_to_string;
+use std::path::{Path, PathBuf};
+
+use crate::config::Config;
+use crate::data::{hex, sha256};
+use crate::figure::Figures;
+use crate::finalize::FINALIZED_FILE;
+use crate::report::walk_files;
+use crate::trash::trash;
+use crate::warning::{warn, Code};
+use crate::{exit_with_error, filesystem, find_project_root};
+
+/// Maximum number of differing cells shown per table
+const MAX_CELL_DIFFERENCES: usize = 10;
+
+/// Name of the snapshot of the blessed results, in the project root
+pub const SNAPSHOT_FILE: &str = "results.snapshot.toml";
+
+#[derive(Subcommand)]
+pub enum ResultsCommand {
+    /// Compare two results directories, e.g. from before and after a rerun
+    Diff(DiffArgs),
+
+    /// Record the checksums and summary statistics of the current results as blessed
+    Freeze(FreezeArgs),
+
+    /// Compare results against the frozen snapshot, with the tolerances in the snapshot
+    Check(CheckArgs),
+
+    /// Delete old timestamped directories in results/, following the retention policy in the
+    /// config file
+    Prune(PruneArgs),
+}
+
+#[derive(Args)]
+pub struct DiffArgs {
+    a: PathBuf,
+
+    b: PathBuf,
+
+    #[clap(flatten)]
+    tolerance: Tolerance,
+}
+
+#[derive(Args)]
+pub struct FreezeArgs {
+    /// Directory of the results, relative to the project root
+    #[clap(default_value = "results")]
+    dir: PathBuf,
+}
+
+#[derive(Args)]
+pub struct CheckArgs {
+    /// Directory of the fresh results (default: the directory which was frozen)
+    dir: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct PruneArgs {
+    /// Only list the directories which would be deleted
+    #[clap(long)]
+    dry_run: bool,
+
+    /// Keep the N newest directories, overriding `keep_last` in the config file
+    #[clap(long, value_name = "N")]
+    keep_last: Option<usize>,
+}
+
+/// Tolerance when comparing numbers in tables, like numpy's `isclose`.
+#[derive(Args, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct Tolerance {
+    /// Numbers in CSV and TSV files differing by at most this much are equal
+    #[clap(long, default_value = "0")]
+    #[serde(default)]
+    abs_tol: f64,
+
+    /// Numbers in CSV and TSV files differing by at most this fraction of the larger are equal
+    #[clap(long, default_value = "0")]
+    #[serde(default)]
+    rel_tol: f64,
+}
+
+impl Tolerance {
+    fn close(&self, x: f64, y: f64) -> bool {
+        x == y
+            || (x - y).abs() <= self.abs_tol.max(self.rel_tol * x.abs().max(y.abs()))
+            || (x.is_nan() && y.is_nan())
+    }
+
+    fn equal(&self, a: &str, b: &str) -> bool {
+        if a == b {
+            return true;
+        }
+        match (a.trim().parse::<f64>(), b.trim().parse::<f64>()) {
+            (Ok(x), Ok(y)) => self.close(x, y),
+            _ => false,
+        }
+    }
+}
+
+/// The tolerance for the files matching a pattern, e.g. "tables/*.csv".
+#[derive(Serialize, Deserialize)]
+pub struct FileTolerance {
+    /// Pattern of paths relative to the results directory, where `*` matches anything
+    pub pattern: String,
+    #[serde(flatten)]
+    pub tolerance: Tolerance,
+}
+
+/// Summary of a column of a table.
+#[derive(Serialize, Deserialize)]
+pub struct Column {
+    pub name: String,
+    /// Statistics of the values, if they are all numbers
+    pub mean: Option<f64>,
+    pub sd: Option<f64>,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    /// Checksum of the values, if they are not all numbers
+    pub sha256: Option<String>,
+}
+
+/// A file of the frozen results.
+#[derive(Serialize, Deserialize)]
+pub struct FrozenFile {
+    /// Path relative to the results directory
+    pub path: String,
+    /// Size in bytes
+    pub size: u64,
+    /// Hex-encoded SHA-256 checksum
+    pub sha256: String,
+    /// Number of rows including the header, for CSV and TSV files
+    pub rows: Option<usize>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub columns: Vec<Column>,
+}
+
+/// A snapshot of blessed results, made with `investigate results freeze`.
+#[derive(Serialize, Deserialize)]
+pub struct Snapshot {
+    /// Directory of the results, relative to the project root
+    pub dir: String,
+    /// Date in YYYY-MM-DD format
+    pub frozen: String,
+    /// The git commit HEAD was at when the results were frozen
+    pub commit: Option<String>,
+    /// Tolerances of numbers in tables, by the first matching pattern. Other files must be equal.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tolerance: Vec<FileTolerance>,
+    #[serde(default)]
+    pub file: Vec<FrozenFile>,
+}
+
+impl Snapshot {
+    pub fn load(root: &Path) -> Option<Snapshot> {
+        let path = root.join(SNAPSHOT_FILE);
+        let content = read_to_string(&path).ok()?;
+        Some(
+            toml::from_str(&content)
+                .unwrap_or_else(|e| exit_with_error(&format!("Could not parse {:?}: {}", path, e))),
+        )
+    }
+
+    pub fn save(&self, root: &Path) {
+        let path = root.join(SNAPSHOT_FILE);
+        let content = toml::to_string_pretty(self).expect("Error when serializing snapshot");
+        filesystem::write(&path, content)
+            .unwrap_or_else(|_| panic!("Error when writing file {:?}", path))
+    }
+
+    /// Get the tolerance of the file at `path`, relative to the results directory.
+    fn tolerance(&self, path: &str) -> Tolerance {
+        self.tolerance
+            .iter()
+            .find(|t| matches_pattern(&t.pattern, path))
+            .map(|t| t.tolerance)
+            .unwrap_or_default()
+    }
+}
+
+/// Check if `path` matches `pattern`, where `*` matches any sequence of characters.
+pub fn matches_pattern(pattern: &str, path: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = path.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(i) => rest = &rest[i + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
+/// Get the delimiter of `path` if it's a table which can be compared cell by cell.
+pub fn delimiter(path: &Path) -> Option<char> {
+    match path.extension()?.to_str()? {
+        "csv" => Some(','),
+        "tsv" => Some('\t'),
+        _ => None,
+    }
+}
+
+/// Parse the delimited table `content`, with fields optionally quoted by `"`.
+pub fn parse_table(content: &str, delimiter: char) -> Vec<Vec<String>> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = content.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' if quoted || field.is_empty() => quoted = !quoted,
+            c if quoted => field.push(c),
+            c if c == delimiter => row.push(std::mem::take(&mut field)),
+            '\r' => (),
+            '\n' => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+            }
+            c => field.push(c),
+        }
+    }
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+    rows
+}
+
+/// Summarize the columns of `table`, the first row of which is the header.
+fn summarize_columns(table: &[Vec<String>]) -> Vec<Column> {
+    let Some((header, rows)) = table.split_first() else {
+        return Vec::new();
+    };
+    let n_columns = table.iter().map(Vec::len).max().unwrap_or_default();
+    (0..n_columns)
+        .map(|j| {
+            let values: Vec<&str> = rows
+                .iter()
+                .map(|row| row.get(j).map(String::as_str).unwrap_or_default())
+                .collect();
+            let numbers: Option<Vec<f64>> = values
+                .iter()
+                .map(|value| value.trim().parse::<f64>().ok())
+                .collect();
+            let name = header
+                .get(j)
+                .cloned()
+                .unwrap_or_else(|| (j + 1).to_string());
+            match numbers {
+                Some(numbers) if !numbers.is_empty() => {
+                    let n = numbers.len() as f64;
+                    let mean = numbers.iter().sum::<f64>() / n;
+                    let variance = numbers.iter().map(|x| (x - mean).powi(2)).sum::<f64>()
+                        / (n - 1.0).max(1.0);
+                    Column {
+                        name,
+                        mean: Some(mean),
+                        sd: Some(variance.sqrt()),
+                        min: Some(numbers.iter().copied().fold(f64::INFINITY, f64::min)),
+                        max: Some(numbers.iter().copied().fold(f64::NEG_INFINITY, f64::max)),
+                        sha256: None,
+                    }
+                }
+                _ => Column {
+                    name,
+                    mean: None,
+                    sd: None,
+                    min: None,
+                    max: None,
+                    sha256: Some(hex(&Sha256::digest(values.join("\n")))),
+                },
+            }
+        })
+        .collect()
+}
+
+/// Get the differences between the tables `a` and `b`, outside the tolerance.
+pub fn compare_tables(a: &str, b: &str, delimiter: char, tolerance: Tolerance) -> Vec<String> {
+    let a = parse_table(a, delimiter);
+    let b = parse_table(b, delimiter);
+    let mut differences = Vec::new();
+    if a.len() != b.len() {
+        differences.push(format!("rows: {} -> {}", a.len(), b.len()));
+    }
+    let header = a.first().cloned().unwrap_or_default();
+    let mut n_cells = 0;
+    for (i, (row_a, row_b)) in a.iter().zip(&b).enumerate() {
+        for j in 0..row_a.len().max(row_b.len()) {
+            let cell_a = row_a.get(j).map(String::as_str);
+            let cell_b = row_b.get(j).map(String::as_str);
+            let equal = match (cell_a, cell_b) {
+                (Some(x), Some(y)) => tolerance.equal(x, y),
+                _ => false,
+            };
+            if equal {
+                continue;
+            }
+            n_cells += 1;
+            if n_cells <= MAX_CELL_DIFFERENCES {
+                let column = match header.get(j) {
+                    Some(name) if i > 0 && !name.is_empty() => format!("\"{}\"", name),
+                    _ => (j + 1).to_string(),
+                };
+                differences.push(format!(
+                    "row {}, column {}: {} -> {}",
+                    i + 1,
+                    column,
+                    cell_a.unwrap_or("(missing)"),
+                    cell_b.unwrap_or("(missing)")
+                ));
+            }
+        }
+    }
+    if n_cells > MAX_CELL_DIFFERENCES {
+        differences.push(format!(
+            "... and {} more differing cells",
+            n_cells - MAX_CELL_DIFFERENCES
+        ));
+    }
+    differences
+}
+
+/// Get the paths of the files in `dir`, relative to it.
+fn relative_files(dir: &Path) -> BTreeSet<PathBuf> {
+    walk_files(dir)
+        .into_iter()
+        .filter_map(|file| file.strip_prefix(dir).ok().map(Path::to_path_buf))
+        .collect()
+}
+
+fn diff(args: DiffArgs) {
+    for dir in [&args.a, &args.b] {
+        if !dir.is_dir() {
+            exit_with_error(&format!("{:?} is not a directory", dir))
+        }
+    }
+    let files_a = relative_files(&args.a);
+    let files_b = relative_files(&args.b);
+    let mut n_differing = 0;
+    for file in files_a.difference(&files_b) {
+        println!("- {}", file.display());
+        n_differing += 1;
+    }
+    for file in files_b.difference(&files_a) {
+        println!("+ {}", file.display());
+        n_differing += 1;
+    }
+    for file in files_a.intersection(&files_b) {
+        let (path_a, path_b) = (args.a.join(file), args.b.join(file));
+        let checksum = |path: &Path| {
+            sha256(path).unwrap_or_else(|_| exit_with_error(&format!("Could not read {:?}", path)))
+        };
+        if checksum(&path_a) == checksum(&path_b) {
+            continue;
+        }
+        let size = |path: &Path| path.metadata().map(|m| m.len()).unwrap_or_default();
+        let differences = match (
+            delimiter(file),
+            read_to_string(&path_a),
+            read_to_string(&path_b),
+        ) {
+            (Some(delimiter), Ok(a), Ok(b)) => compare_tables(&a, &b, delimiter, args.tolerance),
+            _ if size(&path_a) == size(&path_b) => vec!["content differs".to_owned()],
+            _ => vec![format!(
+                "size: {} -> {} bytes",
+                size(&path_a),
+                size(&path_b)
+            )],
+        };
+        // Tables which only differ within the tolerance are equal
+        if differences.is_empty() {
+            continue;
+        }
+        println!("~ {}", file.display());
+        for difference in differences {
+            println!("    {}", difference);
+        }
+        n_differing += 1;
+    }
+    let n_files = files_a.union(&files_b).count();
+    if n_differing == 0 {
+        println!("No differences in {} files", n_files);
+    } else {
+        println!("{} of {} files differ", n_differing, n_files);
+        std::process::exit(1)
+    }
+}
+
+/// Record the file at `path` as `relative` in a snapshot.
+fn freeze_file(path: &Path, relative: &str) -> FrozenFile {
+    let mut frozen = FrozenFile {
+        path: relative.to_owned(),
+        size: path.metadata().map(|m| m.len()).unwrap_or_default(),
+        sha256: sha256(path)
+            .unwrap_or_else(|_| exit_with_error(&format!("Could not read {:?}", path))),
+        rows: None,
+        columns: Vec::new(),
+    };
+    if let (Some(delimiter), Ok(content)) = (delimiter(path), read_to_string(path)) {
+        let table = parse_table(&content, delimiter);
+        frozen.rows = Some(table.len());
+        frozen.columns = summarize_columns(&table);
+    }
+    frozen
+}
+
+fn freeze(args: FreezeArgs) {
+    let root = find_project_root();
+    let dir = root.join(&args.dir);
+    if !dir.is_dir() {
+        exit_with_error(&format!("{:?} is not a directory", dir))
+    }
+    // Tolerances are configured by hand, so they are kept when refreezing
+    let tolerance = Snapshot::load(&root)
+        .map(|snapshot| snapshot.tolerance)
+        .unwrap_or_default();
+    let file: Vec<FrozenFile> = relative_files(&dir)
+        .iter()
+        .map(|relative| freeze_file(&dir.join(relative), &relative.to_string_lossy()))
+        .collect();
+    let commit = Repository::discover(&root)
+        .ok()
+        .and_then(|repo| Some(repo.head().ok()?.target()?.to_string()));
+    let snapshot = Snapshot {
+        dir: args.dir.to_string_lossy().into_owned(),
+        frozen: Local::today().format("%Y-%m-%d").to_string(),
+        commit,
+        tolerance,
+        file,
+    };
+    snapshot.save(&root);
+    println!(
+        "Froze {} files of {} in {}",
+        snapshot.file.len(),
+        snapshot.dir,
+        SNAPSHOT_FILE
+    );
+}
+
+/// Get the differences of the summary of a table from the frozen summary `frozen`.
+fn compare_summaries(frozen: &FrozenFile, fresh: &FrozenFile, tolerance: Tolerance) -> Vec<String> {
+    let mut differences = Vec::new();
+    if frozen.rows != fresh.rows {
+        differences.push(format!(
+            "rows: {} -> {}",
+            frozen.rows.unwrap_or_default(),
+            fresh.rows.unwrap_or_default()
+        ));
+    }
+    for column in &frozen.columns {
+        let Some(new) = fresh.columns.iter().find(|c| c.name == column.name) else {
+            differences.push(format!("column \"{}\" is missing", column.name));
+            continue;
+        };
+        if column.sha256.is_some() || new.sha256.is_some() {
+            if column.sha256 != new.sha256 {
+                differences.push(format!("column \"{}\": values changed", column.name));
+            }
+            continue;
+        }
+        for (statistic, old, new) in [
+            ("mean", column.mean, new.mean),
+            ("sd", column.sd, new.sd),
+            ("min", column.min, new.min),
+            ("max", column.max, new.max),
+        ] {
+            if let (Some(old), Some(new)) = (old, new) {
+                if !tolerance.close(old, new) {
+                    differences.push(format!(
+                        "column \"{}\": {} {} -> {}",
+                        column.name, statistic, old, new
+                    ));
+                }
+            }
+        }
+    }
+    for column in &fresh.columns {
+        if !frozen.columns.iter().any(|c| c.name == column.name) {
+            differences.push(format!("column \"{}\" is new", column.name));
+        }
+    }
+    differences
+}
+
+fn check(args: CheckArgs) {
+    let root = find_project_root();
+    let snapshot = Snapshot::load(&root).unwrap_or_else(|| {
+        exit_with_error(&format!(
+            "No {} found. Use `investigate results freeze` first",
+            SNAPSHOT_FILE
+        ))
+    });
+    let dir = match args.dir {
+        Some(dir) => dir,
+        None => root.join(&snapshot.dir),
+    };
+    if !dir.is_dir() {
+        exit_with_error(&format!("{:?} is not a directory", dir))
+    }
+    let mut n_failed = 0;
+    for frozen in &snapshot.file {
+        let path = dir.join(&frozen.path);
+        if !path.is_file() {
+            println!("FAIL {}: missing", frozen.path);
+            n_failed += 1;
+            continue;
+        }
+        let fresh = freeze_file(&path, &frozen.path);
+        if fresh.sha256 == frozen.sha256 {
+            continue;
+        }
+        let differences = if frozen.rows.is_some() && fresh.rows.is_some() {
+            compare_summaries(frozen, &fresh, snapshot.tolerance(&frozen.path))
+        } else {
+            vec![format!(
+                "content changed, size {} -> {} bytes",
+                frozen.size, fresh.size
+            )]
+        };
+        if differences.is_empty() {
+            continue;
+        }
+        println!("FAIL {}", frozen.path);
+        for difference in differences {
+            println!("    {}", difference);
+        }
+        n_failed += 1;
+    }
+    for relative in relative_files(&dir) {
+        let relative = relative.to_string_lossy();
+        if !snapshot.file.iter().any(|f| f.path == relative) {
+            warn(
+                Code::FileChanged,
+                &format!("{} is not in the snapshot", relative),
+            );
+        }
+    }
+    if n_failed > 0 {
+        println!(
+            "{} of {} files differ from the snapshot of {}",
+            n_failed,
+            snapshot.file.len(),
+            snapshot.frozen
+        );
+        std::process::exit(1)
+    }
+    println!(
+        "All {} files match the snapshot of {}",
+        snapshot.file.len(),
+        snapshot.frozen
+    );
+}
+
+/// Get the date of the timestamped results directory `name`, named like "2024-05-01",
+/// "2024-05-01T12-00" or "20240501_run2", or None if it's not timestamped.
+fn directory_date(name: &str) -> Option<chrono::NaiveDate> {
+    let parse = |length: usize, format: &str| {
+        chrono::NaiveDate::parse_from_str(name.get(..length)?, format).ok()
+    };
+    parse(10, "%Y-%m-%d").or_else(|| parse(8, "%Y%m%d"))
+}
+
+/// Get the texts referring to results: the paths of the registered figures, the files in paper/
+/// and the directory of the frozen snapshot.
+fn references(root: &Path) -> Vec<String> {
+    let mut texts: Vec<String> = Figures::load(root)
+        .figure
+        .into_iter()
+        .map(|figure| figure.file)
+        .collect();
+    texts.extend(
+        walk_files(&root.join("paper"))
+            .iter()
+            .filter_map(|path| read_to_string(path).ok()),
+    );
+    texts.extend(Snapshot::load(root).map(|snapshot| snapshot.dir));
+    texts
+}
+
+fn prune(args: PruneArgs) {
+    let root = find_project_root();
+    if root.join(FINALIZED_FILE).exists() {
+        exit_with_error("Project is finalized, so its results must not be deleted")
+    }
+    let retention = Config::load().retention;
+    let keep_last = args.keep_last.or(retention.keep_last).unwrap_or_else(|| {
+        exit_with_error(
+            "No retention policy. Set keep_last in the [retention] section of the config file, \
+            or use --keep-last",
+        )
+    });
+    let mut directories: Vec<(chrono::NaiveDate, String)> = std::fs::read_dir(root.join("results"))
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let name = entry.file_name().to_string_lossy().into_owned();
+            (entry.path().is_dir()).then_some((directory_date(&name)?, name))
+        })
+        .collect();
+    // Newest first
+    directories.sort_by(|a, b| b.cmp(a));
+    let references = if retention.keep_referenced.unwrap_or(true) {
+        references(&root)
+    } else {
+        Vec::new()
+    };
+    let mut pruned = Vec::new();
+    for (_, name) in directories.iter().skip(keep_last) {
+        let relative = format!("results/{}", name);
+        // A reference to results/2024-05-01 is not one to results/2024-05-01T12-00
+        let referenced = references.iter().any(|text| {
+            text.match_indices(&relative).any(|(i, _)| {
+                !text[i + relative.len()..]
+                    .starts_with(|c: char| c.is_alphanumeric() || "-_.".contains(c))
+            })
+        });
+        if referenced {
+            println!("Keeping {}, which is referenced", relative);
+            continue;
+        }
+        println!(
+            "{} {}",
+            if args.dry_run {
+                "Would delete"
+            } else {
+                "Deleting"
+            },
+            relative
+        );
+        pruned.push(root.join(&relative));
+    }
+    if pruned.is_empty() {
+        println!(
+            "Nothing to prune among {} timestamped directories in results/",
+            directories.len()
+        );
+    } else if !args.dry_run {
+        let id = trash(&root, &pruned);
+        println!(
+            "Moved them to the trash. Restore them with `investigate trash restore {}`",
+            id
+        );
+    }
+}
+
+pub fn run(command: ResultsCommand) {
+    match command {
+        ResultsCommand::Diff(args) => diff(args),
+        ResultsCommand::Freeze(args) => freeze(args),
+        ResultsCommand::Check(args) => check(args),
+        ResultsCommand::Prune(args) => prune(args),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tolerance(abs_tol: f64, rel_tol: f64) -> Tolerance {
+        Tolerance { abs_tol, rel_tol }
+    }
+
+    /// Summarize the CSV table `content` like `investigate results freeze`.
+    fn summary(content: &str) -> FrozenFile {
+        let table = parse_table(content, ',');
+        FrozenFile {
+            path: "table.csv".to_owned(),
+            size: content.len() as u64,
+            sha256: hex(&Sha256::digest(content)),
+            rows: Some(table.len()),
+            columns: summarize_columns(&table),
+        }
+    }
+
+    #[test]
+    fn matches_patterns() {
+        assert!(matches_pattern("tables/*.csv", "tables/counts.csv"));
+        assert!(matches_pattern("*", "anything/at/all"));
+        assert!(matches_pattern("*.csv", "a.csv"));
+        assert!(matches_pattern("a*b*c", "abc"));
+        assert!(matches_pattern("exact.tsv", "exact.tsv"));
+        assert!(!matches_pattern("tables/*.csv", "figures/counts.csv"));
+        assert!(!matches_pattern("tables/*.csv", "tables/counts.tsv"));
+        assert!(!matches_pattern("exact.tsv", "exact.tsv.bak"));
+        // The prefix and suffix must not overlap
+        assert!(!matches_pattern("ab*ba", "aba"));
+    }
+
+    #[test]
+    fn parses_tables() {
+        assert_eq!(
+            parse_table("a,b\r\n1,\"x, \"\"y\"\"\"\n2,\n", ','),
+            vec![vec!["a", "b"], vec!["1", "x, \"y\""], vec!["2", ""]]
+        );
+        // A final line without a newline is a row too
+        assert_eq!(
+            parse_table("a\tb\n1\t2", '\t'),
+            vec![vec!["a", "b"], vec!["1", "2"]]
+        );
+        assert!(parse_table("", ',').is_empty());
+    }
+
+    #[test]
+    fn compares_tables_within_tolerance() {
+        let a = "name,value\nx,1.0\ny,100\n";
+        let b = "name,value\nx,1.05\ny,101\n";
+        assert!(compare_tables(a, a, ',', Tolerance::default()).is_empty());
+        assert!(compare_tables(a, b, ',', tolerance(0.1, 0.02)).is_empty());
+        assert_eq!(
+            compare_tables(a, b, ',', tolerance(0.1, 0.0)),
+            vec!["row 3, column \"value\": 100 -> 101"]
+        );
+        assert_eq!(compare_tables(a, b, ',', Tolerance::default()).len(), 2);
+        // Text is never within a tolerance
+        assert_eq!(
+            compare_tables("a\nx\n", "a\ny\n", ',', tolerance(1.0, 1.0)),
+            vec!["row 2, column \"a\": x -> y"]
+        );
+    }
+
+    #[test]
+    fn compares_tables_with_missing_and_new_columns() {
+        assert_eq!(
+            compare_tables("a,b\n1,2\n", "a\n1\n", ',', Tolerance::default()),
+            vec![
+                "row 1, column 2: b -> (missing)",
+                "row 2, column \"b\": 2 -> (missing)"
+            ]
+        );
+        assert_eq!(
+            compare_tables("a\n1\n", "a,b\n1,2\n", ',', Tolerance::default()),
+            vec![
+                "row 1, column 2: (missing) -> b",
+                "row 2, column 2: (missing) -> 2"
+            ]
+        );
+        // Only the rows of both tables are compared cell by cell
+        assert_eq!(
+            compare_tables("a\n1\n", "a\n1\n2\n", ',', Tolerance::default()),
+            vec!["rows: 2 -> 3"]
+        );
+    }
+
+    #[test]
+    fn compares_summaries() {
+        let frozen = summary("name,value\nx,1.0\ny,2.0\n");
+        let close = summary("name,value\nx,1.01\ny,2.0\n");
+        assert!(compare_summaries(&frozen, &frozen, Tolerance::default()).is_empty());
+        assert!(compare_summaries(&frozen, &close, tolerance(0.02, 0.0)).is_empty());
+        let shifted = summary("name,value\nx,2.0\ny,3.0\n");
+        assert_eq!(
+            compare_summaries(&frozen, &shifted, tolerance(0.5, 0.0)),
+            vec![
+                "column \"value\": mean 1.5 -> 2.5",
+                "column \"value\": min 1 -> 2",
+                "column \"value\": max 2 -> 3"
+            ]
+        );
+        let renamed = summary("name,score\nz,1.0\ny,2.0\n");
+        assert_eq!(
+            compare_summaries(&frozen, &renamed, tolerance(1.0, 1.0)),
+            vec![
+                "column \"name\": values changed",
+                "column \"value\" is missing",
+                "column \"score\" is new"
+            ]
+        );
+        let longer = summary("name,value\nx,1.0\ny,2.0\nz,1.5\n");
+        assert_eq!(
+            compare_summaries(&frozen, &longer, tolerance(1.0, 1.0)),
+            vec!["rows: 3 -> 4", "column \"name\": values changed"]
+        );
+    }
+
+    #[test]
+    fn finds_dates_of_timestamped_directories() {
+        let date = |y, m, d| chrono::NaiveDate::from_ymd_opt(y, m, d);
+        assert_eq!(directory_date("2024-05-01"), date(2024, 5, 1));
+        assert_eq!(directory_date("2024-05-01T12-00"), date(2024, 5, 1));
+        assert_eq!(directory_date("20240501_run2"), date(2024, 5, 1));
+        assert_eq!(directory_date("figures"), None);
+        assert_eq!(directory_date("2024-13-01"), None);
+        assert_eq!(directory_date("2024"), None);
+    }
+}
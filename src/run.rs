@@ -0,0 +1,746 @@
+use chrono::Local;
+use clap::{ArgEnum, Args, Subcommand};
+use git2::Repository;
+use serde::{Deserialize, Serialize};
+
+use std::ffi::OsString;
+use std::fs::read_to_string;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::SystemTime;
+
+use crate::cache::{self, CACHE_INDEX};
+use crate::config::Config;
+use crate::data::shell_join;
+use crate::exec::project_command;
+use crate::git::is_clean;
+use crate::lock::lock;
+use crate::metadata::Metadata;
+use crate::notify::{can_notify, notify};
+use crate::report::walk_files;
+use crate::warning::{warn, Code};
+use crate::{exit_with_error, filesystem, find_project_root};
+
+/// Name of the log of tracked runs, in the project root, with one JSON record per line
+pub const RUNS_FILE: &str = "runs.jsonl";
+
+/// Directories whose files are inputs to the analysis
+const INPUT_DIRECTORIES: [&str; 2] = ["raw", "choices"];
+
+/// Directories whose files are outputs of the analysis
+const OUTPUT_DIRECTORIES: [&str; 2] = ["results", "cache"];
+
+/// Directory of the logs of scheduled runs, relative to the project root
+pub const LOGS_DIR: &str = "results/logs";
+
+#[derive(Args)]
+pub struct RunArgs {
+    /// Submit the command as a SLURM job, optionally with extra options to sbatch,
+    /// e.g. --sbatch="--mem=64G --time=2-0"
+    #[clap(
+        long,
+        value_name = "OPTIONS",
+        min_values = 0,
+        require_equals = true,
+        default_missing_value = "",
+        conflicts_with_all = &["at", "nohup"]
+    )]
+    sbatch: Option<String>,
+
+    /// Run the command at TIME with `at`, e.g. "02:00" or "now + 1 hour"
+    #[clap(long, value_name = "TIME", conflicts_with = "nohup")]
+    at: Option<String>,
+
+    /// Run the command in the background, where it continues after logging out
+    #[clap(long)]
+    nohup: bool,
+
+    /// Notify when the command finishes, with the webhook or command in the config file
+    #[clap(long, env = "INVESTIGATE_NOTIFY")]
+    notify: bool,
+
+    /// Log file of a scheduled run, set when the run is scheduled
+    #[clap(long, hide = true)]
+    log: Option<String>,
+
+    /// File to write the CPU time and peak memory of the command to, set when the
+    /// command is run through investigate inside the project's environment
+    #[clap(long, hide = true, value_name = "FILE")]
+    measure: Option<PathBuf>,
+
+    /// Command to run, given after `--`
+    #[clap(last = true, required = true)]
+    command: Vec<OsString>,
+}
+
+/// A run of a command with `investigate run`.
+#[derive(Serialize, Deserialize)]
+pub struct Run {
+    pub id: String,
+    pub command: Vec<String>,
+    /// Start and end time in RFC 3339 format
+    pub started: String,
+    pub finished: String,
+    /// Exit code, if the command exited normally
+    pub exit_code: Option<i32>,
+    /// The git commit HEAD was at, and whether there were uncommitted changes
+    pub commit: Option<String>,
+    pub dirty: bool,
+    /// Files in the project the command ran, e.g. src/main.py
+    pub scripts: Vec<String>,
+    /// Files in raw/ and choices/ named by the command or its scripts. This is approximate.
+    pub inputs: Vec<String>,
+    /// Files in results/ and cache/ written during the run
+    pub outputs: Vec<String>,
+    /// Log of the output, relative to the project root, if the run was scheduled
+    pub log: Option<String>,
+    /// ID of the SLURM job, if the run was a job
+    pub slurm_job_id: Option<String>,
+    /// Wall time and CPU time in seconds, and peak resident memory in bytes
+    pub wall_seconds: Option<f64>,
+    pub cpu_seconds: Option<f64>,
+    pub peak_rss: Option<u64>,
+}
+
+#[derive(Subcommand)]
+pub enum RunsCommand {
+    /// List the recorded runs, with the resources they used
+    List(ListArgs),
+
+    /// Print a workflow rule running the command of a recorded run, with its inputs and outputs
+    Promote(PromoteArgs),
+}
+
+#[derive(Args)]
+pub struct ListArgs {
+    /// Only list the last N runs
+    #[clap(long, value_name = "N")]
+    last: Option<usize>,
+}
+
+/// A workflow manager to promote a run to.
+#[derive(Copy, Clone, PartialEq, Eq, ArgEnum)]
+pub enum Workflow {
+    /// A Snakemake rule
+    Snakemake,
+    /// A Nextflow process
+    Nextflow,
+}
+
+#[derive(Args)]
+pub struct PromoteArgs {
+    /// ID of the run, or the start of it, as shown by `investigate runs list`
+    id: String,
+
+    #[clap(arg_enum, value_parser, long)]
+    to: Workflow,
+}
+
+/// Load the runs of the project at `root`, oldest first.
+pub fn load_runs(root: &Path) -> Vec<Run> {
+    let path = root.join(RUNS_FILE);
+    let content = read_to_string(&path).unwrap_or_default();
+    content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .filter_map(|(i, line)| match serde_json::from_str(line) {
+            Ok(run) => Some(run),
+            Err(_) => {
+                warn(
+                    Code::InvalidRecord,
+                    &format!("Skipping invalid line {} of {}", i + 1, RUNS_FILE),
+                );
+                None
+            }
+        })
+        .collect()
+}
+
+/// Find the run with ID `id`, or the start of it, exiting unless exactly one run matches.
+pub fn find_run<'a>(runs: &'a [Run], id: &str) -> &'a Run {
+    let matching: Vec<&Run> = runs.iter().filter(|run| run.id.starts_with(id)).collect();
+    match matching[..] {
+        [run] => run,
+        [] => exit_with_error(&format!("No run with ID {} in {}", id, RUNS_FILE)),
+        _ => exit_with_error(&format!("Several runs have IDs starting with {}", id)),
+    }
+}
+
+/// Append `run` to the log. Runs may finish at the same time, e.g. in array jobs,
+/// so the log is locked while appending.
+fn append_run(root: &Path, run: &Run) {
+    let path = root.join(RUNS_FILE);
+    let line = serde_json::to_string(run).expect("Error when serializing run") + "\n";
+    let _lock = lock(&path);
+    filesystem::current()
+        .append(&path, line.as_bytes())
+        .unwrap_or_else(|_| panic!("Error when writing file {:?}", path))
+}
+
+/// Get the paths relative to `root` of the files in `directories`.
+fn files_in(root: &Path, directories: &[&str]) -> Vec<String> {
+    directories
+        .iter()
+        .flat_map(|dir| walk_files(&root.join(dir)))
+        .filter_map(|file| {
+            file.strip_prefix(root)
+                .ok()
+                .map(|relative| relative.to_string_lossy().into_owned())
+        })
+        .collect()
+}
+
+/// Get the input files named in `texts`, by their path or file name.
+fn find_inputs(root: &Path, texts: &[String]) -> Vec<String> {
+    files_in(root, &INPUT_DIRECTORIES)
+        .into_iter()
+        .filter(|input| {
+            let name = Path::new(input)
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            texts.iter().any(|text| text.contains(name.as_str()))
+        })
+        .collect()
+}
+
+/// Run `program`, write the CPU time in seconds and the peak resident memory in bytes
+/// of it and the processes it waited for to `output`, then exit like it.
+/// `investigate run` runs the command through this inside the project's environment,
+/// so the wrapper entering the environment, like `conda run`, isn't measured.
+#[cfg(unix)]
+fn measure(program: &OsString, rest: &[OsString], output: &Path) -> ! {
+    let child = Command::new(program)
+        .args(rest)
+        .spawn()
+        .unwrap_or_else(|_| exit_with_error(&format!("Could not run {:?}", program)));
+    let mut status = 0;
+    // SAFETY: rusage is plain data, which wait4 fills in
+    let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+    if unsafe { libc::wait4(child.id() as libc::pid_t, &mut status, 0, &mut usage) } < 0 {
+        exit_with_error(&format!("Could not wait for {:?}", program))
+    }
+    let seconds = |time: libc::timeval| time.tv_sec as f64 + time.tv_usec as f64 / 1e6;
+    // Linux reports the peak in kilobytes, macOS in bytes
+    let unit = if cfg!(target_os = "macos") { 1 } else { 1024 };
+    let cpu_seconds = seconds(usage.ru_utime) + seconds(usage.ru_stime);
+    let peak_rss = usage.ru_maxrss as u64 * unit;
+    if std::fs::write(output, format!("{} {}\n", cpu_seconds, peak_rss)).is_err() {
+        panic!("Error when writing file {:?}", output)
+    }
+    if libc::WIFSIGNALED(status) {
+        // Die of the same signal, so the caller sees the command as killed
+        let signal = libc::WTERMSIG(status);
+        unsafe {
+            libc::signal(signal, libc::SIG_DFL);
+            libc::raise(signal);
+        }
+        std::process::exit(128 + signal)
+    }
+    std::process::exit(libc::WEXITSTATUS(status))
+}
+
+#[cfg(not(unix))]
+fn measure(program: &OsString, rest: &[OsString], _output: &Path) -> ! {
+    let status = Command::new(program)
+        .args(rest)
+        .status()
+        .unwrap_or_else(|_| exit_with_error(&format!("Could not run {:?}", program)));
+    std::process::exit(status.code().unwrap_or(1))
+}
+
+/// The command running `program` in the project's environment, through
+/// `investigate run --measure usage_file` where the usage can be measured.
+fn tracked_command(
+    root: &Path,
+    program: &OsString,
+    rest: &[OsString],
+    usage_file: &Path,
+) -> Command {
+    match std::env::current_exe() {
+        Ok(exe) if cfg!(unix) => {
+            let mut args: Vec<OsString> = vec![
+                "run".into(),
+                "--measure".into(),
+                usage_file.into(),
+                "--".into(),
+                program.clone(),
+            ];
+            args.extend(rest.iter().cloned());
+            project_command(root, &exe.into_os_string(), &args)
+        }
+        _ => project_command(root, program, rest),
+    }
+}
+
+/// Read the CPU time and peak memory written by `investigate run --measure`, if any,
+/// and remove the file.
+fn read_usage(usage_file: &Path) -> (Option<f64>, Option<u64>) {
+    let contents = std::fs::read_to_string(usage_file).unwrap_or_default();
+    let _ = std::fs::remove_file(usage_file);
+    let mut fields = contents.split_whitespace();
+    (
+        fields.next().and_then(|field| field.parse().ok()),
+        fields.next().and_then(|field| field.parse().ok()),
+    )
+}
+
+/// Format `seconds` like "2h05m", "3m12s" or "4.2s".
+pub fn format_duration(seconds: f64) -> String {
+    let whole = seconds.round() as u64;
+    if whole >= 3600 {
+        format!("{}h{:02}m", whole / 3600, whole % 3600 / 60)
+    } else if whole >= 60 {
+        format!("{}m{:02}s", whole / 60, whole % 60)
+    } else {
+        format!("{:.1}s", seconds)
+    }
+}
+
+/// Format `bytes` like "1.5 GB".
+pub fn format_bytes(bytes: u64) -> String {
+    let units = ["B", "KB", "MB", "GB", "TB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < units.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, units[unit])
+}
+
+fn list(args: ListArgs) {
+    let root = find_project_root();
+    let runs = load_runs(&root);
+    if runs.is_empty() {
+        println!("No runs recorded in {}", RUNS_FILE);
+        return;
+    }
+    let skip = args
+        .last
+        .map(|last| runs.len().saturating_sub(last))
+        .unwrap_or_default();
+    println!(
+        "{:<8}  {:<19}  {:>4}  {:>8}  {:>8}  {:>9}  COMMAND",
+        "ID", "STARTED", "EXIT", "WALL", "CPU", "PEAK RSS"
+    );
+    let unknown = || "-".to_owned();
+    for run in runs.iter().skip(skip) {
+        println!(
+            "{:<8}  {:<19}  {:>4}  {:>8}  {:>8}  {:>9}  {}",
+            run.id,
+            run.started
+                .get(..19)
+                .unwrap_or(&run.started)
+                .replace('T', " "),
+            run.exit_code
+                .map(|code| code.to_string())
+                .unwrap_or_else(unknown),
+            run.wall_seconds
+                .map(format_duration)
+                .unwrap_or_else(unknown),
+            run.cpu_seconds.map(format_duration).unwrap_or_else(unknown),
+            run.peak_rss.map(format_bytes).unwrap_or_else(unknown),
+            run.command.join(" ")
+        );
+    }
+}
+
+/// Get the name of the rule or process running `run`: that of its first script, or else of
+/// its program, as an identifier.
+fn rule_name(run: &Run) -> String {
+    let named = run.scripts.first().or(run.command.first());
+    let stem = named
+        .and_then(|named| Path::new(named).file_stem())
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let name: String = stem
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    match name.chars().next() {
+        Some(c) if !c.is_ascii_digit() => name,
+        _ => format!("run_{}", name),
+    }
+}
+
+/// Get `text` as a double-quoted string literal of Python or Groovy.
+fn quoted(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Get the Snakemake rule running the command of `run`.
+fn snakemake_rule(run: &Run) -> String {
+    let mut lines = vec![format!("rule {}:", rule_name(run))];
+    for (section, files) in [
+        (
+            "input",
+            run.scripts.iter().chain(&run.inputs).collect::<Vec<_>>(),
+        ),
+        ("output", run.outputs.iter().collect()),
+    ] {
+        if !files.is_empty() {
+            lines.push(format!("    {}:", section));
+            lines.extend(
+                files
+                    .iter()
+                    .map(|file| format!("        {},", quoted(file))),
+            );
+        }
+    }
+    // Snakemake formats the shell command, so braces must be doubled
+    let command = shell_join(&run.command)
+        .replace('{', "{{")
+        .replace('}', "}}");
+    lines.push("    shell:".to_owned());
+    lines.push(format!("        {}", quoted(&command)));
+    lines.join("\n")
+}
+
+/// Get the Nextflow process running the command of `run`, and how to call it.
+fn nextflow_process(run: &Run) -> String {
+    let name = rule_name(run).to_uppercase();
+    let inputs: Vec<&String> = run.scripts.iter().chain(&run.inputs).collect();
+    let mut lines = vec![
+        format!("process {} {{", name),
+        "    publishDir projectDir, mode: 'copy'".to_owned(),
+    ];
+    // Inputs are staged in the process' work directory under their paths in the project
+    if !inputs.is_empty() {
+        lines.push(String::new());
+        lines.push("    input:".to_owned());
+        lines.extend(
+            inputs
+                .iter()
+                .map(|file| format!("    path {}", quoted(file))),
+        );
+    }
+    if !run.outputs.is_empty() {
+        lines.push(String::new());
+        lines.push("    output:".to_owned());
+        lines.extend(
+            run.outputs
+                .iter()
+                .map(|file| format!("    path {}", quoted(file))),
+        );
+    }
+    let command = shell_join(&run.command)
+        .replace('\\', "\\\\")
+        .replace('$', "\\$");
+    lines.push(String::new());
+    lines.push("    script:".to_owned());
+    lines.push("    \"\"\"".to_owned());
+    lines.push(format!("    {}", command));
+    lines.push("    \"\"\"".to_owned());
+    lines.push("}".to_owned());
+    let arguments: Vec<String> = inputs
+        .iter()
+        .map(|file| format!("file(\"${{projectDir}}/{}\")", file))
+        .collect();
+    lines.push(String::new());
+    lines.push(format!(
+        "// In the workflow block: {}({})",
+        name,
+        arguments.join(", ")
+    ));
+    lines.join("\n")
+}
+
+fn promote(args: PromoteArgs) {
+    let root = find_project_root();
+    let runs = load_runs(&root);
+    let run = find_run(&runs, &args.id);
+    if run.exit_code != Some(0) {
+        warn(Code::RunProblem, &format!("Run {} did not succeed", run.id));
+    }
+    if run.outputs.is_empty() {
+        warn(
+            Code::RunProblem,
+            &format!(
+                "Run {} wrote no files in results/ or cache/, so the rule has no outputs",
+                run.id
+            ),
+        );
+    }
+    println!(
+        "{} Promoted from run {} of `investigate run`, started {}. Its inputs are approximate.",
+        if args.to == Workflow::Snakemake {
+            "#"
+        } else {
+            "//"
+        },
+        run.id,
+        run.started
+    );
+    match args.to {
+        Workflow::Snakemake => println!("{}", snakemake_rule(run)),
+        Workflow::Nextflow => println!("{}", nextflow_process(run)),
+    }
+}
+
+pub fn run_runs(command: RunsCommand) {
+    match command {
+        RunsCommand::List(args) => list(args),
+        RunsCommand::Promote(args) => promote(args),
+    }
+}
+
+/// Quote `arg` for a POSIX shell.
+fn shell_quote(arg: &str) -> String {
+    format!("'{}'", arg.replace('\'', "'\\''"))
+}
+
+/// Schedule `investigate run` of the command in `args` with SLURM, `at` or `nohup`,
+/// logging its output to a file in results/logs/.
+fn schedule(root: &Path, args: &RunArgs) {
+    // Logs are named after the script run, if any, else the program
+    let named = args
+        .command
+        .iter()
+        .find(|arg| root.join(arg).is_file())
+        .unwrap_or(&args.command[0]);
+    let stem = Path::new(named)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let log = format!(
+        "{}/{}-{}.log",
+        LOGS_DIR,
+        Local::now().format("%Y%m%d-%H%M%S"),
+        stem
+    );
+    std::fs::create_dir_all(root.join(LOGS_DIR))
+        .unwrap_or_else(|_| panic!("Error when creating directory {}", LOGS_DIR));
+    let executable = std::env::current_exe()
+        .unwrap_or_else(|_| exit_with_error("Could not find the investigate executable"));
+    // The scheduled command records the run like any other
+    let mut tracked = vec![
+        executable.to_string_lossy().into_owned(),
+        "run".to_owned(),
+        format!("--log={}", log),
+    ];
+    if args.notify {
+        tracked.push("--notify".to_owned());
+    }
+    tracked.push("--".to_owned());
+    tracked.extend(
+        args.command
+            .iter()
+            .map(|arg| arg.to_string_lossy().into_owned()),
+    );
+    let script = format!(
+        "cd {} && {}",
+        shell_quote(&root.to_string_lossy()),
+        tracked
+            .iter()
+            .map(|arg| shell_quote(arg))
+            .collect::<Vec<_>>()
+            .join(" ")
+    );
+    if let Some(options) = &args.sbatch {
+        let output = Command::new("sbatch")
+            .arg("--parsable")
+            .arg(format!("--output={}", log))
+            .args(options.split_whitespace())
+            .arg("--wrap")
+            .arg(&script)
+            .current_dir(root)
+            .output()
+            .unwrap_or_else(|_| exit_with_error("Could not run sbatch. Is SLURM installed?"));
+        if !output.status.success() {
+            exit_with_error(&format!(
+                "sbatch failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ))
+        }
+        let job = String::from_utf8_lossy(&output.stdout).trim().to_owned();
+        println!("Submitted SLURM job {}, logging to {}", job, log);
+    } else if let Some(time) = &args.at {
+        let mut child = Command::new("at")
+            .arg(time)
+            .stdin(Stdio::piped())
+            .spawn()
+            .unwrap_or_else(|_| exit_with_error("Could not run at. Is it installed?"));
+        child
+            .stdin
+            .take()
+            .expect("Error when opening stdin of at")
+            .write_all(format!("{} > {} 2>&1\n", script, shell_quote(&log)).as_bytes())
+            .expect("Error when writing to at");
+        if !child.wait().is_ok_and(|status| status.success()) {
+            exit_with_error("at failed to schedule the command")
+        }
+        println!("Scheduled the command at {}, logging to {}", time, log);
+    } else {
+        // The shell exits right away, leaving the command running in the background
+        let output = Command::new("sh")
+            .arg("-c")
+            .arg(format!(
+                "nohup sh -c {} > {} 2>&1 < /dev/null & echo $!",
+                shell_quote(&script),
+                shell_quote(&log)
+            ))
+            .current_dir(root)
+            .output()
+            .unwrap_or_else(|_| exit_with_error("Could not run sh"));
+        println!(
+            "Running the command in the background as process {}, logging to {}",
+            String::from_utf8_lossy(&output.stdout).trim(),
+            log
+        );
+    }
+}
+
+/// Number of lines at the end of the log included in notifications
+const NOTIFY_LOG_LINES: usize = 20;
+
+/// Get the message notifying that `run` of the project at `root` finished.
+fn notification(root: &Path, run: &Run) -> String {
+    let name = Metadata::load(root).name;
+    let outcome = match run.exit_code {
+        Some(0) => "succeeded".to_owned(),
+        Some(code) => format!("failed with exit code {}", code),
+        None => "was killed".to_owned(),
+    };
+    let mut message = format!(
+        "Run {} of project {} {} after {}: {}",
+        run.id,
+        name,
+        outcome,
+        run.wall_seconds
+            .map(format_duration)
+            .unwrap_or_else(|| "?".to_owned()),
+        run.command.join(" ")
+    );
+    if let Some(log) = &run.log {
+        let content = read_to_string(root.join(log)).unwrap_or_default();
+        let lines: Vec<&str> = content.lines().collect();
+        let tail = &lines[lines.len().saturating_sub(NOTIFY_LOG_LINES)..];
+        if tail.is_empty() {
+            return message;
+        }
+        message.push_str(&format!(
+            "\n\nLast lines of {}:\n```\n{}\n```",
+            log,
+            tail.join("\n")
+        ));
+    }
+    message
+}
+
+pub fn run(args: RunArgs) {
+    if let Some(output) = &args.measure {
+        let (program, rest) = args
+            .command
+            .split_first()
+            .expect("Error when parsing command");
+        measure(program, rest, output)
+    }
+    let root = find_project_root();
+    let notify_config = Config::load().notify;
+    if args.notify && !can_notify(&notify_config) {
+        exit_with_error(
+            "--notify requires a webhook or command in the [notify] section of the config file",
+        )
+    }
+    if args.sbatch.is_some() || args.at.is_some() || args.nohup {
+        schedule(&root, &args);
+        return;
+    }
+    let (program, rest) = args
+        .command
+        .split_first()
+        .expect("Error when parsing command");
+    let command: Vec<String> = args
+        .command
+        .iter()
+        .map(|arg| arg.to_string_lossy().into_owned())
+        .collect();
+    let scripts: Vec<String> = command
+        .iter()
+        .filter(|arg| !Path::new(arg).is_absolute() && root.join(arg).is_file())
+        .cloned()
+        .collect();
+    let (commit, dirty) = match Repository::discover(&root) {
+        Ok(repo) => (
+            repo.head()
+                .ok()
+                .and_then(|head| Some(head.target()?.to_string())),
+            !is_clean(&repo),
+        ),
+        Err(_) => (None, false),
+    };
+
+    let start = SystemTime::now();
+    let started = Local::now().to_rfc3339();
+    let usage_file = std::env::temp_dir().join(format!(
+        "investigate-usage-{}",
+        uuid::Uuid::new_v4().simple()
+    ));
+    let status = tracked_command(&root, program, rest, &usage_file)
+        .status()
+        .unwrap_or_else(|_| exit_with_error(&format!("Could not run {:?}", program)));
+    let finished = Local::now().to_rfc3339();
+    let wall_seconds = start.elapsed().map(|elapsed| elapsed.as_secs_f64()).ok();
+    let (cpu_seconds, peak_rss) = read_usage(&usage_file);
+
+    let outputs = files_in(&root, &OUTPUT_DIRECTORIES)
+        .into_iter()
+        .filter(|output| {
+            root.join(output)
+                .metadata()
+                .and_then(|m| m.modified())
+                .is_ok_and(|modified| modified >= start)
+        })
+        .collect();
+    let mut texts = command.clone();
+    texts.extend(
+        scripts
+            .iter()
+            .filter_map(|script| read_to_string(root.join(script)).ok()),
+    );
+    let run = Run {
+        id: uuid::Uuid::new_v4().simple().to_string()[..8].to_owned(),
+        command,
+        started,
+        finished,
+        exit_code: status.code(),
+        commit,
+        dirty,
+        inputs: find_inputs(&root, &texts),
+        scripts,
+        outputs,
+        log: args.log,
+        slurm_job_id: std::env::var("SLURM_JOB_ID").ok(),
+        wall_seconds,
+        cpu_seconds,
+        peak_rss,
+    };
+    append_run(&root, &run);
+    // What a successful run wrote to cache/ is up to date with its scripts and inputs
+    let cached: Vec<String> = run
+        .outputs
+        .iter()
+        .filter(|output| output.starts_with("cache/") && *output != CACHE_INDEX)
+        .cloned()
+        .collect();
+    if status.success() && !cached.is_empty() {
+        // Scripts may use any code in src/, so all of it counts as code which made the outputs
+        let mut code: Vec<String> = run
+            .scripts
+            .iter()
+            .filter(|script| !script.starts_with("src/"))
+            .cloned()
+            .collect();
+        if root.join("src").is_dir() {
+            code.push("src".to_owned());
+        }
+        cache::record(&root, &cached, &code, &run.inputs);
+    }
+    if args.notify {
+        notify(&notify_config, &notification(&root, &run));
+    }
+    std::process::exit(status.code().unwrap_or(1))
+}
@@ -0,0 +1,310 @@
+use toml::{Table, Value};
+
+use std::path::Path;
+
+use crate::config::Config;
+use crate::metadata::Metadata;
+use crate::permissions::parse_umask;
+use crate::storage::parse_bytes;
+use crate::warning::is_code;
+
+/// Keys of the config file
+const CONFIG_KEYS: [&str; 10] = [
+    "author",
+    "defaults",
+    "profile",
+    "blocks",
+    "notify",
+    "retention",
+    "permissions",
+    "storage",
+    "warnings",
+    "stats",
+];
+
+/// Keys of a profile, and of the `[defaults]` section of the config file
+const PROFILE_KEYS: [&str; 19] = [
+    "language",
+    "directories",
+    "templates",
+    "scripts",
+    "docs",
+    "params",
+    "badges",
+    "index",
+    "dir_readmes",
+    "lfs",
+    "annex",
+    "protect_raw",
+    "annex_remote",
+    "channels",
+    "packages",
+    "ci",
+    "assignment",
+    "benchmark",
+    "modulefile",
+];
+
+const AUTHOR_KEYS: [&str; 2] = ["name", "email"];
+
+const NOTIFY_KEYS: [&str; 2] = ["webhook", "command"];
+
+const RETENTION_KEYS: [&str; 2] = ["keep_last", "keep_referenced"];
+
+const PERMISSIONS_KEYS: [&str; 3] = ["group", "setgid", "umask"];
+
+const STORAGE_KEYS: [&str; 3] = ["min_free", "quota_command", "locations"];
+
+const WARNINGS_KEYS: [&str; 1] = ["allow"];
+
+/// Keys of a directory given as a table, like `{ name = "raw", description = "..." }`
+const DIRECTORY_KEYS: [&str; 2] = ["name", "description"];
+
+/// Keys of the project's metadata file
+const METADATA_KEYS: [&str; 22] = [
+    "name",
+    "uuid",
+    "created",
+    "investigate_version",
+    "version",
+    "language",
+    "authors",
+    "environment",
+    "directories",
+    "data_remote",
+    "seed",
+    "encryption_recipients",
+    "description",
+    "license",
+    "keywords",
+    "doi",
+    "osf_node",
+    "contributors",
+    "reopened",
+    "sessions",
+    "dates",
+    "tools",
+];
+
+const CONTRIBUTOR_KEYS: [&str; 5] = ["name", "email", "role", "since", "until"];
+
+const REOPENING_KEYS: [&str; 3] = ["date", "author", "reason"];
+
+const SESSION_KEYS: [&str; 4] = ["start", "end", "author", "note"];
+
+const DATE_KEYS: [&str; 3] = ["name", "date", "note"];
+
+const TOOL_KEYS: [&str; 2] = ["version", "sha256"];
+
+/// Kind of file which can be validated.
+#[derive(Copy, Clone)]
+pub enum FileKind {
+    Config,
+    Metadata,
+}
+
+/// Get the number of single-character edits needed to turn `a` into `b`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// Get the known key most similar to `key`, if any is similar enough to be a typo.
+fn suggest<'a>(key: &str, known: &[&'a str]) -> Option<&'a str> {
+    known
+        .iter()
+        .map(|candidate| (edit_distance(key, candidate), *candidate))
+        .filter(|(distance, _)| *distance <= 2.max(key.len() / 3))
+        .min()
+        .map(|(_, candidate)| candidate)
+}
+
+/// Report the keys of `table` which are not in `known`, prefixing them by `prefix`.
+fn check_keys(table: &Table, known: &[&str], prefix: &str, problems: &mut Vec<String>) {
+    for key in table.keys() {
+        if known.contains(&key.as_str()) {
+            continue;
+        }
+        let mut problem = format!("Unknown key `{}{}`", prefix, key);
+        if let Some(suggestion) = suggest(key, known) {
+            problem.push_str(&format!(". Did you mean `{}{}`?", prefix, suggestion));
+        }
+        problems.push(problem);
+    }
+}
+
+/// Report the unknown keys of the tables in the array `value`, if it is one.
+fn check_array(value: Option<&Value>, known: &[&str], prefix: &str, problems: &mut Vec<String>) {
+    for table in value
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_table)
+    {
+        check_keys(table, known, prefix, problems);
+    }
+}
+
+fn check_profile(profile: &Table, prefix: &str, problems: &mut Vec<String>) {
+    check_keys(profile, &PROFILE_KEYS, prefix, problems);
+    check_array(
+        profile.get("directories"),
+        &DIRECTORY_KEYS,
+        &format!("{}directories.", prefix),
+        problems,
+    );
+}
+
+/// Get the unknown keys of a parsed config file, with suggestions for typos.
+pub fn unknown_config_keys(config: &Table) -> Vec<String> {
+    let mut problems = Vec::new();
+    check_keys(config, &CONFIG_KEYS, "", &mut problems);
+    let section = |name: &str| config.get(name).and_then(Value::as_table);
+    if let Some(author) = section("author") {
+        check_keys(author, &AUTHOR_KEYS, "author.", &mut problems);
+    }
+    if let Some(notify) = section("notify") {
+        check_keys(notify, &NOTIFY_KEYS, "notify.", &mut problems);
+    }
+    if let Some(retention) = section("retention") {
+        check_keys(retention, &RETENTION_KEYS, "retention.", &mut problems);
+    }
+    if let Some(permissions) = section("permissions") {
+        check_keys(
+            permissions,
+            &PERMISSIONS_KEYS,
+            "permissions.",
+            &mut problems,
+        );
+    }
+    if let Some(storage) = section("storage") {
+        check_keys(storage, &STORAGE_KEYS, "storage.", &mut problems);
+    }
+    if let Some(warnings) = section("warnings") {
+        check_keys(warnings, &WARNINGS_KEYS, "warnings.", &mut problems);
+    }
+    if let Some(defaults) = section("defaults") {
+        check_profile(defaults, "defaults.", &mut problems);
+    }
+    for (name, profile) in section("profile").into_iter().flatten() {
+        if let Some(profile) = profile.as_table() {
+            check_profile(profile, &format!("profile.{}.", name), &mut problems);
+        }
+    }
+    problems
+}
+
+fn unknown_metadata_keys(metadata: &Table) -> Vec<String> {
+    let mut problems = Vec::new();
+    check_keys(metadata, &METADATA_KEYS, "", &mut problems);
+    check_array(
+        metadata.get("contributors"),
+        &CONTRIBUTOR_KEYS,
+        "contributors.",
+        &mut problems,
+    );
+    check_array(
+        metadata.get("reopened"),
+        &REOPENING_KEYS,
+        "reopened.",
+        &mut problems,
+    );
+    check_array(
+        metadata.get("sessions"),
+        &SESSION_KEYS,
+        "sessions.",
+        &mut problems,
+    );
+    check_array(metadata.get("dates"), &DATE_KEYS, "dates.", &mut problems);
+    let tools = metadata.get("tools").and_then(Value::as_table);
+    for (name, tool) in tools.into_iter().flatten() {
+        if let Some(tool) = tool.as_table() {
+            check_keys(tool, &TOOL_KEYS, &format!("tools.{}.", name), &mut problems);
+        }
+    }
+    problems
+}
+
+/// Validate the file at `path`, returning its problems: syntax errors, values of the wrong type,
+/// and unknown keys.
+pub fn validate(path: &Path, kind: FileKind) -> Vec<String> {
+    match std::fs::read_to_string(path) {
+        Ok(content) => validate_content(&content, kind),
+        Err(e) => vec![format!("Could not read file: {}", e)],
+    }
+}
+
+/// Validate `content` of a file of the given kind, like `validate`.
+pub fn validate_content(content: &str, kind: FileKind) -> Vec<String> {
+    let table: Table = match toml::from_str(content) {
+        Ok(table) => table,
+        Err(e) => return vec![e.to_string().trim().to_owned()],
+    };
+    let mut invalid = Vec::new();
+    let umask = table
+        .get("permissions")
+        .and_then(|permissions| permissions.get("umask"))
+        .and_then(Value::as_str);
+    if let Some(umask) = umask.filter(|_| matches!(kind, FileKind::Config)) {
+        if parse_umask(umask).is_none() {
+            invalid.push(format!(
+                "permissions.umask: \"{}\" is not an octal umask, like \"002\"",
+                umask
+            ));
+        }
+    }
+    let min_free = table
+        .get("storage")
+        .and_then(|storage| storage.get("min_free"))
+        .and_then(Value::as_str);
+    if let Some(min_free) = min_free.filter(|_| matches!(kind, FileKind::Config)) {
+        if parse_bytes(min_free).is_none() {
+            invalid.push(format!(
+                "storage.min_free: \"{}\" is not a size, like \"50 GB\"",
+                min_free
+            ));
+        }
+    }
+    let allowed = table
+        .get("warnings")
+        .and_then(|warnings| warnings.get("allow"))
+        .and_then(Value::as_array);
+    for id in allowed
+        .filter(|_| matches!(kind, FileKind::Config))
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+    {
+        if !is_code(&id.trim().to_uppercase()) {
+            invalid.push(format!(
+                "warnings.allow: \"{}\" is not a warning code, see `investigate warnings`",
+                id
+            ));
+        }
+    }
+    let (unknown, typed) = match kind {
+        FileKind::Config => (
+            unknown_config_keys(&table),
+            toml::from_str::<Config>(content).err(),
+        ),
+        FileKind::Metadata => (
+            unknown_metadata_keys(&table),
+            toml::from_str::<Metadata>(content).err(),
+        ),
+    };
+    typed
+        .map(|e| e.to_string().trim().to_owned())
+        .into_iter()
+        .chain(invalid)
+        .chain(unknown)
+        .collect()
+}
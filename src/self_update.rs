@@ -0,0 +1,233 @@
+use clap::Args;
+use serde_json::Value;
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::data::sha256;
+use crate::{exit_with_error, require_network};
+
+/// The GitHub repository whose releases contain the binaries of investigate.
+const REPOSITORY: &str = "jakobnissen/investigate";
+
+#[derive(Args)]
+pub struct SelfUpdateArgs {
+    /// Only check whether a newer version exists, without installing it
+    #[clap(long)]
+    check: bool,
+}
+
+/// A file attached to a GitHub release.
+struct Asset {
+    name: String,
+    url: String,
+}
+
+/// Download `url` with curl, to `output` if given, else returning the body.
+fn curl(url: &str, output: Option<&Path>) -> Vec<u8> {
+    let mut command = Command::new("curl");
+    command.args(["-sSfL", "-H", "Accept: application/vnd.github+json", url]);
+    if let Some(output) = output {
+        command.arg("-o").arg(output);
+    }
+    let result = command
+        .output()
+        .unwrap_or_else(|_| exit_with_error("Could not run curl"));
+    if !result.status.success() {
+        exit_with_error(&format!(
+            "Could not download {}: {}",
+            url,
+            String::from_utf8_lossy(&result.stderr).trim()
+        ))
+    }
+    result.stdout
+}
+
+/// Parse a version like "v1.2.3" into its numbers, if it has that form.
+fn parse_version(version: &str) -> Option<(u64, u64, u64)> {
+    let numbers: Vec<u64> = version
+        .trim_start_matches('v')
+        .split('.')
+        .map(|n| n.parse().ok())
+        .collect::<Option<_>>()?;
+    match numbers[..] {
+        [major, minor, patch] => Some((major, minor, patch)),
+        _ => None,
+    }
+}
+
+/// Check if the release asset `name` is a binary for this platform, judged by its name,
+/// e.g. "investigate-x86_64-unknown-linux-musl.tar.gz".
+fn is_for_platform(name: &str) -> bool {
+    let name = name.to_lowercase();
+    let systems: &[&str] = match std::env::consts::OS {
+        "macos" => &["darwin", "macos", "apple"],
+        os => &[os],
+    };
+    let architectures: &[&str] = match std::env::consts::ARCH {
+        "aarch64" => &["aarch64", "arm64"],
+        "x86_64" => &["x86_64", "amd64"],
+        arch => &[arch],
+    };
+    systems.iter().any(|os| name.contains(os))
+        && architectures.iter().any(|arch| name.contains(arch))
+        && !name.ends_with(".sha256")
+        && !name.ends_with(".sig")
+}
+
+/// Find the expected SHA-256 checksum of `asset` among `assets`: either in an `ASSET.sha256`
+/// file, or in a file listing the checksums of every asset, like `SHA256SUMS`.
+fn expected_checksum(asset: &Asset, assets: &[Asset]) -> Option<String> {
+    let own = format!("{}.sha256", asset.name);
+    let checksum_file = assets.iter().find(|a| a.name == own).or_else(|| {
+        assets
+            .iter()
+            .find(|a| a.name.to_lowercase().starts_with("sha256sums"))
+    })?;
+    let listing = String::from_utf8_lossy(&curl(&checksum_file.url, None)).into_owned();
+    listing.lines().find_map(|line| {
+        let mut fields = line.split_whitespace();
+        let checksum = fields.next()?;
+        match fields.next() {
+            // sha256sum marks files read in binary mode with a leading '*'
+            Some(name) if name.trim_start_matches('*') != asset.name => None,
+            _ => Some(checksum.to_lowercase()),
+        }
+    })
+}
+
+/// Get the path of the binary in the downloaded `archive`, unpacking it if it's a tarball.
+fn unpack(archive: &Path, directory: &Path) -> PathBuf {
+    let name = archive.file_name().unwrap_or_default().to_string_lossy();
+    if !(name.ends_with(".tar.gz") || name.ends_with(".tgz")) {
+        return archive.to_owned();
+    }
+    let status = Command::new("tar")
+        .arg("-xzf")
+        .arg(archive)
+        .arg("-C")
+        .arg(directory)
+        .status()
+        .unwrap_or_else(|_| exit_with_error("Could not run tar"));
+    if !status.success() {
+        exit_with_error(&format!("Could not unpack {}", name))
+    }
+    crate::report::walk_files(directory)
+        .into_iter()
+        .find(|path| path.file_stem().is_some_and(|stem| stem == "investigate"))
+        .unwrap_or_else(|| exit_with_error(&format!("No investigate binary found in {}", name)))
+}
+
+/// Replace the running executable by `binary`.
+fn replace_executable(binary: &Path, executable: &Path) -> std::io::Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(binary, std::fs::Permissions::from_mode(0o755))?;
+    }
+    // Copy next to the executable first, so the final rename is atomic and on one filesystem
+    let temporary = executable.with_extension("new");
+    std::fs::copy(binary, &temporary)?;
+    // Windows can't replace a running executable, but can rename it. The old one left over
+    // from the previous update isn't running anymore, so it can be removed
+    #[cfg(windows)]
+    let old = {
+        let old = executable.with_extension("old");
+        let _ = std::fs::remove_file(&old);
+        std::fs::rename(executable, &old).inspect_err(|_| {
+            let _ = std::fs::remove_file(&temporary);
+        })?;
+        old
+    };
+    std::fs::rename(&temporary, executable).inspect_err(|_| {
+        let _ = std::fs::remove_file(&temporary);
+        #[cfg(windows)]
+        let _ = std::fs::rename(&old, executable);
+    })
+}
+
+pub fn run(args: SelfUpdateArgs) {
+    require_network("Updating investigate");
+    let current = env!("CARGO_PKG_VERSION");
+    let url = format!(
+        "https://api.github.com/repos/{}/releases/latest",
+        REPOSITORY
+    );
+    let release: Value = serde_json::from_slice(&curl(&url, None))
+        .unwrap_or_else(|_| exit_with_error("Could not parse the release from GitHub"));
+    let tag = release
+        .get("tag_name")
+        .and_then(Value::as_str)
+        .unwrap_or_else(|| exit_with_error("The latest release on GitHub has no tag"));
+    let latest = tag.trim_start_matches('v');
+    if parse_version(latest) <= parse_version(current) {
+        println!("investigate {} is the latest version", current);
+        return;
+    }
+    let assets: Vec<Asset> = release
+        .get("assets")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|asset| {
+            Some(Asset {
+                name: asset.get("name")?.as_str()?.to_owned(),
+                url: asset.get("browser_download_url")?.as_str()?.to_owned(),
+            })
+        })
+        .collect();
+    let asset = assets
+        .iter()
+        .find(|asset| is_for_platform(&asset.name))
+        .unwrap_or_else(|| {
+            exit_with_error(&format!(
+                "Release {} has no binary for {}-{}. Build it from source instead",
+                tag,
+                std::env::consts::ARCH,
+                std::env::consts::OS
+            ))
+        });
+    if args.check {
+        println!(
+            "investigate {} is available (installed: {}), as {}.\n\
+            Install it with `investigate self-update`",
+            latest, current, asset.name
+        );
+        return;
+    }
+    // Never install a binary which can't be verified
+    let checksum = expected_checksum(asset, &assets).unwrap_or_else(|| {
+        exit_with_error(&format!(
+            "Release {} has no SHA-256 checksum of {}, so it can't be verified",
+            tag, asset.name
+        ))
+    });
+    let executable = std::env::current_exe()
+        .and_then(|path| path.canonicalize())
+        .unwrap_or_else(|_| exit_with_error("Could not find the path of investigate"));
+    let directory = std::env::temp_dir().join(format!("investigate-update-{}", std::process::id()));
+    std::fs::create_dir_all(&directory)
+        .unwrap_or_else(|_| panic!("Error when creating directory {:?}", directory));
+    let download = directory.join(&asset.name);
+    println!("Downloading {}", asset.name);
+    curl(&asset.url, Some(&download));
+    let actual =
+        sha256(&download).unwrap_or_else(|_| panic!("Error when reading file {:?}", download));
+    if actual != checksum {
+        let _ = std::fs::remove_dir_all(&directory);
+        exit_with_error(&format!(
+            "Checksum of {} is {}, but the release says {}. Not installing it",
+            asset.name, actual, checksum
+        ))
+    }
+    let binary = unpack(&download, &directory);
+    let result = replace_executable(&binary, &executable);
+    let _ = std::fs::remove_dir_all(&directory);
+    if let Err(e) = result {
+        exit_with_error(&format!(
+            "Could not replace {:?}: {}. Is it writable?",
+            executable, e
+        ))
+    }
+    println!("Updated investigate from {} to {}", current, latest);
+}
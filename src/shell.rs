@@ -0,0 +1,197 @@
+use clap::{ArgEnum, Args};
+
+use std::path::Path;
+
+use crate::metadata::Metadata;
+use crate::new::Language;
+use crate::{exit_with_error, find_project_root_opt};
+
+#[derive(Copy, Clone, PartialEq, Eq, ArgEnum)]
+pub enum HookShell {
+    Bash,
+    Zsh,
+    Fish,
+    /// A custom module for the starship prompt, to add to starship.toml
+    Starship,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, ArgEnum)]
+pub enum InitShell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+#[derive(Args)]
+pub struct InitArgs {
+    /// Shell to print the function for
+    #[clap(arg_enum, value_parser)]
+    shell: InitShell,
+}
+
+#[derive(Args)]
+pub struct HookArgs {
+    /// Shell to print the prompt snippet for
+    #[clap(arg_enum, value_parser, required_unless_present = "prompt")]
+    shell: Option<HookShell>,
+
+    /// Print the prompt segment of the current project, as the snippets do. Exits with an
+    /// error outside a project
+    #[clap(long)]
+    prompt: bool,
+}
+
+const BASH_HOOK: &str = r#"# Show the current investigate project in the prompt
+__investigate_prompt() {
+    local segment
+    segment=$(command investigate shell-hook --prompt 2>/dev/null)
+    INVESTIGATE_PROMPT=${segment:+"[$segment] "}
+}
+if [[ ";${PROMPT_COMMAND:-};" != *";__investigate_prompt;"* ]]; then
+    PROMPT_COMMAND="__investigate_prompt${PROMPT_COMMAND:+;$PROMPT_COMMAND}"
+fi
+if [[ $PS1 != *'${INVESTIGATE_PROMPT}'* ]]; then
+    PS1='${INVESTIGATE_PROMPT}'"$PS1"
+fi
+"#;
+
+const ZSH_HOOK: &str = r#"# Show the current investigate project in the prompt
+__investigate_prompt() {
+    local segment
+    segment=$(command investigate shell-hook --prompt 2>/dev/null)
+    INVESTIGATE_PROMPT=${segment:+"[$segment] "}
+}
+autoload -Uz add-zsh-hook
+add-zsh-hook precmd __investigate_prompt
+setopt prompt_subst
+if [[ $PROMPT != *'${INVESTIGATE_PROMPT}'* ]]; then
+    PROMPT='${INVESTIGATE_PROMPT}'"$PROMPT"
+fi
+"#;
+
+const FISH_HOOK: &str = r#"# Show the current investigate project in the prompt
+if not functions -q __investigate_original_prompt
+    functions -c fish_prompt __investigate_original_prompt
+    function fish_prompt
+        set -l segment (command investigate shell-hook --prompt 2>/dev/null)
+        test -n "$segment"; and echo -n "[$segment] "
+        __investigate_original_prompt
+    end
+end
+"#;
+
+const STARSHIP_MODULE: &str = r#"# Show the current investigate project in the prompt
+[custom.investigate]
+description = "The current investigate project and whether its environment is active"
+command = "investigate shell-hook --prompt"
+when = "investigate shell-hook --prompt"
+format = '[\[$output\]]($style) '
+style = "bold blue"
+"#;
+
+/// Environment variable naming the file `investigate new` writes the path of the new project
+/// to, so the shell function of `investigate shell-init` can cd into it.
+const CD_FILE_VAR: &str = "INVESTIGATE_CD_FILE";
+
+const BASH_INIT: &str = r#"# Make `investigate new` cd into the new project
+investigate() {
+    if [ "$1" != new ]; then
+        command investigate "$@"
+        return
+    fi
+    local file code
+    file=$(mktemp) || return
+    INVESTIGATE_CD_FILE="$file" command investigate "$@"
+    code=$?
+    if [ "$code" -eq 0 ] && [ -s "$file" ]; then
+        cd -- "$(cat -- "$file")" || code=$?
+    fi
+    rm -f -- "$file"
+    return "$code"
+}
+"#;
+
+const FISH_INIT: &str = r#"# Make `investigate new` cd into the new project
+function investigate
+    if test "$argv[1]" != new
+        command investigate $argv
+        return
+    end
+    set -l file (mktemp); or return
+    INVESTIGATE_CD_FILE=$file command investigate $argv
+    set -l code $status
+    if test $code -eq 0; and test -s $file
+        cd (cat $file); or set code $status
+    end
+    rm -f $file
+    return $code
+end
+"#;
+
+/// Tell the shell function of `investigate shell-init`, if it's the caller, to cd to `path`.
+pub fn request_cd(path: &Path) {
+    let Some(file) = std::env::var_os(CD_FILE_VAR) else {
+        return;
+    };
+    let path = std::path::absolute(path).unwrap_or_else(|_| path.to_owned());
+    std::fs::write(&file, path.as_os_str().as_encoded_bytes())
+        .unwrap_or_else(|_| panic!("Error when writing file {:?}", file));
+}
+
+/// Get whether the environment of the project at `root` is active in the calling shell, or
+/// None if it has no environment to activate.
+fn environment_active(root: &Path, metadata: &Metadata) -> Option<bool> {
+    let var = |name: &str| std::env::var_os(name).unwrap_or_default();
+    let venv = root.join(".venv");
+    if venv.is_dir() {
+        Some(Path::new(&var("VIRTUAL_ENV")) == venv)
+    } else if let Some(name) = &metadata.environment {
+        Some(var("CONDA_DEFAULT_ENV") == name.as_str())
+    } else if metadata.language == Some(Language::Julia) {
+        Some(Path::new(&var("JULIA_PROJECT")) == root)
+    } else {
+        None
+    }
+}
+
+/// Print e.g. "rna_seq (env)", or "rna_seq (env inactive)" if the project's environment is
+/// not the active one, which is how results end up made with the wrong package versions.
+fn print_prompt() {
+    let Some(root) = find_project_root_opt() else {
+        std::process::exit(1)
+    };
+    let metadata = Metadata::load(&root);
+    match environment_active(&root, &metadata) {
+        None => println!("{}", metadata.name),
+        Some(true) => println!("{} (env)", metadata.name),
+        Some(false) => match std::env::var("CONDA_DEFAULT_ENV") {
+            Ok(other) if other != "base" && metadata.environment.is_some() => {
+                println!("{} (wrong env: {})", metadata.name, other)
+            }
+            _ => println!("{} (env inactive)", metadata.name),
+        },
+    }
+}
+
+pub fn run_hook(args: HookArgs) {
+    if args.prompt {
+        return print_prompt();
+    }
+    let snippet = match args.shell {
+        Some(HookShell::Bash) => BASH_HOOK,
+        Some(HookShell::Zsh) => ZSH_HOOK,
+        Some(HookShell::Fish) => FISH_HOOK,
+        Some(HookShell::Starship) => STARSHIP_MODULE,
+        None => exit_with_error("No shell given"),
+    };
+    print!("{}", snippet);
+}
+
+pub fn run_init(args: InitArgs) {
+    // zsh understands the bash function as it is
+    let function = match args.shell {
+        InitShell::Bash | InitShell::Zsh => BASH_INIT,
+        InitShell::Fish => FISH_INIT,
+    };
+    print!("{}", function);
+}
@@ -0,0 +1,235 @@
+use clap::{Args, Subcommand};
+
+use std::collections::BTreeSet;
+use std::ffi::OsString;
+use std::fs::read_to_string;
+use std::path::Path;
+
+use crate::config::Config;
+use crate::env::parse_dependencies;
+use crate::exec::project_command;
+use crate::julia::manifest_versions;
+use crate::templates::Templates;
+use crate::tools::parse_version;
+use crate::warning::{warn, Code};
+use crate::{exit_with_error, filesystem, find_project_root};
+
+/// Path of the table of software versions, relative to the project root
+pub const SOFTWARE_FILE: &str = "results/software_versions.tsv";
+
+/// Path of the methods section snippet, relative to the project root
+const METHODS_FILE: &str = "paper/software_methods.md";
+
+#[derive(Subcommand)]
+pub enum SoftwareCommand {
+    /// Write the versions of the project's software to results/software_versions.tsv, and a
+    /// paragraph for the paper's methods section citing them
+    Record(RecordArgs),
+}
+
+#[derive(Args)]
+pub struct RecordArgs {
+    /// Programs to also record the version of, as printed by `PROGRAM --version` in the
+    /// project's environment. Programs recorded before are recorded again
+    programs: Vec<String>,
+}
+
+/// A piece of software and its version, as a row of the table.
+struct Software {
+    name: String,
+    version: String,
+    /// Where the version is from: "conda", "pip", "julia" or "command"
+    source: &'static str,
+}
+
+/// Get the version in a Conda lock file entry like "=1.26.4=py311h64a7726_0" or "==2.31.0".
+fn lock_version(constraint: &str) -> Option<String> {
+    let version = constraint.trim_start_matches(['=', ' ']);
+    let version = version.split('=').next()?;
+    (!version.is_empty()).then(|| version.to_owned())
+}
+
+/// Get the versions of the Conda and pip packages the environment spec asks for, as resolved
+/// in environment.lock.yml. All of them would also list every dependency of the dependencies.
+fn conda_software(root: &Path) -> Vec<Software> {
+    let Ok(lock) = read_to_string(root.join("environment.lock.yml")) else {
+        if root.join("environment.yml").is_file() {
+            warn(
+                Code::NotLocked,
+                "No environment.lock.yml found, so the versions of the Conda packages \
+                are not recorded",
+            );
+        }
+        return Vec::new();
+    };
+    let requested: Option<BTreeSet<String>> = read_to_string(root.join("environment.yml"))
+        .ok()
+        .map(|spec| {
+            parse_dependencies(&spec)
+                .into_iter()
+                .map(|package| package.name.to_lowercase())
+                .collect()
+        });
+    parse_dependencies(&lock)
+        .into_iter()
+        .filter(|package| {
+            requested
+                .as_ref()
+                .is_none_or(|names| names.contains(&package.name.to_lowercase()))
+        })
+        .filter_map(|package| {
+            Some(Software {
+                version: lock_version(package.version.as_deref()?)?,
+                source: if package.pip { "pip" } else { "conda" },
+                name: package.name,
+            })
+        })
+        .collect()
+}
+
+/// Get the versions of Julia and the direct dependencies in Project.toml, as resolved in
+/// Manifest.toml.
+fn julia_software(root: &Path) -> Vec<Software> {
+    let Ok(project) = read_to_string(root.join("Project.toml")) else {
+        return Vec::new();
+    };
+    let manifest = root.join("Manifest.toml");
+    if !manifest.is_file() {
+        warn(
+            Code::NotLocked,
+            "No Manifest.toml found, so the versions of the Julia packages are \
+            not recorded",
+        );
+        return Vec::new();
+    }
+    let project: toml::Table = toml::from_str(&project)
+        .unwrap_or_else(|e| exit_with_error(&format!("Could not parse Project.toml: {}", e)));
+    let (versions, julia_version) = manifest_versions(&manifest);
+    let deps = project.get("deps").and_then(toml::Value::as_table);
+    let julia = julia_version.map(|version| Software {
+        name: "julia".to_owned(),
+        version,
+        source: "julia",
+    });
+    let packages = versions
+        .into_iter()
+        .filter(|(name, _)| deps.is_some_and(|deps| deps.contains_key(name)))
+        .map(|(name, version)| Software {
+            name,
+            version,
+            source: "julia",
+        });
+    julia.into_iter().chain(packages).collect()
+}
+
+/// Get the version of `program`, as printed by `program --version` in the project's environment.
+fn program_version(root: &Path, program: &str) -> Option<String> {
+    let output = project_command(
+        root,
+        &OsString::from(program),
+        &[OsString::from("--version")],
+    )
+    .output()
+    .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    parse_version(&String::from_utf8_lossy(&output.stdout))
+        .or_else(|| parse_version(&String::from_utf8_lossy(&output.stderr)))
+}
+
+/// Get the programs recorded in the existing table of software versions.
+fn recorded_programs(root: &Path) -> Vec<String> {
+    let content = read_to_string(root.join(SOFTWARE_FILE)).unwrap_or_default();
+    content
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split('\t').collect();
+            (fields.get(2) == Some(&"command")).then(|| fields[0].to_owned())
+        })
+        .collect()
+}
+
+/// Join `items` like "a, b and c".
+fn join_list(items: &[String]) -> String {
+    match items {
+        [] => String::new(),
+        [item] => item.clone(),
+        [init @ .., last] => format!("{} and {}", init.join(", "), last),
+    }
+}
+
+fn record(args: RecordArgs) {
+    let root = find_project_root();
+    let mut programs = recorded_programs(&root);
+    for program in args.programs {
+        if !programs.contains(&program) {
+            programs.push(program);
+        }
+    }
+    let mut software = conda_software(&root);
+    software.extend(julia_software(&root));
+    for program in programs {
+        match program_version(&root, &program) {
+            Some(version) => software.push(Software {
+                name: program,
+                version,
+                source: "command",
+            }),
+            None => warn(
+                Code::UnknownToolVersion,
+                &format!(
+                    "Could not get the version of {} with `{} --version`, so it is \
+                    not recorded",
+                    program, program
+                ),
+            ),
+        }
+    }
+    if software.is_empty() {
+        exit_with_error(
+            "No software versions found. Lock the environment, or give programs to record",
+        )
+    }
+    let mut table = String::from("software\tversion\tsource\n");
+    for entry in &software {
+        table.push_str(&format!(
+            "{}\t{}\t{}\n",
+            entry.name, entry.version, entry.source
+        ));
+    }
+    let path = root.join(SOFTWARE_FILE);
+    filesystem::create_dir_all(root.join("results"))
+        .unwrap_or_else(|_| panic!("Error when creating directory {:?}", root.join("results")));
+    filesystem::write(&path, table)
+        .unwrap_or_else(|_| panic!("Error when writing file {:?}", path));
+    println!(
+        "Recorded the versions of {} programs and packages in {}",
+        software.len(),
+        SOFTWARE_FILE
+    );
+    let cited: Vec<String> = software
+        .iter()
+        .map(|entry| format!("{} (version {})", entry.name, entry.version))
+        .collect();
+    let templates = Templates::new(Vec::new(), &Config::load());
+    let methods = templates.render(
+        "software_methods",
+        &[("software", &join_list(&cited)), ("table", SOFTWARE_FILE)],
+    );
+    if root.join("paper").is_dir() {
+        let path = root.join(METHODS_FILE);
+        filesystem::write(&path, methods)
+            .unwrap_or_else(|_| panic!("Error when writing file {:?}", path));
+        println!("Wrote methods paragraph to {}", METHODS_FILE);
+    } else {
+        print!("{}", methods);
+    }
+}
+
+pub fn run(command: SoftwareCommand) {
+    match command {
+        SoftwareCommand::Record(args) => record(args),
+    }
+}
@@ -0,0 +1,204 @@
+use std::ffi::OsStr;
+use std::io::{Error, ErrorKind, Result, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+
+use crate::data::shell_join;
+use crate::exit_with_error;
+use crate::filesystem::Filesystem;
+
+/// A directory on another machine, given as `[user@]host:path`.
+pub struct Target {
+    pub host: String,
+    pub path: PathBuf,
+}
+
+impl Target {
+    /// Parse `dirname` as a remote directory, like "user@server:/data/projects/foo". Returns
+    /// `None` for local paths, including those with a Windows drive letter, like "C:\foo".
+    /// As for `scp`, a colon before any slash makes it remote, so a local directory with a colon
+    /// in its name must be given with a slash, like "./runs:2024".
+    pub fn parse(dirname: &OsStr) -> Option<Target> {
+        let (host, path) = dirname.to_str()?.split_once(':')?;
+        if host.len() < 2 || host.contains(['/', '\\']) || path.is_empty() {
+            return None;
+        }
+        Some(Target {
+            host: host.to_owned(),
+            path: PathBuf::from(path),
+        })
+    }
+}
+
+/// Get a command running `program` with `args` in the directory `dir` on `host` over SSH.
+pub fn command(host: &str, program: &str, args: &[String], dir: &Path) -> Command {
+    let mut words = vec![program.to_owned()];
+    words.extend_from_slice(args);
+    remote_shell(
+        host,
+        &format!("cd {} && {}", quote(dir), shell_join(&words)),
+    )
+}
+
+/// Get a command running `script` in the shell of `host` over SSH. The SSH connection is shared
+/// between commands, so the many small commands of creating a project don't each log in.
+pub fn remote_shell(host: &str, script: &str) -> Command {
+    let mut command = Command::new("ssh");
+    #[cfg(unix)]
+    command.args([
+        "-o",
+        "ControlMaster=auto",
+        "-o",
+        &format!(
+            "ControlPath={}",
+            std::env::temp_dir().join("investigate-ssh-%C").display()
+        ),
+        "-o",
+        "ControlPersist=60",
+    ]);
+    command.args([host, "--", script]);
+    command
+}
+
+fn quote(path: &Path) -> String {
+    shell_join(&[path.to_string_lossy().into_owned()])
+}
+
+/// The filesystem of another machine, accessed with the `ssh` command.
+pub struct Ssh {
+    host: String,
+}
+
+impl Ssh {
+    /// Connect to the host of `target`, exiting if it can't be reached.
+    pub fn connect(target: &Target) -> Ssh {
+        let host = &target.host;
+        let ssh = Ssh { host: host.clone() };
+        match remote_shell(host, "true").status() {
+            Ok(status) if status.success() => ssh,
+            _ => exit_with_error(&format!(
+                "Could not connect to {} over SSH. Check that `ssh {}` works, or for a local \
+                directory, write it as ./{}:{}",
+                host,
+                host,
+                host,
+                target.path.display()
+            )),
+        }
+    }
+
+    /// Run `script` on the host, with `input` as its stdin, failing if it exits with an error.
+    fn run(&self, script: &str, input: &[u8]) -> Result<Output> {
+        let mut child = remote_shell(&self.host, script)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        child
+            .stdin
+            .take()
+            .expect("stdin is piped")
+            .write_all(input)?;
+        let output = child.wait_with_output()?;
+        if output.status.success() {
+            Ok(output)
+        } else {
+            Err(Error::other(
+                String::from_utf8_lossy(&output.stderr).trim().to_owned(),
+            ))
+        }
+    }
+
+    fn test(&self, flag: &str, path: &Path) -> bool {
+        self.run(&format!("test {} {}", flag, quote(path)), &[])
+            .is_ok()
+    }
+}
+
+impl Filesystem for Ssh {
+    fn exists(&self, path: &Path) -> bool {
+        self.test("-e", path)
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        self.test("-d", path)
+    }
+
+    fn read_to_string(&self, path: &Path) -> Result<String> {
+        let output = self.run(&format!("cat {}", quote(path)), &[])?;
+        String::from_utf8(output.stdout).map_err(|e| Error::new(ErrorKind::InvalidData, e))
+    }
+
+    fn read_dir(&self, path: &Path) -> Result<Vec<PathBuf>> {
+        let output = self.run(&format!("ls -A {}", quote(path)), &[])?;
+        Ok(String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(|name| path.join(name))
+            .collect())
+    }
+
+    fn write(&self, path: &Path, contents: &[u8]) -> Result<()> {
+        self.run(&format!("cat > {}", quote(path)), contents)
+            .map(|_| ())
+    }
+
+    fn append(&self, path: &Path, contents: &[u8]) -> Result<()> {
+        self.run(&format!("cat >> {}", quote(path)), contents)
+            .map(|_| ())
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> Result<()> {
+        self.run(&format!("mv -f {} {}", quote(from), quote(to)), &[])
+            .map(|_| ())
+    }
+
+    fn create_dir(&self, path: &Path) -> Result<()> {
+        self.run(&format!("mkdir {}", quote(path)), &[]).map(|_| ())
+    }
+
+    fn create_dir_all(&self, path: &Path) -> Result<()> {
+        self.run(&format!("mkdir -p {}", quote(path)), &[])
+            .map(|_| ())
+    }
+
+    fn set_executable(&self, path: &Path) -> Result<()> {
+        self.run(&format!("chmod 755 {}", quote(path)), &[])
+            .map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(dirname: &str) -> Option<(String, String)> {
+        Target::parse(OsStr::new(dirname))
+            .map(|target| (target.host, target.path.display().to_string()))
+    }
+
+    #[test]
+    fn parses_remote_directories() {
+        let remote = |host: &str, path: &str| Some((host.to_owned(), path.to_owned()));
+        assert_eq!(
+            parse("me@cluster:/data/foo"),
+            remote("me@cluster", "/data/foo")
+        );
+        assert_eq!(parse("cluster:foo"), remote("cluster", "foo"));
+        // Like scp, a colon before any slash is a host, even without a user
+        assert_eq!(parse("runs:2024"), remote("runs", "2024"));
+    }
+
+    #[test]
+    fn parses_local_directories() {
+        for dirname in [
+            "foo",
+            "./runs:2024",
+            "/data/runs:2024",
+            "C:\\foo",
+            "C:/foo",
+            "foo:",
+        ] {
+            assert_eq!(parse(dirname), None, "{}", dirname);
+        }
+    }
+}
@@ -0,0 +1,159 @@
+use serde::{Deserialize, Serialize};
+
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::PathBuf;
+
+use crate::config::{data_dir, Config};
+use crate::exit_with_error;
+use crate::lock::lock;
+use crate::new::Language;
+use crate::warning::{warn, Code};
+
+/// A project created by `investigate new`, as recorded in the stats file.
+#[derive(Serialize, Deserialize)]
+struct ProjectRecord {
+    created: String,
+    language: Option<Language>,
+    profile: Option<String>,
+    options: Vec<String>,
+}
+
+/// Get the path of the stats file, with one JSON line per created project.
+fn stats_path() -> Option<PathBuf> {
+    Some(data_dir()?.join("stats.jsonl"))
+}
+
+fn enabled(config: &Config) -> bool {
+    config.stats.unwrap_or(false)
+}
+
+/// Record that a project was created, if the user has opted in to stats.
+/// The stats never leave this computer.
+pub fn record_project(
+    config: &Config,
+    language: Option<Language>,
+    profile: Option<String>,
+    options: Vec<String>,
+) {
+    if !enabled(config) {
+        return;
+    }
+    let path = match stats_path() {
+        Some(path) => path,
+        None => return,
+    };
+    let record = ProjectRecord {
+        created: chrono::Local::now().format("%Y-%m-%d").to_string(),
+        language,
+        profile,
+        options,
+    };
+    let line = serde_json::to_string(&record).expect("Error when serializing stats");
+    let _lock = lock(&path);
+    let result = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .and_then(|mut file| writeln!(file, "{}", line));
+    if let Err(e) = result {
+        warn(
+            Code::StatsProblem,
+            &format!("Could not record stats in {:?}: {}", path, e),
+        );
+    }
+}
+
+fn load_records(path: &PathBuf) -> Vec<ProjectRecord> {
+    let content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(_) => return Vec::new(),
+    };
+    content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| match serde_json::from_str(line) {
+            Ok(record) => Some(record),
+            Err(e) => {
+                warn(
+                    Code::InvalidRecord,
+                    &format!("Skipping malformed line in {:?}: {}", path, e),
+                );
+                None
+            }
+        })
+        .collect()
+}
+
+fn print_counts(title: &str, counts: &BTreeMap<String, usize>, total: usize) {
+    println!("\n{}:", title);
+    let mut counts: Vec<(&String, &usize)> = counts.iter().collect();
+    counts.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+    let width = counts.iter().map(|(name, _)| name.len()).max().unwrap_or(0);
+    for (name, count) in counts {
+        println!(
+            "  {:width$}  {:>4}  ({:.0}%)",
+            name,
+            count,
+            100.0 * *count as f64 / total as f64,
+            width = width
+        );
+    }
+}
+
+pub fn run() {
+    let config = Config::load();
+    let path = stats_path()
+        .unwrap_or_else(|| exit_with_error("Could not find the data directory: $HOME is not set"));
+    let records = load_records(&path);
+    if records.is_empty() {
+        if enabled(&config) {
+            println!("No projects recorded yet");
+        } else {
+            println!(
+                "Stats are not enabled. Enable them with `investigate config set stats true`\n\
+                to record the options of the projects you create, in {:?}.\n\
+                The stats are only kept on this computer.",
+                path
+            );
+        }
+        return;
+    }
+    if !enabled(&config) {
+        warn(
+            Code::StatsProblem,
+            "Stats are disabled, no new projects are recorded",
+        );
+    }
+    let total = records.len();
+    let first = records.iter().map(|r| r.created.as_str()).min();
+    println!(
+        "{} projects created since {}",
+        total,
+        first.unwrap_or_default()
+    );
+    let mut languages: BTreeMap<String, usize> = BTreeMap::new();
+    let mut profiles: BTreeMap<String, usize> = BTreeMap::new();
+    let mut options: BTreeMap<String, usize> = BTreeMap::new();
+    for record in &records {
+        let language = match record.language {
+            Some(Language::Python) => "python",
+            Some(Language::Julia) => "julia",
+            None => "none",
+        };
+        *languages.entry(language.to_owned()).or_default() += 1;
+        if let Some(profile) = &record.profile {
+            *profiles.entry(profile.clone()).or_default() += 1;
+        }
+        for option in &record.options {
+            *options.entry(option.clone()).or_default() += 1;
+        }
+    }
+    print_counts("Languages", &languages, total);
+    if !profiles.is_empty() {
+        print_counts("Profiles", &profiles, total);
+    }
+    if !options.is_empty() {
+        print_counts("Options", &options, total);
+    }
+}
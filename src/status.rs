@@ -0,0 +1,28 @@
+use crate::dashboard::{print_items, summarize};
+use crate::find_project_root;
+use crate::metadata::Metadata;
+use crate::todo::{load_items, TODO_FILE};
+use crate::tools::versions;
+
+pub fn run() {
+    let root = find_project_root();
+    let metadata = Metadata::load(&root);
+    let summary = summarize(&root, &metadata, &versions());
+    println!("{} ({})", summary.name, summary.root.display());
+    println!("Status:");
+    print_items(&summary.status);
+    println!("Key dates:");
+    if summary.dates.is_empty() {
+        println!("  none approaching or passed");
+    }
+    print_items(&summary.dates);
+    println!("Open items in {}:", TODO_FILE);
+    let items = load_items(&root);
+    let open: Vec<_> = items.iter().filter(|item| !item.done).collect();
+    if open.is_empty() {
+        println!("  none");
+    }
+    for item in open {
+        println!("  #{} {}", item.number, item.text);
+    }
+}
@@ -0,0 +1,152 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::config::StorageConfig;
+use crate::run::format_bytes;
+use crate::warning::{warn, Code};
+
+/// Free space below which creating a project gives a warning, unless configured
+const DEFAULT_MIN_FREE: u64 = 1024 * 1024 * 1024;
+
+/// Parse a size like "50 GB", "50G" or "1024", in bytes, with units of 1024 like `format_bytes`.
+pub fn parse_bytes(text: &str) -> Option<u64> {
+    let text = text.trim();
+    let split = text
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let number: f64 = number.parse().ok()?;
+    let power = match unit.trim().to_uppercase().trim_end_matches(['B', 'I']) {
+        "" => 0,
+        "K" => 1,
+        "M" => 2,
+        "G" => 3,
+        "T" => 4,
+        "P" => 5,
+        _ => return None,
+    };
+    Some((number * 1024f64.powi(power)) as u64)
+}
+
+/// Get the closest existing directory containing `path`, which may not exist yet.
+fn existing_ancestor(path: &Path) -> PathBuf {
+    let path = std::path::absolute(path).unwrap_or_else(|_| path.to_owned());
+    path.ancestors()
+        .find(|ancestor| ancestor.is_dir())
+        .unwrap_or(Path::new("/"))
+        .to_owned()
+}
+
+/// Get the number of bytes available to the user on the filesystem with `path`.
+#[cfg(unix)]
+fn free_space(path: &Path) -> Option<u64> {
+    use std::os::unix::ffi::OsStrExt;
+    let path = std::ffi::CString::new(path.as_os_str().as_bytes()).ok()?;
+    // SAFETY: statvfs is plain data, which statvfs fills in
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 {
+        return None;
+    }
+    Some(stat.f_bavail as u64 * stat.f_frsize as u64)
+}
+
+#[cfg(not(unix))]
+fn free_space(_path: &Path) -> Option<u64> {
+    None
+}
+
+/// Get the bytes left of the user's quota at `path`, by running the configured quota command
+/// with the path as `$1`. The command prints the bytes left, like "120G", first in its output.
+fn quota_left(command: &str, path: &Path) -> Option<u64> {
+    let output = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .arg("sh")
+        .arg(path)
+        .output();
+    let output = match output {
+        Ok(output) if output.status.success() => output,
+        _ => {
+            warn(
+                Code::QuotaFailed,
+                &format!("The quota command `{}` failed", command),
+            );
+            return None;
+        }
+    };
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let first = stdout.split_whitespace().next().unwrap_or_default();
+    let bytes = parse_bytes(first);
+    if bytes.is_none() {
+        warn(
+            Code::QuotaFailed,
+            &format!(
+                "Could not read the bytes left from the output of the quota command `{}`",
+                command
+            ),
+        );
+    }
+    bytes
+}
+
+/// Get the space available at `path`: the free space of its filesystem, or the quota left if
+/// that is smaller.
+fn available(path: &Path, config: &StorageConfig) -> Option<u64> {
+    let directory = existing_ancestor(path);
+    let free = free_space(&directory);
+    let quota = config
+        .quota_command
+        .as_deref()
+        .and_then(|command| quota_left(command, &directory));
+    match (free, quota) {
+        (Some(free), Some(quota)) => Some(free.min(quota)),
+        (free, quota) => free.or(quota),
+    }
+}
+
+/// Warn if there is little space left where a project is about to be created at `path`,
+/// suggesting the configured alternative locations which have enough.
+pub fn check_space(path: &Path, config: &StorageConfig) {
+    let min_free = match &config.min_free {
+        None => DEFAULT_MIN_FREE,
+        Some(text) => parse_bytes(text).unwrap_or_else(|| {
+            warn(
+                Code::ConfigProblem,
+                &format!("Invalid size \"{}\" for min_free in the config file", text),
+            );
+            DEFAULT_MIN_FREE
+        }),
+    };
+    let Some(space) = available(path, config) else {
+        return;
+    };
+    if space >= min_free {
+        return;
+    }
+    warn(
+        Code::LowSpace,
+        &format!(
+            "Only {} is available for {:?}, less than the {} set as min_free. \
+            The project's files may fail to be written later",
+            format_bytes(space),
+            path,
+            format_bytes(min_free)
+        ),
+    );
+    let alternatives: Vec<String> = config
+        .locations
+        .iter()
+        .flatten()
+        .filter_map(|location| {
+            let space = available(location, config)?;
+            (space >= min_free)
+                .then(|| format!("{} ({} available)", location.display(), format_bytes(space)))
+        })
+        .collect();
+    if !alternatives.is_empty() {
+        eprintln!(
+            "Consider creating it in one of: {}",
+            alternatives.join(", ")
+        );
+    }
+}
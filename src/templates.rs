@@ -0,0 +1,823 @@
+use clap::{Args, Subcommand};
+use git2::Repository;
+use serde::Deserialize;
+use uuid::Uuid;
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::read_to_string;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::clone::{derive, name_from_path};
+use crate::config::{config_dir, site_dir, Config};
+use crate::contributor::write_authors;
+use crate::git::commit_paths;
+use crate::metadata::Metadata;
+use crate::new::{write, Existing};
+use crate::warning::{warn, Code};
+use crate::{exit_with_error, find_project_root, require_network};
+
+/// Templates compiled into the binary. User templates with the same name take precedence.
+const BUILTIN: [(&str, &str); 61] = [
+    ("assignment", include_str!("../templates/assignment")),
+    (
+        "benchmark_metrics",
+        include_str!("../templates/benchmark_metrics"),
+    ),
+    (
+        "benchmark_tools",
+        include_str!("../templates/benchmark_tools"),
+    ),
+    ("changelog", include_str!("../templates/changelog")),
+    (
+        "ci_github_julia",
+        include_str!("../templates/ci_github_julia"),
+    ),
+    (
+        "ci_github_python",
+        include_str!("../templates/ci_github_python"),
+    ),
+    (
+        "ci_gitlab_julia",
+        include_str!("../templates/ci_gitlab_julia"),
+    ),
+    (
+        "ci_gitlab_python",
+        include_str!("../templates/ci_gitlab_python"),
+    ),
+    ("citation", include_str!("../templates/citation")),
+    ("cli_python", include_str!("../templates/cli_python")),
+    (
+        "compathelper_julia",
+        include_str!("../templates/compathelper_julia"),
+    ),
+    ("data_access", include_str!("../templates/data_access")),
+    ("decision", include_str!("../templates/decision")),
+    ("decisions", include_str!("../templates/decisions")),
+    ("dir_readme", include_str!("../templates/dir_readme")),
+    (
+        "dir_readme_cache",
+        include_str!("../templates/dir_readme_cache"),
+    ),
+    (
+        "dir_readme_choices",
+        include_str!("../templates/dir_readme_choices"),
+    ),
+    (
+        "dir_readme_paper",
+        include_str!("../templates/dir_readme_paper"),
+    ),
+    (
+        "dir_readme_raw",
+        include_str!("../templates/dir_readme_raw"),
+    ),
+    (
+        "dir_readme_results",
+        include_str!("../templates/dir_readme_results"),
+    ),
+    (
+        "dir_readme_src",
+        include_str!("../templates/dir_readme_src"),
+    ),
+    (
+        "dir_readme_tmp",
+        include_str!("../templates/dir_readme_tmp"),
+    ),
+    ("docs_index", include_str!("../templates/docs_index")),
+    (
+        "docs_make_julia",
+        include_str!("../templates/docs_make_julia"),
+    ),
+    (
+        "docs_project_julia",
+        include_str!("../templates/docs_project_julia"),
+    ),
+    ("env_example", include_str!("../templates/env_example")),
+    ("environment", include_str!("../templates/environment")),
+    ("envrc", include_str!("../templates/envrc")),
+    ("finalized", include_str!("../templates/finalized")),
+    ("gitattributes", include_str!("../templates/gitattributes")),
+    ("gitignore", include_str!("../templates/gitignore")),
+    ("handover", include_str!("../templates/handover")),
+    ("main_julia", include_str!("../templates/main_julia")),
+    ("main_python", include_str!("../templates/main_python")),
+    (
+        "makefile_docs_julia",
+        include_str!("../templates/makefile_docs_julia"),
+    ),
+    (
+        "makefile_docs_python",
+        include_str!("../templates/makefile_docs_python"),
+    ),
+    (
+        "makefile_scripts",
+        include_str!("../templates/makefile_scripts"),
+    ),
+    ("mkdocs", include_str!("../templates/mkdocs")),
+    ("modulefile", include_str!("../templates/modulefile")),
+    (
+        "package_python",
+        include_str!("../templates/package_python"),
+    ),
+    ("params_julia", include_str!("../templates/params_julia")),
+    ("params_python", include_str!("../templates/params_python")),
+    ("params_toml", include_str!("../templates/params_toml")),
+    ("params_yaml", include_str!("../templates/params_yaml")),
+    (
+        "pre_commit_raw",
+        include_str!("../templates/pre_commit_raw"),
+    ),
+    ("paths_julia", include_str!("../templates/paths_julia")),
+    ("paths_python", include_str!("../templates/paths_python")),
+    ("paths_r", include_str!("../templates/paths_r")),
+    ("project", include_str!("../templates/project")),
+    ("pyproject", include_str!("../templates/pyproject")),
+    ("readme", include_str!("../templates/readme")),
+    (
+        "readme_directories",
+        include_str!("../templates/readme_directories"),
+    ),
+    (
+        "readme_scripts",
+        include_str!("../templates/readme_scripts"),
+    ),
+    (
+        "runtests_julia",
+        include_str!("../templates/runtests_julia"),
+    ),
+    ("script_julia", include_str!("../templates/script_julia")),
+    ("script_python", include_str!("../templates/script_python")),
+    (
+        "software_methods",
+        include_str!("../templates/software_methods"),
+    ),
+    ("sync_data", include_str!("../templates/sync_data")),
+    ("tagbot_julia", include_str!("../templates/tagbot_julia")),
+    (
+        "template_usage",
+        include_str!("../templates/template_usage"),
+    ),
+    ("todo", include_str!("../templates/todo")),
+];
+
+/// Name of the file in a template directory which declares the directory it inherits from
+const TEMPLATE_DIR_FILE: &str = "templates.toml";
+
+/// The settings of a template directory, in its templates.toml.
+#[derive(Deserialize, Default)]
+struct TemplateDir {
+    /// Template directory whose templates this one inherits, relative to this one,
+    /// e.g. an institute's base templates
+    parent: Option<PathBuf>,
+    /// Text blocks, taking precedence over those of the parent and of the config files
+    #[serde(default)]
+    blocks: BTreeMap<String, String>,
+}
+
+impl TemplateDir {
+    fn load(dir: &Path) -> TemplateDir {
+        let path = dir.join(TEMPLATE_DIR_FILE);
+        match read_to_string(&path) {
+            Err(_) => TemplateDir::default(),
+            Ok(content) => toml::from_str(&content)
+                .unwrap_or_else(|e| exit_with_error(&format!("Could not parse {:?}: {}", path, e))),
+        }
+    }
+}
+
+/// The set of templates used to create a project.
+pub struct Templates {
+    /// Directories with user templates, in order of precedence
+    dirs: Vec<PathBuf>,
+    /// Text blocks from the template directories and config files, available as `{blocks.NAME}`
+    blocks: BTreeMap<String, String>,
+}
+
+impl Templates {
+    /// Get the templates in `dirs`, followed by the user, site and built-in templates.
+    /// Each directory is followed by the chain of directories it inherits from.
+    pub fn new(mut roots: Vec<PathBuf>, config: &Config) -> Templates {
+        roots.extend(config_dir().map(|dir| dir.join("templates")));
+        roots.push(site_dir().join("templates"));
+        let mut dirs: Vec<PathBuf> = Vec::new();
+        let mut blocks = BTreeMap::new();
+        for root in roots {
+            let mut next = Some(root);
+            // A directory already in the chain has had its parents added, which also ends cycles
+            while let Some(dir) = next.take().filter(|dir| !dirs.contains(dir)) {
+                let settings = TemplateDir::load(&dir);
+                for (name, text) in settings.blocks {
+                    blocks.entry(name).or_insert(text);
+                }
+                if let Some(parent) = settings.parent {
+                    let parent = dir.join(parent);
+                    if !parent.is_dir() {
+                        warn(
+                            Code::TemplatesMissing,
+                            &format!(
+                                "Parent {:?} of template directory {:?} does not exist",
+                                parent, dir
+                            ),
+                        );
+                    }
+                    next = Some(parent);
+                }
+                dirs.push(dir);
+            }
+        }
+        for (name, text) in &config.blocks {
+            blocks.entry(name.clone()).or_insert_with(|| text.clone());
+        }
+        Templates { dirs, blocks }
+    }
+
+    /// Get the path of the user or site template which takes precedence over the built-in
+    /// template `name`, if any.
+    pub fn source(&self, name: &str) -> Option<PathBuf> {
+        self.dirs
+            .iter()
+            .map(|dir| dir.join(name))
+            .find(|path| path.is_file())
+    }
+
+    /// Check if a user or built-in template named `name` exists.
+    pub fn has(&self, name: &str) -> bool {
+        self.dirs.iter().any(|dir| dir.join(name).is_file())
+            || BUILTIN.iter().any(|(n, _)| *n == name)
+    }
+
+    /// Get the raw content of the template `name`.
+    pub fn get(&self, name: &str) -> String {
+        self.get_from(name, 0)
+            .unwrap_or_else(|| panic!("No built-in template named \"{}\"", name))
+    }
+
+    /// Get the raw content of the template `name` in the directories from the `start`th on,
+    /// or the built-in one. A template can include the one it overrides as `{parent}`, so
+    /// it only needs to add to it.
+    fn get_from(&self, name: &str, start: usize) -> Option<String> {
+        for (i, dir) in self.dirs.iter().enumerate().skip(start) {
+            let path = dir.join(name);
+            if !path.is_file() {
+                continue;
+            }
+            let content = read_to_string(&path).unwrap_or_else(|_| {
+                exit_with_error(&format!("Could not read template {:?}", path))
+            });
+            if !variables(&content).iter().any(|v| v == "parent") {
+                return Some(content);
+            }
+            let parent = self.get_from(name, i + 1).unwrap_or_else(|| {
+                exit_with_error(&format!(
+                    "Template {:?} uses {{parent}}, but there is no template \"{}\" to inherit",
+                    path, name
+                ))
+            });
+            return Some(substitute(&content, "parent", &parent));
+        }
+        BUILTIN
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, content)| content.to_string())
+    }
+
+    /// Get the template `name`, with each `{variable}` replaced by its value in `vars`.
+    pub fn render(&self, name: &str, vars: &[(&str, &str)]) -> String {
+        let blocks: Vec<(String, &str)> = self
+            .blocks
+            .iter()
+            .map(|(block, text)| (format!("blocks.{}", block), text.as_str()))
+            .collect();
+        let vars: Vec<(&str, &str)> = vars
+            .iter()
+            .copied()
+            .chain(blocks.iter().map(|(block, text)| (block.as_str(), *text)))
+            .collect();
+        let template = self.get(name);
+        render_str(&template, &vars).unwrap_or_else(|e| match e {
+            RenderError::Unknown(v) => exit_with_error(&format!(
+                "Unknown variable {{{}}} in template \"{}\"",
+                v, name
+            )),
+            RenderError::Unterminated(offset) => exit_with_error(&format!(
+                "Unclosed {{ on line {} of template \"{}\"",
+                line_at(&template, offset),
+                name
+            )),
+        })
+    }
+}
+
+/// Get the names of the variables `{variable}` in `template`, in order of first use.
+pub fn variables(template: &str) -> Vec<String> {
+    parse_variables(template).0
+}
+
+/// Get the names of the variables `{variable}` in `template`, in order of first use, and the
+/// byte offset of a `{` which is never closed, if any. Variables after it are not found.
+fn parse_variables(template: &str) -> (Vec<String>, Option<usize>) {
+    let mut seen = BTreeSet::new();
+    let mut names = Vec::new();
+    let mut rest = template;
+    while let Some(i) = rest.find(['{', '}']) {
+        let brace = rest.as_bytes()[i];
+        rest = &rest[i + 1..];
+        if rest.as_bytes().first() == Some(&brace) {
+            rest = &rest[1..];
+        } else if brace == b'{' {
+            let Some(end) = rest.find('}') else {
+                return (names, Some(template.len() - rest.len() - 1));
+            };
+            let name = &rest[..end];
+            if seen.insert(name) {
+                names.push(name.to_owned());
+            }
+            rest = &rest[end + 1..];
+        }
+    }
+    (names, None)
+}
+
+/// Replace `{name}` in `template` by `value`, leaving other variables and escaped braces as is.
+fn substitute(template: &str, name: &str, value: &str) -> String {
+    let mut result = String::with_capacity(template.len() + value.len());
+    let mut rest = template;
+    while let Some(i) = rest.find(['{', '}']) {
+        let brace = rest.as_bytes()[i];
+        let escaped = rest.as_bytes().get(i + 1) == Some(&brace);
+        let variable = rest[i + 1..]
+            .strip_prefix(name)
+            .and_then(|s| s.strip_prefix('}'));
+        match variable {
+            Some(after) if brace == b'{' && !escaped => {
+                result.push_str(&rest[..i]);
+                result.push_str(value);
+                rest = after;
+            }
+            _ => {
+                let end = if escaped { i + 2 } else { i + 1 };
+                result.push_str(&rest[..end]);
+                rest = &rest[end..];
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+/// Why a template could not be rendered.
+pub enum RenderError {
+    /// A variable without a value, by name
+    Unknown(String),
+    /// A `{` which is never closed, at this byte offset
+    Unterminated(usize),
+}
+
+/// Replace each `{variable}` in `template` by its value. As with `format!`, `{{` and `}}`
+/// are literal braces.
+pub fn render_str(template: &str, vars: &[(&str, &str)]) -> Result<String, RenderError> {
+    let mut result = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(i) = rest.find(['{', '}']) {
+        result.push_str(&rest[..i]);
+        let brace = rest.as_bytes()[i];
+        rest = &rest[i + 1..];
+        if rest.as_bytes().first() == Some(&brace) {
+            result.push(brace as char);
+            rest = &rest[1..];
+        } else if brace == b'}' {
+            result.push('}');
+        } else {
+            let end = rest
+                .find('}')
+                .ok_or(RenderError::Unterminated(template.len() - rest.len() - 1))?;
+            let name = &rest[..end];
+            let value = vars
+                .iter()
+                .find(|(n, _)| *n == name)
+                .ok_or_else(|| RenderError::Unknown(name.to_owned()))?
+                .1;
+            result.push_str(value);
+            rest = &rest[end + 1..];
+        }
+    }
+    result.push_str(rest);
+    Ok(result)
+}
+
+#[derive(Subcommand)]
+pub enum TemplatesCommand {
+    /// List the built-in templates, and the variables each of them uses
+    List,
+
+    /// Print a built-in template
+    Show(ShowArgs),
+
+    /// Copy built-in templates to the user template directory, to customize them
+    Eject(EjectArgs),
+
+    /// Render the templates in a template directory with sample values, and check the results
+    Test(TestArgs),
+
+    /// Make a template repository from the current project, without its data, results and
+    /// history, so others can start projects like it without investigate
+    Export(ExportArgs),
+}
+
+#[derive(Args)]
+pub struct ShowArgs {
+    /// Name of the template, as shown by `investigate templates list`
+    name: String,
+}
+
+#[derive(Args)]
+pub struct EjectArgs {
+    /// Names of the templates to copy. Copies all templates if none are given
+    names: Vec<String>,
+
+    /// Overwrite templates already in the user template directory
+    #[clap(long)]
+    force: bool,
+}
+
+#[derive(Args)]
+pub struct TestArgs {
+    /// The template directory to test
+    dir: PathBuf,
+}
+
+#[derive(Args)]
+pub struct ExportArgs {
+    /// Directory to write the template repository to. Its name is the template's name
+    dir: PathBuf,
+
+    /// Push it to this new GitHub repository, as OWNER/NAME or NAME, and mark it as a template
+    /// repository. Needs the GitHub CLI `gh`, logged in
+    #[clap(long, value_name = "REPO")]
+    github: Option<String>,
+
+    /// Make the GitHub repository public instead of private
+    #[clap(long, requires = "github")]
+    public: bool,
+}
+
+fn builtin(name: &str) -> &'static str {
+    BUILTIN
+        .iter()
+        .find(|(n, _)| *n == name)
+        .map(|(_, content)| *content)
+        .unwrap_or_else(|| {
+            exit_with_error(&format!(
+                "No built-in template named \"{}\". See `investigate templates list`",
+                name
+            ))
+        })
+}
+
+fn list() {
+    let templates = Templates::new(Vec::new(), &Config::load());
+    let width = BUILTIN
+        .iter()
+        .map(|(name, _)| name.len())
+        .max()
+        .unwrap_or(0);
+    for (name, content) in BUILTIN {
+        let variables: Vec<String> = variables(content)
+            .into_iter()
+            .map(|v| format!("{{{}}}", v))
+            .collect();
+        let line = format!("{:width$}  {}", name, variables.join(" "), width = width);
+        println!("{}", line.trim_end());
+        if let Some(path) = templates.source(name) {
+            println!("{:width$}  (overridden by {:?})", "", path, width = width);
+        }
+    }
+    println!("\nEvery template can also use the text blocks of the config file as {{blocks.NAME}}");
+}
+
+fn eject(args: EjectArgs) {
+    let dir = config_dir()
+        .map(|dir| dir.join("templates"))
+        .unwrap_or_else(|| {
+            exit_with_error("Could not locate the template directory, as $HOME is not set")
+        });
+    let names: Vec<&str> = if args.names.is_empty() {
+        BUILTIN.iter().map(|(name, _)| *name).collect()
+    } else {
+        args.names.iter().map(String::as_str).collect()
+    };
+    // Check every name before copying anything
+    let contents: Vec<&str> = names.iter().map(|name| builtin(name)).collect();
+    std::fs::create_dir_all(&dir)
+        .unwrap_or_else(|_| panic!("Error when creating directory {:?}", dir));
+    for (name, content) in names.iter().zip(contents) {
+        let path = dir.join(name);
+        if path.exists() && !args.force {
+            warn(
+                Code::NotOverwritten,
+                &format!(
+                    "{:?} already exists, so it was not overwritten. \
+                    Use --force to overwrite it",
+                    path
+                ),
+            );
+            continue;
+        }
+        std::fs::write(&path, content)
+            .unwrap_or_else(|_| panic!("Error when writing file {:?}", path));
+        println!("Wrote {:?}", path);
+    }
+}
+
+/// Run the GitHub CLI with `args` in `dir`, exiting with an error if it fails.
+fn gh(dir: &Path, args: &[&str]) {
+    let output = Command::new("gh")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .unwrap_or_else(|_| exit_with_error("Could not run the GitHub CLI `gh`. Is it installed?"));
+    if !output.status.success() {
+        exit_with_error(&format!(
+            "`gh {}` failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))
+    }
+}
+
+fn export(args: ExportArgs) {
+    let root = find_project_root();
+    let metadata = Metadata::load(&root);
+    if args.dir.exists() {
+        exit_with_error(&format!("Path {:?} already exists", args.dir))
+    }
+    let name = name_from_path(&args.dir);
+    // Fail before writing anything if the template can't be pushed
+    if args.github.is_some() {
+        require_network("Pushing the template to GitHub");
+        gh(Path::new("."), &["auth", "status"]);
+    }
+    let config = Config::load();
+    let mut template = derive(&root, &metadata, &args.dir, &name, &config);
+    // Those using the template are its authors, not whoever exported it
+    template.authors.clear();
+    template.contributors.clear();
+    template.save(&args.dir);
+    write_authors(&args.dir, &template, &[], Existing::Force);
+    let readme = args.dir.join("README.md");
+    let mut content = read_to_string(&readme).unwrap_or_default();
+    content.push_str(&Templates::new(Vec::new(), &config).render(
+        "template_usage",
+        &[("source", &metadata.name), ("name", &name)],
+    ));
+    write(&readme, &content, Existing::Force);
+
+    let result = Repository::init(&args.dir).and_then(|repo| {
+        commit_paths(
+            &repo,
+            &["."],
+            &format!("Create template from project {}", metadata.name),
+            false,
+        )
+    });
+    if let Err(e) = result {
+        exit_with_error(&format!(
+            "Could not commit the template to a git repository: {}",
+            e.message()
+        ))
+    }
+    println!("Wrote template repository {:?}", args.dir);
+
+    if let Some(repo) = args.github {
+        let visibility = if args.public { "--public" } else { "--private" };
+        gh(
+            &args.dir,
+            &[
+                "repo", "create", &repo, visibility, "--source", ".", "--push",
+            ],
+        );
+        gh(&args.dir, &["repo", "edit", "--template"]);
+        println!(
+            "Pushed it to the GitHub template repository {}. \
+            Others can create projects from it with \"Use this template\"",
+            repo
+        );
+    }
+}
+
+/// Format of a file rendered from a template, which it must be valid in.
+enum Format {
+    Toml,
+    Yaml,
+}
+
+/// Get the format of the file rendered from the template `name`, if it's checked.
+fn format(name: &str) -> Option<Format> {
+    match name {
+        "docs_project_julia" | "params_toml" | "project" | "pyproject" => Some(Format::Toml),
+        "citation" | "compathelper_julia" | "environment" | "mkdocs" | "params_yaml"
+        | "tagbot_julia" => Some(Format::Yaml),
+        name if name.starts_with("ci_") => Some(Format::Yaml),
+        _ => None,
+    }
+}
+
+/// Get a sample value of the variable `var` of the template `name`, formatted like the
+/// values it's rendered with, so the rendered template is valid if the real one is.
+fn sample(name: &str, var: &str) -> String {
+    match (name, var) {
+        ("pyproject", "authors") => r#"{ name = "Jane Doe", email = "jane@example.com" }"#,
+        ("citation", "authors") => "  - family-names: \"Doe\"\n    given-names: \"Jane\"",
+        ("environment", "channels") => "  - conda-forge",
+        ("environment", "dependencies") => "\n  - python",
+        (_, "author" | "authors") => "Jane Doe <jane@example.com>",
+        (_, "date") => "2024-01-31",
+        (_, "version") => "0.1.0",
+        (_, "seed") => "1234",
+        (_, "uuid_str") => "0b6f6ba4-2a1c-4c39-9e3e-6a7b2f0a8c1d",
+        (_, "module_name") => "SampleProject",
+        (_, "commit") => "0123456789abcdef0123456789abcdef01234567",
+        _ => return format!("sample_{}", var),
+    }
+    .to_owned()
+}
+
+/// Get the line number of the first line of `content` containing `pattern`, if any.
+fn line_of(content: &str, pattern: &str) -> Option<usize> {
+    content
+        .lines()
+        .position(|line| line.contains(pattern))
+        .map(|i| i + 1)
+}
+
+/// Get the line number of the byte at `offset` in `content`.
+fn line_at(content: &str, offset: usize) -> usize {
+    content[..offset.min(content.len())].matches('\n').count() + 1
+}
+
+/// Render the template `name` in `dir` with sample values, write it to `output`, and return
+/// the problems found, each with the line it's on.
+fn test_template(templates: &Templates, dir: &Path, name: &str, output: &Path) -> Vec<String> {
+    let path = dir.join(name);
+    let raw = read_to_string(&path)
+        .unwrap_or_else(|_| exit_with_error(&format!("Could not read template {:?}", path)));
+    let at = |line: Option<usize>| match line {
+        Some(line) => format!("{}:{}", path.display(), line),
+        None => path.display().to_string(),
+    };
+    let mut problems = Vec::new();
+    // Directory READMEs without a template of their own are rendered like the generic one
+    let reference = BUILTIN
+        .iter()
+        .find(|(n, _)| *n == name)
+        .or_else(|| {
+            name.starts_with("dir_readme_")
+                .then(|| BUILTIN.iter().find(|(n, _)| *n == "dir_readme"))
+                .flatten()
+        })
+        .map(|(_, content)| *content);
+    let Some(reference) = reference else {
+        problems.push(format!(
+            "{}: Not the name of a built-in template, so investigate never uses it",
+            at(None)
+        ));
+        return problems;
+    };
+    if let (_, Some(offset)) = parse_variables(&raw) {
+        problems.push(format!(
+            "{}: Unclosed {{. Write {{{{ for a literal brace",
+            at(Some(line_at(&raw, offset)))
+        ));
+        return problems;
+    }
+    let known = variables(reference);
+    let content = templates.get(name);
+    let samples: Vec<(String, String)> = variables(&content)
+        .into_iter()
+        .filter_map(|var| {
+            let value = if let Some(block) = var.strip_prefix("blocks.") {
+                templates.blocks.get(block).cloned()
+            } else {
+                known.contains(&var).then(|| sample(name, &var))
+            };
+            if value.is_none() {
+                let line = line_of(&raw, &format!("{{{}}}", var));
+                problems.push(format!(
+                    "{}: Unknown variable {{{}}}{}",
+                    at(line),
+                    var,
+                    if line.is_none() {
+                        ", in its parent"
+                    } else {
+                        ""
+                    }
+                ));
+            }
+            value.map(|value| (var, value))
+        })
+        .collect();
+    // Escaped braces around a variable name are almost always meant as the variable
+    for var in &known {
+        for pattern in [format!("{{{{{}}}}}", var), format!("{{{{ {} }}}}", var)] {
+            if let Some(line) = line_of(&raw, &pattern) {
+                problems.push(format!(
+                    "{}: {} is left as a placeholder. Variables are written {{{}}}",
+                    at(Some(line)),
+                    pattern,
+                    var
+                ));
+            }
+        }
+    }
+    if !problems.is_empty() {
+        return problems;
+    }
+    let vars: Vec<(&str, &str)> = samples
+        .iter()
+        .map(|(var, value)| (var.as_str(), value.as_str()))
+        .collect();
+    let rendered = match render_str(&content, &vars) {
+        Ok(rendered) => rendered,
+        // The template itself was checked above, so the problem is in its parent
+        Err(RenderError::Unterminated(_)) => {
+            problems.push(format!("{}: Unclosed {{, in its parent", at(None)));
+            return problems;
+        }
+        Err(RenderError::Unknown(var)) => {
+            problems.push(format!(
+                "{}: Unknown variable {{{}}}, in its parent",
+                at(None),
+                var
+            ));
+            return problems;
+        }
+    };
+    std::fs::write(output.join(name), &rendered)
+        .unwrap_or_else(|_| panic!("Error when writing file {:?}", output.join(name)));
+    let invalid = match format(name) {
+        None => None,
+        Some(Format::Toml) => toml::from_str::<toml::Table>(&rendered).err().map(|e| {
+            let line = e.span().map(|span| line_at(&rendered, span.start));
+            ("TOML", line, e.message().to_owned())
+        }),
+        Some(Format::Yaml) => yaml_rust2::YamlLoader::load_from_str(&rendered)
+            .err()
+            .map(|e| ("YAML", Some(e.marker().line()), e.info().to_owned())),
+    };
+    if let Some((format, line, message)) = invalid {
+        problems.push(format!(
+            "{}: Renders as invalid {}{}: {}",
+            at(None),
+            format,
+            line.map(|line| format!(" on line {} of the output", line))
+                .unwrap_or_default(),
+            message
+        ));
+    }
+    problems
+}
+
+fn test(args: TestArgs) {
+    if !args.dir.is_dir() {
+        exit_with_error(&format!("{:?} is not a directory", args.dir))
+    }
+    let templates = Templates::new(vec![args.dir.clone()], &Config::load());
+    let mut names: Vec<String> = args
+        .dir
+        .read_dir()
+        .unwrap_or_else(|_| exit_with_error(&format!("Could not read {:?}", args.dir)))
+        .flatten()
+        .filter(|entry| entry.path().is_file())
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .filter(|name| name != TEMPLATE_DIR_FILE && !name.starts_with('.'))
+        .collect();
+    names.sort();
+    let output = std::env::temp_dir().join(format!("investigate-templates-{}", Uuid::new_v4()));
+    std::fs::create_dir_all(&output)
+        .unwrap_or_else(|_| panic!("Error when creating directory {:?}", output));
+    let mut n_problems = 0;
+    for name in &names {
+        for problem in test_template(&templates, &args.dir, name, &output) {
+            println!("{}", problem);
+            n_problems += 1;
+        }
+    }
+    println!(
+        "Tested {} templates, rendered with sample values to {:?}",
+        names.len(),
+        output
+    );
+    if n_problems > 0 {
+        exit_with_error(&format!("Found {} problems in the templates", n_problems))
+    }
+}
+
+pub fn run(command: TemplatesCommand) {
+    match command {
+        TemplatesCommand::List => list(),
+        TemplatesCommand::Show(args) => print!("{}", builtin(&args.name)),
+        TemplatesCommand::Eject(args) => eject(args),
+        TemplatesCommand::Test(args) => test(args),
+        TemplatesCommand::Export(args) => export(args),
+    }
+}
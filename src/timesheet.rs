@@ -0,0 +1,217 @@
+use chrono::{Datelike, Local, NaiveDateTime};
+use clap::{ArgEnum, Args, Subcommand};
+
+use std::collections::BTreeMap;
+
+use crate::author::author_string;
+use crate::config::Config;
+use crate::metadata::{Metadata, WorkSession, METADATA_FILE};
+use crate::redact::quote_field;
+use crate::{capitalize, exit_with_error, find_project_root};
+
+/// Format of the start and end of sessions
+const TIME_FORMAT: &str = "%Y-%m-%dT%H:%M:%S";
+
+#[derive(Subcommand)]
+pub enum TimeCommand {
+    /// Start a work session on the project
+    Start(StartArgs),
+
+    /// Stop your running work session
+    Stop(StopArgs),
+
+    /// Summarize the time worked on the project, by week or month
+    Report(ReportArgs),
+}
+
+#[derive(Args)]
+pub struct StartArgs {
+    /// What the session is for, e.g. "QC of batch 2"
+    note: Option<String>,
+}
+
+#[derive(Args)]
+pub struct StopArgs {
+    /// What was done, replacing the note given when starting
+    note: Option<String>,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, ArgEnum)]
+pub enum Period {
+    Week,
+    Month,
+}
+
+#[derive(Args)]
+pub struct ReportArgs {
+    /// Period to sum the time over
+    #[clap(arg_enum, value_parser, long, default_value = "week")]
+    by: Period,
+
+    /// List each session instead of the sums
+    #[clap(long)]
+    sessions: bool,
+
+    /// Only count the sessions of authors whose "NAME <EMAIL>" contains this
+    #[clap(long)]
+    author: Option<String>,
+
+    /// Print comma-separated values, e.g. for a spreadsheet
+    #[clap(long)]
+    csv: bool,
+}
+
+fn parse_time(time: &str) -> NaiveDateTime {
+    NaiveDateTime::parse_from_str(time, TIME_FORMAT).unwrap_or_else(|_| {
+        exit_with_error(&format!(
+            "Invalid session time \"{}\" in {}",
+            time, METADATA_FILE
+        ))
+    })
+}
+
+fn now() -> NaiveDateTime {
+    Local::now().naive_local()
+}
+
+/// Get the hours of `session`, counting a running session until now.
+fn hours(session: &WorkSession) -> f64 {
+    let end = session.end.as_deref().map(parse_time).unwrap_or_else(now);
+    (end - parse_time(&session.start)).num_seconds().max(0) as f64 / 3600.0
+}
+
+fn start(args: StartArgs) {
+    let root = find_project_root();
+    let mut metadata = Metadata::load(&root);
+    let author = author_string(&Config::load());
+    if let Some(running) = metadata
+        .sessions
+        .iter()
+        .find(|session| session.end.is_none() && session.author == author)
+    {
+        exit_with_error(&format!(
+            "You have a session running since {}. Stop it with `investigate time stop`",
+            running.start.replace('T', " ")
+        ))
+    }
+    let start = now().format(TIME_FORMAT).to_string();
+    println!("Started work session at {}", start.replace('T', " "));
+    metadata.sessions.push(WorkSession {
+        start,
+        end: None,
+        author,
+        note: args.note,
+    });
+    metadata.save(&root);
+}
+
+fn stop(args: StopArgs) {
+    let root = find_project_root();
+    let mut metadata = Metadata::load(&root);
+    let author = author_string(&Config::load());
+    let Some(session) = metadata
+        .sessions
+        .iter_mut()
+        .find(|session| session.end.is_none() && session.author == author)
+    else {
+        exit_with_error("You have no running session. Start one with `investigate time start`")
+    };
+    session.end = Some(now().format(TIME_FORMAT).to_string());
+    if args.note.is_some() {
+        session.note = args.note;
+    }
+    println!("Stopped work session after {:.2} hours", hours(session));
+    metadata.save(&root);
+}
+
+/// Get the period `time` falls in, like "2024-W05" or "2024-02".
+fn period(time: NaiveDateTime, by: Period) -> String {
+    match by {
+        Period::Week => {
+            let week = time.iso_week();
+            format!("{}-W{:02}", week.year(), week.week())
+        }
+        Period::Month => time.format("%Y-%m").to_string(),
+    }
+}
+
+fn report(args: ReportArgs) {
+    let root = find_project_root();
+    let metadata = Metadata::load(&root);
+    let sessions: Vec<&WorkSession> = metadata
+        .sessions
+        .iter()
+        .filter(|session| {
+            args.author
+                .as_deref()
+                .is_none_or(|author| session.author.contains(author))
+        })
+        .collect();
+    if sessions.is_empty() {
+        println!("No work sessions recorded. Start one with `investigate time start`");
+        return;
+    }
+    let separator = if args.csv { "," } else { "  " };
+    if args.sessions {
+        if args.csv {
+            println!("start,end,hours,author,note");
+        }
+        for session in sessions {
+            let fields = [
+                session.start.replace('T', " "),
+                session
+                    .end
+                    .as_deref()
+                    .map(|end| end.replace('T', " "))
+                    .unwrap_or_else(|| "running".to_owned()),
+                format!("{:.2}", hours(session)),
+                session.author.clone(),
+                session.note.clone().unwrap_or_default(),
+            ];
+            let fields: Vec<String> = if args.csv {
+                fields.iter().map(|field| quote_field(field, ',')).collect()
+            } else {
+                fields.to_vec()
+            };
+            println!("{}", fields.join(separator));
+        }
+        return;
+    }
+    // Hours and number of sessions in each period
+    let mut periods: BTreeMap<String, (f64, usize)> = BTreeMap::new();
+    for session in &sessions {
+        let entry = periods
+            .entry(period(parse_time(&session.start), args.by))
+            .or_default();
+        entry.0 += hours(session);
+        entry.1 += 1;
+    }
+    let name = match args.by {
+        Period::Week => "week",
+        Period::Month => "month",
+    };
+    if args.csv {
+        println!("{},hours,sessions", name);
+        for (period, (hours, n)) in &periods {
+            println!("{},{:.2},{}", period, hours, n);
+        }
+        return;
+    }
+    println!("{:<8}  {:>8}  {:>8}", capitalize(name), "Hours", "Sessions");
+    for (period, (hours, n)) in &periods {
+        println!("{:<8}  {:>8.2}  {:>8}", period, hours, n);
+    }
+    let total: f64 = sessions.iter().map(|session| hours(session)).sum();
+    println!("{:<8}  {:>8.2}  {:>8}", "Total", total, sessions.len());
+    if sessions.iter().any(|session| session.end.is_none()) {
+        println!("Running sessions are counted until now");
+    }
+}
+
+pub fn run(command: TimeCommand) {
+    match command {
+        TimeCommand::Start(args) => start(args),
+        TimeCommand::Stop(args) => stop(args),
+        TimeCommand::Report(args) => report(args),
+    }
+}
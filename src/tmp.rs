@@ -0,0 +1,156 @@
+use chrono::{Duration, Local, NaiveDate};
+use clap::{Args, Subcommand};
+
+use std::fs::read_to_string;
+use std::path::{Path, PathBuf};
+
+use crate::trash::trash;
+use crate::warning::{warn, Code};
+use crate::{exit_with_error, filesystem, find_project_root};
+
+/// Name of the file in a scratch directory which holds the date it expires, as YYYY-MM-DD
+const EXPIRY_FILE: &str = ".expires";
+
+#[derive(Subcommand)]
+pub enum TmpCommand {
+    /// Create a scratch directory tmp/<DATE>_<LABEL>/, which expires after some days
+    New(NewArgs),
+
+    /// List the scratch directories in tmp/, and when they expire
+    List,
+
+    /// Move expired scratch directories to the trash
+    Gc(GcArgs),
+}
+
+#[derive(Args)]
+pub struct NewArgs {
+    /// What the directory is for, e.g. "peak_calling"
+    label: String,
+
+    /// Number of days until the directory expires
+    #[clap(long, default_value_t = 30)]
+    days: i64,
+}
+
+#[derive(Args)]
+pub struct GcArgs {
+    /// Only list the expired directories, without removing them
+    #[clap(long)]
+    dry_run: bool,
+}
+
+/// A scratch directory made with `investigate tmp new`.
+pub struct Scratch {
+    pub path: PathBuf,
+    pub expires: NaiveDate,
+}
+
+/// Get the scratch directories in tmp/ of the project at `root`, by name. Directories without
+/// an expiry, which were not made by `investigate tmp new`, are left out.
+pub fn scratch_directories(root: &Path) -> Vec<Scratch> {
+    let mut directories: Vec<Scratch> = root
+        .join("tmp")
+        .read_dir()
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|entry| {
+            let path = entry.path();
+            let expiry = read_to_string(path.join(EXPIRY_FILE)).ok()?;
+            let Ok(expires) = NaiveDate::parse_from_str(expiry.trim(), "%Y-%m-%d") else {
+                warn(
+                    Code::InvalidRecord,
+                    &format!("Invalid expiry date in {:?}", path.join(EXPIRY_FILE)),
+                );
+                return None;
+            };
+            Some(Scratch { path, expires })
+        })
+        .collect();
+    directories.sort_by(|a, b| a.path.cmp(&b.path));
+    directories
+}
+
+/// Get the scratch directories of the project at `root` which have expired.
+pub fn expired(root: &Path) -> Vec<Scratch> {
+    let today = Local::now().naive_local().date();
+    scratch_directories(root)
+        .into_iter()
+        .filter(|scratch| scratch.expires < today)
+        .collect()
+}
+
+fn new(args: NewArgs) {
+    let root = find_project_root();
+    if args.label.is_empty() || args.label.contains(['/', '\\']) || args.label.starts_with('.') {
+        exit_with_error(&format!("Invalid label \"{}\"", args.label))
+    }
+    if args.days < 0 {
+        exit_with_error("The number of days can't be negative")
+    }
+    let today = Local::now().naive_local().date();
+    let name = format!("{}_{}", today.format("%Y-%m-%d"), args.label);
+    let path = root.join("tmp").join(&name);
+    if filesystem::current().exists(&path) {
+        exit_with_error(&format!("tmp/{} already exists", name))
+    }
+    filesystem::create_dir_all(&path)
+        .unwrap_or_else(|_| panic!("Error when creating directory {:?}", path));
+    let expires = today + Duration::days(args.days);
+    let expiry = path.join(EXPIRY_FILE);
+    filesystem::write(&expiry, format!("{}\n", expires.format("%Y-%m-%d")))
+        .unwrap_or_else(|_| panic!("Error when writing file {:?}", expiry));
+    println!("Created tmp/{}, which expires on {}", name, expires);
+}
+
+fn list() {
+    let root = find_project_root();
+    let directories = scratch_directories(&root);
+    if directories.is_empty() {
+        println!("No scratch directories. Create one with `investigate tmp new LABEL`");
+        return;
+    }
+    let today = Local::now().naive_local().date();
+    for scratch in directories {
+        let name = scratch.path.strip_prefix(&root).unwrap_or(&scratch.path);
+        let status = if scratch.expires < today {
+            format!("expired on {}", scratch.expires)
+        } else {
+            format!("expires on {}", scratch.expires)
+        };
+        println!("{}  {}", name.display(), status);
+    }
+}
+
+fn gc(args: GcArgs) {
+    let root = find_project_root();
+    let expired = expired(&root);
+    if expired.is_empty() {
+        println!("No expired scratch directories");
+        return;
+    }
+    for scratch in &expired {
+        let name = scratch.path.strip_prefix(&root).unwrap_or(&scratch.path);
+        println!("{}  expired on {}", name.display(), scratch.expires);
+    }
+    if args.dry_run {
+        return;
+    }
+    let paths: Vec<PathBuf> = expired.into_iter().map(|scratch| scratch.path).collect();
+    let id = trash(&root, &paths);
+    println!(
+        "Moved {} expired scratch directories to the trash. Restore them with \
+        `investigate trash restore {}`",
+        paths.len(),
+        id
+    );
+}
+
+pub fn run(command: TmpCommand) {
+    match command {
+        TmpCommand::New(args) => new(args),
+        TmpCommand::List => list(),
+        TmpCommand::Gc(args) => gc(args),
+    }
+}
@@ -0,0 +1,224 @@
+use chrono::Local;
+use clap::{Args, Subcommand};
+
+use std::fs::read_to_string;
+use std::path::Path;
+
+use crate::config::Config;
+use crate::decisions::DECISIONS_FILE;
+use crate::run::{find_run, load_runs};
+use crate::templates::Templates;
+use crate::warning::{warn, Code};
+use crate::{exit_with_error, filesystem, find_project_root};
+
+/// Name of the list of follow-ups in the root of every project
+pub const TODO_FILE: &str = "TODO.md";
+
+#[derive(Subcommand)]
+pub enum TodoCommand {
+    /// Add an item to the project's TODO list
+    Add(AddArgs),
+
+    /// List the open items of the TODO list
+    List(ListArgs),
+
+    /// Mark items of the TODO list as done
+    Done(DoneArgs),
+}
+
+#[derive(Args)]
+pub struct AddArgs {
+    /// What to do, e.g. "Check the outliers of batch 2"
+    text: String,
+
+    /// ID of the run the item follows up on, or the start of it
+    #[clap(long)]
+    run: Option<String>,
+
+    /// Title of the decision the item follows up on, or part of it
+    #[clap(long)]
+    decision: Option<String>,
+}
+
+#[derive(Args)]
+pub struct ListArgs {
+    /// Also list the items which are done
+    #[clap(long)]
+    all: bool,
+}
+
+#[derive(Args)]
+pub struct DoneArgs {
+    /// Numbers of the items, as shown by `investigate todo list`
+    #[clap(required = true)]
+    numbers: Vec<usize>,
+}
+
+/// An item of the TODO list, a line like "- [ ] #3 Check the outliers (added 2024-05-01)".
+pub struct Item {
+    pub number: usize,
+    pub done: bool,
+    pub text: String,
+}
+
+impl Item {
+    fn parse(line: &str) -> Option<Item> {
+        let (done, rest) = if let Some(rest) = line.strip_prefix("- [ ] ") {
+            (false, rest)
+        } else if let Some(rest) = line
+            .strip_prefix("- [x] ")
+            .or_else(|| line.strip_prefix("- [X] "))
+        {
+            (true, rest)
+        } else {
+            return None;
+        };
+        let (number, text) = rest.strip_prefix('#')?.split_once(' ')?;
+        Some(Item {
+            number: number.parse().ok()?,
+            done,
+            text: text.to_owned(),
+        })
+    }
+
+    fn line(&self) -> String {
+        format!(
+            "- [{}] #{} {}",
+            if self.done { "x" } else { " " },
+            self.number,
+            self.text
+        )
+    }
+}
+
+/// Load the items of the TODO list of the project at `root`, in the order they were added.
+pub fn load_items(root: &Path) -> Vec<Item> {
+    read_to_string(root.join(TODO_FILE))
+        .unwrap_or_default()
+        .lines()
+        .filter_map(Item::parse)
+        .collect()
+}
+
+/// Get the title of the one decision in the log of the project at `root` containing `query`.
+fn find_decision(root: &Path, query: &str) -> String {
+    let log = read_to_string(root.join(DECISIONS_FILE)).unwrap_or_default();
+    let lowercase = query.to_lowercase();
+    let matching: Vec<&str> = log
+        .lines()
+        .filter_map(|line| line.strip_prefix("## "))
+        .map(|heading| heading.split_once(": ").map_or(heading, |(_, title)| title))
+        .filter(|title| title.to_lowercase().contains(&lowercase))
+        .collect();
+    match matching[..] {
+        [title] => title.to_owned(),
+        [] => exit_with_error(&format!(
+            "No decision with a title containing \"{}\" in {}",
+            query, DECISIONS_FILE
+        )),
+        _ => exit_with_error(&format!(
+            "Several decisions have titles containing \"{}\"",
+            query
+        )),
+    }
+}
+
+fn add(args: AddArgs) {
+    let root = find_project_root();
+    let text = args.text.trim();
+    if text.is_empty() || text.contains('\n') {
+        exit_with_error("The item must be a single line of text")
+    }
+    let mut details = vec![format!("added {}", Local::today().format("%Y-%m-%d"))];
+    if let Some(id) = &args.run {
+        details.push(format!("run {}", find_run(&load_runs(&root), id).id));
+    }
+    if let Some(query) = &args.decision {
+        details.push(format!("decision \"{}\"", find_decision(&root, query)));
+    }
+    let item = Item {
+        number: load_items(&root)
+            .iter()
+            .map(|item| item.number)
+            .max()
+            .unwrap_or(0)
+            + 1,
+        done: false,
+        text: format!("{} ({})", text, details.join(", ")),
+    };
+    let path = root.join(TODO_FILE);
+    let mut content = read_to_string(&path)
+        .unwrap_or_else(|_| Templates::new(Vec::new(), &Config::load()).get("todo"));
+    if !content.is_empty() && !content.ends_with('\n') {
+        content.push('\n');
+    }
+    content.push_str(&item.line());
+    content.push('\n');
+    filesystem::write(&path, content)
+        .unwrap_or_else(|_| panic!("Error when writing file {:?}", path));
+    println!("Added #{} to {}", item.number, TODO_FILE);
+}
+
+fn list(args: ListArgs) {
+    let items = load_items(&find_project_root());
+    let shown: Vec<&Item> = items.iter().filter(|item| args.all || !item.done).collect();
+    if shown.is_empty() {
+        println!("No open items in {}", TODO_FILE);
+        return;
+    }
+    for item in shown {
+        println!("{}", item.line().trim_start_matches("- "));
+    }
+}
+
+fn done(args: DoneArgs) {
+    let root = find_project_root();
+    let path = root.join(TODO_FILE);
+    let content = read_to_string(&path)
+        .unwrap_or_else(|_| exit_with_error(&format!("The project has no {}", TODO_FILE)));
+    let today = Local::today().format("%Y-%m-%d").to_string();
+    let mut remaining = args.numbers.clone();
+    let mut lines: Vec<String> = Vec::new();
+    let mut closed: Vec<String> = Vec::new();
+    for line in content.lines() {
+        match Item::parse(line) {
+            Some(mut item) if remaining.contains(&item.number) => {
+                remaining.retain(|&n| n != item.number);
+                if item.done {
+                    warn(Code::Skipped, &format!("#{} is already done", item.number));
+                } else {
+                    item.done = true;
+                    // Items added here end with their details, which the date is added to
+                    item.text = match item.text.strip_suffix(')') {
+                        Some(text) => format!("{}, done {})", text, today),
+                        None => format!("{} (done {})", item.text, today),
+                    };
+                    closed.push(format!("Done: #{} {}", item.number, item.text));
+                }
+                lines.push(item.line());
+            }
+            _ => lines.push(line.to_owned()),
+        }
+    }
+    if !remaining.is_empty() {
+        let numbers: Vec<String> = remaining.iter().map(|n| format!("#{}", n)).collect();
+        exit_with_error(&format!(
+            "No items {} in {}. Nothing was changed",
+            numbers.join(", "),
+            TODO_FILE
+        ))
+    }
+    filesystem::write(&path, lines.join("\n") + "\n")
+        .unwrap_or_else(|_| panic!("Error when writing file {:?}", path));
+    for message in closed {
+        println!("{}", message);
+    }
+}
+
+pub fn run(command: TodoCommand) {
+    match command {
+        TodoCommand::Add(args) => add(args),
+        TodoCommand::List(args) => list(args),
+        TodoCommand::Done(args) => done(args),
+    }
+}
@@ -0,0 +1,139 @@
+use serde::{Deserialize, Serialize};
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::process::Command;
+
+use crate::data::sha256;
+use crate::ssh;
+
+/// External tools whose versions are recorded when a project is created, if installed
+const TOOLS: [&str; 5] = ["conda", "mamba", "julia", "git", "quarto"];
+
+/// The version of an external tool, see `investigate fsck`.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolVersion {
+    pub version: String,
+    /// Checksum of the executable, which tells apart builds of the same version
+    pub sha256: String,
+}
+
+/// Find the executable `name` on the PATH, with the platform's suffix, like ".exe" on Windows.
+fn executable(name: &str) -> Option<PathBuf> {
+    let file_name = format!("{}{}", name, std::env::consts::EXE_SUFFIX);
+    std::env::split_paths(&std::env::var_os("PATH")?)
+        .map(|dir| dir.join(&file_name))
+        .find(|path| path.is_file())
+}
+
+/// Get the version number in the output of `TOOL --version`, e.g. "2.43.0" in
+/// "git version 2.43.0".
+pub fn parse_version(output: &str) -> Option<String> {
+    output
+        .split_whitespace()
+        .map(|word| word.trim_start_matches('v'))
+        .find(|word| {
+            word.contains('.')
+                && word
+                    .split('.')
+                    .next()
+                    .is_some_and(|n| n.parse::<u32>().is_ok())
+        })
+        .map(str::to_owned)
+}
+
+/// Get the version of the tool `name`, if it's installed.
+fn version(name: &str) -> Option<ToolVersion> {
+    let path = executable(name)?;
+    let output = Command::new(&path).arg("--version").output().ok()?;
+    let version = parse_version(&String::from_utf8_lossy(&output.stdout))
+        .or_else(|| parse_version(&String::from_utf8_lossy(&output.stderr)))?;
+    Some(ToolVersion {
+        version,
+        sha256: sha256(&path).ok()?,
+    })
+}
+
+/// Get the version of the tool `name` on `host` over SSH, if it's installed there.
+fn version_on(host: &str, name: &str) -> Option<ToolVersion> {
+    let script = format!(
+        "p=$(command -v {}) && [ -f \"$p\" ] && \"$p\" --version 2>&1 && \
+        (sha256sum \"$p\" || shasum -a 256 \"$p\")",
+        name
+    );
+    let output = ssh::remote_shell(host, &script).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    // The checksum is the first word of the last line, after the output of --version
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let (version_output, checksum_line) = stdout.trim_end().rsplit_once('\n')?;
+    Some(ToolVersion {
+        version: parse_version(version_output)?,
+        sha256: checksum_line.split_whitespace().next()?.to_owned(),
+    })
+}
+
+/// Get the versions of the installed external tools.
+pub fn versions() -> BTreeMap<String, ToolVersion> {
+    versions_with(version)
+}
+
+/// Get the versions of the external tools installed on `host`, asked over SSH.
+pub fn versions_on(host: &str) -> BTreeMap<String, ToolVersion> {
+    versions_with(|name| version_on(host, name))
+}
+
+fn versions_with<F>(version: F) -> BTreeMap<String, ToolVersion>
+where
+    F: Fn(&str) -> Option<ToolVersion> + Sync,
+{
+    // Some tools take a while to start, so they're asked at once
+    std::thread::scope(|scope| {
+        let version = &version;
+        let handles: Vec<_> = TOOLS
+            .iter()
+            .map(|name| (name, scope.spawn(move || version(name))))
+            .collect();
+        handles
+            .into_iter()
+            .filter_map(|(name, handle)| {
+                let version = handle.join().expect("Error when getting tool version")?;
+                Some((name.to_string(), version))
+            })
+            .collect()
+    })
+}
+
+/// Get the major and minor version of `version`, e.g. (1, 10) for "1.10.2".
+fn major_minor(version: &str) -> (Option<u32>, Option<u32>) {
+    let mut parts = version.split(['.', '-', '+']).map(|part| part.parse().ok());
+    (parts.next().flatten(), parts.next().flatten())
+}
+
+/// Get warnings about the tools in `recorded` whose installed version differs significantly,
+/// that is in the major or minor version, or which are no longer installed.
+pub fn drift(recorded: &BTreeMap<String, ToolVersion>) -> Vec<String> {
+    drift_from(recorded, &versions())
+}
+
+/// Like `drift`, with the `installed` versions given, to compare several projects to them.
+pub fn drift_from(
+    recorded: &BTreeMap<String, ToolVersion>,
+    installed: &BTreeMap<String, ToolVersion>,
+) -> Vec<String> {
+    recorded
+        .iter()
+        .filter_map(|(name, then)| match installed.get(name) {
+            None => Some(format!(
+                "{} {} was used to create the project, but is not installed",
+                name, then.version
+            )),
+            Some(now) if major_minor(&now.version) != major_minor(&then.version) => Some(format!(
+                "{} is version {}, but the project was created with version {}",
+                name, now.version, then.version
+            )),
+            Some(_) => None,
+        })
+        .collect()
+}
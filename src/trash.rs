@@ -0,0 +1,325 @@
+use chrono::Local;
+use clap::{Args, Subcommand};
+
+use std::cell::RefCell;
+use std::fs::read_to_string;
+use std::io::Result;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use crate::data::copy_recursive;
+use crate::filesystem::Filesystem;
+use crate::report::walk_files;
+use crate::{exit_with_error, filesystem, find_project_root};
+
+/// Directory in the project root which destructive commands move files to
+pub const TRASH_DIR: &str = ".investigate-trash";
+
+#[derive(Subcommand)]
+pub enum TrashCommand {
+    /// List what was moved to the trash, by when it was moved
+    List,
+
+    /// Move files from the trash back to where they were
+    Restore(RestoreArgs),
+
+    /// Delete the contents of the trash for good
+    Empty(EmptyArgs),
+}
+
+#[derive(Args)]
+pub struct RestoreArgs {
+    /// ID of what to restore, as shown by `investigate trash list` (default: the latest)
+    id: Option<String>,
+}
+
+#[derive(Args)]
+pub struct EmptyArgs {
+    /// Only delete what was moved to the trash more than this many days ago
+    #[clap(long, value_name = "DAYS")]
+    older_than: Option<i64>,
+}
+
+/// Move `path` by renaming it, or by copying it if it's on another filesystem.
+fn move_path(from: &Path, to: &Path) -> std::io::Result<()> {
+    let filesystem = filesystem::current();
+    if let Some(parent) = to.parent() {
+        filesystem.create_dir_all(parent)?;
+    }
+    if filesystem.rename(from, to).is_ok() {
+        return Ok(());
+    }
+    copy_recursive(from, to)?;
+    if from.is_dir() {
+        std::fs::remove_dir_all(from)
+    } else {
+        std::fs::remove_file(from)
+    }
+}
+
+/// Start a new batch in the trash of the project at `root`, and return its ID.
+fn new_batch(root: &Path) -> String {
+    let trash = root.join(TRASH_DIR);
+    let filesystem = filesystem::current();
+    let fail = |e: std::io::Error| -> ! {
+        exit_with_error(&format!("Could not create the trash in {:?}: {}", trash, e))
+    };
+    filesystem
+        .create_dir_all(&trash)
+        .unwrap_or_else(|e| fail(e));
+    // The trash is never committed, in projects created before it existed too
+    let gitignore = trash.join(".gitignore");
+    if !filesystem.exists(&gitignore) {
+        filesystem
+            .write(&gitignore, b"*\n")
+            .unwrap_or_else(|e| fail(e));
+    }
+    let timestamp = Local::now().format("%Y-%m-%dT%H-%M-%S").to_string();
+    let id = (0..)
+        .map(|n| match n {
+            0 => timestamp.clone(),
+            n => format!("{}-{}", timestamp, n),
+        })
+        .find(|id| !filesystem.exists(&trash.join(id)))
+        .expect("Error when naming trash");
+    filesystem
+        .create_dir_all(&trash.join(&id))
+        .unwrap_or_else(|e| fail(e));
+    id
+}
+
+/// Move `relative`, a path relative to the project `root`, to the batch `id` of its trash.
+fn move_to_batch(root: &Path, id: &str, relative: &Path) {
+    let trash = root.join(TRASH_DIR);
+    move_path(&root.join(relative), &trash.join(id).join(relative)).unwrap_or_else(|e| {
+        exit_with_error(&format!(
+            "Could not move {} to the trash: {}",
+            relative.display(),
+            e
+        ))
+    });
+    // Record each path as it's moved, so nothing trashed is lost if a later move fails
+    let paths = paths_file(&trash, id);
+    filesystem::current()
+        .append(&paths, format!("{}\n", relative.display()).as_bytes())
+        .unwrap_or_else(|e| exit_with_error(&format!("Could not write {:?}: {}", paths, e)));
+}
+
+/// Move `paths` in the project at `root` to its trash, instead of deleting them, and return
+/// the ID to restore them with. They keep their path relative to the root, so restoring them
+/// only needs the ID.
+pub fn trash(root: &Path, paths: &[PathBuf]) -> String {
+    let id = new_batch(root);
+    for path in paths {
+        let absolute = std::path::absolute(path).unwrap_or_else(|_| path.clone());
+        let relative = absolute.strip_prefix(root).unwrap_or_else(|_| {
+            exit_with_error(&format!(
+                "Can't move {:?} to the trash, as it's outside the project",
+                path
+            ))
+        });
+        move_to_batch(root, &id, relative);
+    }
+    id
+}
+
+/// A filesystem which moves the files of the project at `root` to its trash before they are
+/// overwritten with something else, for `investigate new --force`.
+pub struct Trashing {
+    inner: Rc<dyn Filesystem>,
+    root: PathBuf,
+    /// ID of the batch in the trash, once a file is moved there
+    id: RefCell<Option<String>>,
+}
+
+impl Trashing {
+    pub fn new(inner: Rc<dyn Filesystem>, root: &Path) -> Trashing {
+        Trashing {
+            inner,
+            root: root.to_owned(),
+            id: RefCell::new(None),
+        }
+    }
+
+    /// Get the ID of the batch the overwritten files were moved to, if any were.
+    pub fn id(&self) -> Option<String> {
+        self.id.borrow().clone()
+    }
+
+    fn is_replaced(&self, path: &Path, contents: &[u8]) -> bool {
+        self.inner.exists(path)
+            && !self.inner.is_dir(path)
+            && self
+                .inner
+                .read_to_string(path)
+                .map_or(true, |old| old.as_bytes() != contents)
+    }
+}
+
+impl Filesystem for Trashing {
+    fn exists(&self, path: &Path) -> bool {
+        self.inner.exists(path)
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        self.inner.is_dir(path)
+    }
+
+    fn read_to_string(&self, path: &Path) -> Result<String> {
+        self.inner.read_to_string(path)
+    }
+
+    fn read_dir(&self, path: &Path) -> Result<Vec<PathBuf>> {
+        self.inner.read_dir(path)
+    }
+
+    fn write(&self, path: &Path, contents: &[u8]) -> Result<()> {
+        // The trash itself is written through this filesystem too
+        let in_project = path
+            .strip_prefix(&self.root)
+            .ok()
+            .filter(|relative| !relative.starts_with(TRASH_DIR));
+        if let Some(relative) = in_project.filter(|_| self.is_replaced(path, contents)) {
+            let existing = self.id();
+            let id = existing.unwrap_or_else(|| new_batch(&self.root));
+            move_to_batch(&self.root, &id, relative);
+            self.id.replace(Some(id));
+        }
+        self.inner.write(path, contents)
+    }
+
+    fn append(&self, path: &Path, contents: &[u8]) -> Result<()> {
+        self.inner.append(path, contents)
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> Result<()> {
+        self.inner.rename(from, to)
+    }
+
+    fn create_dir(&self, path: &Path) -> Result<()> {
+        self.inner.create_dir(path)
+    }
+
+    fn create_dir_all(&self, path: &Path) -> Result<()> {
+        self.inner.create_dir_all(path)
+    }
+
+    fn set_executable(&self, path: &Path) -> Result<()> {
+        self.inner.set_executable(path)
+    }
+}
+
+/// Get the IDs of the contents of the trash, oldest first.
+fn ids(trash: &Path) -> Vec<String> {
+    let mut ids: Vec<String> = trash
+        .read_dir()
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|entry| entry.path().is_dir())
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .collect();
+    ids.sort();
+    ids
+}
+
+/// Get the file listing the paths moved to the trash under `id`.
+fn paths_file(trash: &Path, id: &str) -> PathBuf {
+    trash.join(format!("{}.paths", id))
+}
+
+/// Get the paths relative to the project root of the files and directories moved to the
+/// trash under `id`.
+fn entries(trash: &Path, id: &str) -> Vec<PathBuf> {
+    read_to_string(paths_file(trash, id))
+        .unwrap_or_default()
+        .lines()
+        .map(PathBuf::from)
+        .collect()
+}
+
+/// Delete what was moved to the trash under `id`.
+fn delete(trash: &Path, id: &str) -> std::io::Result<()> {
+    std::fs::remove_dir_all(trash.join(id))?;
+    let _ = std::fs::remove_file(paths_file(trash, id));
+    Ok(())
+}
+
+fn list(trash: &Path) {
+    let ids = ids(trash);
+    if ids.is_empty() {
+        println!("The trash is empty");
+        return;
+    }
+    for id in ids {
+        println!("{}", id);
+        for entry in entries(trash, &id) {
+            let size: u64 = walk_files(&trash.join(&id).join(&entry))
+                .iter()
+                .chain(std::iter::once(&trash.join(&id).join(&entry)))
+                .filter(|path| path.is_file())
+                .filter_map(|path| path.metadata().ok())
+                .map(|metadata| metadata.len())
+                .sum();
+            println!("  {} ({} bytes)", entry.display(), size);
+        }
+    }
+}
+
+fn restore(root: &Path, trash: &Path, args: RestoreArgs) {
+    let ids = ids(trash);
+    let id = match args.id {
+        Some(id) if ids.contains(&id) => id,
+        Some(id) => exit_with_error(&format!("Nothing in the trash with ID {}", id)),
+        None => ids
+            .last()
+            .cloned()
+            .unwrap_or_else(|| exit_with_error("The trash is empty")),
+    };
+    let entries = entries(trash, &id);
+    // Restoring must not overwrite anything, or it would destroy data itself
+    if let Some(existing) = entries.iter().find(|entry| root.join(entry).exists()) {
+        exit_with_error(&format!(
+            "{} exists, so it can't be restored. Move it away first",
+            existing.display()
+        ))
+    }
+    for entry in &entries {
+        move_path(&trash.join(&id).join(entry), &root.join(entry)).unwrap_or_else(|e| {
+            exit_with_error(&format!("Could not restore {}: {}", entry.display(), e))
+        });
+        println!("Restored {}", entry.display());
+    }
+    let _ = delete(trash, &id);
+}
+
+fn empty(trash: &Path, args: EmptyArgs) {
+    let cutoff = args
+        .older_than
+        .map(|days| (Local::now() - chrono::Duration::days(days)).format("%Y-%m-%dT%H-%M-%S"))
+        .map(|cutoff| cutoff.to_string());
+    let mut n = 0;
+    for id in ids(trash) {
+        // IDs start with the time they were trashed, so they compare like times
+        if cutoff
+            .as_ref()
+            .is_some_and(|cutoff| id.as_str() >= cutoff.as_str())
+        {
+            continue;
+        }
+        delete(trash, &id)
+            .unwrap_or_else(|e| exit_with_error(&format!("Could not delete {}: {}", id, e)));
+        n += 1;
+    }
+    println!("Deleted {} items from the trash", n);
+}
+
+pub fn run(command: TrashCommand) {
+    let root = find_project_root();
+    let trash = root.join(TRASH_DIR);
+    match command {
+        TrashCommand::List => list(&trash),
+        TrashCommand::Restore(args) => restore(&root, &trash, args),
+        TrashCommand::Empty(args) => empty(&trash, args),
+    }
+}
@@ -0,0 +1,276 @@
+use clap::Args;
+use git2::Repository;
+
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+use crate::directories::DirectorySpec;
+use crate::git::relative_to_workdir;
+use crate::manifest::{Manifest, MANIFEST_FILE};
+use crate::metadata::Metadata;
+use crate::run::format_bytes;
+use crate::{find_project_root, is_plain};
+
+#[derive(Args)]
+pub struct TreeArgs {
+    /// How many levels of directories to show
+    #[clap(long, default_value_t = 2)]
+    depth: usize,
+
+    /// Also show hidden files and directories, except .git
+    #[clap(short, long)]
+    all: bool,
+}
+
+/// What is shown about the project besides its files.
+struct Context {
+    root: PathBuf,
+    all: bool,
+    /// The repository, and the path of the project in it
+    git: Option<(Repository, PathBuf)>,
+    /// Files tracked by git, relative to the project root
+    tracked: BTreeSet<PathBuf>,
+    /// Paths recorded in the manifest, relative to raw/
+    manifest: BTreeSet<String>,
+    /// The project's directories, with their descriptions
+    directories: Vec<(String, Option<String>)>,
+}
+
+/// The size and number of files of a file or directory, and how many of them git tracks
+/// and ignores.
+#[derive(Default)]
+struct Stats {
+    size: u64,
+    files: usize,
+    tracked: usize,
+    ignored: usize,
+}
+
+impl Context {
+    fn new(root: PathBuf, all: bool) -> Context {
+        let metadata = Metadata::load(&root);
+        let git = Repository::discover(&root).ok().and_then(|repo| {
+            let prefix = relative_to_workdir(&repo, &root)?;
+            Some((repo, prefix))
+        });
+        let tracked = match &git {
+            None => BTreeSet::new(),
+            Some((repo, prefix)) => repo
+                .index()
+                .map(|index| {
+                    index
+                        .iter()
+                        .filter_map(|entry| {
+                            let path = PathBuf::from(String::from_utf8_lossy(&entry.path).as_ref());
+                            path.strip_prefix(prefix).ok().map(Path::to_owned)
+                        })
+                        .collect()
+                })
+                .unwrap_or_default(),
+        };
+        let manifest = Manifest::load(&root)
+            .data
+            .into_iter()
+            .map(|entry| entry.path)
+            .collect();
+        let directories = metadata
+            .directories
+            .iter()
+            .map(|name| {
+                let directory = DirectorySpec::Name(name.clone()).resolve();
+                (directory.name, directory.description)
+            })
+            .collect();
+        Context {
+            root,
+            all,
+            git,
+            tracked,
+            manifest,
+            directories,
+        }
+    }
+
+    fn relative<'a>(&self, path: &'a Path) -> &'a Path {
+        path.strip_prefix(&self.root).unwrap_or(path)
+    }
+
+    /// Describe how git treats the file or directory at `path`.
+    fn git_status(&self, stats: &Stats) -> Option<String> {
+        self.git.as_ref()?;
+        let status = if stats.files == 0 {
+            return None;
+        } else if stats.tracked == stats.files {
+            "tracked".to_owned()
+        } else if stats.tracked > 0 {
+            format!("{} of {} files tracked", stats.tracked, stats.files)
+        } else if stats.ignored == stats.files {
+            "ignored".to_owned()
+        } else {
+            "untracked".to_owned()
+        };
+        Some(status)
+    }
+
+    fn is_ignored(&self, path: &Path) -> bool {
+        self.git.as_ref().is_some_and(|(repo, prefix)| {
+            repo.is_path_ignored(prefix.join(self.relative(path)))
+                .unwrap_or(false)
+        })
+    }
+
+    /// Describe whether the data at `path` is recorded in the manifest, if it's in raw/.
+    fn manifest_status(&self, path: &Path) -> Option<String> {
+        let relative = self.relative(path);
+        if relative == Path::new("raw") {
+            let entries = data_entries(path);
+            let recorded = entries
+                .iter()
+                .filter(|entry| self.manifest.contains(*entry))
+                .count();
+            return Some(format!(
+                "{} of {} in {}",
+                recorded,
+                entries.len(),
+                MANIFEST_FILE
+            ));
+        }
+        let name = relative.strip_prefix("raw").ok()?.to_string_lossy();
+        if name.contains('/') || is_raw_documentation(&name) {
+            return None;
+        }
+        Some(match self.manifest.contains(name.as_ref()) {
+            true => "in manifest".to_owned(),
+            false => "not in manifest".to_owned(),
+        })
+    }
+
+    /// Get the first sentence of the description of the directory at `path`, if it's one of
+    /// the project's directories.
+    fn description(&self, path: &Path) -> Option<String> {
+        let relative = self.relative(path).to_string_lossy();
+        let description = self
+            .directories
+            .iter()
+            .find(|(name, _)| *name == relative)
+            .and_then(|(_, description)| description.as_deref())?;
+        let description = description.split_whitespace().collect::<Vec<_>>().join(" ");
+        // A sentence ends at a period followed by a capital letter, unlike after "e.g."
+        let end = description
+            .match_indices(". ")
+            .map(|(i, _)| i)
+            .find(|&i| description[i + 2..].starts_with(char::is_uppercase));
+        Some(match end {
+            Some(end) => description[..=end].to_owned(),
+            None => description,
+        })
+    }
+
+    fn entries(&self, dir: &Path) -> Vec<PathBuf> {
+        let mut entries: Vec<PathBuf> = dir
+            .read_dir()
+            .into_iter()
+            .flatten()
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| {
+                let name = path.file_name().unwrap_or_default().to_string_lossy();
+                name != ".git" && (self.all || !name.starts_with('.'))
+            })
+            .collect();
+        // Directories first, like the README lists them
+        entries.sort_by_key(|path| (!path.is_dir(), path.clone()));
+        entries
+    }
+
+    /// Get the stats of `path`, and the lines showing its contents below it, down to `depth`.
+    fn visit(&self, path: &Path, indent: &str, depth: usize) -> (Stats, Vec<String>) {
+        let metadata = path.symlink_metadata();
+        if !path.is_dir() || metadata.is_ok_and(|m| m.file_type().is_symlink()) {
+            let tracked = self.tracked.contains(self.relative(path));
+            let stats = Stats {
+                size: path.metadata().map(|m| m.len()).unwrap_or(0),
+                files: 1,
+                tracked: tracked as usize,
+                ignored: (!tracked && self.is_ignored(path)) as usize,
+            };
+            return (stats, Vec::new());
+        }
+        let mut stats = Stats::default();
+        let mut lines = Vec::new();
+        let entries = self.entries(path);
+        for (i, entry) in entries.iter().enumerate() {
+            let last = i + 1 == entries.len();
+            let (branch, corner, line) = if is_plain() {
+                ("|-- ", "`-- ", "|   ")
+            } else {
+                ("├── ", "└── ", "│   ")
+            };
+            let child_indent = format!("{}{}", indent, if last { "    " } else { line });
+            let (child, child_lines) = self.visit(entry, &child_indent, depth.saturating_sub(1));
+            if depth > 0 {
+                let connector = if last { corner } else { branch };
+                lines.push(format!(
+                    "{}{}{}",
+                    indent,
+                    connector,
+                    self.line(entry, &child)
+                ));
+                lines.extend(child_lines);
+            }
+            stats.size += child.size;
+            stats.files += child.files;
+            stats.tracked += child.tracked;
+            stats.ignored += child.ignored;
+        }
+        (stats, lines)
+    }
+
+    /// Get the line showing `path`, with its annotations.
+    fn line(&self, path: &Path, stats: &Stats) -> String {
+        let mut name = path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into_owned();
+        if path.is_dir() {
+            name.push('/');
+        }
+        let annotations: Vec<String> = std::iter::once(format_bytes(stats.size))
+            .chain(self.git_status(stats))
+            .chain(self.manifest_status(path))
+            .collect();
+        let mut line = format!("{}  ({})", name, annotations.join(", "));
+        if let Some(description) = self.description(path) {
+            line.push_str(&format!("  {}", description));
+        }
+        line
+    }
+}
+
+/// Check if `name` in raw/ documents the data rather than being data.
+fn is_raw_documentation(name: &str) -> bool {
+    name == "README.md" || name == "MANIFEST.toml" || name.ends_with(".ACCESS.md")
+}
+
+/// Get the names of the data in raw/, which should each be recorded in the manifest.
+fn data_entries(raw: &Path) -> Vec<String> {
+    raw.read_dir()
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .filter(|name| !name.starts_with('.') && !is_raw_documentation(name))
+        .collect()
+}
+
+pub fn run(args: TreeArgs) {
+    let root = find_project_root();
+    let context = Context::new(root.clone(), args.all);
+    let (stats, lines) = context.visit(&root, "", args.depth);
+    let name = root.file_name().unwrap_or_default().to_string_lossy();
+    println!("{}/  ({})", name, format_bytes(stats.size));
+    for line in lines {
+        println!("{}", line);
+    }
+}
@@ -0,0 +1,119 @@
+use std::ffi::OsString;
+use std::io::ErrorKind;
+use std::path::Path;
+use std::process::Command;
+
+use crate::exec::project_command;
+use crate::metadata::Metadata;
+use crate::new::{convert_name_to_module, convert_name_to_package, Language};
+use crate::report::walk_files;
+use crate::{exit_with_error, find_project_root};
+
+/// Number of lines of the output of a broken component to show
+const OUTPUT_LINES: usize = 10;
+
+/// A generated component of the project, and the command exercising it.
+struct Check {
+    name: String,
+    command: Command,
+}
+
+/// Get a command running `program` with `args` in the project's environment.
+fn in_environment(root: &Path, program: &str, args: &[&str]) -> Command {
+    let args: Vec<OsString> = args.iter().map(OsString::from).collect();
+    project_command(root, &OsString::from(program), &args)
+}
+
+/// Get the checks of the components the project at `root` has.
+fn checks(root: &Path, metadata: &Metadata) -> Vec<Check> {
+    let mut checks = Vec::new();
+    match metadata.language {
+        Some(Language::Python) => {
+            // The package with --scripts, else the main script, which only runs when executed
+            let module = if root.join("pyproject.toml").is_file() {
+                convert_name_to_package(&metadata.name)
+            } else {
+                "main".to_owned()
+            };
+            let mut command =
+                in_environment(root, "python", &["-c", &format!("import {}", module)]);
+            command.env("PYTHONPATH", root.join("src"));
+            checks.push(Check {
+                name: format!("Python: import {}", module),
+                command,
+            });
+        }
+        Some(Language::Julia) => {
+            let module = convert_name_to_module(&metadata.name);
+            checks.push(Check {
+                name: format!("Julia: using {}", module),
+                command: in_environment(root, "julia", &["-e", &format!("using {}", module)]),
+            });
+        }
+        None => (),
+    }
+    if root.join("Makefile").is_file() {
+        let mut command = Command::new("make");
+        command.arg("-n").current_dir(root);
+        checks.push(Check {
+            name: "Makefile: make -n".to_owned(),
+            command,
+        });
+    }
+    for file in walk_files(&root.join("paper")) {
+        if file.extension().is_some_and(|e| e == "qmd") {
+            let relative = file.strip_prefix(root).unwrap_or(&file);
+            let mut command = Command::new("quarto");
+            command.arg("inspect").arg(relative).current_dir(root);
+            checks.push(Check {
+                name: format!("Quarto: {}", relative.display()),
+                command,
+            });
+        }
+    }
+    checks
+}
+
+pub fn run() {
+    let root = find_project_root();
+    let metadata = Metadata::load(&root);
+    let checks = checks(&root, &metadata);
+    if checks.is_empty() {
+        println!("Project has no components to verify");
+        return;
+    }
+    let width = checks.iter().map(|c| c.name.len()).max().unwrap_or(0);
+    let mut broken = Vec::new();
+    for mut check in checks {
+        let program = check.command.get_program().to_string_lossy().into_owned();
+        match check.command.output() {
+            Err(e) if e.kind() == ErrorKind::NotFound => println!(
+                "{:width$}  skipped, since {} is not installed",
+                check.name,
+                program,
+                width = width
+            ),
+            Err(e) => {
+                println!("{:width$}  broken", check.name, width = width);
+                println!("    Could not run {}: {}", program, e);
+                broken.push(check.name);
+            }
+            Ok(output) if output.status.success() => {
+                println!("{:width$}  ok", check.name, width = width)
+            }
+            Ok(output) => {
+                println!("{:width$}  broken", check.name, width = width);
+                let text = String::from_utf8_lossy(&output.stderr).into_owned()
+                    + &String::from_utf8_lossy(&output.stdout);
+                let lines: Vec<&str> = text.lines().filter(|l| !l.trim().is_empty()).collect();
+                for line in &lines[lines.len().saturating_sub(OUTPUT_LINES)..] {
+                    println!("    {}", line);
+                }
+                broken.push(check.name);
+            }
+        }
+    }
+    if !broken.is_empty() {
+        exit_with_error(&format!("Broken: {}", broken.join(", ")))
+    }
+}
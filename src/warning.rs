@@ -0,0 +1,227 @@
+use serde::Serialize;
+
+use std::sync::{Mutex, OnceLock};
+
+use crate::config::allowed_warnings;
+use crate::exit_with_error;
+
+/// A kind of warning. Each has a stable code, like W001, which tooling can match on, and which
+/// can be allowed with --allow or the `[warnings]` section of the config, to suppress it.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum Code {
+    UnknownAuthor,
+    InvalidAuthor,
+    ConfigProblem,
+    GitFailed,
+    AnnexFailed,
+    EnvironmentFailed,
+    NotLocked,
+    HookNotInstalled,
+    NotOverwritten,
+    InvalidRecord,
+    FileChanged,
+    LowSpace,
+    QuotaFailed,
+    PermissionsFailed,
+    MissingMetadata,
+    NotifyFailed,
+    ToolDrift,
+    ExpiredScratch,
+    RunProblem,
+    NotCopied,
+    ArchiveFailed,
+    UndoFailed,
+    DataIgnored,
+    NoChecksum,
+    StatsProblem,
+    NoRegistry,
+    WatchFailed,
+    TemplatesMissing,
+    UnknownToolVersion,
+    RemoteReplaced,
+    Skipped,
+    WrongEnvironment,
+}
+
+/// Every kind of warning, by code. Codes must never be reused.
+const CODES: [Code; 32] = [
+    Code::UnknownAuthor,
+    Code::InvalidAuthor,
+    Code::ConfigProblem,
+    Code::GitFailed,
+    Code::AnnexFailed,
+    Code::EnvironmentFailed,
+    Code::NotLocked,
+    Code::HookNotInstalled,
+    Code::NotOverwritten,
+    Code::InvalidRecord,
+    Code::FileChanged,
+    Code::LowSpace,
+    Code::QuotaFailed,
+    Code::PermissionsFailed,
+    Code::MissingMetadata,
+    Code::NotifyFailed,
+    Code::ToolDrift,
+    Code::ExpiredScratch,
+    Code::RunProblem,
+    Code::NotCopied,
+    Code::ArchiveFailed,
+    Code::UndoFailed,
+    Code::DataIgnored,
+    Code::NoChecksum,
+    Code::StatsProblem,
+    Code::NoRegistry,
+    Code::WatchFailed,
+    Code::TemplatesMissing,
+    Code::UnknownToolVersion,
+    Code::RemoteReplaced,
+    Code::Skipped,
+    Code::WrongEnvironment,
+];
+
+impl Code {
+    pub fn id(self) -> &'static str {
+        match self {
+            Code::UnknownAuthor => "W001",
+            Code::InvalidAuthor => "W002",
+            Code::ConfigProblem => "W003",
+            Code::GitFailed => "W004",
+            Code::AnnexFailed => "W005",
+            Code::EnvironmentFailed => "W006",
+            Code::NotLocked => "W007",
+            Code::HookNotInstalled => "W008",
+            Code::NotOverwritten => "W009",
+            Code::InvalidRecord => "W010",
+            Code::FileChanged => "W011",
+            Code::LowSpace => "W012",
+            Code::QuotaFailed => "W013",
+            Code::PermissionsFailed => "W014",
+            Code::MissingMetadata => "W015",
+            Code::NotifyFailed => "W016",
+            Code::ToolDrift => "W017",
+            Code::ExpiredScratch => "W018",
+            Code::RunProblem => "W019",
+            Code::NotCopied => "W020",
+            Code::ArchiveFailed => "W021",
+            Code::UndoFailed => "W022",
+            Code::DataIgnored => "W023",
+            Code::NoChecksum => "W024",
+            Code::StatsProblem => "W025",
+            Code::NoRegistry => "W026",
+            Code::WatchFailed => "W027",
+            Code::TemplatesMissing => "W028",
+            Code::UnknownToolVersion => "W029",
+            Code::RemoteReplaced => "W030",
+            Code::Skipped => "W031",
+            Code::WrongEnvironment => "W032",
+        }
+    }
+
+    fn description(self) -> &'static str {
+        match self {
+            Code::UnknownAuthor => "The author's name and email are unknown",
+            Code::InvalidAuthor => "$INVESTIGATE_AUTHOR is invalid",
+            Code::ConfigProblem => "Unknown keys or invalid values in a config file",
+            Code::GitFailed => "A git repository could not be created or committed to",
+            Code::AnnexFailed => "git-annex could not be set up",
+            Code::EnvironmentFailed => "The Conda or Julia environment could not be created",
+            Code::NotLocked => "The environment has no lock file or manifest",
+            Code::HookNotInstalled => "The git hook protecting raw/ was not installed",
+            Code::NotOverwritten => "An existing file was not overwritten",
+            Code::InvalidRecord => "An invalid record in a log or file was skipped",
+            Code::FileChanged => "A file is missing or differs from its recorded checksum",
+            Code::LowSpace => "Little space is left where a project is created",
+            Code::QuotaFailed => "The quota command failed",
+            Code::PermissionsFailed => "The group or permissions of files could not be set",
+            Code::MissingMetadata => "Metadata which repositories want is missing",
+            Code::NotifyFailed => "A notification could not be sent",
+            Code::ToolDrift => {
+                "An external tool differs from the version the project was made with"
+            }
+            Code::ExpiredScratch => "A scratch directory in tmp/ has expired",
+            Code::RunProblem => "A recorded run failed, or wrote no outputs",
+            Code::NotCopied => "A file could not be copied",
+            Code::ArchiveFailed => "The project could not be archived",
+            Code::UndoFailed => "A change could not be undone after an error",
+            Code::DataIgnored => "A data file is ignored by git",
+            Code::NoChecksum => "The checksum of a data file could not be computed",
+            Code::StatsProblem => "The stats could not be recorded, or are disabled",
+            Code::NoRegistry => "The project registry could not be located",
+            Code::WatchFailed => "An error occurred while watching files",
+            Code::TemplatesMissing => "A configured template directory does not exist",
+            Code::UnknownToolVersion => "The version of a tool could not be determined",
+            Code::RemoteReplaced => "The data remote of the project was replaced",
+            Code::Skipped => "Something was skipped, as it was already done",
+            Code::WrongEnvironment => {
+                "Conda and Julia run on the other side of the Windows/WSL boundary from the project"
+            }
+        }
+    }
+}
+
+/// Check if `id` is the code of a warning, like "W001".
+pub fn is_code(id: &str) -> bool {
+    CODES.iter().any(|code| code.id() == id)
+}
+
+/// A warning which was printed, kept for JSON output.
+#[derive(Serialize, Clone)]
+pub struct Emitted {
+    pub code: &'static str,
+    pub message: String,
+}
+
+/// Codes allowed by --allow
+static ALLOWED: OnceLock<Vec<String>> = OnceLock::new();
+
+/// Codes allowed by the config files, which are only read if a warning is given
+static CONFIG_ALLOWED: OnceLock<Vec<String>> = OnceLock::new();
+
+static EMITTED: Mutex<Vec<Emitted>> = Mutex::new(Vec::new());
+
+/// Allow the warnings with codes `ids`, given on the command line, exiting on unknown codes.
+pub fn allow(ids: Vec<String>) {
+    let ids: Vec<String> = ids.iter().map(|id| id.trim().to_uppercase()).collect();
+    if let Some(unknown) = ids.iter().find(|id| !is_code(id)) {
+        exit_with_error(&format!(
+            "Unknown warning code {}. See `investigate warnings` for the codes",
+            unknown
+        ))
+    }
+    ALLOWED
+        .set(ids)
+        .unwrap_or_else(|_| panic!("Warnings were allowed twice"));
+}
+
+fn is_allowed(id: &str) -> bool {
+    let allowed = |ids: &Vec<String>| ids.iter().any(|allowed| allowed == id);
+    ALLOWED.get().is_some_and(allowed) || allowed(CONFIG_ALLOWED.get_or_init(allowed_warnings))
+}
+
+/// Print a warning with `code` to stderr, unless it's allowed.
+pub fn warn(code: Code, message: &str) {
+    let id = code.id();
+    if is_allowed(id) {
+        return;
+    }
+    eprintln!("Warning[{}]: {}", id, message);
+    EMITTED
+        .lock()
+        .expect("Warnings are never poisoned")
+        .push(Emitted {
+            code: id,
+            message: message.to_owned(),
+        });
+}
+
+/// Get the warnings printed so far.
+pub fn emitted() -> Vec<Emitted> {
+    EMITTED.lock().expect("Warnings are never poisoned").clone()
+}
+
+/// Print the codes of the warnings, and what they are about.
+pub fn run() {
+    for code in CODES {
+        println!("{}  {}", code.id(), code.description());
+    }
+}
@@ -0,0 +1,288 @@
+use chrono::Local;
+use clap::Args;
+use notify::{EventKind, RecursiveMode, Watcher};
+
+use std::ffi::OsString;
+use std::fs::{read_to_string, File};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::exec::project_command;
+use crate::run::{load_runs, LOGS_DIR, RUNS_FILE};
+use crate::warning::{warn, Code};
+use crate::{exit_with_error, find_project_root};
+
+#[derive(Args)]
+pub struct WatchArgs {
+    /// Target in the Makefile or justfile to run, or the ID of a run recorded by
+    /// `investigate run` to run again
+    target: String,
+
+    /// Directory or file to watch, relative to the project root. Can be given multiple times
+    #[clap(
+        long,
+        value_name = "PATH",
+        default_value = "src",
+        multiple_occurrences = true
+    )]
+    path: Vec<PathBuf>,
+
+    /// Milliseconds to wait for further changes before running, so saving several files at once
+    /// runs the target once
+    #[clap(long, value_name = "MS", default_value_t = 500)]
+    debounce: u64,
+}
+
+/// Check if `text`, a Makefile or justfile, has a rule or recipe named `target`.
+fn has_rule(text: &str, target: &str) -> bool {
+    text.lines().any(|line| {
+        let Some(rest) = line.strip_prefix(target) else {
+            return false;
+        };
+        // Recipes in justfiles may take parameters, as in "test filter:"
+        let trimmed = rest.trim_start();
+        rest.starts_with([':', ' '])
+            && rest.contains(':')
+            && !trimmed.starts_with(":=")
+            && !trimmed.starts_with('=')
+    })
+}
+
+/// What to run when the watched files change.
+struct Target {
+    command: Vec<OsString>,
+    /// Whether the command must be run in the project's environment. `investigate run` sets
+    /// it up itself.
+    in_environment: bool,
+    /// Name of the log of the last run, in results/logs/
+    log: String,
+}
+
+/// Get what to run for `target` in the project at `root`.
+fn find_target(root: &Path, target: &str) -> Target {
+    let read = |name: &str| read_to_string(root.join(name)).ok();
+    if read("Makefile").is_some_and(|text| has_rule(&text, target)) {
+        return Target {
+            command: vec!["make".into(), target.into()],
+            in_environment: true,
+            log: format!("watch-{}", target),
+        };
+    }
+    if ["justfile", "Justfile", ".justfile"]
+        .iter()
+        .any(|name| read(name).is_some_and(|text| has_rule(&text, target)))
+    {
+        return Target {
+            command: vec!["just".into(), target.into()],
+            in_environment: true,
+            log: format!("watch-{}", target),
+        };
+    }
+    let runs = load_runs(root);
+    let matching: Vec<_> = runs
+        .iter()
+        .filter(|run| run.id.starts_with(target))
+        .collect();
+    match matching[..] {
+        [run] => {
+            let executable = std::env::current_exe()
+                .unwrap_or_else(|_| exit_with_error("Could not find the investigate executable"));
+            let log = format!("watch-{}", run.id);
+            // Run through `investigate run`, so each run is recorded like the original
+            let mut command = vec![
+                executable.into_os_string(),
+                "run".into(),
+                format!("--log={}/{}.log", LOGS_DIR, log).into(),
+                "--".into(),
+            ];
+            command.extend(run.command.iter().map(OsString::from));
+            Target {
+                command,
+                in_environment: false,
+                log,
+            }
+        }
+        [] => exit_with_error(&format!(
+            "{} is neither a target in the Makefile or justfile, nor the ID of a run in {}",
+            target, RUNS_FILE
+        )),
+        _ => exit_with_error(&format!("Several runs have IDs starting with {}", target)),
+    }
+}
+
+/// Copy `from` to the terminal output `to`, and to `log`.
+fn tee(mut from: impl Read, mut to: impl Write, log: Arc<Mutex<File>>) {
+    let mut buffer = [0; 8192];
+    while let Ok(n) = from.read(&mut buffer) {
+        if n == 0 {
+            break;
+        }
+        let _ = to.write_all(&buffer[..n]);
+        let _ = to.flush();
+        let _ = log
+            .lock()
+            .expect("Error when locking log")
+            .write_all(&buffer[..n]);
+    }
+}
+
+/// Run `target`, showing its output and writing it to `log`.
+fn run_logged(root: &Path, target: &Target, log: &Path) {
+    let file = File::create(log).unwrap_or_else(|_| panic!("Error when creating file {:?}", log));
+    let file = Arc::new(Mutex::new(file));
+    let start = Instant::now();
+    let (program, args) = target
+        .command
+        .split_first()
+        .expect("Error when getting command");
+    let mut command = if target.in_environment {
+        project_command(root, program, args)
+    } else {
+        let mut command = Command::new(program);
+        command.args(args).current_dir(root);
+        command
+    };
+    let child = command
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn();
+    let mut child = match child {
+        Ok(child) => child,
+        Err(e) => exit_with_error(&format!(
+            "Could not run {}: {}",
+            program.to_string_lossy(),
+            e
+        )),
+    };
+    let stdout = child.stdout.take().expect("Error when capturing output");
+    let stderr = child.stderr.take().expect("Error when capturing output");
+    std::thread::scope(|scope| {
+        let log = Arc::clone(&file);
+        scope.spawn(move || tee(stdout, std::io::stdout(), log));
+        let log = Arc::clone(&file);
+        scope.spawn(move || tee(stderr, std::io::stderr(), log));
+    });
+    let status = child.wait().expect("Error when waiting for command");
+    let seconds = start.elapsed().as_secs_f64();
+    let time = Local::now().format("%H:%M:%S");
+    if status.success() {
+        println!("[{}] Succeeded in {:.1}s", time, seconds);
+    } else {
+        match status.code() {
+            Some(code) => println!(
+                "[{}] Failed with exit code {} in {:.1}s",
+                time, code, seconds
+            ),
+            None => println!("[{}] Failed in {:.1}s", time, seconds),
+        }
+    }
+}
+
+/// Check if a change to `path` should trigger a run. Changes to hidden files, like editor swap
+/// files, and to Python's bytecode caches don't.
+fn is_relevant(root: &Path, path: &Path) -> bool {
+    let path = path.strip_prefix(root).unwrap_or(path);
+    !path.components().any(|component| {
+        let name = component.as_os_str().to_string_lossy();
+        (name.starts_with('.') && name.len() > 1 && name != "..") || name == "__pycache__"
+    }) && !path.to_string_lossy().ends_with('~')
+}
+
+/// Wait for changes to the watched files, and for `debounce` to pass without further changes.
+/// Returns the changed files, or None if the watcher stopped.
+fn wait_for_changes(
+    root: &Path,
+    events: &Receiver<notify::Result<notify::Event>>,
+    debounce: Duration,
+) -> Option<Vec<PathBuf>> {
+    let mut changed: Vec<PathBuf> = Vec::new();
+    loop {
+        let event = if changed.is_empty() {
+            events.recv().ok()?
+        } else {
+            match events.recv_timeout(debounce) {
+                Ok(event) => event,
+                Err(RecvTimeoutError::Timeout) => return Some(changed),
+                Err(RecvTimeoutError::Disconnected) => return None,
+            }
+        };
+        let event = match event {
+            Ok(event) => event,
+            Err(e) => {
+                warn(
+                    Code::WatchFailed,
+                    &format!("Error when watching files: {}", e),
+                );
+                continue;
+            }
+        };
+        if matches!(event.kind, EventKind::Access(_)) {
+            continue;
+        }
+        for path in event
+            .paths
+            .into_iter()
+            .filter(|path| is_relevant(root, path))
+        {
+            if !changed.contains(&path) {
+                changed.push(path);
+            }
+        }
+    }
+}
+
+pub fn run(args: WatchArgs) {
+    let root = find_project_root();
+    let target = find_target(&root, &args.target);
+    let (sender, events) = channel();
+    let mut watcher = notify::recommended_watcher(sender)
+        .unwrap_or_else(|e| exit_with_error(&format!("Could not watch files: {}", e)));
+    for path in &args.path {
+        watcher
+            .watch(&root.join(path), RecursiveMode::Recursive)
+            .unwrap_or_else(|e| exit_with_error(&format!("Could not watch {:?}: {}", path, e)));
+    }
+    std::fs::create_dir_all(root.join(LOGS_DIR))
+        .unwrap_or_else(|_| panic!("Error when creating directory {}", LOGS_DIR));
+    let log = root.join(LOGS_DIR).join(format!("{}.log", target.log));
+    let shown: Vec<String> = target
+        .command
+        .iter()
+        .map(|arg| arg.to_string_lossy().into_owned())
+        .collect();
+    let watched: Vec<String> = args
+        .path
+        .iter()
+        .map(|path| path.display().to_string())
+        .collect();
+    println!(
+        "Running `{}` when {} changes. The output of the last run is in {}/{}.log",
+        shown.join(" "),
+        watched.join(", "),
+        LOGS_DIR,
+        target.log
+    );
+    run_logged(&root, &target, &log);
+    let debounce = Duration::from_millis(args.debounce);
+    while let Some(changed) = wait_for_changes(&root, &events, debounce) {
+        let names: Vec<String> = changed
+            .iter()
+            .map(|path| {
+                path.strip_prefix(&root)
+                    .unwrap_or(path)
+                    .display()
+                    .to_string()
+            })
+            .collect();
+        println!(
+            "[{}] Changed: {}",
+            Local::now().format("%H:%M:%S"),
+            names.join(", ")
+        );
+        run_logged(&root, &target, &log);
+    }
+}
@@ -0,0 +1,156 @@
+use std::ffi::OsStr;
+use std::path::Path;
+use std::process::Command;
+
+use crate::env::offline_env;
+use crate::run_in_wsl;
+use crate::warning::{warn, Code};
+
+/// A path as seen from inside WSL, the Windows Subsystem for Linux.
+struct WslPath {
+    /// The distribution whose filesystem the path is in, or `None` for a Windows drive
+    distro: Option<String>,
+    /// The path inside WSL, like /home/me/project or /mnt/c/Users/me/project
+    path: String,
+}
+
+/// Translate the Windows path `path`, like `\\wsl$\Ubuntu\home\me` or `C:\Users\me`, to the
+/// path inside WSL.
+fn to_wsl(path: &Path) -> Option<WslPath> {
+    let path = path.to_string_lossy().replace('\\', "/");
+    // Paths made absolute on Windows may be verbatim, like \\?\C:\Users or \\?\UNC\wsl$\Ubuntu
+    let path = match path.strip_prefix("//?/") {
+        Some(rest) => match rest.strip_prefix("UNC/") {
+            Some(share) => format!("//{}", share),
+            None => rest.to_owned(),
+        },
+        None => path,
+    };
+    for prefix in ["//wsl$/", "//wsl.localhost/"] {
+        if path
+            .get(..prefix.len())
+            .is_some_and(|start| start.eq_ignore_ascii_case(prefix))
+        {
+            let rest = &path[prefix.len()..];
+            let (distro, rest) = rest.split_once('/').unwrap_or((rest, ""));
+            return Some(WslPath {
+                distro: Some(distro.to_owned()),
+                path: format!("/{}", rest),
+            });
+        }
+    }
+    let mut chars = path.chars();
+    match (chars.next(), chars.next(), chars.next()) {
+        (Some(drive), Some(':'), Some('/') | None) if drive.is_ascii_alphabetic() => {
+            Some(WslPath {
+                distro: None,
+                path: format!("/mnt/{}{}", drive.to_ascii_lowercase(), &path[2..]),
+            })
+        }
+        _ => None,
+    }
+}
+
+/// Translate `path` inside WSL on a Windows drive, like /mnt/c/Users/me, to the Windows path.
+fn to_windows(path: &Path) -> Option<String> {
+    let rest = path.to_str()?.strip_prefix("/mnt/")?;
+    let (drive, rest) = rest.split_once('/').unwrap_or((rest, ""));
+    let is_drive = drive.len() == 1 && drive.chars().all(|c| c.is_ascii_alphabetic());
+    is_drive.then(|| format!("{}:\\{}", drive.to_uppercase(), rest.replace('/', "\\")))
+}
+
+/// Check if investigate runs inside WSL.
+fn is_inside_wsl() -> bool {
+    cfg!(target_os = "linux")
+        && (std::env::var_os("WSL_DISTRO_NAME").is_some()
+            || Path::new("/proc/sys/fs/binfmt_misc/WSLInterop").exists())
+}
+
+/// Warn if the project at `root` is on the other side of the boundary between Windows and WSL,
+/// so Conda and Julia would make its environment for the wrong system.
+pub fn check(root: &Path) {
+    let absolute = std::path::absolute(root).unwrap_or_else(|_| root.to_owned());
+    if cfg!(windows) && !run_in_wsl() {
+        if let Some(WslPath {
+            distro: Some(distro),
+            path,
+        }) = to_wsl(&absolute)
+        {
+            warn(
+                Code::WrongEnvironment,
+                &format!(
+                    "The project is at {} in WSL ({}), but Conda and Julia run on Windows, so \
+                    its environment is made for Windows. Pass --wsl to run them inside WSL",
+                    path, distro
+                ),
+            );
+        }
+    } else if is_inside_wsl() {
+        if let Some(windows) = to_windows(&absolute) {
+            warn(
+                Code::WrongEnvironment,
+                &format!(
+                    "The project is on a Windows drive, at {}, but Conda and Julia run inside \
+                    WSL, so its environment can only be used from WSL. Files on Windows drives \
+                    are also slow to use from WSL",
+                    windows
+                ),
+            );
+        }
+    }
+}
+
+/// Get the project directory `root` as seen from inside WSL, if programs run there.
+fn inside(root: &Path) -> Option<WslPath> {
+    let absolute = std::path::absolute(root).unwrap_or_else(|_| root.to_owned());
+    to_wsl(&absolute).filter(|_| cfg!(windows) && run_in_wsl())
+}
+
+/// Check if programs for the project at `root` run inside WSL, with --wsl on Windows.
+pub fn is_used(root: &Path) -> bool {
+    inside(root).is_some()
+}
+
+/// Get a command running `program`, like conda or julia, in the project directory `root`. With
+/// --wsl on Windows, it runs inside WSL, in the distribution the project is in.
+pub fn command(program: impl AsRef<OsStr>, root: &Path) -> Command {
+    let Some(inside) = inside(root) else {
+        let mut command = Command::new(program);
+        command.current_dir(root);
+        return command;
+    };
+    let mut command = Command::new("wsl.exe");
+    if let Some(distro) = &inside.distro {
+        command.args(["-d", distro]);
+    }
+    command.args(["--cd", &inside.path, "--"]).arg(program);
+    // Only the environment variables listed in $WSLENV are passed into WSL
+    let mut shared: Vec<String> = std::env::var("WSLENV")
+        .ok()
+        .filter(|shared| !shared.is_empty())
+        .into_iter()
+        .collect();
+    shared.extend(offline_env().iter().map(|(name, _)| name.to_string()));
+    command.env("WSLENV", shared.join(":"));
+    command
+}
+
+/// Set the environment variable `name` of `command` from `command` to the path `path`. If the
+/// command runs inside WSL, the variable is passed into it, with the path translated.
+pub fn env_path(command: &mut Command, name: &str, path: &Path) {
+    command.env(name, path);
+    let shared = command
+        .get_envs()
+        .find(|(key, _)| *key == "WSLENV")
+        .and_then(|(_, value)| value)
+        .map(|value| value.to_string_lossy().into_owned());
+    if let Some(shared) = shared {
+        let entry = format!("{}/p", name);
+        let shared = if shared.is_empty() {
+            entry
+        } else {
+            format!("{}:{}", shared, entry)
+        };
+        command.env("WSLENV", shared);
+    }
+}